@@ -726,7 +726,7 @@ impl CommitFinalizer {
         finalized_commits
     }
 
-    fn try_update_gc_round(&mut self, last_finalized_commit_round: Round) {
+    pub(crate) fn try_update_gc_round(&mut self, last_finalized_commit_round: Round) {
         // GC TransactionCertifier state only with finalized commits, to ensure unfinalized transactions
         // can access their reject votes from TransactionCertifier.
         let gc_round = self
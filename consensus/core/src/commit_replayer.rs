@@ -0,0 +1,577 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline replay of consensus commits from the blocks in a consensus store.
+//!
+//! [CommitReplayer] feeds the blocks of a store back through `UniversalCommitter`, `Linearizer`
+//! and `CommitFinalizer`, round by round from genesis, on top of an in-memory DAG. The commits it
+//! produces are compared against the commits persisted in the store, and the transactions its
+//! finalization rejects are compared against the persisted rejections, so that a suspected commit
+//! divergence or leader scoring bug can be reproduced from a captured DB.
+
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use consensus_config::{AuthorityIndex, Committee, Parameters};
+use consensus_types::block::{BlockRef, Round, TransactionIndex};
+use mysten_metrics::monitored_mpsc::{UnboundedReceiver, unbounded_channel};
+use parking_lot::RwLock;
+use prometheus::Registry;
+use sui_protocol_config::ProtocolConfig;
+use tracing::info;
+
+use crate::{
+    block::{CertifiedBlocksOutput, Slot, VerifiedBlock},
+    block_verifier::NoopBlockVerifier,
+    commit::{CommitAPI as _, CommitIndex, CommitRange, CommitRef, CommittedSubDag, TrustedCommit},
+    commit_finalizer::CommitFinalizer,
+    context::{Clock, Context},
+    dag_state::DagState,
+    error::ConsensusResult,
+    leader_schedule::LeaderSchedule,
+    linearizer::Linearizer,
+    metrics::initialise_metrics,
+    storage::{Store, mem_store::MemStore, rocksdb_store::RocksDBStore},
+    transaction_certifier::TransactionCertifier,
+    universal_committer::{
+        UniversalCommitter, universal_committer_builder::UniversalCommitterBuilder,
+    },
+};
+
+/// Number of rounds of blocks read from the store and accepted at a time.
+const ROUNDS_PER_BATCH: Round = 100;
+
+/// Replays the commit sequence of an epoch from its blocks. The replay is deterministic: the
+/// blocks are accepted in round order, and the commit rule runs after every batch of rounds, like
+/// it runs after blocks are accepted by `Core`.
+///
+/// Commits that a validator received through commit sync, without the blocks needed to decide
+/// them locally, may not be reproducible from its store. These are reported as
+/// [Divergence::Missing].
+pub struct CommitReplayer {
+    context: Arc<Context>,
+    dag_state: Arc<RwLock<DagState>>,
+    leader_schedule: Arc<LeaderSchedule>,
+    committer: UniversalCommitter,
+    linearizer: Linearizer,
+    transaction_certifier: TransactionCertifier,
+    commit_finalizer: CommitFinalizer,
+    last_decided_leader: Slot,
+
+    // The outputs of TransactionCertifier and CommitFinalizer are not consumed by the replay, but
+    // the channels are kept open so that sending to them does not fail.
+    _certified_blocks_receiver: UnboundedReceiver<CertifiedBlocksOutput>,
+    _finalized_commits_receiver: UnboundedReceiver<CommittedSubDag>,
+}
+
+/// The outcome of replaying a store.
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    /// Number of blocks read from the store.
+    pub blocks: usize,
+    /// Number of commits produced by the replay.
+    pub replayed_commits: usize,
+    /// Number of commits persisted in the store.
+    pub persisted_commits: usize,
+    /// Differences between the replay and the store, in the order they were found.
+    pub divergences: Vec<Divergence>,
+}
+
+#[derive(Debug)]
+pub enum Divergence {
+    /// The replayed commit differs from the commit persisted at the same index.
+    Commit {
+        replayed: TrustedCommit,
+        persisted: TrustedCommit,
+    },
+    /// The commit persisted in the store was not produced by the replay.
+    Missing { persisted: TrustedCommit },
+    /// The commits match, but their finalization rejected different transactions.
+    RejectedTransactions {
+        commit: CommitRef,
+        replayed: BTreeMap<BlockRef, Vec<TransactionIndex>>,
+        persisted: BTreeMap<BlockRef, Vec<TransactionIndex>>,
+    },
+}
+
+impl CommitReplayer {
+    /// Creates a replayer for an epoch with the given committee and protocol config, as seen by
+    /// the authority `own_index` whose store is replayed.
+    pub fn new(
+        epoch_start_timestamp_ms: u64,
+        own_index: AuthorityIndex,
+        committee: Committee,
+        protocol_config: ProtocolConfig,
+    ) -> Self {
+        let context = Context::new(
+            epoch_start_timestamp_ms,
+            own_index,
+            committee,
+            Parameters::default(),
+            protocol_config,
+            initialise_metrics(Registry::new()),
+            Arc::new(Clock::default()),
+        );
+        Self::with_context(Arc::new(context))
+    }
+
+    pub(crate) fn with_context(context: Arc<Context>) -> Self {
+        let dag_state = Arc::new(RwLock::new(DagState::new(
+            context.clone(),
+            Arc::new(MemStore::new()),
+        )));
+        let leader_schedule = Arc::new(LeaderSchedule::from_store(
+            context.clone(),
+            dag_state.clone(),
+        ));
+        let number_of_leaders = context
+            .protocol_config
+            .mysticeti_num_leaders_per_round()
+            .unwrap_or(1);
+        let committer = UniversalCommitterBuilder::new(
+            context.clone(),
+            leader_schedule.clone(),
+            dag_state.clone(),
+        )
+        .with_number_of_leaders(number_of_leaders)
+        .with_pipeline(true)
+        .build();
+        let linearizer = Linearizer::new(context.clone(), dag_state.clone());
+
+        let (certified_blocks_sender, certified_blocks_receiver) =
+            unbounded_channel("consensus_replay_certified_blocks");
+        let transaction_certifier = TransactionCertifier::new(
+            context.clone(),
+            Arc::new(NoopBlockVerifier {}),
+            dag_state.clone(),
+            certified_blocks_sender,
+        );
+        let (finalized_commits_sender, finalized_commits_receiver) =
+            unbounded_channel("consensus_replay_finalized_commits");
+        let commit_finalizer = CommitFinalizer::new(
+            context.clone(),
+            dag_state.clone(),
+            transaction_certifier.clone(),
+            finalized_commits_sender,
+        );
+
+        let last_decided_leader = dag_state.read().last_commit_leader();
+        Self {
+            context,
+            dag_state,
+            leader_schedule,
+            committer,
+            linearizer,
+            transaction_certifier,
+            commit_finalizer,
+            last_decided_leader,
+            _certified_blocks_receiver: certified_blocks_receiver,
+            _finalized_commits_receiver: finalized_commits_receiver,
+        }
+    }
+
+    /// Replays all blocks in `store`, comparing the resulting commits and finalized transactions
+    /// against the ones persisted in `store`. The replayer must be freshly created.
+    pub async fn replay(&mut self, store: &RocksDBStore) -> ConsensusResult<ReplayReport> {
+        let mut report = ReplayReport {
+            persisted_commits: store
+                .read_last_commit()?
+                .map_or(0, |commit| commit.index() as usize),
+            ..Default::default()
+        };
+
+        // Rounds in a DAG are contiguous, so the first empty batch marks the end of the store.
+        let mut start_round = 1;
+        loop {
+            let end_round = start_round + ROUNDS_PER_BATCH - 1;
+            let blocks = store.scan_blocks_by_round(start_round, end_round)?;
+            if blocks.is_empty() {
+                break;
+            }
+            report.blocks += blocks.len();
+            self.add_blocks(blocks);
+
+            let commits = self.try_commit();
+            self.dag_state.write().flush();
+            if let (Some(first), Some(last)) = (commits.first(), commits.last()) {
+                let range = CommitRange::new(first.commit_ref.index..=last.commit_ref.index);
+                let replayed = self.dag_state.read().store().scan_commits(range.clone())?;
+                let persisted = store.scan_commits(range)?;
+                report.replayed_commits += replayed.len();
+                for (replayed, persisted) in replayed.into_iter().zip(persisted) {
+                    if replayed.reference() != persisted.reference() {
+                        report.divergences.push(Divergence::Commit {
+                            replayed,
+                            persisted,
+                        });
+                    }
+                }
+            }
+
+            for commit in self.finalize(commits).await {
+                // Commits that were not finalized before the store was captured have no persisted
+                // rejections to compare against.
+                let Some(persisted) = store.read_rejected_transactions(commit.commit_ref)? else {
+                    continue;
+                };
+                if persisted != commit.rejected_transactions_by_block {
+                    report.divergences.push(Divergence::RejectedTransactions {
+                        commit: commit.commit_ref,
+                        replayed: commit.rejected_transactions_by_block,
+                        persisted,
+                    });
+                }
+            }
+
+            info!(
+                "Replayed rounds {start_round}..={end_round}: {} commits, {} divergences",
+                report.replayed_commits,
+                report.divergences.len(),
+            );
+            start_round = end_round + 1;
+        }
+
+        // Persisted commits past the last replayed one were never compared.
+        if report.persisted_commits > report.replayed_commits {
+            let range = CommitRange::new(
+                report.replayed_commits as CommitIndex + 1
+                    ..=report.persisted_commits as CommitIndex,
+            );
+            for persisted in store.scan_commits(range)? {
+                report.divergences.push(Divergence::Missing { persisted });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Accepts blocks into the DAG, and their reject votes into the transaction certifier. Own
+    /// votes are not stored, but they are part of the own blocks, so none are added here.
+    fn add_blocks(&mut self, blocks: Vec<VerifiedBlock>) {
+        if self.context.protocol_config.mysticeti_fastpath() {
+            self.transaction_certifier
+                .add_voted_blocks(blocks.iter().map(|b| (b.clone(), vec![])).collect());
+        }
+        self.dag_state.write().accept_blocks(blocks);
+    }
+
+    /// Runs the commit rule and linearizes the decided leaders, following `Core::try_commit()` and
+    /// `CommitObserver::handle_commit()` for locally decided commits.
+    fn try_commit(&mut self) -> Vec<CommittedSubDag> {
+        let mut committed_sub_dags = vec![];
+        loop {
+            let mut commits_until_update = self
+                .leader_schedule
+                .commits_until_leader_schedule_update(self.dag_state.clone());
+            if commits_until_update == 0 {
+                self.leader_schedule
+                    .update_leader_schedule_v2(&self.dag_state);
+                commits_until_update = self
+                    .leader_schedule
+                    .commits_until_leader_schedule_update(self.dag_state.clone());
+            }
+            assert!(commits_until_update > 0);
+
+            let mut decided_leaders = self.committer.try_decide(self.last_decided_leader);
+            if decided_leaders.len() >= commits_until_update {
+                let _ = decided_leaders.split_off(commits_until_update);
+            }
+            let Some(last_decided) = decided_leaders.last().cloned() else {
+                break;
+            };
+            self.last_decided_leader = last_decided.slot();
+
+            let sequenced_leaders = decided_leaders
+                .into_iter()
+                .filter_map(|leader| leader.into_committed_block())
+                .collect::<Vec<_>>();
+            if sequenced_leaders.is_empty() {
+                break;
+            }
+
+            let mut sub_dags = self.linearizer.handle_commit(sequenced_leaders);
+            if self
+                .leader_schedule
+                .leader_schedule_updated(&self.dag_state)
+            {
+                sub_dags[0].reputation_scores_desc = self
+                    .leader_schedule
+                    .leader_swap_table
+                    .read()
+                    .reputation_scores_desc
+                    .clone();
+            }
+            self.dag_state.write().add_scoring_subdags(sub_dags.clone());
+
+            committed_sub_dags.extend(sub_dags);
+        }
+        committed_sub_dags
+    }
+
+    /// Passes commits through `CommitFinalizer`, returning the commits that became finalized.
+    async fn finalize(&mut self, commits: Vec<CommittedSubDag>) -> Vec<CommittedSubDag> {
+        if !self.context.protocol_config.mysticeti_fastpath() {
+            return commits;
+        }
+
+        let mut finalized_commits = vec![];
+        for commit in commits {
+            finalized_commits.extend(self.commit_finalizer.process_commit(commit).await);
+        }
+        if let Some(last) = finalized_commits.last() {
+            self.commit_finalizer.try_update_gc_round(last.leader.round);
+        }
+        finalized_commits
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Commit {
+                replayed,
+                persisted,
+            } => {
+                let mut differences = vec![];
+                if replayed.leader() != persisted.leader() {
+                    differences.push(format!(
+                        "leader {} != {}",
+                        replayed.leader(),
+                        persisted.leader()
+                    ));
+                }
+                if replayed.blocks() != persisted.blocks() {
+                    differences.push(format!(
+                        "{} blocks != {} blocks",
+                        replayed.blocks().len(),
+                        persisted.blocks().len()
+                    ));
+                }
+                if replayed.timestamp_ms() != persisted.timestamp_ms() {
+                    differences.push(format!(
+                        "timestamp {} != {}",
+                        replayed.timestamp_ms(),
+                        persisted.timestamp_ms()
+                    ));
+                }
+                if replayed.previous_digest() != persisted.previous_digest() {
+                    differences.push("previous digest".to_string());
+                }
+                write!(
+                    f,
+                    "Commit {}: replayed {} != persisted {} ({})",
+                    replayed.index(),
+                    replayed.reference(),
+                    persisted.reference(),
+                    differences.join(", ")
+                )
+            }
+            Divergence::Missing { persisted } => write!(
+                f,
+                "Commit {}: persisted {} was not replayed",
+                persisted.index(),
+                persisted.reference()
+            ),
+            Divergence::RejectedTransactions {
+                commit,
+                replayed,
+                persisted,
+            } => write!(
+                f,
+                "Commit {commit}: replayed rejected transactions {replayed:?} != persisted {persisted:?}"
+            ),
+        }
+    }
+}
+
+impl Divergence {
+    /// The index of the commit that diverged.
+    pub fn commit_index(&self) -> CommitIndex {
+        match self {
+            Divergence::Commit { replayed, .. } => replayed.index(),
+            Divergence::Missing { persisted } => persisted.index(),
+            Divergence::RejectedTransactions { commit, .. } => commit.index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{storage::WriteBatch, test_dag_builder::DagBuilder};
+
+    fn build_dag() -> (Arc<Context>, DagBuilder) {
+        let (mut context, _) = Context::new_for_test(4);
+        context
+            .protocol_config
+            .set_mysticeti_fastpath_for_testing(true);
+        let context = Arc::new(context);
+        let mut dag_builder = DagBuilder::new(context.clone());
+        dag_builder.layers(1..=10).build();
+        (context, dag_builder)
+    }
+
+    fn write_store(
+        path: &str,
+        blocks: Vec<VerifiedBlock>,
+        commits: Vec<TrustedCommit>,
+        finalized_commits: Vec<(CommitRef, BTreeMap<BlockRef, Vec<TransactionIndex>>)>,
+    ) {
+        let store = RocksDBStore::new(path);
+        store
+            .write(WriteBatch::new(blocks, commits, vec![], finalized_commits))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_matches_store() {
+        let (context, mut dag_builder) = build_dag();
+        // Leaders up to round 8 have the votes and certificates to be decided.
+        let commits: Vec<_> = dag_builder
+            .get_sub_dag_and_commits(1..=8)
+            .into_iter()
+            .map(|(_, commit)| commit)
+            .collect();
+        let finalized = commits
+            .iter()
+            .map(|c| (c.reference(), BTreeMap::new()))
+            .collect();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().to_str().unwrap();
+        write_store(path, dag_builder.all_blocks(), commits.clone(), finalized);
+
+        let store = RocksDBStore::new(path);
+        let report = CommitReplayer::with_context(context)
+            .replay(&store)
+            .await
+            .unwrap();
+
+        assert_eq!(report.blocks, 4 * 10);
+        assert_eq!(report.persisted_commits, commits.len());
+        assert_eq!(report.replayed_commits, commits.len());
+        assert!(report.divergences.is_empty(), "{:?}", report.divergences);
+    }
+
+    #[tokio::test]
+    async fn test_replay_reports_missing_commits() {
+        let (context, mut dag_builder) = build_dag();
+        let commits: Vec<_> = dag_builder
+            .get_sub_dag_and_commits(1..=8)
+            .into_iter()
+            .map(|(_, commit)| commit)
+            .collect();
+
+        // The store has all commits, but only the blocks up to round 6, as if the later commits
+        // were received through commit sync.
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().to_str().unwrap();
+        write_store(path, dag_builder.blocks(1..=6), commits.clone(), vec![]);
+
+        let store = RocksDBStore::new(path);
+        let report = CommitReplayer::with_context(context)
+            .replay(&store)
+            .await
+            .unwrap();
+
+        assert_eq!(report.persisted_commits, commits.len());
+        assert!(report.replayed_commits < commits.len());
+        let indices: Vec<_> = report
+            .divergences
+            .iter()
+            .map(|d| d.commit_index())
+            .collect();
+        let expected: Vec<_> =
+            (report.replayed_commits as CommitIndex + 1..=commits.len() as CommitIndex).collect();
+        assert_eq!(indices, expected);
+        assert!(
+            report
+                .divergences
+                .iter()
+                .all(|d| matches!(d, Divergence::Missing { .. }))
+        );
+        assert!(
+            report.divergences[0]
+                .to_string()
+                .contains("was not replayed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_reports_divergence() {
+        let (context, mut dag_builder) = build_dag();
+        let mut commits: Vec<_> = dag_builder
+            .get_sub_dag_and_commits(1..=8)
+            .into_iter()
+            .map(|(_, commit)| commit)
+            .collect();
+
+        // The persisted commit 5 has a different timestamp, which also changes the previous digest
+        // of every commit after it.
+        let original = &commits[4];
+        commits[4] = TrustedCommit::new_for_test(
+            original.index(),
+            original.previous_digest(),
+            original.timestamp_ms() + 1,
+            original.leader(),
+            original.blocks().to_vec(),
+        );
+        for i in 5..commits.len() {
+            let commit = commits[i].clone();
+            commits[i] = TrustedCommit::new_for_test(
+                commit.index(),
+                commits[i - 1].digest(),
+                commit.timestamp_ms(),
+                commit.leader(),
+                commit.blocks().to_vec(),
+            );
+        }
+
+        // Commit 2 was persisted with a rejected transaction.
+        let rejected = BTreeMap::from([(commits[1].leader(), vec![0])]);
+        let finalized = vec![(commits[1].reference(), rejected.clone())];
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().to_str().unwrap();
+        write_store(path, dag_builder.all_blocks(), commits, finalized);
+
+        let store = RocksDBStore::new(path);
+        let report = CommitReplayer::with_context(context)
+            .replay(&store)
+            .await
+            .unwrap();
+
+        let indices: Vec<_> = report
+            .divergences
+            .iter()
+            .map(|d| d.commit_index())
+            .collect();
+        assert_eq!(indices, vec![5, 6, 7, 8, 2]);
+
+        let Divergence::Commit { persisted, .. } = &report.divergences[0] else {
+            panic!("Expected a commit divergence");
+        };
+        assert_eq!(persisted.index(), 5);
+        assert!(report.divergences[0].to_string().contains("timestamp"));
+        assert!(
+            !report.divergences[0]
+                .to_string()
+                .contains("previous digest")
+        );
+        assert!(
+            report.divergences[1]
+                .to_string()
+                .contains("previous digest")
+        );
+
+        let Divergence::RejectedTransactions {
+            replayed,
+            persisted,
+            ..
+        } = &report.divergences[4]
+        else {
+            panic!("Expected a rejected transactions divergence");
+        };
+        assert!(replayed.is_empty());
+        assert_eq!(persisted, &rejected);
+    }
+}
@@ -0,0 +1,480 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline inspection of the DAG held in a consensus store.
+//!
+//! [InspectedDag] collects the blocks of a range of rounds, and annotates them with the commits
+//! they were part of, the leaders of those commits, ancestors that are referenced but missing from
+//! the store, and equivocations (multiple blocks for the same slot). It can be rendered as
+//! Graphviz, or in the text syntax accepted by the DAG parser used in tests, and serialized as
+//! JSON.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+};
+
+use consensus_config::AuthorityIndex;
+use consensus_types::block::{BlockRef, Round};
+use serde::Serialize;
+
+use crate::{
+    block::{BlockAPI as _, Slot, VerifiedBlock},
+    commit::{CommitAPI as _, CommitIndex, CommitRange, TrustedCommit},
+    error::ConsensusResult,
+    storage::{Store, rocksdb_store::RocksDBStore},
+};
+
+/// Number of commits read from the store at a time, while looking for commits that include blocks
+/// in the inspected range.
+const COMMIT_SCAN_BATCH: CommitIndex = 1000;
+
+/// Authorities are named by a single letter in the DAG text syntax.
+const MAX_TEXT_AUTHORITIES: usize = 26;
+
+/// The blocks of a range of rounds in a consensus store, with annotations.
+#[derive(Serialize, Debug)]
+pub struct InspectedDag {
+    pub start_round: Round,
+    pub end_round: Round,
+    /// Number of authorities whose blocks or ancestors appear in the range.
+    pub num_authorities: usize,
+    pub blocks: Vec<InspectedBlock>,
+    /// Commits that include at least one block in the range.
+    pub commits: Vec<InspectedCommit>,
+    /// Ancestors in the range that blocks refer to, but that are not in the store.
+    pub missing_ancestors: Vec<String>,
+    /// Slots in the range with more than one block.
+    pub equivocations: Vec<Equivocation>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InspectedBlock {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub round: Round,
+    pub author: u32,
+    pub timestamp_ms: u64,
+    pub num_transactions: usize,
+    pub ancestors: Vec<String>,
+    /// The commit that included this block, if it has been committed.
+    pub commit: Option<CommitIndex>,
+    /// Whether this block is the leader of a commit.
+    pub leader: bool,
+    /// Whether another block exists for the same slot.
+    pub equivocating: bool,
+
+    #[serde(skip)]
+    block_ref: BlockRef,
+    #[serde(skip)]
+    ancestor_refs: Vec<BlockRef>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InspectedCommit {
+    pub index: CommitIndex,
+    pub leader: String,
+    pub leader_round: Round,
+    pub blocks: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Equivocation {
+    pub slot: String,
+    pub blocks: Vec<String>,
+}
+
+impl InspectedDag {
+    /// Read the blocks from `start_round` to `end_round` (inclusive) from `store`, along with the
+    /// commits that include them.
+    pub fn from_store(
+        store: &RocksDBStore,
+        start_round: Round,
+        end_round: Round,
+    ) -> ConsensusResult<Self> {
+        let blocks = store.scan_blocks_by_round(start_round, end_round)?;
+
+        // Commits are read backwards from the last one, until the leader round falls below the
+        // range. A commit only includes blocks from rounds up to its leader's.
+        let mut commits = vec![];
+        if let Some(last) = store.read_last_commit()? {
+            let mut end = last.index();
+            loop {
+                let start = end.saturating_sub(COMMIT_SCAN_BATCH - 1).max(1);
+                let batch = store.scan_commits(CommitRange::new(start..=end))?;
+                let done = start == 1 || batch.iter().any(|c| c.leader().round < start_round);
+                commits.extend(batch.into_iter().rev());
+                if done {
+                    break;
+                }
+                end = start - 1;
+            }
+        }
+        commits.reverse();
+
+        Ok(Self::new(blocks, &commits, start_round, end_round))
+    }
+
+    /// Annotate `blocks` from `start_round` to `end_round` (inclusive) with `commits`. Blocks and
+    /// commits outside of the range are ignored.
+    pub fn new(
+        blocks: Vec<VerifiedBlock>,
+        commits: &[TrustedCommit],
+        start_round: Round,
+        end_round: Round,
+    ) -> Self {
+        let in_range = |round: Round| (start_round..=end_round).contains(&round);
+        let blocks: Vec<_> = blocks.into_iter().filter(|b| in_range(b.round())).collect();
+        let present: BTreeSet<BlockRef> = blocks.iter().map(|b| b.reference()).collect();
+
+        let mut by_slot: BTreeMap<(Round, AuthorityIndex), Vec<BlockRef>> = BTreeMap::new();
+        for block_ref in &present {
+            by_slot
+                .entry((block_ref.round, block_ref.author))
+                .or_default()
+                .push(*block_ref);
+        }
+
+        let mut committed_in: BTreeMap<BlockRef, CommitIndex> = BTreeMap::new();
+        let mut leaders = BTreeSet::new();
+        let mut inspected_commits = vec![];
+        for commit in commits {
+            if !commit.blocks().iter().any(|b| in_range(b.round)) {
+                continue;
+            }
+
+            leaders.insert(commit.leader());
+            for block_ref in commit.blocks() {
+                committed_in.insert(*block_ref, commit.index());
+            }
+
+            inspected_commits.push(InspectedCommit {
+                index: commit.index(),
+                leader: commit.leader().to_string(),
+                leader_round: commit.leader().round,
+                blocks: commit.blocks().iter().map(|b| b.to_string()).collect(),
+            });
+        }
+
+        // Genesis blocks are not stored, so only ancestors from stored rounds can be missing.
+        let mut missing = BTreeSet::new();
+        let mut num_authorities = 0;
+        for block in &blocks {
+            num_authorities = num_authorities.max(block.author().value() + 1);
+            for ancestor in block.ancestors() {
+                num_authorities = num_authorities.max(ancestor.author.value() + 1);
+                if ancestor.round > 0 && in_range(ancestor.round) && !present.contains(ancestor) {
+                    missing.insert(*ancestor);
+                }
+            }
+        }
+
+        let blocks = blocks
+            .iter()
+            .map(|block| {
+                let block_ref = block.reference();
+                InspectedBlock {
+                    reference: block_ref.to_string(),
+                    round: block.round(),
+                    author: block.author().value() as u32,
+                    timestamp_ms: block.timestamp_ms(),
+                    num_transactions: block.transactions().len(),
+                    ancestors: block.ancestors().iter().map(|a| a.to_string()).collect(),
+                    commit: committed_in.get(&block_ref).copied(),
+                    leader: leaders.contains(&block_ref),
+                    equivocating: by_slot[&(block_ref.round, block_ref.author)].len() > 1,
+                    block_ref,
+                    ancestor_refs: block.ancestors().to_vec(),
+                }
+            })
+            .collect();
+
+        let equivocations = by_slot
+            .into_iter()
+            .filter(|(_, refs)| refs.len() > 1)
+            .map(|((round, authority), refs)| Equivocation {
+                slot: Slot::new(round, authority).to_string(),
+                blocks: refs.iter().map(|r| r.to_string()).collect(),
+            })
+            .collect();
+
+        Self {
+            start_round,
+            end_round,
+            num_authorities,
+            blocks,
+            commits: inspected_commits,
+            missing_ancestors: missing.iter().map(|r| r.to_string()).collect(),
+            equivocations,
+        }
+    }
+
+    /// Render the DAG in Graphviz's DOT language. Blocks of the same round are ranked together,
+    /// with edges pointing to their ancestors. Commit leaders are drawn in bold, committed blocks
+    /// are filled, equivocating blocks are outlined in red, and missing ancestors are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph DAG {{").unwrap();
+        writeln!(dot, "    rankdir=BT;").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+
+        let mut by_round: BTreeMap<Round, Vec<String>> = BTreeMap::new();
+        for block in &self.blocks {
+            let id = node_id(&block.block_ref);
+            let mut attrs = vec![format!(
+                "label=\"{}\\n{}\"",
+                Slot::from(block.block_ref),
+                block.block_ref.digest
+            )];
+            if let Some(commit) = block.commit {
+                attrs.push("style=filled".to_string());
+                attrs.push(format!("tooltip=\"commit {commit}\""));
+            }
+            if block.leader {
+                attrs.push("penwidth=3".to_string());
+            }
+            if block.equivocating {
+                attrs.push("color=red".to_string());
+            }
+            writeln!(dot, "    {id} [{}];", attrs.join(", ")).unwrap();
+            by_round.entry(block.round).or_default().push(id);
+        }
+
+        for missing in self.missing_refs() {
+            let id = node_id(&missing);
+            writeln!(
+                dot,
+                "    {id} [label=\"{}\\nmissing\", style=dashed];",
+                Slot::from(missing)
+            )
+            .unwrap();
+            by_round.entry(missing.round).or_default().push(id);
+        }
+
+        for (round, ids) in by_round {
+            writeln!(
+                dot,
+                "    {{ rank=same; /* round {round} */ {}; }}",
+                ids.join("; ")
+            )
+            .unwrap();
+        }
+
+        // Edges to ancestors outside of the range are left out, as their nodes are not drawn.
+        for block in &self.blocks {
+            for ancestor in &block.ancestor_refs {
+                if ancestor.round >= self.start_round {
+                    writeln!(
+                        dot,
+                        "    {} -> {};",
+                        node_id(&block.block_ref),
+                        node_id(ancestor)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    /// Render the DAG in the text syntax of the DAG parser used in tests, so that it can be
+    /// replayed in a unit test. The syntax names authorities by letter, so at most 26 authorities
+    /// are supported, and it has a single block per slot, so only the first block of an
+    /// equivocating slot is included. Ancestors that are not part of the output (below the range,
+    /// or missing) are left out, except for genesis blocks.
+    pub fn to_dag_text(&self) -> anyhow::Result<String> {
+        anyhow::ensure!(
+            self.num_authorities <= MAX_TEXT_AUTHORITIES,
+            "DAG text syntax supports at most {MAX_TEXT_AUTHORITIES} authorities, found {}",
+            self.num_authorities,
+        );
+
+        let mut included = BTreeSet::new();
+        let mut by_round: BTreeMap<Round, Vec<&InspectedBlock>> = BTreeMap::new();
+        for block in &self.blocks {
+            if included.insert((block.block_ref.round, block.block_ref.author)) {
+                by_round.entry(block.round).or_default().push(block);
+            }
+        }
+
+        let mut text = String::new();
+        writeln!(text, "DAG {{").unwrap();
+        writeln!(text, "    Round 0 : {{ {} }},", self.num_authorities).unwrap();
+        for (round, blocks) in by_round {
+            writeln!(text, "    Round {round} : {{").unwrap();
+            for block in blocks {
+                let ancestors: Vec<_> = block
+                    .ancestor_refs
+                    .iter()
+                    .filter(|a| a.round == 0 || included.contains(&(a.round, a.author)))
+                    .map(|a| format!("{}{}", authority_letter(a.author), a.round))
+                    .collect();
+                writeln!(
+                    text,
+                    "        {} -> [{}],",
+                    authority_letter(block.block_ref.author),
+                    ancestors.join(", ")
+                )
+                .unwrap();
+            }
+            writeln!(text, "    }},").unwrap();
+        }
+        writeln!(text, "}}").unwrap();
+        Ok(text)
+    }
+
+    fn missing_refs(&self) -> BTreeSet<BlockRef> {
+        let present: BTreeSet<_> = self.blocks.iter().map(|b| b.block_ref).collect();
+        self.blocks
+            .iter()
+            .flat_map(|b| &b.ancestor_refs)
+            .filter(|a| {
+                a.round > 0
+                    && (self.start_round..=self.end_round).contains(&a.round)
+                    && !present.contains(a)
+            })
+            .copied()
+            .collect()
+    }
+}
+
+fn node_id(block_ref: &BlockRef) -> String {
+    format!(
+        "\"{}_{}_{}\"",
+        block_ref.round, block_ref.author, block_ref.digest
+    )
+}
+
+fn authority_letter(authority: AuthorityIndex) -> char {
+    (b'A' + authority.value() as u8) as char
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        context::Context, storage::WriteBatch, test_dag_builder::DagBuilder,
+        test_dag_parser::parse_dag,
+    };
+
+    fn build_dag() -> DagBuilder {
+        let (context, _) = Context::new_for_test(4);
+        let mut dag_builder = DagBuilder::new(Arc::new(context));
+        dag_builder.layers(1..=6).build();
+        // Authority D equivocates in round 7.
+        dag_builder
+            .layer(7)
+            .authorities(vec![AuthorityIndex::new_for_test(3)])
+            .equivocate(1)
+            .build();
+        dag_builder
+    }
+
+    #[cfg(not(tidehunter))]
+    #[tokio::test]
+    async fn test_inspect_store() {
+        let mut dag_builder = build_dag();
+        let commits: Vec<_> = dag_builder
+            .get_sub_dag_and_commits(1..=4)
+            .into_iter()
+            .map(|(_, commit)| commit)
+            .collect();
+
+        // Leave one round 5 block out of the store.
+        let all_blocks = dag_builder.all_blocks();
+        let skipped = all_blocks
+            .iter()
+            .find(|b| b.round() == 5)
+            .unwrap()
+            .reference();
+        let stored: Vec<_> = all_blocks
+            .into_iter()
+            .filter(|b| b.reference() != skipped)
+            .collect();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().to_str().unwrap();
+        {
+            let store = RocksDBStore::new(path);
+            store
+                .write(
+                    WriteBatch::default()
+                        .blocks(stored)
+                        .commits(commits.clone()),
+                )
+                .unwrap();
+        }
+
+        let store = RocksDBStore::open_read_only(path);
+        let dag = InspectedDag::from_store(&store, 3, 7).unwrap();
+
+        assert_eq!(dag.num_authorities, 4);
+        assert!(dag.blocks.iter().all(|b| (3..=7).contains(&b.round)));
+        assert_eq!(dag.missing_ancestors, vec![skipped.to_string()]);
+
+        // Every commit whose sub-dag reaches round 3 is included, with its leader annotated.
+        assert!(!dag.commits.is_empty());
+        for commit in &dag.commits {
+            let leader = dag.blocks.iter().find(|b| b.reference == commit.leader);
+            assert!(leader.is_none_or(|b| b.leader && b.commit == Some(commit.index)));
+        }
+
+        // Blocks above the last committed leader are not committed.
+        assert!(
+            dag.blocks
+                .iter()
+                .filter(|b| b.round >= 5)
+                .all(|b| b.commit.is_none())
+        );
+
+        // The equivocating slot in round 7 is reported.
+        assert_eq!(dag.equivocations.len(), 1);
+        assert_eq!(dag.equivocations[0].blocks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_dag_text_round_trip() {
+        let dag_builder = build_dag();
+        let dag = InspectedDag::new(dag_builder.all_blocks(), &[], 1, 7);
+
+        let text = dag.to_dag_text().unwrap();
+        let (_, parsed) = parse_dag(&text).expect("Invalid DAG text");
+
+        // Equivocating blocks are collapsed into a single block per slot.
+        let parsed = InspectedDag::new(parsed.all_blocks(), &[], 1, 7);
+        assert_eq!(parsed.blocks.len(), 4 * 7);
+        assert!(parsed.equivocations.is_empty());
+        let slots = |dag: &InspectedDag| -> BTreeSet<_> {
+            dag.blocks.iter().map(|b| (b.round, b.author)).collect()
+        };
+        assert_eq!(slots(&parsed), slots(&dag));
+    }
+
+    #[tokio::test]
+    async fn test_dot_output() {
+        let mut dag_builder = build_dag();
+        let commits: Vec<_> = dag_builder
+            .get_sub_dag_and_commits(1..=4)
+            .into_iter()
+            .map(|(_, commit)| commit)
+            .collect();
+
+        let dag = InspectedDag::new(dag_builder.all_blocks(), &commits, 1, 7);
+        let dot = dag.to_dot();
+
+        assert!(dot.starts_with("digraph DAG {"));
+        assert_eq!(dot.matches("penwidth=3").count(), commits.len());
+        assert_eq!(dot.matches("color=red").count(), 2);
+        assert_eq!(
+            dot.matches(" -> ").count(),
+            dag.blocks
+                .iter()
+                .map(|b| b.ancestor_refs.iter().filter(|a| a.round >= 1).count())
+                .sum::<usize>()
+        );
+    }
+}
@@ -13,11 +13,13 @@ mod commit;
 mod commit_consumer;
 mod commit_finalizer;
 mod commit_observer;
+pub mod commit_replayer;
 mod commit_syncer;
 mod commit_vote_monitor;
 mod context;
 mod core;
 mod core_thread;
+pub mod dag_inspector;
 mod dag_state;
 mod error;
 mod leader_schedule;
@@ -38,6 +40,8 @@ mod transaction;
 mod transaction_certifier;
 mod universal_committer;
 
+#[cfg(test)]
+mod test_byzantine;
 /// Consensus test utilities.
 #[cfg(test)]
 mod test_dag;
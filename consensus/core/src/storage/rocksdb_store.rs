@@ -150,6 +150,50 @@ impl RocksDBStore {
     }
 }
 
+impl RocksDBStore {
+    /// Opens an existing store read-only, as a secondary instance, so that it can be inspected
+    /// offline, or while the validator that owns it is running. Writes to the returned store fail.
+    #[cfg(not(tidehunter))]
+    pub fn open_read_only(path: &str) -> Self {
+        let tables = Self::get_read_only_handle(
+            path.into(),
+            None,
+            None,
+            MetricConf::new("consensus_read_only"),
+        );
+        Self {
+            blocks: tables.blocks,
+            digests_by_authorities: tables.digests_by_authorities,
+            commits: tables.commits,
+            commit_votes: tables.commit_votes,
+            commit_info: tables.commit_info,
+            finalized_commits: tables.finalized_commits,
+        }
+    }
+
+    /// Reads all blocks from `start_round` to `end_round` (inclusive), ordered by round, then
+    /// author, then digest.
+    pub fn scan_blocks_by_round(
+        &self,
+        start_round: Round,
+        end_round: Round,
+    ) -> ConsensusResult<Vec<VerifiedBlock>> {
+        let mut blocks = vec![];
+        for kv in self.blocks.safe_range_iter((
+            Included((start_round, AuthorityIndex::MIN, BlockDigest::MIN)),
+            Included((end_round, AuthorityIndex::MAX, BlockDigest::MAX)),
+        )) {
+            let ((round, author, digest), serialized) = kv?;
+            let signed_block: SignedBlock =
+                bcs::from_bytes(&serialized).map_err(ConsensusError::MalformedBlock)?;
+            let block = VerifiedBlock::new_verified(signed_block, serialized);
+            assert_eq!(BlockRef::new(round, author, digest), block.reference());
+            blocks.push(block);
+        }
+        Ok(blocks)
+    }
+}
+
 impl Store for RocksDBStore {
     fn write(&self, write_batch: WriteBatch) -> ConsensusResult<()> {
         fail_point!("consensus-store-before-write");
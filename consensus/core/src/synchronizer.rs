@@ -1157,6 +1157,43 @@ impl<C: NetworkClient, V: BlockVerifier, D: CoreThreadDispatcher> Synchronizer<C
     }
 }
 
+#[cfg(test)]
+impl<C: NetworkClient, V: BlockVerifier, D: CoreThreadDispatcher> Synchronizer<C, V, D> {
+    /// Processes the response of a fetch request for `block_refs` from `peer_index`, the same way
+    /// the fetch tasks do, without starting the synchronizer.
+    pub(crate) async fn process_fetched_blocks_for_test(
+        serialized_blocks: Vec<Bytes>,
+        block_refs: BTreeSet<BlockRef>,
+        peer_index: AuthorityIndex,
+        core_dispatcher: Arc<D>,
+        block_verifier: Arc<V>,
+        transaction_certifier: TransactionCertifier,
+        context: Arc<Context>,
+    ) -> ConsensusResult<()> {
+        let inflight_blocks_map = InflightBlocksMap::new();
+        let blocks_guard = inflight_blocks_map
+            .lock_blocks(block_refs, peer_index)
+            .expect("Failed to lock blocks");
+        let commit_vote_monitor = Arc::new(CommitVoteMonitor::new(context.clone()));
+        let (commands_sender, _commands_receiver) =
+            channel("consensus_synchronizer_commands", 1_000);
+
+        Self::process_fetched_blocks(
+            serialized_blocks,
+            peer_index,
+            blocks_guard,
+            core_dispatcher,
+            block_verifier,
+            transaction_certifier,
+            commit_vote_monitor,
+            context,
+            commands_sender,
+            "test",
+        )
+        .await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -1176,7 +1213,7 @@ mod tests {
     use crate::commit::{CommitVote, TrustedCommit};
     use crate::{
         CommitDigest, CommitIndex,
-        block::{TestBlock, VerifiedBlock},
+        block::{SignedBlock, TestBlock, VerifiedBlock},
         block_verifier::NoopBlockVerifier,
         commit::CommitRange,
         commit_vote_monitor::CommitVoteMonitor,
@@ -1191,7 +1228,14 @@ mod tests {
         },
     };
     use crate::{
-        authority_service::COMMIT_LAG_MULTIPLIER, core_thread::MockCoreThreadDispatcher,
+        authority_service::COMMIT_LAG_MULTIPLIER,
+        block::genesis_blocks,
+        block_verifier::SignedBlockVerifier,
+        core_thread::MockCoreThreadDispatcher,
+        test_byzantine::{
+            ByzantineAction, ByzantineAuthority, ByzantineSchedule, InvalidAncestors,
+        },
+        transaction::NoopTransactionVerifier,
         transaction_certifier::TransactionCertifier,
     };
 
@@ -1963,4 +2007,97 @@ mod tests {
         // Check blocks were unlocked
         assert_eq!(inflight_blocks_map.num_of_locked_blocks(), 0);
     }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn synchronizer_fetch_blocks_from_byzantine_peer() {
+        // GIVEN
+        let (context, keys) = Context::new_for_test(4);
+        let context = Arc::new(context);
+        let block_verifier = Arc::new(SignedBlockVerifier::new(
+            context.clone(),
+            Arc::new(NoopTransactionVerifier),
+        ));
+        let commit_vote_monitor = Arc::new(CommitVoteMonitor::new(context.clone()));
+        let core_dispatcher = Arc::new(MockCoreThreadDispatcher::default());
+        let network_client = Arc::new(MockNetworkClient::default());
+        let (blocks_sender, _blocks_receiver) =
+            monitored_mpsc::unbounded_channel("consensus_block_output");
+        let store = Arc::new(MemStore::new());
+        let dag_state = Arc::new(RwLock::new(DagState::new(context.clone(), store)));
+        let transaction_certifier = TransactionCertifier::new(
+            context.clone(),
+            block_verifier.clone(),
+            dag_state.clone(),
+            blocks_sender,
+        );
+
+        let handle = Synchronizer::start(
+            network_client.clone(),
+            context.clone(),
+            core_dispatcher.clone(),
+            commit_vote_monitor,
+            block_verifier,
+            transaction_certifier,
+            dag_state,
+            false,
+        );
+
+        // AND a Byzantine peer whose round 2 block does not have enough parents
+        let byzantine_peer = AuthorityIndex::new_for_test(3);
+        let mut byzantine = ByzantineAuthority::new(
+            Arc::new(
+                context
+                    .as_ref()
+                    .clone()
+                    .with_authority_index(byzantine_peer),
+            ),
+            keys[byzantine_peer].1.clone(),
+            ByzantineSchedule::default().at(
+                2..=2,
+                ByzantineAction::InvalidAncestors(InvalidAncestors::TooFew),
+            ),
+        );
+        let genesis = genesis_blocks(&context)
+            .iter()
+            .map(|b| b.reference())
+            .collect::<Vec<_>>();
+        let to_verified = |serialized: Bytes| {
+            let signed: SignedBlock = bcs::from_bytes(&serialized).unwrap();
+            VerifiedBlock::new_verified(signed, serialized)
+        };
+        let valid = to_verified(byzantine.propose(1, &genesis, 1000).remove(0).serialized);
+        let invalid = to_verified(byzantine.propose(2, &genesis, 2000).remove(0).serialized);
+
+        // WHEN fetching both blocks from the Byzantine peer
+        network_client
+            .stub_fetch_blocks(vec![valid.clone(), invalid.clone()], byzantine_peer, None)
+            .await;
+        let missing_blocks = BTreeSet::from([valid.reference(), invalid.reference()]);
+        assert!(
+            handle
+                .fetch_blocks(missing_blocks, byzantine_peer)
+                .await
+                .is_ok()
+        );
+        sleep(Duration::from_millis(1_000)).await;
+
+        // THEN the whole response is rejected, and nothing reaches core
+        assert!(core_dispatcher.get_add_blocks().await.is_empty());
+
+        // WHEN the valid block is fetched again from an honest peer
+        let honest_peer = AuthorityIndex::new_for_test(1);
+        network_client
+            .stub_fetch_blocks(vec![valid.clone()], honest_peer, None)
+            .await;
+        assert!(
+            handle
+                .fetch_blocks(BTreeSet::from([valid.reference()]), honest_peer)
+                .await
+                .is_ok()
+        );
+        sleep(Duration::from_millis(1_000)).await;
+
+        // THEN it is accepted, as the failed fetch released its lock on the block
+        assert_eq!(core_dispatcher.get_add_blocks().await, vec![valid]);
+    }
 }
@@ -0,0 +1,835 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::RangeInclusive,
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use consensus_config::{AuthorityIndex, ProtocolKeyPair};
+use consensus_types::block::{BlockDigest, BlockRef, BlockTimestampMs, Round};
+use mysten_metrics::monitored_mpsc;
+use parking_lot::RwLock;
+
+use crate::{
+    block::{BlockAPI as _, SignedBlock, Slot, TestBlock, VerifiedBlock, genesis_blocks},
+    block_manager::BlockManager,
+    block_verifier::{BlockVerifier as _, SignedBlockVerifier},
+    commit::CommittedSubDag,
+    context::Context,
+    core_thread::MockCoreThreadDispatcher,
+    dag_state::DagState,
+    error::ConsensusError,
+    leader_schedule::{LeaderSchedule, LeaderSwapTable},
+    linearizer::Linearizer,
+    network::tonic_network::TonicClient,
+    storage::mem_store::MemStore,
+    synchronizer::Synchronizer,
+    transaction::NoopTransactionVerifier,
+    transaction_certifier::TransactionCertifier,
+    universal_committer::{
+        UniversalCommitter, universal_committer_builder::UniversalCommitterBuilder,
+    },
+};
+
+/// Duration of a round in the cluster, used for the timestamps of honest blocks.
+const ROUND_DURATION_MS: BlockTimestampMs = 1000;
+
+/// Misbehavior of a Byzantine authority when proposing a block.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ByzantineAction {
+    /// Propose this many additional blocks for the slot. The versions are sent to disjoint sets
+    /// of peers.
+    Equivocate(usize),
+    /// Do not send the block to these peers. They can still fetch it once another peer accepted
+    /// it, like the synchronizer would.
+    Withhold(Vec<AuthorityIndex>),
+    /// Set the block timestamp this far ahead of the honest blocks of the round.
+    FutureTimestamp(BlockTimestampMs),
+    /// Reference invalid ancestors.
+    InvalidAncestors(InvalidAncestors),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum InvalidAncestors {
+    /// An ancestor that does not exist. The block passes verification, but can never be accepted.
+    Unknown,
+    /// An ancestor from the same round as the block.
+    SameRound,
+    /// Two ancestors from the same authority.
+    Duplicated,
+    /// Not enough parents from the previous round to reach quorum.
+    TooFew,
+}
+
+/// Byzantine actions by round. A schedule can be built with `at()`, or parsed from a script of
+/// `<rounds>: <action>` lines (or `;` separated statements), where rounds are a single round or an
+/// inclusive range, and authorities are named by letter like in the DAG parser:
+///
+/// ```ignore
+/// let schedule = ByzantineSchedule::parse(
+///     "1..=5: equivocate 2
+///      6: withhold B C
+///      7..=9: future-timestamp 60000
+///      10: invalid-ancestors unknown",
+/// )
+/// .unwrap();
+/// ```
+///
+/// Invalid ancestors are one of `unknown`, `same-round`, `duplicated` or `too-few`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ByzantineSchedule {
+    actions: BTreeMap<Round, Vec<ByzantineAction>>,
+}
+
+impl ByzantineSchedule {
+    pub(crate) fn at(mut self, rounds: RangeInclusive<Round>, action: ByzantineAction) -> Self {
+        for round in rounds {
+            self.actions.entry(round).or_default().push(action.clone());
+        }
+        self
+    }
+
+    pub(crate) fn parse(script: &str) -> anyhow::Result<Self> {
+        let mut schedule = Self::default();
+        for statement in script.split(['\n', ';']) {
+            let statement = statement.split('#').next().unwrap().trim();
+            if statement.is_empty() {
+                continue;
+            }
+
+            let Some((rounds, action)) = statement.split_once(':') else {
+                anyhow::bail!("Expected '<rounds>: <action>', got {statement:?}");
+            };
+            let rounds = parse_rounds(rounds.trim())?;
+
+            let mut words = action.split_whitespace();
+            let action = match (words.next(), words.next()) {
+                (Some("equivocate"), Some(n)) => ByzantineAction::Equivocate(n.parse()?),
+                (Some("withhold"), Some(first)) => ByzantineAction::Withhold(
+                    std::iter::once(first)
+                        .chain(words.by_ref())
+                        .map(parse_authority)
+                        .collect::<anyhow::Result<_>>()?,
+                ),
+                (Some("future-timestamp"), Some(ms)) => {
+                    ByzantineAction::FutureTimestamp(ms.parse()?)
+                }
+                (Some("invalid-ancestors"), Some(kind)) => {
+                    ByzantineAction::InvalidAncestors(match kind {
+                        "unknown" => InvalidAncestors::Unknown,
+                        "same-round" => InvalidAncestors::SameRound,
+                        "duplicated" => InvalidAncestors::Duplicated,
+                        "too-few" => InvalidAncestors::TooFew,
+                        _ => anyhow::bail!("Unknown kind of invalid ancestors: {kind:?}"),
+                    })
+                }
+                _ => anyhow::bail!("Unknown action: {statement:?}"),
+            };
+            if let Some(extra) = words.next() {
+                anyhow::bail!("Unexpected {extra:?} in {statement:?}");
+            }
+
+            schedule = schedule.at(rounds, action);
+        }
+        Ok(schedule)
+    }
+
+    pub(crate) fn actions(&self, round: Round) -> &[ByzantineAction] {
+        self.actions.get(&round).map_or(&[], Vec::as_slice)
+    }
+}
+
+fn parse_rounds(rounds: &str) -> anyhow::Result<RangeInclusive<Round>> {
+    Ok(match rounds.split_once("..=") {
+        Some((start, end)) => start.trim().parse()?..=end.trim().parse()?,
+        None => {
+            let round = rounds.parse()?;
+            round..=round
+        }
+    })
+}
+
+fn parse_authority(name: &str) -> anyhow::Result<AuthorityIndex> {
+    match name.as_bytes() {
+        [letter @ b'A'..=b'Z'] => Ok(AuthorityIndex::new_for_test((letter - b'A') as u32)),
+        _ => anyhow::bail!("Expected an authority letter, got {name:?}"),
+    }
+}
+
+// Fetched blocks go through the synchronizer's processing of fetch responses, with a core
+// dispatcher that hands the blocks back to the fetching authority.
+type TestSynchronizer = Synchronizer<TonicClient, SignedBlockVerifier, MockCoreThreadDispatcher>;
+
+/// A signed block proposed by a Byzantine authority, and the peers it is sent to.
+pub(crate) struct ByzantineBlock {
+    pub(crate) reference: BlockRef,
+    pub(crate) serialized: Bytes,
+    pub(crate) recipients: Vec<AuthorityIndex>,
+}
+
+/// An authority that proposes properly signed blocks, but misbehaves according to its schedule.
+/// Its blocks build on the honest blocks of the previous round, and on its own last block that
+/// could be accepted by honest authorities.
+pub(crate) struct ByzantineAuthority {
+    context: Arc<Context>,
+    keypair: ProtocolKeyPair,
+    schedule: ByzantineSchedule,
+    last_block: BlockRef,
+}
+
+impl ByzantineAuthority {
+    /// `context` must have the Byzantine authority as its own index.
+    pub(crate) fn new(
+        context: Arc<Context>,
+        keypair: ProtocolKeyPair,
+        schedule: ByzantineSchedule,
+    ) -> Self {
+        let last_block = genesis_blocks(&context)[context.own_index].reference();
+        Self {
+            context,
+            keypair,
+            schedule,
+            last_block,
+        }
+    }
+
+    pub(crate) fn index(&self) -> AuthorityIndex {
+        self.context.own_index
+    }
+
+    /// Proposes the blocks for `round`, with `parents` from the previous round.
+    pub(crate) fn propose(
+        &mut self,
+        round: Round,
+        parents: &[BlockRef],
+        timestamp_ms: BlockTimestampMs,
+    ) -> Vec<ByzantineBlock> {
+        let own_index = self.context.own_index;
+        let mut ancestors = vec![self.last_block];
+        ancestors.extend(parents.iter().filter(|p| p.author != own_index));
+
+        let mut num_blocks = 1;
+        let mut timestamp_ms = timestamp_ms;
+        let mut withheld = BTreeSet::new();
+        let mut invalid = None;
+        for action in self.schedule.actions(round) {
+            match action {
+                ByzantineAction::Equivocate(n) => num_blocks += n,
+                ByzantineAction::Withhold(peers) => withheld.extend(peers.iter().copied()),
+                ByzantineAction::FutureTimestamp(ahead_ms) => timestamp_ms += ahead_ms,
+                ByzantineAction::InvalidAncestors(kind) => invalid = Some(*kind),
+            }
+        }
+
+        match invalid {
+            Some(InvalidAncestors::Unknown) => {
+                ancestors.last_mut().unwrap().digest = BlockDigest::MAX
+            }
+            Some(InvalidAncestors::SameRound) => ancestors.last_mut().unwrap().round = round,
+            Some(InvalidAncestors::Duplicated) => {
+                let duplicated = ancestors[1];
+                *ancestors.last_mut().unwrap() = duplicated;
+            }
+            Some(InvalidAncestors::TooFew) => ancestors.truncate(1),
+            None => {}
+        }
+
+        let recipients: Vec<_> = self
+            .context
+            .committee
+            .authorities()
+            .map(|(index, _)| index)
+            .filter(|index| *index != own_index && !withheld.contains(index))
+            .collect();
+
+        // Versions only differ by their timestamp, and each peer receives one of them.
+        let blocks: Vec<_> = (0..num_blocks)
+            .map(|version| {
+                let block = TestBlock::new(round, own_index.value() as u32)
+                    .set_epoch(self.context.committee.epoch())
+                    .set_timestamp_ms(timestamp_ms + version as BlockTimestampMs)
+                    .set_ancestors(ancestors.clone())
+                    .build();
+                let signed = SignedBlock::new(block, &self.keypair).unwrap();
+                let serialized: Bytes = bcs::to_bytes(&signed).unwrap().into();
+                ByzantineBlock {
+                    reference: VerifiedBlock::new_verified(signed, serialized.clone()).reference(),
+                    serialized,
+                    recipients: recipients
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| i % num_blocks == version)
+                        .map(|(_, peer)| *peer)
+                        .collect(),
+                }
+            })
+            .collect();
+
+        // Later blocks build on the last block that honest authorities can accept.
+        if invalid.is_none() {
+            self.last_block = blocks[0].reference;
+        }
+        blocks
+    }
+}
+
+/// An honest authority in a `ByzantineTestCluster`. It verifies received blocks, accepts them
+/// into its DAG, and commits with the universal committer and the linearizer.
+pub(crate) struct HonestAuthority {
+    context: Arc<Context>,
+    keypair: ProtocolKeyPair,
+    dag_state: Arc<RwLock<DagState>>,
+    block_manager: BlockManager,
+    verifier: Arc<SignedBlockVerifier>,
+    transaction_certifier: TransactionCertifier,
+    committer: UniversalCommitter,
+    linearizer: Linearizer,
+    last_decided: Slot,
+    // The first block accepted for each slot, which is used as an ancestor.
+    first_accepted: BTreeMap<(Round, AuthorityIndex), BlockRef>,
+
+    /// All blocks accepted into the DAG.
+    pub(crate) accepted: BTreeSet<BlockRef>,
+    /// Errors from verifying received blocks.
+    pub(crate) rejected: Vec<ConsensusError>,
+    /// Committed sub-dags, in order.
+    pub(crate) commits: Vec<CommittedSubDag>,
+}
+
+impl HonestAuthority {
+    fn new(context: Arc<Context>, keypair: ProtocolKeyPair) -> Self {
+        let dag_state = Arc::new(RwLock::new(DagState::new(
+            context.clone(),
+            Arc::new(MemStore::new()),
+        )));
+        let leader_schedule = Arc::new(LeaderSchedule::new(
+            context.clone(),
+            LeaderSwapTable::default(),
+        ));
+        let committer =
+            UniversalCommitterBuilder::new(context.clone(), leader_schedule, dag_state.clone())
+                .with_pipeline(true)
+                .build();
+        let first_accepted = genesis_blocks(&context)
+            .iter()
+            .map(|b| ((b.round(), b.author()), b.reference()))
+            .collect();
+
+        let last_decided = dag_state.read().last_commit_leader();
+
+        let verifier = Arc::new(SignedBlockVerifier::new(
+            context.clone(),
+            Arc::new(NoopTransactionVerifier),
+        ));
+        let (blocks_sender, _blocks_receiver) =
+            monitored_mpsc::unbounded_channel("consensus_block_output");
+        let transaction_certifier = TransactionCertifier::new(
+            context.clone(),
+            verifier.clone(),
+            dag_state.clone(),
+            blocks_sender,
+        );
+
+        Self {
+            keypair,
+            block_manager: BlockManager::new(context.clone(), dag_state.clone()),
+            verifier,
+            transaction_certifier,
+            linearizer: Linearizer::new(context.clone(), dag_state.clone()),
+            last_decided,
+            first_accepted,
+            accepted: BTreeSet::new(),
+            rejected: vec![],
+            commits: vec![],
+            committer,
+            dag_state,
+            context,
+        }
+    }
+
+    pub(crate) fn index(&self) -> AuthorityIndex {
+        self.context.own_index
+    }
+
+    /// Whether the block manager is waiting on `block_ref` as a missing ancestor.
+    pub(crate) fn is_missing(&self, block_ref: &BlockRef) -> bool {
+        self.block_manager.missing_blocks().contains(block_ref)
+    }
+
+    /// Proposes and accepts an own block for `round`, referencing the first accepted block of
+    /// each authority in the previous round.
+    fn propose(&mut self, round: Round) -> VerifiedBlock {
+        let own_index = self.context.own_index;
+        let mut ancestors = vec![self.first_accepted[&(round - 1, own_index)]];
+        ancestors.extend(
+            self.first_accepted
+                .range((round - 1, AuthorityIndex::MIN)..=(round - 1, AuthorityIndex::MAX))
+                .filter(|((_, author), _)| *author != own_index)
+                .map(|(_, block_ref)| *block_ref),
+        );
+
+        let block = TestBlock::new(round, own_index.value() as u32)
+            .set_epoch(self.context.committee.epoch())
+            .set_timestamp_ms(round as BlockTimestampMs * ROUND_DURATION_MS)
+            .set_ancestors(ancestors)
+            .build();
+        let signed = SignedBlock::new(block, &self.keypair).unwrap();
+        let serialized: Bytes = bcs::to_bytes(&signed).unwrap().into();
+        let block = VerifiedBlock::new_verified(signed, serialized);
+
+        let (accepted, missing) = self.block_manager.try_accept_blocks(vec![block.clone()]);
+        assert!(
+            missing.is_empty(),
+            "Own block {block} has missing ancestors"
+        );
+        self.record_accepted(&accepted);
+        block
+    }
+
+    /// Verifies and tries to accept a received block, returning the blocks that were accepted.
+    fn receive(&mut self, serialized: Bytes) -> Vec<VerifiedBlock> {
+        let signed: SignedBlock = bcs::from_bytes(&serialized).unwrap();
+        match self.verifier.verify_and_vote(signed, serialized) {
+            Ok((block, _)) => {
+                let (accepted, _missing) = self.block_manager.try_accept_blocks(vec![block]);
+                self.record_accepted(&accepted);
+                accepted
+            }
+            Err(e) => {
+                self.rejected.push(e);
+                vec![]
+            }
+        }
+    }
+
+    /// Processes the response of a fetch request for `block_refs` from `peer`, and tries to accept
+    /// the fetched blocks. A response with any invalid block is rejected as a whole.
+    async fn receive_fetched(
+        &mut self,
+        peer: AuthorityIndex,
+        block_refs: BTreeSet<BlockRef>,
+        serialized_blocks: Vec<Bytes>,
+    ) -> Vec<VerifiedBlock> {
+        let core_dispatcher = Arc::new(MockCoreThreadDispatcher::default());
+        let result = TestSynchronizer::process_fetched_blocks_for_test(
+            serialized_blocks,
+            block_refs,
+            peer,
+            core_dispatcher.clone(),
+            self.verifier.clone(),
+            self.transaction_certifier.clone(),
+            self.context.clone(),
+        )
+        .await;
+        if let Err(e) = result {
+            self.rejected.push(e);
+            return vec![];
+        }
+
+        let blocks = core_dispatcher.get_add_blocks().await;
+        let (accepted, _missing) = self.block_manager.try_accept_blocks(blocks);
+        self.record_accepted(&accepted);
+        accepted
+    }
+
+    /// Serves a fetch request with the requested blocks in the DAG.
+    fn serve_fetch(&self, block_refs: &BTreeSet<BlockRef>) -> Vec<Bytes> {
+        let block_refs: Vec<_> = block_refs.iter().copied().collect();
+        self.dag_state
+            .read()
+            .get_blocks(&block_refs)
+            .into_iter()
+            .flatten()
+            .map(|block| block.serialized().clone())
+            .collect()
+    }
+
+    fn record_accepted(&mut self, blocks: &[VerifiedBlock]) {
+        for block in blocks {
+            let block_ref = block.reference();
+            self.accepted.insert(block_ref);
+            self.first_accepted
+                .entry((block_ref.round, block_ref.author))
+                .or_insert(block_ref);
+        }
+    }
+
+    fn try_commit(&mut self) {
+        let decided = self.committer.try_decide(self.last_decided);
+        let Some(last) = decided.last() else {
+            return;
+        };
+        self.last_decided = last.slot();
+
+        let leaders = decided
+            .into_iter()
+            .filter_map(|leader| leader.into_committed_block())
+            .collect();
+        self.commits.extend(self.linearizer.handle_commit(leaders));
+    }
+}
+
+/// A committee of honest authorities and one Byzantine authority, advancing in lock-step rounds.
+///
+/// In each round, every honest authority proposes a block on top of the blocks it accepted in
+/// the previous round, and the Byzantine authority proposes according to its schedule. All blocks
+/// are delivered to their recipients and verified. Missing ancestors are then fetched from the
+/// other authorities through the synchronizer, until no more can be fetched. Honest authorities
+/// serve the blocks in their DAG, and the Byzantine authority serves any block it proposed.
+/// Finally, every honest authority tries to commit.
+pub(crate) struct ByzantineTestCluster {
+    byzantine: ByzantineAuthority,
+    honest: Vec<HonestAuthority>,
+    // All blocks proposed by the Byzantine authority, including those it withheld.
+    byzantine_blocks: BTreeMap<BlockRef, Bytes>,
+    // The honest blocks of the last round.
+    last_round: Vec<BlockRef>,
+    round: Round,
+}
+
+impl ByzantineTestCluster {
+    pub(crate) fn new(
+        num_authorities: usize,
+        byzantine: AuthorityIndex,
+        schedule: ByzantineSchedule,
+    ) -> Self {
+        let (context, keys) = Context::new_for_test(num_authorities);
+        let mut byzantine_authority = None;
+        let mut honest = vec![];
+        for (i, (_, keypair)) in keys.into_iter().enumerate() {
+            let index = AuthorityIndex::new_for_test(i as u32);
+            let context = Arc::new(context.clone().with_authority_index(index));
+            if index == byzantine {
+                byzantine_authority =
+                    Some(ByzantineAuthority::new(context, keypair, schedule.clone()));
+            } else {
+                honest.push(HonestAuthority::new(context, keypair));
+            }
+        }
+
+        let last_round = genesis_blocks(&context)
+            .iter()
+            .map(|b| b.reference())
+            .collect();
+        Self {
+            byzantine: byzantine_authority.expect("Byzantine authority must be in the committee"),
+            honest,
+            byzantine_blocks: BTreeMap::new(),
+            last_round,
+            round: 0,
+        }
+    }
+
+    pub(crate) fn honest(&self) -> &[HonestAuthority] {
+        &self.honest
+    }
+
+    pub(crate) async fn run_rounds(&mut self, rounds: Round) {
+        for _ in 0..rounds {
+            self.run_round().await;
+        }
+    }
+
+    /// Runs a round, and returns the blocks proposed by the Byzantine authority.
+    pub(crate) async fn run_round(&mut self) -> Vec<ByzantineBlock> {
+        self.round += 1;
+        let round = self.round;
+
+        let honest_blocks: Vec<_> = self.honest.iter_mut().map(|a| a.propose(round)).collect();
+        let byzantine_blocks = self.byzantine.propose(
+            round,
+            &self.last_round,
+            round as BlockTimestampMs * ROUND_DURATION_MS,
+        );
+
+        for authority in &mut self.honest {
+            for block in &honest_blocks {
+                if block.author() != authority.index() {
+                    authority.receive(block.serialized().clone());
+                }
+            }
+            for block in &byzantine_blocks {
+                if block.recipients.contains(&authority.index()) {
+                    authority.receive(block.serialized.clone());
+                }
+            }
+        }
+        self.byzantine_blocks.extend(
+            byzantine_blocks
+                .iter()
+                .map(|b| (b.reference, b.serialized.clone())),
+        );
+        self.synchronize().await;
+
+        for authority in &mut self.honest {
+            authority.try_commit();
+        }
+
+        self.last_round = honest_blocks.iter().map(|b| b.reference()).collect();
+        byzantine_blocks
+    }
+
+    /// Fetches the missing ancestors of every honest authority from the other authorities, in
+    /// committee order, until no more blocks can be fetched.
+    async fn synchronize(&mut self) {
+        loop {
+            let mut progress = false;
+            let peers = self.peers();
+            for own_index in self.honest.iter().map(|a| a.index()).collect::<Vec<_>>() {
+                for &peer in peers.iter().filter(|peer| **peer != own_index) {
+                    let missing = self.honest_mut(own_index).block_manager.missing_blocks();
+                    if missing.is_empty() {
+                        break;
+                    }
+                    let response = self.serve_fetch(peer, &missing);
+                    if response.is_empty() {
+                        continue;
+                    }
+                    let accepted = self
+                        .honest_mut(own_index)
+                        .receive_fetched(peer, missing, response)
+                        .await;
+                    progress |= !accepted.is_empty();
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+    }
+
+    fn peers(&self) -> Vec<AuthorityIndex> {
+        let mut peers: Vec<_> = self.honest.iter().map(|a| a.index()).collect();
+        peers.push(self.byzantine.index());
+        peers.sort();
+        peers
+    }
+
+    fn honest_mut(&mut self, index: AuthorityIndex) -> &mut HonestAuthority {
+        self.honest
+            .iter_mut()
+            .find(|a| a.index() == index)
+            .expect("Authority must be honest")
+    }
+
+    fn serve_fetch(&self, peer: AuthorityIndex, block_refs: &BTreeSet<BlockRef>) -> Vec<Bytes> {
+        if peer == self.byzantine.index() {
+            return block_refs
+                .iter()
+                .filter_map(|block_ref| self.byzantine_blocks.get(block_ref).cloned())
+                .collect();
+        }
+        self.honest
+            .iter()
+            .find(|a| a.index() == peer)
+            .expect("Peer must be in the committee")
+            .serve_fetch(block_refs)
+    }
+
+    /// Asserts that the commit sequences of honest authorities are prefixes of each other, and
+    /// that every honest authority has committed at least `min_commits` sub-dags.
+    pub(crate) fn assert_safe_and_live(&self, min_commits: usize) {
+        let longest = self.honest.iter().max_by_key(|a| a.commits.len()).unwrap();
+        for authority in &self.honest {
+            assert!(
+                authority.commits.len() >= min_commits,
+                "Authority {} committed {} < {min_commits} sub-dags",
+                authority.index(),
+                authority.commits.len(),
+            );
+            for (commit, expected) in authority.commits.iter().zip(&longest.commits) {
+                assert_eq!(
+                    commit.commit_ref,
+                    expected.commit_ref,
+                    "Authority {} diverged from authority {}",
+                    authority.index(),
+                    longest.index(),
+                );
+            }
+        }
+    }
+
+    pub(crate) fn byzantine(&self) -> AuthorityIndex {
+        self.byzantine.index()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NUM_ROUNDS: Round = 30;
+
+    fn authority(letter: char) -> AuthorityIndex {
+        parse_authority(&letter.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let schedule = ByzantineSchedule::parse(
+            "# Split the network, then lie about time.
+             1..=2: equivocate 2
+             3: withhold B C; 3: future-timestamp 5000
+             4: invalid-ancestors too-few",
+        )
+        .unwrap();
+
+        let expected = ByzantineSchedule::default()
+            .at(1..=2, ByzantineAction::Equivocate(2))
+            .at(
+                3..=3,
+                ByzantineAction::Withhold(vec![authority('B'), authority('C')]),
+            )
+            .at(3..=3, ByzantineAction::FutureTimestamp(5000))
+            .at(
+                4..=4,
+                ByzantineAction::InvalidAncestors(InvalidAncestors::TooFew),
+            );
+        assert_eq!(schedule, expected);
+        assert!(schedule.actions(5).is_empty());
+
+        assert!(ByzantineSchedule::parse("1: equivocate").is_err());
+        assert!(ByzantineSchedule::parse("1: withhold b").is_err());
+        assert!(ByzantineSchedule::parse("1: invalid-ancestors bogus").is_err());
+        assert!(ByzantineSchedule::parse("x: equivocate 1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_honest_cluster() {
+        let mut cluster =
+            ByzantineTestCluster::new(4, authority('D'), ByzantineSchedule::default());
+        cluster.run_rounds(NUM_ROUNDS).await;
+
+        // With every leader directly committed, all leaders up to 2 rounds behind are committed.
+        cluster.assert_safe_and_live(NUM_ROUNDS as usize - 2);
+        assert!(cluster.honest().iter().all(|a| a.rejected.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_equivocation() {
+        let schedule =
+            ByzantineSchedule::default().at(1..=NUM_ROUNDS, ByzantineAction::Equivocate(2));
+        let mut cluster = ByzantineTestCluster::new(4, authority('D'), schedule);
+        cluster.run_rounds(NUM_ROUNDS).await;
+
+        // Equivocating blocks are valid, and each honest authority receives a different version.
+        // Versions referenced by other honest blocks are fetched, so some slots end up with
+        // multiple blocks in the DAG.
+        let byzantine = cluster.byzantine();
+        for authority in cluster.honest() {
+            assert!(authority.rejected.is_empty());
+            let byzantine_blocks = authority
+                .accepted
+                .iter()
+                .filter(|b| b.author == byzantine)
+                .count();
+            assert!(byzantine_blocks > NUM_ROUNDS as usize);
+        }
+        cluster.assert_safe_and_live(NUM_ROUNDS as usize / 2);
+    }
+
+    #[tokio::test]
+    async fn test_withheld_blocks_are_synchronized() {
+        let schedule = ByzantineSchedule::default().at(
+            1..=NUM_ROUNDS,
+            ByzantineAction::Withhold(vec![authority('B'), authority('C')]),
+        );
+        let mut cluster = ByzantineTestCluster::new(4, authority('D'), schedule);
+
+        let mut withheld = vec![];
+        for _ in 0..NUM_ROUNDS {
+            withheld.extend(cluster.run_round().await.into_iter().map(|b| b.reference));
+        }
+
+        // Only A receives the blocks of D, but B and C fetch them once A's blocks reference them.
+        // The blocks of the last round are not referenced yet.
+        for authority in cluster.honest() {
+            for block_ref in &withheld[..withheld.len() - 1] {
+                assert!(authority.accepted.contains(block_ref));
+            }
+        }
+        cluster.assert_safe_and_live(NUM_ROUNDS as usize / 2);
+    }
+
+    #[tokio::test]
+    async fn test_future_timestamps_do_not_move_commit_timestamps() {
+        let schedule = ByzantineSchedule::default()
+            .at(1..=NUM_ROUNDS, ByzantineAction::FutureTimestamp(3_600_000));
+        let mut cluster = ByzantineTestCluster::new(4, authority('D'), schedule);
+        cluster.run_rounds(NUM_ROUNDS).await;
+
+        // Blocks with future timestamps are accepted, but the commit timestamp is the median of
+        // the leader's parents by stake, so a single authority can't move it forward.
+        cluster.assert_safe_and_live(NUM_ROUNDS as usize - 2);
+        for authority in cluster.honest() {
+            assert!(authority.rejected.is_empty());
+            for commit in &authority.commits {
+                assert!(
+                    commit.timestamp_ms
+                        <= commit.leader.round as BlockTimestampMs * ROUND_DURATION_MS
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_ancestors_are_rejected() {
+        for kind in [
+            InvalidAncestors::SameRound,
+            InvalidAncestors::Duplicated,
+            InvalidAncestors::TooFew,
+        ] {
+            let schedule =
+                ByzantineSchedule::default().at(5..=5, ByzantineAction::InvalidAncestors(kind));
+            let mut cluster = ByzantineTestCluster::new(4, authority('D'), schedule);
+            cluster.run_rounds(4).await;
+            let invalid = cluster.run_round().await;
+            cluster.run_rounds(NUM_ROUNDS - 5).await;
+
+            let expected = match kind {
+                InvalidAncestors::SameRound => "InvalidAncestorRound",
+                InvalidAncestors::Duplicated => "DuplicatedAncestorsAuthority",
+                InvalidAncestors::TooFew => "InsufficientParentStakes",
+                InvalidAncestors::Unknown => unreachable!(),
+            };
+            for authority in cluster.honest() {
+                assert_eq!(authority.rejected.len(), 1, "{kind:?}");
+                assert_eq!(authority.rejected[0].name(), expected);
+                assert!(!authority.accepted.contains(&invalid[0].reference));
+            }
+
+            // The Byzantine authority recovers by building on its last valid block.
+            cluster.assert_safe_and_live(NUM_ROUNDS as usize / 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_ancestor_is_never_accepted() {
+        let schedule = ByzantineSchedule::default().at(
+            5..=5,
+            ByzantineAction::InvalidAncestors(InvalidAncestors::Unknown),
+        );
+        let mut cluster = ByzantineTestCluster::new(4, authority('D'), schedule);
+        cluster.run_rounds(4).await;
+        let invalid = cluster.run_round().await;
+
+        // The block passes verification, but stays suspended on an ancestor that nobody can
+        // provide.
+        let unknown = BlockRef::new(4, authority('C'), BlockDigest::MAX);
+        for authority in cluster.honest() {
+            assert!(authority.rejected.is_empty());
+            assert!(authority.is_missing(&unknown));
+            assert!(!authority.accepted.contains(&invalid[0].reference));
+        }
+
+        // It does not block the honest authorities.
+        cluster.run_rounds(NUM_ROUNDS - 5).await;
+        for authority in cluster.honest() {
+            assert!(!authority.accepted.contains(&invalid[0].reference));
+        }
+        cluster.assert_safe_and_live(NUM_ROUNDS as usize / 2);
+    }
+}
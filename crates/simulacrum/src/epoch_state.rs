@@ -1,6 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -10,7 +11,8 @@ use sui_config::{
 use sui_execution::Executor;
 use sui_protocol_config::{Chain, ProtocolConfig, ProtocolVersion};
 use sui_types::{
-    committee::{Committee, EpochId},
+    base_types::AuthorityName,
+    committee::{Committee, EpochId, StakeUnit},
     effects::TransactionEffects,
     execution_params::ExecutionOrEarlyError,
     gas::SuiGasStatus,
@@ -25,8 +27,9 @@ use sui_types::{
 
 use crate::SimulatorStore;
 
+#[derive(Clone)]
 pub struct EpochState {
-    epoch_start_state: EpochStartSystemState,
+    epoch_start_state: Arc<EpochStartSystemState>,
     committee: Committee,
     protocol_config: ProtocolConfig,
     limits_metrics: Arc<LimitsMetrics>,
@@ -48,7 +51,7 @@ impl EpochState {
         system_state: SuiSystemState,
         protocol_config: ProtocolConfig,
     ) -> Self {
-        let epoch_start_state = system_state.into_epoch_start_state();
+        let epoch_start_state = Arc::new(system_state.into_epoch_start_state());
         let committee = epoch_start_state.get_sui_committee();
         let registry = prometheus::Registry::new();
         let limits_metrics = Arc::new(LimitsMetrics::new(&registry));
@@ -66,6 +69,12 @@ impl EpochState {
         }
     }
 
+    /// Replace the committee derived from the system state with one made up of `voting_rights`.
+    pub fn with_committee(mut self, voting_rights: BTreeMap<AuthorityName, StakeUnit>) -> Self {
+        self.committee = Committee::new(self.epoch(), voting_rights);
+        self
+    }
+
     pub fn epoch(&self) -> EpochId {
         self.epoch_start_state.epoch()
     }
@@ -10,6 +10,7 @@
 //!
 //! [`Simulacrum`]: crate::Simulacrum
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -20,7 +21,7 @@ use rand::rngs::OsRng;
 use sui_config::verifier_signing_config::VerifierSigningConfig;
 use sui_config::{genesis, transaction_deny_config::TransactionDenyConfig};
 use sui_framework_snapshot::load_bytecode_snapshot;
-use sui_protocol_config::ProtocolVersion;
+use sui_protocol_config::{Chain, ProtocolConfig, ProtocolVersion};
 use sui_storage::blob::{Blob, BlobEncoding};
 use sui_swarm_config::genesis_config::AccountConfig;
 use sui_swarm_config::network_config::NetworkConfig;
@@ -33,11 +34,12 @@ use sui_types::messages_consensus::ConsensusDeterminedVersionAssignments;
 use sui_types::object::{Object, Owner};
 use sui_types::storage::ObjectKey;
 use sui_types::storage::{ObjectStore, ReadStore, RpcStateReader};
+use sui_types::sui_system_state::SuiSystemStateTrait;
 use sui_types::sui_system_state::epoch_start_sui_system_state::EpochStartSystemState;
 use sui_types::transaction::EndOfEpochTransactionKind;
 use sui_types::{
     base_types::{EpochId, SuiAddress},
-    committee::Committee,
+    committee::{Committee, StakeUnit},
     effects::TransactionEffects,
     error::ExecutionError,
     gas_coin::MIST_PER_SUI,
@@ -49,6 +51,7 @@ use sui_types::{
 
 use self::epoch_state::EpochState;
 pub use self::store::SimulatorStore;
+pub use self::store::copy_on_write::{CopyOnWriteStore, StoreSnapshot};
 pub use self::store::in_mem_store::InMemoryStore;
 use self::store::in_mem_store::KeyStore;
 use sui_core::mock_checkpoint_builder::{MockCheckpointBuilder, ValidatorKeypairProvider};
@@ -98,7 +101,7 @@ pub mod store;
 /// [mod]: index.html
 pub struct Simulacrum<R = OsRng, Store: SimulatorStore = InMemoryStore> {
     rng: R,
-    keystore: KeyStore,
+    keystore: Arc<KeyStore>,
     #[allow(unused)]
     genesis: genesis::Genesis,
     store: Store,
@@ -106,6 +109,9 @@ pub struct Simulacrum<R = OsRng, Store: SimulatorStore = InMemoryStore> {
 
     // Epoch specific data
     epoch_state: EpochState,
+    /// When seeded from an existing network whose validator keys are not available, checkpoints
+    /// are signed by this committee of local validators instead of the on-chain one.
+    signing_committee: Option<BTreeMap<AuthorityName, StakeUnit>>,
 
     // Other
     deny_config: TransactionDenyConfig,
@@ -181,9 +187,107 @@ where
     }
 }
 
+impl<R> Simulacrum<R, CopyOnWriteStore>
+where
+    R: rand::RngCore + rand::CryptoRng,
+{
+    /// Create a new Simulacrum instance using the provided `rng`, backed by a
+    /// [`CopyOnWriteStore`].
+    ///
+    /// Unlike [`Simulacrum::new_with_rng`], the resulting instance supports taking cheap
+    /// snapshots of its state with [`Simulacrum::snapshot`], rolling back to them with
+    /// [`Simulacrum::restore`] and branching into independent instances with
+    /// [`Simulacrum::fork`].
+    pub fn new_forkable_with_rng(mut rng: R) -> Self {
+        let config = ConfigBuilder::new_with_temp_dir()
+            .rng(&mut rng)
+            .with_chain_start_timestamp_ms(1)
+            .deterministic_committee_size(NonZeroUsize::new(1).unwrap())
+            .build();
+        let store = CopyOnWriteStore::new(&config.genesis);
+        Self::new_with_network_config_store(&config, rng, store)
+    }
+}
+
+/// A point-in-time capture of the state of a [`Simulacrum`] backed by a [`CopyOnWriteStore`].
+///
+/// Snapshots share their underlying store data with the instance they were taken from, so they
+/// are cheap to take and to hold on to. See [`Simulacrum::snapshot`].
+#[derive(Clone)]
+pub struct SimulacrumSnapshot {
+    store: StoreSnapshot,
+    checkpoint_builder: MockCheckpointBuilder,
+    epoch_state: EpochState,
+}
+
+impl<R> Simulacrum<R, CopyOnWriteStore> {
+    /// Capture the current state of the chain.
+    ///
+    /// This includes the contents of the store, any transactions that have been executed but not
+    /// yet included in a checkpoint, and the current epoch's state. The chain can later be rolled
+    /// back to this point with [`Simulacrum::restore`].
+    ///
+    /// ```
+    /// use simulacrum::Simulacrum;
+    /// use rand::{SeedableRng, rngs::StdRng};
+    /// use sui_types::gas_coin::MIST_PER_SUI;
+    ///
+    /// # fn main() {
+    /// let mut simulacrum = Simulacrum::new_forkable_with_rng(StdRng::seed_from_u64(1));
+    /// let snapshot = simulacrum.snapshot();
+    ///
+    /// let (account, _, _) = simulacrum.funded_account(MIST_PER_SUI).unwrap();
+    /// assert!(simulacrum.store().owned_objects(account).next().is_some());
+    ///
+    /// // Rolling back forgets about the funded account.
+    /// simulacrum.restore(&snapshot);
+    /// assert!(simulacrum.store().owned_objects(account).next().is_none());
+    /// # }
+    /// ```
+    pub fn snapshot(&mut self) -> SimulacrumSnapshot {
+        SimulacrumSnapshot {
+            store: self.store.snapshot(),
+            checkpoint_builder: self.checkpoint_builder.clone(),
+            epoch_state: self.epoch_state.clone(),
+        }
+    }
+
+    /// Roll the chain back to the state captured in `snapshot`.
+    ///
+    /// The snapshot can be restored any number of times, and may also have been taken from
+    /// another instance forked from the same chain. The RNG is not affected.
+    pub fn restore(&mut self, snapshot: &SimulacrumSnapshot) {
+        self.store.restore(&snapshot.store);
+        self.checkpoint_builder = snapshot.checkpoint_builder.clone();
+        self.epoch_state = snapshot.epoch_state.clone();
+    }
+}
+
+impl<R: Clone> Simulacrum<R, CopyOnWriteStore> {
+    /// Create an independent Simulacrum that starts from the current state of this one.
+    ///
+    /// The two instances share all state up to this point, and any transactions, checkpoints or
+    /// epoch changes applied to one of them afterwards are not visible to the other. The fork
+    /// starts with a copy of this instance's RNG, and does not inherit its data ingestion path.
+    pub fn fork(&mut self) -> Self {
+        Self {
+            rng: self.rng.clone(),
+            keystore: self.keystore.clone(),
+            genesis: self.genesis.clone(),
+            store: self.store.fork(),
+            checkpoint_builder: self.checkpoint_builder.clone(),
+            epoch_state: self.epoch_state.clone(),
+            signing_committee: self.signing_committee.clone(),
+            deny_config: self.deny_config.clone(),
+            data_ingestion_path: None,
+            verifier_signing_config: self.verifier_signing_config.clone(),
+        }
+    }
+}
+
 impl<R, S: store::SimulatorStore> Simulacrum<R, S> {
     pub fn new_with_network_config_store(config: &NetworkConfig, rng: R, store: S) -> Self {
-        let keystore = KeyStore::from_network_config(config);
+        let keystore = Arc::new(KeyStore::from_network_config(config));
         let checkpoint_builder = MockCheckpointBuilder::new(config.genesis.checkpoint());
 
         let genesis = &config.genesis;
@@ -196,12 +300,91 @@ impl<R, S: store::SimulatorStore> Simulacrum<R, S> {
             store,
             checkpoint_builder,
             epoch_state,
+            signing_committee: None,
             deny_config: TransactionDenyConfig::default(),
             verifier_signing_config: VerifierSigningConfig::default(),
             data_ingestion_path: None,
         }
     }
 
+    /// Create a new Simulacrum instance on top of a `store` that has been seeded with the state of
+    /// an existing network as of some checkpoint, instead of with `config`'s genesis (see
+    /// [`InMemoryStore::new_from_formal_snapshot`] and [`InMemoryStore::new_from_checkpoint_files`]).
+    ///
+    /// The network's validator keys are not available, so checkpoints created from here on are
+    /// signed by the validators in `config` instead. The genesis gas coins of the accounts in
+    /// `config` are added to the store (unless it already holds them) so that those accounts can be
+    /// used to send transactions.
+    /// `chain` selects the protocol config to execute with, and must match the network the state
+    /// was taken from.
+    pub fn new_with_checkpoint_store(
+        config: &NetworkConfig,
+        rng: R,
+        mut store: S,
+        chain: Chain,
+    ) -> Result<Self> {
+        let keystore = Arc::new(KeyStore::from_network_config(config));
+        let checkpoint = store
+            .get_highest_checkpint()
+            .context("Store has not been seeded with a checkpoint")?;
+
+        let system_state = store.get_system_state();
+        let protocol_version: ProtocolVersion = system_state.protocol_version().into();
+        let protocol_config = ProtocolConfig::get_for_version_if_supported(protocol_version, chain)
+            .with_context(|| {
+                format!("Protocol version {protocol_version:?} is not supported by this binary")
+            })?;
+        let signing_committee: BTreeMap<_, _> = config
+            .genesis
+            .committee()?
+            .voting_rights
+            .into_iter()
+            .collect();
+        let epoch_state = EpochState::new_with_protocol_config(system_state, protocol_config)
+            .with_committee(signing_committee.clone());
+
+        // The system state has already moved on to the next epoch if the seed checkpoint was the
+        // last one in its epoch.
+        let expected_epoch = if checkpoint.end_of_epoch_data.is_some() {
+            checkpoint.epoch + 1
+        } else {
+            checkpoint.epoch
+        };
+        ensure!(
+            epoch_state.epoch() == expected_epoch,
+            "System state is for epoch {} but checkpoint {} is followed by epoch {expected_epoch}",
+            epoch_state.epoch(),
+            checkpoint.sequence_number,
+        );
+
+        let accounts: BTreeSet<_> = keystore.accounts().map(|(address, _)| *address).collect();
+        let gas_coins = config
+            .genesis
+            .objects()
+            .iter()
+            .filter(|object| {
+                store::SimulatorStore::get_object(&store, &object.id()).is_none()
+                    && object.is_gas_coin()
+                    && matches!(object.owner, Owner::AddressOwner(owner) if accounts.contains(&owner))
+            })
+            .map(|object| (object.id(), object.clone()))
+            .collect();
+        store.update_objects(gas_coins, vec![]);
+
+        Ok(Self {
+            rng,
+            keystore,
+            genesis: config.genesis.clone(),
+            store,
+            checkpoint_builder: MockCheckpointBuilder::new(checkpoint),
+            epoch_state,
+            signing_committee: Some(signing_committee),
+            deny_config: TransactionDenyConfig::default(),
+            verifier_signing_config: VerifierSigningConfig::default(),
+            data_ingestion_path: None,
+        })
+    }
+
     /// Attempts to execute the provided Transaction.
     ///
     /// The provided Transaction undergoes the same types of checks that a Validator does prior to
@@ -397,10 +580,13 @@ impl<R, S: store::SimulatorStore> Simulacrum<R, S> {
         self.execute_transaction(tx.into())
             .expect("advancing the epoch cannot fail");
 
-        let new_epoch_state = EpochState::new_with_protocol_config(
+        let mut new_epoch_state = EpochState::new_with_protocol_config(
             self.store.get_system_state(),
             self.epoch_state.protocol_config().clone(),
         );
+        if let Some(signing_committee) = &self.signing_committee {
+            new_epoch_state = new_epoch_state.with_committee(signing_committee.clone());
+        }
         let end_of_epoch_data = EndOfEpochData {
             next_epoch_committee: new_epoch_state.committee().voting_rights.clone(),
             next_epoch_protocol_version,
@@ -536,7 +722,10 @@ impl<R, S: store::SimulatorStore> Simulacrum<R, S> {
 
     pub fn set_data_ingestion_path(&mut self, data_ingestion_path: PathBuf) {
         self.data_ingestion_path = Some(data_ingestion_path);
-        let checkpoint = self.store.get_checkpoint_by_sequence_number(0).unwrap();
+        // A store seeded from an existing network's checkpoint has no genesis checkpoint to write.
+        let Some(checkpoint) = self.store.get_checkpoint_by_sequence_number(0) else {
+            return;
+        };
         let contents = self
             .store
             .get_checkpoint_contents(&checkpoint.content_digest);
@@ -748,7 +937,7 @@ impl<T: Send + Sync, V: store::SimulatorStore + Send + Sync> RpcStateReader for
     }
 }
 
-impl Simulacrum {
+impl<R, S: store::SimulatorStore> Simulacrum<R, S> {
     /// Generate a random transfer transaction.
     /// TODO: This is here today to make it easier to write tests. But we should utilize all the
     /// existing code for generating transactions in sui-test-transaction-builder by defining a trait
@@ -788,7 +977,18 @@ impl Simulacrum {
 mod tests {
     use std::time::Duration;
 
+    use fastcrypto::hash::MultisetHash;
     use rand::{SeedableRng, rngs::StdRng};
+    use sui_config::object_storage_config::{ObjectStoreConfig, ObjectStoreType};
+    use sui_core::authority::authority_store_tables::AuthorityPerpetualTables;
+    use sui_snapshot::StateSnapshotWriterV1;
+    use sui_storage::FileCompression;
+    use sui_types::full_checkpoint_content::CheckpointData;
+    use sui_types::gas::GasCostSummary;
+    use sui_types::global_state_hash::GlobalStateHash;
+    use sui_types::messages_checkpoint::{
+        CertifiedCheckpointSummary, CheckpointSummary, ECMHLiveObjectSetDigest,
+    };
     use sui_types::{
         base_types::SuiAddress, effects::TransactionEffectsAPI, gas_coin::GasCoin,
         transaction::TransactionDataAPI,
@@ -900,4 +1100,259 @@ mod tests {
             assert_eq!(checkpoint.network_total_transactions, 2); // genesis + 1 user txn
         };
     }
+
+    #[test]
+    fn seed_from_checkpoint_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sim = Simulacrum::new_with_rng(StdRng::from_seed([9; 32]));
+        sim.set_data_ingestion_path(dir.path().to_path_buf());
+
+        let recipient = SuiAddress::random_for_testing_only();
+        let (tx, transfer_amount) = sim.transfer_txn(recipient);
+        sim.execute_transaction(tx).unwrap();
+        sim.advance_clock(Duration::from_millis(10));
+        let checkpoint = sim.create_checkpoint();
+
+        let store =
+            InMemoryStore::new_from_checkpoint_files(dir.path(), checkpoint.sequence_number)
+                .unwrap();
+
+        // The files must go back to genesis.
+        let empty = tempfile::tempdir().unwrap();
+        let err = InMemoryStore::new_from_checkpoint_files(empty.path(), 0).unwrap_err();
+        assert!(err.to_string().contains("genesis"), "{err}");
+
+        let mut rng = StdRng::from_seed([1; 32]);
+        let config = ConfigBuilder::new_with_temp_dir()
+            .rng(&mut rng)
+            .deterministic_committee_size(NonZeroUsize::new(1).unwrap())
+            .build();
+        let mut seeded =
+            Simulacrum::new_with_checkpoint_store(&config, rng, store, Chain::Unknown).unwrap();
+
+        // The seeded chain picks up exactly where the original left off.
+        assert_eq!(
+            seeded.store().get_clock().timestamp_ms(),
+            sim.store().get_clock().timestamp_ms()
+        );
+        assert_eq!(
+            transfer_amount,
+            seeded
+                .store()
+                .owned_objects(recipient)
+                .next()
+                .and_then(|object| GasCoin::try_from(&object).ok())
+                .unwrap()
+                .value()
+        );
+
+        // ...and can be driven forward with local accounts and validators.
+        let (tx, _) = seeded.transfer_txn(recipient);
+        let effects = seeded.execute_transaction(tx).unwrap().0;
+        assert!(effects.status().is_ok());
+        let next = seeded.create_checkpoint();
+        assert_eq!(next.sequence_number, checkpoint.sequence_number + 1);
+        assert_eq!(next.previous_digest, Some(*checkpoint.digest()));
+
+        seeded.advance_epoch(AdvanceEpochConfig::default());
+        assert_eq!(seeded.epoch_state.epoch(), 1);
+    }
+
+    #[tokio::test]
+    async fn seed_from_formal_snapshot() {
+        let mut rng = StdRng::from_seed([1; 32]);
+        let config = ConfigBuilder::new_with_temp_dir()
+            .rng(&mut rng)
+            .deterministic_committee_size(NonZeroUsize::new(1).unwrap())
+            .build();
+        let objects = config.genesis.objects();
+
+        // Write a formal snapshot of the genesis objects at the end of epoch 0.
+        let db_dir = tempfile::tempdir().unwrap();
+        let staging_dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let perpetual_db = Arc::new(AuthorityPerpetualTables::open(db_dir.path(), None, None));
+        for object in objects {
+            perpetual_db
+                .insert_object_test_only(object.clone())
+                .unwrap();
+        }
+        let mut root_state_hash = GlobalStateHash::default();
+        root_state_hash.insert_all(objects.iter().map(|object| object.digest()));
+        let root_digest = ECMHLiveObjectSetDigest::from(root_state_hash.digest());
+
+        let store_config = |dir: &tempfile::TempDir| ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        StateSnapshotWriterV1::new(
+            &store_config(&staging_dir),
+            &store_config(&snapshot_dir),
+            FileCompression::Zstd,
+            NonZeroUsize::new(1).unwrap(),
+        )
+        .await
+        .unwrap()
+        .write(
+            0,
+            perpetual_db,
+            root_digest.clone(),
+            ChainIdentifier::from(*config.genesis.checkpoint().digest()),
+        )
+        .await
+        .unwrap();
+
+        // The last checkpoint of epoch 0, committing to the snapshot's live object set.
+        let checkpoint_dir = tempfile::tempdir().unwrap();
+        let write_checkpoint = |name: &str, commitment: ECMHLiveObjectSetDigest| {
+            let (committee, keys) = Committee::new_simple_test_committee();
+            let contents = CheckpointContents::new_with_digests_only_for_tests(vec![]);
+            let summary = CheckpointSummary::new(
+                &ProtocolConfig::get_for_max_version_UNSAFE(),
+                0,
+                5,
+                0,
+                &contents,
+                None,
+                GasCostSummary::default(),
+                Some(EndOfEpochData {
+                    next_epoch_committee: committee.voting_rights.clone(),
+                    next_epoch_protocol_version: ProtocolVersion::MAX,
+                    epoch_commitments: vec![commitment.into()],
+                }),
+                0,
+                Vec::new(),
+                Vec::new(),
+            );
+            let checkpoint_data = CheckpointData {
+                checkpoint_summary: CertifiedCheckpointSummary::new_from_keypairs_for_testing(
+                    summary, &keys, &committee,
+                ),
+                checkpoint_contents: contents,
+                transactions: vec![],
+            };
+            let path = checkpoint_dir.path().join(name);
+            let blob = Blob::encode(&checkpoint_data, BlobEncoding::Bcs).unwrap();
+            std::fs::write(&path, blob.to_bytes()).unwrap();
+            path
+        };
+
+        let checkpoint_file = write_checkpoint("5.chk", root_digest);
+        let store = InMemoryStore::new_from_formal_snapshot(
+            snapshot_dir.path().to_path_buf(),
+            0,
+            &checkpoint_file,
+        )
+        .unwrap();
+        assert_eq!(store.get_highest_checkpint().unwrap().sequence_number, 5);
+        for object in objects {
+            assert_eq!(
+                store.get_object(&object.id()).unwrap().digest(),
+                object.digest()
+            );
+        }
+        assert_eq!(store.get_system_state().epoch(), 0);
+
+        // The snapshot is rejected if it does not match the end of epoch state commitment...
+        let wrong_commitment = ECMHLiveObjectSetDigest::from(GlobalStateHash::default().digest());
+        let checkpoint_file = write_checkpoint("wrong.chk", wrong_commitment);
+        let err = InMemoryStore::new_from_formal_snapshot(
+            snapshot_dir.path().to_path_buf(),
+            0,
+            &checkpoint_file,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+
+        // ...or if the checkpoint is from another epoch.
+        assert!(
+            InMemoryStore::new_from_formal_snapshot(
+                snapshot_dir.path().to_path_buf(),
+                1,
+                &checkpoint_file,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut sim = Simulacrum::new_forkable_with_rng(StdRng::from_seed([9; 32]));
+        let recipient = SuiAddress::random_for_testing_only();
+
+        let snapshot = sim.snapshot();
+        let start_checkpoint = *sim
+            .store()
+            .get_highest_checkpint()
+            .unwrap()
+            .sequence_number();
+        let start_clock = sim.store().get_clock().timestamp_ms();
+
+        let (tx, _) = sim.transfer_txn(recipient);
+        sim.execute_transaction(tx).unwrap();
+        sim.advance_clock(Duration::from_millis(10));
+        sim.create_checkpoint();
+        sim.advance_epoch(AdvanceEpochConfig::default());
+        assert!(sim.store().owned_objects(recipient).next().is_some());
+
+        sim.restore(&snapshot);
+        assert!(sim.store().owned_objects(recipient).next().is_none());
+        assert_eq!(sim.store().get_clock().timestamp_ms(), start_clock);
+        assert_eq!(
+            *sim.store()
+                .get_highest_checkpint()
+                .unwrap()
+                .sequence_number(),
+            start_checkpoint
+        );
+        assert_eq!(sim.epoch_state.epoch(), 0);
+
+        // The chain keeps working after being rolled back, and the snapshot can be reused.
+        let (tx, _) = sim.transfer_txn(recipient);
+        sim.execute_transaction(tx).unwrap();
+        let checkpoint = sim.create_checkpoint();
+        assert_eq!(*checkpoint.sequence_number(), start_checkpoint + 1);
+
+        sim.restore(&snapshot);
+        assert!(sim.store().owned_objects(recipient).next().is_none());
+    }
+
+    #[test]
+    fn fork() {
+        let mut sim = Simulacrum::new_forkable_with_rng(StdRng::from_seed([9; 32]));
+        let recipient = SuiAddress::random_for_testing_only();
+
+        let (tx, _) = sim.transfer_txn(recipient);
+        sim.execute_transaction(tx).unwrap();
+        sim.create_checkpoint();
+
+        let mut forked = sim.fork();
+
+        // Changes on the fork are not visible on the original.
+        let (tx, transfer_amount) = forked.transfer_txn(recipient);
+        forked.execute_transaction(tx).unwrap();
+        let forked_checkpoint = forked.create_checkpoint();
+        assert_eq!(forked.store().owned_objects(recipient).count(), 2);
+        assert_eq!(sim.store().owned_objects(recipient).count(), 1);
+
+        // ...and vice versa.
+        sim.advance_clock(Duration::from_millis(10));
+        let checkpoint = sim.create_checkpoint();
+        assert_eq!(
+            checkpoint.sequence_number(),
+            forked_checkpoint.sequence_number()
+        );
+        assert_ne!(checkpoint.digest(), forked_checkpoint.digest());
+        assert_eq!(
+            forked.store().get_clock().timestamp_ms() + 10,
+            sim.store().get_clock().timestamp_ms()
+        );
+        assert!(
+            forked
+                .store()
+                .owned_objects(recipient)
+                .any(|object| GasCoin::try_from(&object).unwrap().value() == transfer_amount)
+        );
+    }
 }
@@ -0,0 +1,678 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use move_binary_format::CompiledModule;
+use move_bytecode_utils::module_cache::GetModule;
+use move_core_types::{language_storage::ModuleId, resolver::ModuleResolver};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use sui_config::genesis;
+use sui_types::error::SuiErrorKind;
+use sui_types::storage::{PackageObject, get_module, load_package_object_from_object_store};
+use sui_types::{
+    base_types::{ObjectID, SequenceNumber, SuiAddress},
+    committee::{Committee, EpochId},
+    digests::{ObjectDigest, TransactionDigest},
+    effects::{TransactionEffects, TransactionEffectsAPI, TransactionEvents},
+    error::SuiError,
+    messages_checkpoint::{
+        CheckpointContents, CheckpointContentsDigest, CheckpointDigest, CheckpointSequenceNumber,
+        VerifiedCheckpoint,
+    },
+    object::{Object, Owner},
+    storage::{BackingPackageStore, ChildObjectResolver, ObjectStore, ParentSync},
+    transaction::VerifiedTransaction,
+};
+
+use super::SimulatorStore;
+use super::in_mem_store::InMemoryStore;
+
+/// The set of writes made on top of the layers below it.
+///
+/// Objects that were deleted in this layer are recorded with a `None` live version so that they
+/// shadow any live version held by a lower layer.
+#[derive(Debug, Default)]
+struct Layer {
+    // Checkpoint data
+    checkpoints: BTreeMap<CheckpointSequenceNumber, VerifiedCheckpoint>,
+    checkpoint_digest_to_sequence_number: HashMap<CheckpointDigest, CheckpointSequenceNumber>,
+    checkpoint_contents: HashMap<CheckpointContentsDigest, CheckpointContents>,
+
+    // Transaction data
+    transactions: HashMap<TransactionDigest, VerifiedTransaction>,
+    effects: HashMap<TransactionDigest, TransactionEffects>,
+    events: HashMap<TransactionDigest, TransactionEvents>,
+
+    // Committee data
+    epoch_to_committee: BTreeMap<EpochId, Committee>,
+
+    // Object data
+    live_objects: HashMap<ObjectID, Option<SequenceNumber>>,
+    objects: HashMap<(ObjectID, SequenceNumber), Object>,
+}
+
+impl Layer {
+    fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+            && self.checkpoint_contents.is_empty()
+            && self.transactions.is_empty()
+            && self.effects.is_empty()
+            && self.events.is_empty()
+            && self.epoch_to_committee.is_empty()
+            && self.live_objects.is_empty()
+            && self.objects.is_empty()
+    }
+
+    /// Apply the writes of `newer`, the layer directly above this one, to this layer.
+    fn merge(&mut self, newer: Layer) {
+        self.checkpoints.extend(newer.checkpoints);
+        self.checkpoint_digest_to_sequence_number
+            .extend(newer.checkpoint_digest_to_sequence_number);
+        self.checkpoint_contents.extend(newer.checkpoint_contents);
+        self.transactions.extend(newer.transactions);
+        self.effects.extend(newer.effects);
+        self.events.extend(newer.events);
+        self.epoch_to_committee.extend(newer.epoch_to_committee);
+        self.live_objects.extend(newer.live_objects);
+        self.objects.extend(newer.objects);
+    }
+}
+
+/// A point-in-time capture of a [`CopyOnWriteStore`].
+///
+/// Taking a snapshot freezes the store's pending writes into an immutable layer which is shared
+/// (not copied) between the store and the snapshot, so snapshots are cheap to take, clone and
+/// restore from.
+#[derive(Clone, Debug)]
+pub struct StoreSnapshot {
+    base: Arc<InMemoryStore>,
+    layers: Vec<Arc<Layer>>,
+}
+
+/// A [`SimulatorStore`] which layers copy-on-write overlays on top of a shared [`InMemoryStore`].
+///
+/// All writes go to a mutable head layer. Reads consult the head first, then each frozen layer
+/// from newest to oldest, and finally the base store. Frozen layers and the base are never
+/// modified, which allows them to be shared between any number of snapshots and forks.
+#[derive(Debug)]
+pub struct CopyOnWriteStore {
+    base: Arc<InMemoryStore>,
+    /// Frozen layers, ordered from oldest to newest.
+    layers: Vec<Arc<Layer>>,
+    head: Layer,
+}
+
+impl CopyOnWriteStore {
+    pub fn new(genesis: &genesis::Genesis) -> Self {
+        Self::new_with_base(InMemoryStore::new(genesis))
+    }
+
+    pub fn new_with_base(base: InMemoryStore) -> Self {
+        Self {
+            base: Arc::new(base),
+            layers: vec![],
+            head: Layer::default(),
+        }
+    }
+
+    pub fn from_snapshot(snapshot: &StoreSnapshot) -> Self {
+        Self {
+            base: snapshot.base.clone(),
+            layers: snapshot.layers.clone(),
+            head: Layer::default(),
+        }
+    }
+
+    /// Capture the current state of the store.
+    ///
+    /// Any writes made since the last snapshot are frozen into a new shared layer, subsequent
+    /// writes go to a fresh head layer and are not visible through the returned snapshot. Layers
+    /// that are no longer shared with any snapshot or fork are collapsed into one, so the number
+    /// of layers is bounded by the number of snapshots that are still held.
+    pub fn snapshot(&mut self) -> StoreSnapshot {
+        if !self.head.is_empty() {
+            let head = std::mem::take(&mut self.head);
+            self.layers.push(Arc::new(head));
+        }
+        self.compact();
+
+        StoreSnapshot {
+            base: self.base.clone(),
+            layers: self.layers.clone(),
+        }
+    }
+
+    /// Roll the store back (or forward) to the state captured in `snapshot`, discarding any
+    /// writes that have not been captured by a snapshot.
+    pub fn restore(&mut self, snapshot: &StoreSnapshot) {
+        self.base = snapshot.base.clone();
+        self.layers = snapshot.layers.clone();
+        self.head = Layer::default();
+    }
+
+    /// Create an independent store which starts from the current state of this one.
+    pub fn fork(&mut self) -> Self {
+        Self::from_snapshot(&self.snapshot())
+    }
+
+    /// Collapse the frozen layers that only this store refers to into a single layer.
+    ///
+    /// Snapshots and forks hold a prefix of the layers that existed when they were taken, so once
+    /// they are dropped (or restored from, replacing the store's layers), the layers above the
+    /// newest one still shared form a suffix that can be merged without changing what any
+    /// snapshot sees.
+    fn compact(&mut self) {
+        let unshared = self
+            .layers
+            .iter()
+            .rposition(|layer| Arc::strong_count(layer) > 1)
+            .map_or(0, |shared| shared + 1);
+        if self.layers.len() - unshared < 2 {
+            return;
+        }
+
+        let merged = self
+            .layers
+            .drain(unshared..)
+            .map(|layer| Arc::try_unwrap(layer).expect("layer should not be shared"))
+            .reduce(|mut older, newer| {
+                older.merge(newer);
+                older
+            })
+            .expect("there are at least two layers to merge");
+        self.layers.push(Arc::new(merged));
+    }
+
+    /// Iterate over all overlay layers, from newest to oldest.
+    fn overlays(&self) -> impl Iterator<Item = &Layer> {
+        std::iter::once(&self.head).chain(self.layers.iter().rev().map(|layer| layer.as_ref()))
+    }
+
+    pub fn get_checkpoint_by_sequence_number(
+        &self,
+        sequence_number: CheckpointSequenceNumber,
+    ) -> Option<&VerifiedCheckpoint> {
+        self.overlays()
+            .find_map(|layer| layer.checkpoints.get(&sequence_number))
+            .or_else(|| self.base.get_checkpoint_by_sequence_number(sequence_number))
+    }
+
+    pub fn get_checkpoint_by_digest(
+        &self,
+        digest: &CheckpointDigest,
+    ) -> Option<&VerifiedCheckpoint> {
+        self.overlays()
+            .find_map(|layer| layer.checkpoint_digest_to_sequence_number.get(digest))
+            .and_then(|sequence_number| self.get_checkpoint_by_sequence_number(*sequence_number))
+            .or_else(|| self.base.get_checkpoint_by_digest(digest))
+    }
+
+    pub fn get_highest_checkpint(&self) -> Option<&VerifiedCheckpoint> {
+        // Checkpoints are created in order, so the newest layer holding any checkpoint holds the
+        // highest one.
+        self.overlays()
+            .find_map(|layer| {
+                layer
+                    .checkpoints
+                    .last_key_value()
+                    .map(|(_, checkpoint)| checkpoint)
+            })
+            .or_else(|| self.base.get_highest_checkpint())
+    }
+
+    pub fn get_checkpoint_contents(
+        &self,
+        digest: &CheckpointContentsDigest,
+    ) -> Option<&CheckpointContents> {
+        self.overlays()
+            .find_map(|layer| layer.checkpoint_contents.get(digest))
+            .or_else(|| self.base.get_checkpoint_contents(digest))
+    }
+
+    pub fn get_committee_by_epoch(&self, epoch: EpochId) -> Option<&Committee> {
+        self.overlays()
+            .find_map(|layer| layer.epoch_to_committee.get(&epoch))
+            .or_else(|| self.base.get_committee_by_epoch(epoch))
+    }
+
+    pub fn get_transaction(&self, digest: &TransactionDigest) -> Option<&VerifiedTransaction> {
+        self.overlays()
+            .find_map(|layer| layer.transactions.get(digest))
+            .or_else(|| self.base.get_transaction(digest))
+    }
+
+    pub fn get_transaction_effects(
+        &self,
+        digest: &TransactionDigest,
+    ) -> Option<&TransactionEffects> {
+        self.overlays()
+            .find_map(|layer| layer.effects.get(digest))
+            .or_else(|| self.base.get_transaction_effects(digest))
+    }
+
+    pub fn get_transaction_events(&self, digest: &TransactionDigest) -> Option<&TransactionEvents> {
+        self.overlays()
+            .find_map(|layer| layer.events.get(digest))
+            .or_else(|| self.base.get_transaction_events(digest))
+    }
+
+    pub fn get_object(&self, id: &ObjectID) -> Option<&Object> {
+        match self.overlays().find_map(|layer| layer.live_objects.get(id)) {
+            Some(Some(version)) => self.get_object_at_version(id, *version),
+            // Deleted in an overlay
+            Some(None) => None,
+            None => self.base.get_object(id),
+        }
+    }
+
+    pub fn get_object_at_version(&self, id: &ObjectID, version: SequenceNumber) -> Option<&Object> {
+        self.overlays()
+            .find_map(|layer| layer.objects.get(&(*id, version)))
+            .or_else(|| self.base.get_object_at_version(id, version))
+    }
+
+    pub fn get_system_state(&self) -> sui_types::sui_system_state::SuiSystemState {
+        sui_types::sui_system_state::get_sui_system_state(self).expect("system state must exist")
+    }
+
+    pub fn get_clock(&self) -> sui_types::clock::Clock {
+        self.get_object(&sui_types::SUI_CLOCK_OBJECT_ID)
+            .expect("clock should exist")
+            .to_rust()
+            .expect("clock object should deserialize")
+    }
+
+    pub fn owned_objects(&self, owner: SuiAddress) -> impl Iterator<Item = &Object> {
+        // The newest entry for an object across all overlays determines whether (and at which
+        // version) it is live.
+        let mut overlaid: HashMap<ObjectID, Option<SequenceNumber>> = HashMap::new();
+        for layer in self.overlays() {
+            for (id, version) in &layer.live_objects {
+                overlaid.entry(*id).or_insert(*version);
+            }
+        }
+
+        let from_overlays = overlaid
+            .iter()
+            .filter_map(|(id, version)| self.get_object_at_version(id, (*version)?))
+            .filter(
+                move |object| matches!(object.owner, Owner::AddressOwner(addr) if addr == owner),
+            )
+            .collect::<Vec<_>>();
+
+        let from_base = self
+            .base
+            .owned_objects(owner)
+            .filter(|object| !overlaid.contains_key(&object.id()))
+            .collect::<Vec<_>>();
+
+        from_overlays.into_iter().chain(from_base)
+    }
+}
+
+impl CopyOnWriteStore {
+    pub fn insert_checkpoint(&mut self, checkpoint: VerifiedCheckpoint) {
+        if let Some(end_of_epoch_data) = &checkpoint.data().end_of_epoch_data {
+            let next_committee = end_of_epoch_data
+                .next_epoch_committee
+                .iter()
+                .cloned()
+                .collect();
+            let committee =
+                Committee::new(checkpoint.epoch().checked_add(1).unwrap(), next_committee);
+            self.insert_committee(committee);
+        }
+
+        self.head
+            .checkpoint_digest_to_sequence_number
+            .insert(*checkpoint.digest(), *checkpoint.sequence_number());
+        self.head
+            .checkpoints
+            .insert(*checkpoint.sequence_number(), checkpoint);
+    }
+
+    pub fn insert_checkpoint_contents(&mut self, contents: CheckpointContents) {
+        self.head
+            .checkpoint_contents
+            .insert(*contents.digest(), contents);
+    }
+
+    pub fn insert_committee(&mut self, committee: Committee) {
+        let epoch = committee.epoch;
+
+        if self.get_committee_by_epoch(epoch).is_some() {
+            return;
+        }
+
+        if epoch == 0 || self.get_committee_by_epoch(epoch - 1).is_some() {
+            self.head.epoch_to_committee.insert(epoch, committee);
+        } else {
+            panic!("committee was inserted into EpochCommitteeMap out of order");
+        }
+    }
+
+    pub fn insert_executed_transaction(
+        &mut self,
+        transaction: VerifiedTransaction,
+        effects: TransactionEffects,
+        events: TransactionEvents,
+        written_objects: BTreeMap<ObjectID, Object>,
+    ) {
+        let deleted_objects = effects.deleted();
+        let tx_digest = *effects.transaction_digest();
+        self.insert_transaction(transaction);
+        self.insert_transaction_effects(effects);
+        self.insert_events(&tx_digest, events);
+        self.update_objects(written_objects, deleted_objects);
+    }
+
+    pub fn insert_transaction(&mut self, transaction: VerifiedTransaction) {
+        self.head
+            .transactions
+            .insert(*transaction.digest(), transaction);
+    }
+
+    pub fn insert_transaction_effects(&mut self, effects: TransactionEffects) {
+        self.head
+            .effects
+            .insert(*effects.transaction_digest(), effects);
+    }
+
+    pub fn insert_events(&mut self, tx_digest: &TransactionDigest, events: TransactionEvents) {
+        self.head.events.insert(*tx_digest, events);
+    }
+
+    pub fn update_objects(
+        &mut self,
+        written_objects: BTreeMap<ObjectID, Object>,
+        deleted_objects: Vec<(ObjectID, SequenceNumber, ObjectDigest)>,
+    ) {
+        for (object_id, _, _) in deleted_objects {
+            self.head.live_objects.insert(object_id, None);
+        }
+
+        for (object_id, object) in written_objects {
+            let version = object.version();
+            self.head.live_objects.insert(object_id, Some(version));
+            self.head.objects.insert((object_id, version), object);
+        }
+    }
+}
+
+impl BackingPackageStore for CopyOnWriteStore {
+    fn get_package_object(
+        &self,
+        package_id: &ObjectID,
+    ) -> sui_types::error::SuiResult<Option<PackageObject>> {
+        load_package_object_from_object_store(self, package_id)
+    }
+}
+
+impl ChildObjectResolver for CopyOnWriteStore {
+    fn read_child_object(
+        &self,
+        parent: &ObjectID,
+        child: &ObjectID,
+        child_version_upper_bound: SequenceNumber,
+    ) -> sui_types::error::SuiResult<Option<Object>> {
+        let child_object = match crate::store::SimulatorStore::get_object(self, child) {
+            None => return Ok(None),
+            Some(obj) => obj,
+        };
+
+        let parent = *parent;
+        if child_object.owner != Owner::ObjectOwner(parent.into()) {
+            return Err(SuiErrorKind::InvalidChildObjectAccess {
+                object: *child,
+                given_parent: parent,
+                actual_owner: child_object.owner.clone(),
+            }
+            .into());
+        }
+
+        if child_object.version() > child_version_upper_bound {
+            return Err(SuiErrorKind::UnsupportedFeatureError {
+                error:
+                    "TODO CopyOnWriteStore::read_child_object does not yet support bounded reads"
+                        .to_owned(),
+            }
+            .into());
+        }
+
+        Ok(Some(child_object))
+    }
+
+    fn get_object_received_at_version(
+        &self,
+        owner: &ObjectID,
+        receiving_object_id: &ObjectID,
+        receive_object_at_version: SequenceNumber,
+        _epoch_id: EpochId,
+    ) -> sui_types::error::SuiResult<Option<Object>> {
+        let recv_object = match crate::store::SimulatorStore::get_object(self, receiving_object_id)
+        {
+            None => return Ok(None),
+            Some(obj) => obj,
+        };
+        if recv_object.owner != Owner::AddressOwner((*owner).into()) {
+            return Ok(None);
+        }
+
+        if recv_object.version() != receive_object_at_version {
+            return Ok(None);
+        }
+        Ok(Some(recv_object))
+    }
+}
+
+impl GetModule for CopyOnWriteStore {
+    type Error = SuiError;
+    type Item = CompiledModule;
+
+    fn get_module_by_id(&self, id: &ModuleId) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self
+            .get_module(id)?
+            .map(|bytes| CompiledModule::deserialize_with_defaults(&bytes).unwrap()))
+    }
+}
+
+impl ModuleResolver for CopyOnWriteStore {
+    type Error = SuiError;
+
+    fn get_module(&self, module_id: &ModuleId) -> Result<Option<Vec<u8>>, Self::Error> {
+        get_module(self, module_id)
+    }
+}
+
+impl ObjectStore for CopyOnWriteStore {
+    fn get_object(&self, object_id: &ObjectID) -> Option<Object> {
+        self.get_object(object_id).cloned()
+    }
+
+    fn get_object_by_key(
+        &self,
+        object_id: &ObjectID,
+        version: sui_types::base_types::VersionNumber,
+    ) -> Option<Object> {
+        self.get_object_at_version(object_id, version).cloned()
+    }
+}
+
+impl ParentSync for CopyOnWriteStore {
+    fn get_latest_parent_entry_ref_deprecated(
+        &self,
+        _object_id: ObjectID,
+    ) -> Option<sui_types::base_types::ObjectRef> {
+        panic!("Never called in newer protocol versions")
+    }
+}
+
+impl SimulatorStore for CopyOnWriteStore {
+    fn get_checkpoint_by_sequence_number(
+        &self,
+        sequence_number: CheckpointSequenceNumber,
+    ) -> Option<VerifiedCheckpoint> {
+        self.get_checkpoint_by_sequence_number(sequence_number)
+            .cloned()
+    }
+
+    fn get_checkpoint_by_digest(&self, digest: &CheckpointDigest) -> Option<VerifiedCheckpoint> {
+        self.get_checkpoint_by_digest(digest).cloned()
+    }
+
+    fn get_highest_checkpint(&self) -> Option<VerifiedCheckpoint> {
+        self.get_highest_checkpint().cloned()
+    }
+
+    fn get_checkpoint_contents(
+        &self,
+        digest: &CheckpointContentsDigest,
+    ) -> Option<CheckpointContents> {
+        self.get_checkpoint_contents(digest).cloned()
+    }
+
+    fn get_committee_by_epoch(&self, epoch: EpochId) -> Option<Committee> {
+        self.get_committee_by_epoch(epoch).cloned()
+    }
+
+    fn get_transaction(&self, digest: &TransactionDigest) -> Option<VerifiedTransaction> {
+        self.get_transaction(digest).cloned()
+    }
+
+    fn get_transaction_effects(&self, digest: &TransactionDigest) -> Option<TransactionEffects> {
+        self.get_transaction_effects(digest).cloned()
+    }
+
+    fn get_transaction_events(&self, digest: &TransactionDigest) -> Option<TransactionEvents> {
+        self.get_transaction_events(digest).cloned()
+    }
+
+    fn get_object(&self, id: &ObjectID) -> Option<Object> {
+        self.get_object(id).cloned()
+    }
+
+    fn get_object_at_version(&self, id: &ObjectID, version: SequenceNumber) -> Option<Object> {
+        self.get_object_at_version(id, version).cloned()
+    }
+
+    fn get_system_state(&self) -> sui_types::sui_system_state::SuiSystemState {
+        self.get_system_state()
+    }
+
+    fn get_clock(&self) -> sui_types::clock::Clock {
+        self.get_clock()
+    }
+
+    fn owned_objects(&self, owner: SuiAddress) -> Box<dyn Iterator<Item = Object> + '_> {
+        Box::new(self.owned_objects(owner).cloned())
+    }
+
+    fn insert_checkpoint(&mut self, checkpoint: VerifiedCheckpoint) {
+        self.insert_checkpoint(checkpoint)
+    }
+
+    fn insert_checkpoint_contents(&mut self, contents: CheckpointContents) {
+        self.insert_checkpoint_contents(contents)
+    }
+
+    fn insert_committee(&mut self, committee: Committee) {
+        self.insert_committee(committee)
+    }
+
+    fn insert_executed_transaction(
+        &mut self,
+        transaction: VerifiedTransaction,
+        effects: TransactionEffects,
+        events: TransactionEvents,
+        written_objects: BTreeMap<ObjectID, Object>,
+    ) {
+        self.insert_executed_transaction(transaction, effects, events, written_objects)
+    }
+
+    fn insert_transaction(&mut self, transaction: VerifiedTransaction) {
+        self.insert_transaction(transaction)
+    }
+
+    fn insert_transaction_effects(&mut self, effects: TransactionEffects) {
+        self.insert_transaction_effects(effects)
+    }
+
+    fn insert_events(&mut self, tx_digest: &TransactionDigest, events: TransactionEvents) {
+        self.insert_events(tx_digest, events)
+    }
+
+    fn update_objects(
+        &mut self,
+        written_objects: BTreeMap<ObjectID, Object>,
+        deleted_objects: Vec<(ObjectID, SequenceNumber, ObjectDigest)>,
+    ) {
+        self.update_objects(written_objects, deleted_objects)
+    }
+
+    fn backing_store(&self) -> &dyn sui_types::storage::BackingStore {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sui_swarm_config::network_config_builder::ConfigBuilder;
+
+    fn write_object(store: &mut CopyOnWriteStore, id: ObjectID, version: u64) {
+        let object = Object::with_id_owner_version_for_testing(
+            id,
+            SequenceNumber::from_u64(version),
+            Owner::AddressOwner(SuiAddress::ZERO),
+        );
+        store.update_objects(BTreeMap::from([(id, object)]), vec![]);
+    }
+
+    fn version(store: &CopyOnWriteStore, id: &ObjectID) -> Option<u64> {
+        store.get_object(id).map(|object| object.version().value())
+    }
+
+    #[test]
+    fn compact_unshared_layers() {
+        let config = ConfigBuilder::new_with_temp_dir().build();
+        let mut store = CopyOnWriteStore::new(&config.genesis);
+        let id = ObjectID::random();
+
+        // Snapshots that are dropped right away leave nothing to keep apart.
+        for v in 1..=10 {
+            write_object(&mut store, id, v);
+            store.snapshot();
+        }
+        assert_eq!(store.layers.len(), 1);
+        assert_eq!(version(&store, &id), Some(10));
+        assert_eq!(
+            store
+                .get_object_at_version(&id, SequenceNumber::from_u64(3))
+                .map(|object| object.version().value()),
+            Some(3)
+        );
+
+        // A held snapshot keeps its layers apart from later ones...
+        let held = store.snapshot();
+        write_object(&mut store, id, 11);
+        store.snapshot();
+        write_object(&mut store, id, 12);
+        store.snapshot();
+        assert_eq!(store.layers.len(), 2);
+
+        // ...and rolling back to it still sees its state, after which the newer layers are gone.
+        store.restore(&held);
+        assert_eq!(version(&store, &id), Some(10));
+        assert_eq!(store.layers.len(), 1);
+
+        // Once it is dropped, its layer is merged with the next one, deletions included.
+        drop(held);
+        store.update_objects(
+            BTreeMap::new(),
+            vec![(id, SequenceNumber::from_u64(10), ObjectDigest::MIN)],
+        );
+        store.snapshot();
+        assert_eq!(store.layers.len(), 1);
+        assert_eq!(version(&store, &id), None);
+    }
+}
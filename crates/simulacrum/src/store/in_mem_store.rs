@@ -1,11 +1,17 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use anyhow::Context;
+use fastcrypto::hash::MultisetHash;
 use move_binary_format::CompiledModule;
 use move_bytecode_utils::module_cache::GetModule;
 use move_core_types::{language_storage::ModuleId, resolver::ModuleResolver};
 use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use sui_config::genesis;
+use sui_core::authority::authority_store_tables::LiveObject;
+use sui_snapshot::reader::LocalStateSnapshotReaderV1;
+use sui_storage::blob::Blob;
 use sui_types::error::SuiErrorKind;
 use sui_types::storage::{PackageObject, get_module, load_package_object_from_object_store};
 use sui_types::{
@@ -15,9 +21,10 @@ use sui_types::{
     digests::{ObjectDigest, TransactionDigest},
     effects::{TransactionEffects, TransactionEffectsAPI, TransactionEvents},
     error::SuiError,
+    full_checkpoint_content::CheckpointData,
     messages_checkpoint::{
-        CheckpointContents, CheckpointContentsDigest, CheckpointDigest, CheckpointSequenceNumber,
-        VerifiedCheckpoint,
+        CheckpointCommitment, CheckpointContents, CheckpointContentsDigest, CheckpointDigest,
+        CheckpointSequenceNumber, ECMHLiveObjectSetDigest, VerifiedCheckpoint,
     },
     object::{Object, Owner},
     storage::{BackingPackageStore, ChildObjectResolver, ObjectStore, ParentSync},
@@ -39,7 +46,7 @@ pub struct InMemoryStore {
     events: HashMap<TransactionDigest, TransactionEvents>,
 
     // Committee data
-    epoch_to_committee: Vec<Committee>,
+    epoch_to_committee: BTreeMap<EpochId, Committee>,
 
     // Object data
     live_objects: HashMap<ObjectID, SequenceNumber>,
@@ -53,6 +60,100 @@ impl InMemoryStore {
         store
     }
 
+    /// Create a store holding the live object set as of `checkpoint`, rebuilt by applying every
+    /// checkpoint file (`<sequence number>.chk`, as written by data ingestion) in `path`, from
+    /// genesis up to and including `checkpoint`.
+    ///
+    /// The live object set can only be rebuilt from the full history of the network, so `path`
+    /// must hold every checkpoint starting from genesis (`0.chk`). To start from a later
+    /// checkpoint without the history before it, use [`Self::new_from_formal_snapshot`] instead.
+    pub fn new_from_checkpoint_files(
+        path: &Path,
+        checkpoint: CheckpointSequenceNumber,
+    ) -> anyhow::Result<Self> {
+        let genesis_file = path.join("0.chk");
+        anyhow::ensure!(
+            genesis_file.exists(),
+            "{} is missing: checkpoint files must start from genesis",
+            genesis_file.display(),
+        );
+
+        let mut store = Self::default();
+        let mut previous_digest = None;
+        for sequence_number in 0..=checkpoint {
+            let checkpoint_data =
+                read_checkpoint_file(&path.join(format!("{sequence_number}.chk")))?;
+            let summary = &checkpoint_data.checkpoint_summary;
+            anyhow::ensure!(
+                *summary.sequence_number() == sequence_number
+                    && summary.previous_digest == previous_digest,
+                "checkpoint file {sequence_number}.chk does not hold checkpoint {sequence_number} \
+                of the chain",
+            );
+            previous_digest = Some(*summary.digest());
+            store.apply_checkpoint_data(checkpoint_data);
+        }
+        Ok(store)
+    }
+
+    /// Create a store holding the live object set at the end of `epoch`, read from a formal snapshot
+    /// that has been downloaded in full to `snapshot_dir` (i.e. `snapshot_dir/epoch_<epoch>`).
+    ///
+    /// `checkpoint_file` must hold the last checkpoint of `epoch`, as written by data ingestion.
+    /// The snapshot is verified against the root state digest that checkpoint commits to, but the
+    /// checkpoint itself is trusted as given.
+    pub fn new_from_formal_snapshot(
+        snapshot_dir: PathBuf,
+        epoch: EpochId,
+        checkpoint_file: &Path,
+    ) -> anyhow::Result<Self> {
+        let checkpoint_data = read_checkpoint_file(checkpoint_file)?;
+        let checkpoint = VerifiedCheckpoint::new_unchecked(checkpoint_data.checkpoint_summary);
+        anyhow::ensure!(
+            checkpoint.epoch() == epoch,
+            "checkpoint {} is from epoch {}, not {epoch}",
+            checkpoint.sequence_number(),
+            checkpoint.epoch(),
+        );
+        let commitment = checkpoint
+            .end_of_epoch_data
+            .as_ref()
+            .with_context(|| {
+                format!(
+                    "checkpoint {} is not the last checkpoint of epoch {epoch}",
+                    checkpoint.sequence_number(),
+                )
+            })?
+            .epoch_commitments
+            .last()
+            .with_context(|| format!("epoch {epoch} has no end of epoch state commitment"))?;
+        let CheckpointCommitment::ECMHLiveObjectSetDigest(expected_digest) = commitment else {
+            anyhow::bail!("Expected ECMHLiveObjectSetDigest");
+        };
+
+        let mut objects = BTreeMap::new();
+        let reader = LocalStateSnapshotReaderV1::new(epoch, snapshot_dir)?;
+        let root_state_hash = reader.for_each_live_object(|live_object| {
+            if let LiveObject::Normal(object) = live_object {
+                objects.insert(object.id(), object);
+            }
+            Ok(())
+        })?;
+
+        let local_digest: ECMHLiveObjectSetDigest = root_state_hash.digest().into();
+        anyhow::ensure!(
+            *expected_digest == local_digest,
+            "End of epoch {epoch} root state digest {} does not match local root state hash {} \
+            computed from snapshot data",
+            expected_digest.digest,
+            local_digest.digest,
+        );
+
+        let mut store = Self::default();
+        store.init_with_checkpoint(checkpoint, checkpoint_data.checkpoint_contents, objects);
+        Ok(store)
+    }
+
     pub fn get_checkpoint_by_sequence_number(
         &self,
         sequence_number: CheckpointSequenceNumber,
@@ -83,7 +184,7 @@ impl InMemoryStore {
     }
 
     pub fn get_committee_by_epoch(&self, epoch: EpochId) -> Option<&Committee> {
-        self.epoch_to_committee.get(epoch as usize)
+        self.epoch_to_committee.get(&epoch)
     }
     pub fn get_transaction(&self, digest: &TransactionDigest) -> Option<&VerifiedTransaction> {
         self.transactions.get(digest)
@@ -157,14 +258,20 @@ impl InMemoryStore {
     }
 
     pub fn insert_committee(&mut self, committee: Committee) {
-        let epoch = committee.epoch as usize;
+        let epoch = committee.epoch;
 
-        if self.epoch_to_committee.get(epoch).is_some() {
+        if self.epoch_to_committee.contains_key(&epoch) {
             return;
         }
 
-        if self.epoch_to_committee.len() == epoch {
-            self.epoch_to_committee.push(committee);
+        // A store seeded from a checkpoint does not start at epoch 0, so the first committee can
+        // be for any epoch.
+        let next_epoch = self
+            .epoch_to_committee
+            .last_key_value()
+            .map(|(epoch, _)| epoch + 1);
+        if next_epoch.is_none_or(|next_epoch| next_epoch == epoch) {
+            self.epoch_to_committee.insert(epoch, committee);
         } else {
             panic!("committee was inserted into EpochCommitteeMap out of order");
         }
@@ -324,6 +431,13 @@ impl ParentSync for InMemoryStore {
     }
 }
 
+fn read_checkpoint_file(path: &Path) -> anyhow::Result<CheckpointData> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("failed to read checkpoint file {}", path.display()))?;
+    Blob::from_bytes(&bytes)
+        .with_context(|| format!("failed to decode checkpoint file {}", path.display()))
+}
+
 #[derive(Debug)]
 pub struct KeyStore {
     validator_keys: BTreeMap<AuthorityName, AuthorityKeyPair>,
@@ -15,6 +15,7 @@ use sui_types::{
     digests::{ObjectDigest, TransactionDigest},
     effects::{TransactionEffects, TransactionEffectsAPI, TransactionEvents},
     error::SuiResult,
+    full_checkpoint_content::CheckpointData,
     messages_checkpoint::{
         CheckpointContents, CheckpointContentsDigest, CheckpointDigest, CheckpointSequenceNumber,
         VerifiedCheckpoint,
@@ -23,6 +24,7 @@ use sui_types::{
     storage::{BackingStore, ChildObjectResolver, ParentSync},
     transaction::{InputObjectKind, VerifiedTransaction},
 };
+pub mod copy_on_write;
 pub mod in_mem_store;
 
 pub trait SimulatorStore:
@@ -54,6 +56,50 @@ pub trait SimulatorStore:
         );
     }
 
+    /// Initialize the store from the state of an existing network as of `checkpoint`, rather than
+    /// from a genesis.
+    ///
+    /// `objects` must be the full live object set as of `checkpoint`.
+    fn init_with_checkpoint(
+        &mut self,
+        checkpoint: VerifiedCheckpoint,
+        contents: CheckpointContents,
+        objects: BTreeMap<ObjectID, Object>,
+    ) {
+        self.insert_checkpoint(checkpoint);
+        self.insert_checkpoint_contents(contents);
+        self.update_objects(objects, vec![]);
+    }
+
+    /// Apply a checkpoint which has already been executed elsewhere, e.g. one read back from a
+    /// checkpoint file, to the store.
+    fn apply_checkpoint_data(&mut self, checkpoint_data: CheckpointData) {
+        let CheckpointData {
+            checkpoint_summary,
+            checkpoint_contents,
+            transactions,
+        } = checkpoint_data;
+
+        for tx in transactions {
+            let tx_digest = *tx.effects.transaction_digest();
+            let removed_objects = tx
+                .effects
+                .all_removed_objects()
+                .into_iter()
+                .map(|(object_ref, _)| object_ref)
+                .collect();
+            let written_objects = tx.output_objects.into_iter().map(|o| (o.id(), o)).collect();
+
+            self.insert_transaction(VerifiedTransaction::new_unchecked(tx.transaction));
+            self.insert_transaction_effects(tx.effects);
+            self.insert_events(&tx_digest, tx.events.unwrap_or_default());
+            self.update_objects(written_objects, removed_objects);
+        }
+
+        self.insert_checkpoint(VerifiedCheckpoint::new_unchecked(checkpoint_summary));
+        self.insert_checkpoint_contents(checkpoint_contents);
+    }
+
     fn get_checkpoint_by_sequence_number(
         &self,
         sequence_number: CheckpointSequenceNumber,
@@ -1260,6 +1260,11 @@ pub struct StateSnapshotConfig {
     /// and are intended to be kept indefinitely.
     #[serde(default)]
     pub archive_interval_epochs: u64,
+    /// Write a full snapshot every N epochs, and a delta snapshot over the previous epoch in
+    /// between, under `delta/epoch_<N>/`. If set to 0 or 1, every snapshot is a full snapshot.
+    /// Only full snapshots are archived, so `archive_interval_epochs` should be a multiple of it.
+    #[serde(default)]
+    pub full_snapshot_interval_epochs: u64,
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
@@ -654,6 +654,23 @@ impl AuthorityStore {
         perpetual_db: &AuthorityPerpetualTables,
         live_objects: impl Iterator<Item = LiveObject>,
         expected_sha3_digest: &[u8; 32],
+    ) -> SuiResult<()> {
+        Self::bulk_insert_filtered_live_objects(
+            perpetual_db,
+            live_objects,
+            expected_sha3_digest,
+            |_| true,
+        )
+    }
+
+    /// Like `bulk_insert_live_objects`, but only inserts the objects accepted by `filter`. The
+    /// sha3 digest is still computed over all of `live_objects`, so that a partial restore is
+    /// checked against the same object references as a full one.
+    pub fn bulk_insert_filtered_live_objects(
+        perpetual_db: &AuthorityPerpetualTables,
+        live_objects: impl Iterator<Item = LiveObject>,
+        expected_sha3_digest: &[u8; 32],
+        filter: impl Fn(&LiveObject) -> bool,
     ) -> SuiResult<()> {
         let mut hasher = Sha3_256::default();
         let mut batch = perpetual_db.objects.batch();
@@ -661,6 +678,9 @@ impl AuthorityStore {
         const MAX_BATCH_SIZE: usize = 100_000;
         for object in live_objects {
             hasher.update(object.object_reference().2.inner());
+            if !filter(&object) {
+                continue;
+            }
             match object {
                 LiveObject::Normal(object) => {
                     let store_object_wrapper = get_store_object(object.clone());
@@ -709,6 +729,35 @@ impl AuthorityStore {
         Ok(())
     }
 
+    /// Removes objects that are no longer live, e.g. when applying a delta state snapshot on top
+    /// of its base.
+    pub fn bulk_remove_live_objects(
+        perpetual_db: &AuthorityPerpetualTables,
+        object_refs: impl Iterator<Item = ObjectRef>,
+    ) -> SuiResult<()> {
+        let mut batch = perpetual_db.objects.batch();
+        let mut written = 0usize;
+        const MAX_BATCH_SIZE: usize = 100_000;
+        for object_ref in object_refs {
+            batch.delete_batch(
+                &perpetual_db.objects,
+                std::iter::once(ObjectKey::from(object_ref)),
+            )?;
+            batch.delete_batch(
+                &perpetual_db.live_owned_object_markers,
+                std::iter::once(object_ref),
+            )?;
+            written += 1;
+            if written > MAX_BATCH_SIZE {
+                batch.write()?;
+                batch = perpetual_db.objects.batch();
+                written = 0;
+            }
+        }
+        batch.write()?;
+        Ok(())
+    }
+
     pub fn set_epoch_start_configuration(
         &self,
         epoch_start_configuration: &EpochStartConfiguration,
@@ -0,0 +1,423 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Local persistence for traffic controller blocklists.
+//!
+//! When `PolicyConfig::blocklist_persistence_path` is set, the blocklists are
+//! periodically snapshotted to `blocklist.json` in that directory and restored
+//! on startup (minus any entries that expired in the meantime), so that a
+//! restart does not lift every active block. Every block and unblock is also
+//! appended as a JSON line to `audit.log`, along with the policy and tally that
+//! triggered it.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use super::policies::TrafficTally;
+
+pub const SNAPSHOT_FILE_NAME: &str = "blocklist.json";
+pub const AUDIT_LOG_FILE_NAME: &str = "audit.log";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlocklistKind {
+    /// The connection IP of the request
+    Client,
+    /// The client IP of a request proxied through a fullnode
+    ProxiedClient,
+}
+
+/// Summary of the tally that caused a client to be blocked.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TallySummary {
+    pub direct: Option<IpAddr>,
+    pub through_fullnode: Option<IpAddr>,
+    pub error_type: Option<String>,
+    pub spam_weight: f32,
+}
+
+impl From<&TrafficTally> for TallySummary {
+    fn from(tally: &TrafficTally) -> Self {
+        Self {
+            direct: tally.direct,
+            through_fullnode: tally.through_fullnode,
+            error_type: tally.error_info.as_ref().map(|(_, error)| error.clone()),
+            spam_weight: tally.spam_weight.value(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BlocklistEntry {
+    pub blocked_at_ms: u64,
+    pub expires_at_ms: u64,
+    /// The policy that added the entry, e.g. `spam:freq-threshold`.
+    pub policy: String,
+    pub tally: TallySummary,
+}
+
+impl BlocklistEntry {
+    pub fn new(ttl: Duration, policy: String, tally: TallySummary) -> Self {
+        let now = SystemTime::now();
+        Self {
+            blocked_at_ms: to_millis(now),
+            expires_at_ms: to_millis(now + ttl),
+            policy,
+            tally,
+        }
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        to_millis(now) >= self.expires_at_ms
+    }
+}
+
+/// A blocklist entry along with the client it blocks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BlocklistRecord {
+    pub ip: IpAddr,
+    pub kind: BlocklistKind,
+    #[serde(flatten)]
+    pub entry: BlocklistEntry,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnblockReason {
+    /// The entry reached the end of its TTL
+    Expired,
+    /// The entry was lifted through the admin interface
+    Admin,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum AuditAction {
+    Block {
+        expires_at_ms: u64,
+        policy: String,
+        tally: TallySummary,
+    },
+    Unblock {
+        reason: UnblockReason,
+    },
+}
+
+/// A line of the audit log.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AuditEvent {
+    pub timestamp_ms: u64,
+    pub ip: IpAddr,
+    pub kind: BlocklistKind,
+    #[serde(flatten)]
+    pub action: AuditAction,
+}
+
+impl AuditEvent {
+    pub fn block(ip: IpAddr, kind: BlocklistKind, entry: &BlocklistEntry) -> Self {
+        Self {
+            timestamp_ms: entry.blocked_at_ms,
+            ip,
+            kind,
+            action: AuditAction::Block {
+                expires_at_ms: entry.expires_at_ms,
+                policy: entry.policy.clone(),
+                tally: entry.tally.clone(),
+            },
+        }
+    }
+
+    pub fn unblock(ip: IpAddr, kind: BlocklistKind, reason: UnblockReason) -> Self {
+        Self {
+            timestamp_ms: to_millis(SystemTime::now()),
+            ip,
+            kind,
+            action: AuditAction::Unblock { reason },
+        }
+    }
+}
+
+pub struct BlocklistStore {
+    snapshot_path: PathBuf,
+    audit_log_path: PathBuf,
+    audit_log: Mutex<File>,
+    /// Held while writing a snapshot, so that concurrent writers never share the
+    /// temporary file, and a snapshot never replaces a newer one.
+    snapshot_lock: Mutex<()>,
+    /// Set whenever the blocklists change, and cleared when they are snapshotted.
+    dirty: AtomicBool,
+}
+
+impl BlocklistStore {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let audit_log_path = dir.join(AUDIT_LOG_FILE_NAME);
+        let audit_log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&audit_log_path)?;
+        Ok(Self {
+            snapshot_path: dir.join(SNAPSHOT_FILE_NAME),
+            audit_log_path,
+            audit_log: Mutex::new(audit_log),
+            snapshot_lock: Mutex::new(()),
+            dirty: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns the entries of the last snapshot that have not yet expired.
+    pub fn load(&self) -> io::Result<Vec<BlocklistRecord>> {
+        let records: Vec<BlocklistRecord> = match fs::read(&self.snapshot_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+        let now = SystemTime::now();
+        Ok(records
+            .into_iter()
+            .filter(|record| !record.entry.is_expired(now))
+            .collect())
+    }
+
+    /// Appends an event to the audit log. Failures are logged rather than
+    /// returned, so that they never get in the way of blocking a client.
+    pub fn record(&self, event: &AuditEvent) {
+        let mut line = match serde_json::to_vec(event) {
+            Ok(line) => line,
+            Err(err) => {
+                error!("Failed to serialize blocklist audit event: {err}");
+                return;
+            }
+        };
+        line.push(b'\n');
+        if let Err(err) = self.audit_log.lock().write_all(&line) {
+            error!("Failed to write blocklist audit log: {err}");
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    pub fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Writes a snapshot of the blocklists if they have changed since the last one.
+    pub fn save_if_dirty(&self, records: impl FnOnce() -> Vec<BlocklistRecord>) {
+        let _guard = self.snapshot_lock.lock();
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        if let Err(err) = self.save(&records()) {
+            error!("Failed to persist blocklists: {err}");
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn save(&self, records: &[BlocklistRecord]) -> io::Result<()> {
+        // Write to a temporary file first, and sync it before renaming it into
+        // place, so that a crash mid-write never leaves a truncated snapshot
+        // behind.
+        let tmp_path = self.snapshot_path.with_extension("json.tmp");
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(&serde_json::to_vec_pretty(records)?)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &self.snapshot_path)?;
+        if let Some(dir) = self.snapshot_path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the audit log events for the given IP, oldest first.
+    pub fn audit_events(&self, ip: IpAddr) -> io::Result<Vec<AuditEvent>> {
+        let reader = BufReader::new(File::open(&self.audit_log_path)?);
+        let mut events = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            match serde_json::from_str::<AuditEvent>(&line) {
+                Ok(event) if event.ip == ip => events.push(event),
+                Ok(_) => {}
+                Err(err) => warn!("Skipping malformed blocklist audit log line: {err}"),
+            }
+        }
+        Ok(events)
+    }
+}
+
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic_controller::TrafficController;
+    use std::net::Ipv4Addr;
+    use sui_macros::sim_test;
+    use sui_types::traffic_control::{PolicyConfig, PolicyType, Weight};
+
+    fn entry(ttl: Duration) -> BlocklistEntry {
+        BlocklistEntry::new(
+            ttl,
+            "spam:freq-threshold".to_string(),
+            TallySummary {
+                direct: Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))),
+                through_fullnode: None,
+                error_type: None,
+                spam_weight: 1.0,
+            },
+        )
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlocklistStore::open(dir.path()).unwrap();
+        assert!(store.load().unwrap().is_empty());
+
+        let alice = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+        let bob = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+        let active = BlocklistRecord {
+            ip: alice,
+            kind: BlocklistKind::Client,
+            entry: entry(Duration::from_secs(60)),
+        };
+        let expired = BlocklistRecord {
+            ip: bob,
+            kind: BlocklistKind::ProxiedClient,
+            entry: entry(Duration::ZERO),
+        };
+
+        // Nothing is written until the blocklists have changed.
+        store.save_if_dirty(|| vec![active.clone(), expired.clone()]);
+        assert!(store.load().unwrap().is_empty());
+
+        store.mark_dirty();
+        store.save_if_dirty(|| vec![active.clone(), expired.clone()]);
+
+        // Expired entries are dropped when loading.
+        let reopened = BlocklistStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.load().unwrap(), vec![active]);
+    }
+
+    #[test]
+    fn test_concurrent_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlocklistStore::open(dir.path()).unwrap();
+        let record = BlocklistRecord {
+            ip: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            kind: BlocklistKind::Client,
+            entry: entry(Duration::from_secs(60)),
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..50 {
+                        store.mark_dirty();
+                        store.save_if_dirty(|| vec![record.clone()]);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(store.load().unwrap(), vec![record]);
+        assert!(!dir.path().join("blocklist.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let alice = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+        let bob = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+        let block = AuditEvent::block(
+            alice,
+            BlocklistKind::Client,
+            &entry(Duration::from_secs(60)),
+        );
+        let other = AuditEvent::block(bob, BlocklistKind::Client, &entry(Duration::from_secs(60)));
+        let unblock = AuditEvent::unblock(alice, BlocklistKind::Client, UnblockReason::Admin);
+
+        {
+            let store = BlocklistStore::open(dir.path()).unwrap();
+            store.record(&block);
+            store.record(&other);
+        }
+
+        // The log is appended to across restarts.
+        let store = BlocklistStore::open(dir.path()).unwrap();
+        store.record(&unblock);
+        assert_eq!(store.audit_events(alice).unwrap(), vec![block, unblock]);
+        assert_eq!(store.audit_events(bob).unwrap(), vec![other]);
+
+        let line = fs::read_to_string(dir.path().join(AUDIT_LOG_FILE_NAME)).unwrap();
+        assert!(
+            line.lines()
+                .last()
+                .unwrap()
+                .contains(r#""action":"unblock""#)
+        );
+    }
+
+    #[sim_test]
+    async fn test_blocklist_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy_config = PolicyConfig {
+            connection_blocklist_ttl_sec: 60,
+            spam_policy_type: PolicyType::TestNConnIP(2),
+            spam_sample_rate: Weight::one(),
+            dry_run: false,
+            blocklist_persistence_path: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let alice = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+
+        let controller = TrafficController::init_for_test(policy_config.clone(), None).await;
+        for _ in 0..2 {
+            controller.tally(TrafficTally::new(Some(alice), None, None, Weight::one()));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!controller.check(&Some(alice), &None).await);
+
+        // Wait for the blocklists to be snapshotted, then restart.
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        let controller = TrafficController::init_for_test(policy_config, None).await;
+        assert!(!controller.check(&Some(alice), &None).await);
+        let entries = controller.blocklist_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].ip, alice);
+        assert_eq!(entries[0].entry.policy, "spam:test-n-conn-ip");
+
+        let lifted = controller.lift_block(alice, None).unwrap();
+        assert_eq!(lifted, entries);
+        assert!(controller.check(&Some(alice), &None).await);
+
+        let inspection = controller.inspect_blocklist(alice).unwrap();
+        assert!(inspection.entries.is_empty());
+        let actions: Vec<_> = inspection
+            .audit_events
+            .into_iter()
+            .map(|event| event.action)
+            .collect();
+        assert!(matches!(actions[0], AuditAction::Block { .. }));
+        assert_eq!(
+            actions[1],
+            AuditAction::Unblock {
+                reason: UnblockReason::Admin
+            }
+        );
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod blocklist_store;
 pub mod metrics;
 pub mod nodefw_client;
 pub mod nodefw_test_server;
@@ -16,6 +17,10 @@ use std::ops::Add;
 use std::sync::Arc;
 use sui_types::error::{SuiError, SuiErrorKind};
 
+use self::blocklist_store::{
+    AuditEvent, BlocklistEntry, BlocklistKind, BlocklistRecord, BlocklistStore, TallySummary,
+    UnblockReason,
+};
 use self::metrics::TrafficControllerMetrics;
 use crate::traffic_controller::nodefw_client::{BlockAddress, BlockAddresses, NodeFWClient};
 use crate::traffic_controller::policies::{
@@ -36,12 +41,51 @@ use tracing::{debug, error, info, trace, warn};
 pub const METRICS_INTERVAL_SECS: u64 = 2;
 pub const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 300;
 
-type Blocklist = Arc<DashMap<IpAddr, SystemTime>>;
+type Blocklist = Arc<DashMap<IpAddr, BlocklistEntry>>;
 
 #[derive(Clone)]
 pub struct Blocklists {
     clients: Blocklist,
     proxied_clients: Blocklist,
+    /// If set, blocklist changes are persisted and audited here.
+    store: Option<Arc<BlocklistStore>>,
+}
+
+impl Blocklists {
+    fn blocklist(&self, kind: BlocklistKind) -> &Blocklist {
+        match kind {
+            BlocklistKind::Client => &self.clients,
+            BlocklistKind::ProxiedClient => &self.proxied_clients,
+        }
+    }
+
+    fn records(&self) -> Vec<BlocklistRecord> {
+        [BlocklistKind::Client, BlocklistKind::ProxiedClient]
+            .into_iter()
+            .flat_map(|kind| {
+                self.blocklist(kind)
+                    .iter()
+                    .map(move |item| BlocklistRecord {
+                        ip: *item.key(),
+                        kind,
+                        entry: item.value().clone(),
+                    })
+            })
+            .collect()
+    }
+
+    fn record(&self, event: AuditEvent) {
+        if let Some(store) = &self.store {
+            store.record(&event);
+        }
+    }
+}
+
+/// An IP's current blocklist entries, along with its audit log history.
+#[derive(Debug)]
+pub struct BlocklistInspection {
+    pub entries: Vec<BlocklistRecord>,
+    pub audit_events: Vec<AuditEvent>,
 }
 
 #[derive(Clone)]
@@ -114,17 +158,35 @@ impl TrafficController {
             }
             None => {
                 let spam_policy = Arc::new(Mutex::new(
-                    TrafficControlPolicy::from_spam_config(policy_config.clone()).await,
+                    TrafficControlPolicy::from_spam_config(policy_config.clone())
+                        .await
+                        .unwrap_or_else(|e| fatal!("Invalid spam policy config: {e}")),
                 ));
                 let error_policy = Arc::new(Mutex::new(
-                    TrafficControlPolicy::from_error_config(policy_config.clone()).await,
+                    TrafficControlPolicy::from_error_config(policy_config.clone())
+                        .await
+                        .unwrap_or_else(|e| fatal!("Invalid error policy config: {e}")),
                 ));
+                let blocklists = Blocklists {
+                    clients: Arc::new(DashMap::new()),
+                    proxied_clients: Arc::new(DashMap::new()),
+                    store: policy_config
+                        .blocklist_persistence_path
+                        .as_ref()
+                        .map(|path| {
+                            let store = BlocklistStore::open(path).unwrap_or_else(|err| {
+                                fatal!(
+                                    "Failed to open blocklist persistence path {:?}: {err}",
+                                    path
+                                )
+                            });
+                            Arc::new(store)
+                        }),
+                };
+                Self::restore_blocklists(&blocklists, &metrics);
                 let this = Self {
                     tally_channel: Arc::new(ParkingLotMutex::new(None)),
-                    acl: Acl::Blocklists(Blocklists {
-                        clients: Arc::new(DashMap::new()),
-                        proxied_clients: Arc::new(DashMap::new()),
-                    }),
+                    acl: Acl::Blocklists(blocklists),
                     metrics,
                     policy_config: Arc::new(RwLock::new(policy_config)),
                     fw_config,
@@ -200,6 +262,105 @@ impl TrafficController {
         self.open_tally_channel(tx);
     }
 
+    /// Re-populates the blocklists from their last persisted snapshot, if any.
+    fn restore_blocklists(blocklists: &Blocklists, metrics: &TrafficControllerMetrics) {
+        let Some(store) = &blocklists.store else {
+            return;
+        };
+        let records = match store.load() {
+            Ok(records) => records,
+            Err(err) => {
+                error!("Failed to load persisted blocklists, starting empty: {err}");
+                return;
+            }
+        };
+        info!("Restoring {} persisted blocklist entries", records.len());
+        for BlocklistRecord { ip, kind, entry } in records {
+            blocklists.blocklist(kind).insert(ip, entry);
+        }
+        metrics
+            .connection_ip_blocklist_len
+            .set(blocklists.clients.len() as i64);
+        metrics
+            .proxy_ip_blocklist_len
+            .set(blocklists.proxied_clients.len() as i64);
+    }
+
+    /// Returns all current blocklist entries.
+    pub fn blocklist_entries(&self) -> Vec<BlocklistRecord> {
+        match &self.acl {
+            Acl::Blocklists(blocklists) => blocklists.records(),
+            Acl::Allowlist(_) => vec![],
+        }
+    }
+
+    /// Returns the current blocklist entries for `ip`, and every block and
+    /// unblock of it recorded in the audit log.
+    pub fn inspect_blocklist(&self, ip: IpAddr) -> Result<BlocklistInspection, SuiError> {
+        let blocklists = self.blocklists()?;
+        let entries = blocklists
+            .records()
+            .into_iter()
+            .filter(|record| record.ip == ip)
+            .collect();
+        let audit_events = match &blocklists.store {
+            Some(store) => store.audit_events(ip).map_err(|err| {
+                SuiErrorKind::InvalidAdminRequest(format!(
+                    "Failed to read blocklist audit log: {err}"
+                ))
+            })?,
+            None => vec![],
+        };
+        Ok(BlocklistInspection {
+            entries,
+            audit_events,
+        })
+    }
+
+    /// Removes `ip` from the given blocklist, or from both if `kind` is None,
+    /// returning the entries that were removed.
+    pub fn lift_block(
+        &self,
+        ip: IpAddr,
+        kind: Option<BlocklistKind>,
+    ) -> Result<Vec<BlocklistRecord>, SuiError> {
+        let blocklists = self.blocklists()?;
+        let kinds = match kind {
+            Some(kind) => vec![kind],
+            None => vec![BlocklistKind::Client, BlocklistKind::ProxiedClient],
+        };
+        let mut lifted = vec![];
+        for kind in kinds {
+            if let Some((ip, entry)) = blocklists.blocklist(kind).remove(&ip) {
+                info!("Lifting {:?} block on {:?} by admin request", kind, ip);
+                self.blocklist_len_gauge(kind).dec();
+                blocklists.record(AuditEvent::unblock(ip, kind, UnblockReason::Admin));
+                lifted.push(BlocklistRecord { ip, kind, entry });
+            }
+        }
+        if let Some(store) = &blocklists.store {
+            store.save_if_dirty(|| blocklists.records());
+        }
+        Ok(lifted)
+    }
+
+    fn blocklists(&self) -> Result<&Blocklists, SuiError> {
+        match &self.acl {
+            Acl::Blocklists(blocklists) => Ok(blocklists),
+            Acl::Allowlist(_) => Err(SuiErrorKind::InvalidAdminRequest(
+                "Traffic controller is configured with an allowlist".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    fn blocklist_len_gauge(&self, kind: BlocklistKind) -> &IntGauge {
+        match kind {
+            BlocklistKind::Client => &self.metrics.connection_ip_blocklist_len,
+            BlocklistKind::ProxiedClient => &self.metrics.proxy_ip_blocklist_len,
+        }
+    }
+
     pub async fn get_current_state(&self) -> TrafficControlReconfigParams {
         let mut result = TrafficControlReconfigParams {
             error_threshold: None,
@@ -275,6 +436,13 @@ impl TrafficController {
                 }
                 Ok(())
             }
+            TrafficControlPolicy::TokenBucket(ref mut policy) => {
+                policy.set_client_capacity(threshold);
+                if let Some(dry_run) = dry_run {
+                    policy.config.dry_run = dry_run;
+                }
+                Ok(())
+            }
             TrafficControlPolicy::TestNConnIP(ref mut policy) => {
                 policy.threshold = threshold;
                 if let Some(dry_run) = dry_run {
@@ -383,15 +551,12 @@ impl TrafficController {
         client: &Option<IpAddr>,
         proxied_client: &Option<IpAddr>,
     ) -> bool {
-        let client_check = self.check_and_clear_blocklist(
-            client,
-            blocklists.clients.clone(),
-            &self.metrics.connection_ip_blocklist_len,
-        );
+        let client_check =
+            self.check_and_clear_blocklist(client, blocklists, BlocklistKind::Client);
         let proxied_client_check = self.check_and_clear_blocklist(
             proxied_client,
-            blocklists.proxied_clients.clone(),
-            &self.metrics.proxy_ip_blocklist_len,
+            blocklists,
+            BlocklistKind::ProxiedClient,
         );
         let (client_check, proxied_client_check) =
             futures::future::join(client_check, proxied_client_check).await;
@@ -401,25 +566,27 @@ impl TrafficController {
     async fn check_and_clear_blocklist(
         &self,
         client: &Option<IpAddr>,
-        blocklist: Blocklist,
-        blocklist_len_gauge: &IntGauge,
+        blocklists: &Blocklists,
+        kind: BlocklistKind,
     ) -> bool {
         let client = match client {
             Some(client) => client,
             None => return true,
         };
+        let blocklist = blocklists.blocklist(kind);
         let now = SystemTime::now();
         // the below two blocks cannot be nested, otherwise we will deadlock
         // due to aquiring the lock on get, then holding across the remove
         let (should_block, should_remove) = {
             match blocklist.get(client) {
-                Some(expiration) if now >= *expiration => (false, true),
+                Some(entry) if entry.is_expired(now) => (false, true),
                 None => (false, false),
                 _ => (true, false),
             }
         };
         if should_remove && blocklist.remove(client).is_some() {
-            blocklist_len_gauge.dec();
+            self.blocklist_len_gauge(kind).dec();
+            blocklists.record(AuditEvent::unblock(*client, kind, UnblockReason::Expired));
         }
         !should_block
     }
@@ -430,15 +597,27 @@ impl TrafficController {
 /// IPs in the blocklist for clients that are added, then once blocked,
 /// never checked again. This function runs periodically to clear out any
 /// such stale IPs. This also ensures that the blocklist length metric
-/// accurately reflects TTL.
+/// accurately reflects TTL, and persists the blocklists if they have changed.
 async fn run_clear_blocklists_loop(blocklists: Blocklists, metrics: Arc<TrafficControllerMetrics>) {
     loop {
         tokio::time::sleep(Duration::from_secs(3)).await;
         let now = SystemTime::now();
-        blocklists.clients.retain(|_, expiration| now < *expiration);
-        blocklists
-            .proxied_clients
-            .retain(|_, expiration| now < *expiration);
+        for kind in [BlocklistKind::Client, BlocklistKind::ProxiedClient] {
+            let mut expired = vec![];
+            blocklists.blocklist(kind).retain(|ip, entry| {
+                let keep = !entry.is_expired(now);
+                if !keep {
+                    expired.push(*ip);
+                }
+                keep
+            });
+            for ip in expired {
+                blocklists.record(AuditEvent::unblock(ip, kind, UnblockReason::Expired));
+            }
+        }
+        if let Some(store) = &blocklists.store {
+            store.save_if_dirty(|| blocklists.records());
+        }
         metrics
             .connection_ip_blocklist_len
             .set(blocklists.clients.len() as i64);
@@ -600,7 +779,10 @@ async fn handle_error_tally(
         .tally_error_types
         .with_label_values(&[error_type.as_str()])
         .inc();
-    let resp = policy.lock().await.handle_tally(tally);
+    let (resp, policy_name) = {
+        let mut policy = policy.lock().await;
+        (policy.handle_tally(tally.clone()), policy.name())
+    };
     metrics.error_tally_handled.inc();
     if let Some(fw_config) = fw_config
         && fw_config.delegate_error_blocking
@@ -618,7 +800,16 @@ async fn handle_error_tally(
         )
         .await;
     }
-    handle_policy_response(resp, policy_config, blocklists, metrics).await;
+    handle_policy_response(
+        resp,
+        policy_config,
+        blocklists,
+        metrics,
+        "error",
+        policy_name,
+        &tally,
+    )
+    .await;
     Ok(())
 }
 
@@ -635,7 +826,10 @@ async fn handle_spam_tally(
     if !(tally.spam_weight.is_sampled() && policy_config.spam_sample_rate.is_sampled()) {
         return Ok(());
     }
-    let resp = policy.lock().await.handle_tally(tally.clone());
+    let (resp, policy_name) = {
+        let mut policy = policy.lock().await;
+        (policy.handle_tally(tally.clone()), policy.name())
+    };
     metrics.tally_handled.inc();
     if let Some(fw_config) = fw_config
         && fw_config.delegate_spam_blocking
@@ -653,7 +847,16 @@ async fn handle_spam_tally(
         )
         .await;
     }
-    handle_policy_response(resp, policy_config, blocklists, metrics).await;
+    handle_policy_response(
+        resp,
+        policy_config,
+        blocklists,
+        metrics,
+        "spam",
+        policy_name,
+        &tally,
+    )
+    .await;
     Ok(())
 }
 
@@ -662,6 +865,10 @@ async fn handle_policy_response(
     policy_config: &PolicyConfig,
     blocklists: Arc<Blocklists>,
     metrics: Arc<TrafficControllerMetrics>,
+    // Whether the response came from the "spam" or "error" policy
+    policy_kind: &str,
+    policy_name: &str,
+    tally: &TrafficTally,
 ) {
     let PolicyResponse {
         block_client,
@@ -672,31 +879,38 @@ async fn handle_policy_response(
         proxy_blocklist_ttl_sec,
         ..
     } = policy_config;
-    if let Some(client) = block_client
-        && blocklists
-            .clients
-            .insert(
-                client,
-                SystemTime::now() + Duration::from_secs(*connection_blocklist_ttl_sec),
-            )
-            .is_none()
-    {
-        // Only increment the metric if the client was not already blocked
-        debug!("Adding client {:?} to blocklist", client);
-        metrics.connection_ip_blocklist_len.inc();
-    }
-    if let Some(client) = block_proxied_client
-        && blocklists
-            .proxied_clients
-            .insert(
-                client,
-                SystemTime::now() + Duration::from_secs(*proxy_blocklist_ttl_sec),
-            )
-            .is_none()
-    {
-        // Only increment the metric if the client was not already blocked
-        debug!("Adding proxied client {:?} to blocklist", client);
-        metrics.proxy_ip_blocklist_len.inc();
+    let new_entry = |ttl_sec: u64| {
+        BlocklistEntry::new(
+            Duration::from_secs(ttl_sec),
+            format!("{policy_kind}:{policy_name}"),
+            TallySummary::from(tally),
+        )
+    };
+    if let Some(client) = block_client {
+        let entry = new_entry(*connection_blocklist_ttl_sec);
+        let event = AuditEvent::block(client, BlocklistKind::Client, &entry);
+        if blocklists.clients.insert(client, entry).is_none() {
+            // Only increment the metric and audit the block if the client
+            // was not already blocked
+            debug!("Adding client {:?} to blocklist", client);
+            metrics.connection_ip_blocklist_len.inc();
+            blocklists.record(event);
+        } else if let Some(store) = &blocklists.store {
+            store.mark_dirty();
+        }
+    }
+    if let Some(client) = block_proxied_client {
+        let entry = new_entry(*proxy_blocklist_ttl_sec);
+        let event = AuditEvent::block(client, BlocklistKind::ProxiedClient, &entry);
+        if blocklists.proxied_clients.insert(client, entry).is_none() {
+            // Only increment the metric and audit the block if the client
+            // was not already blocked
+            debug!("Adding proxied client {:?} to blocklist", client);
+            metrics.proxy_ip_blocklist_len.inc();
+            blocklists.record(event);
+        } else if let Some(store) = &blocklists.store {
+            store.mark_dirty();
+        }
     }
 }
 
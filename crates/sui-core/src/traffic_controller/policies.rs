@@ -4,16 +4,20 @@
 
 use std::{collections::HashMap, net::IpAddr, sync::Arc};
 
+use anyhow::{anyhow, ensure};
 use count_min_sketch::CountMinSketch32;
+use futures::{FutureExt, future::BoxFuture};
 use mysten_metrics::spawn_monitored_task;
 use parking_lot::RwLock;
 use std::cmp::Reverse;
-use std::collections::{BinaryHeap, VecDeque};
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::time::Duration;
 use std::time::{Instant, SystemTime};
-use sui_types::traffic_control::{FreqThresholdConfig, PolicyConfig, PolicyType, Weight};
+use sui_types::traffic_control::{
+    ErrorWeightedConfig, FreqThresholdConfig, PolicyConfig, PolicyType, TokenBucketConfig, Weight,
+};
 use tracing::{info, trace};
 
 const HIGHEST_RATES_CAPACITY: usize = 20;
@@ -72,7 +76,11 @@ impl TrafficSketch {
         sketch_probability: f64,
         sketch_tolerance: f64,
         highest_rates_capacity: usize,
-    ) -> Self {
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            update_interval >= Duration::from_secs(1),
+            "Update interval too short, must be at least 1 second"
+        );
         // intentionally round down via integer division. We can't have a partial sketch
         let num_sketches = window_size.as_secs() / update_interval.as_secs();
         let new_window_size = Duration::from_secs(num_sketches * update_interval.as_secs());
@@ -85,19 +93,15 @@ impl TrafficSketch {
         }
         let window_size = new_window_size;
 
-        assert!(
+        ensure!(
             window_size < Duration::from_secs(600),
             "window_size too large. Max 600 seconds"
         );
-        assert!(
+        ensure!(
             update_interval < window_size,
             "Update interval may not be larger than window size"
         );
-        assert!(
-            update_interval >= Duration::from_secs(1),
-            "Update interval too short, must be at least 1 second"
-        );
-        assert!(
+        ensure!(
             num_sketches <= 10,
             "Given parameters require too many sketches to be stored. Reduce window size or increase update interval."
         );
@@ -107,8 +111,8 @@ impl TrafficSketch {
                 sketch_probability,
                 sketch_tolerance,
             )
-            .expect("Failed to estimate memory for CountMinSketch32");
-        assert!(
+            .map_err(|e| anyhow!("Failed to estimate memory for CountMinSketch32: {e:?}"))?;
+        ensure!(
             mem_estimate < 128_000_000,
             "Memory estimate for traffic sketch exceeds 128MB. Reduce window size or increase update interval."
         );
@@ -121,10 +125,10 @@ impl TrafficSketch {
                     sketch_probability,
                     sketch_tolerance,
                 )
-                .expect("Failed to create CountMinSketch32"),
+                .map_err(|e| anyhow!("Failed to create CountMinSketch32: {e:?}"))?,
             );
         }
-        Self {
+        Ok(Self {
             sketches,
             window_size,
             update_interval,
@@ -135,7 +139,7 @@ impl TrafficSketch {
                 proxied: BinaryHeap::with_capacity(highest_rates_capacity),
                 capacity: highest_rates_capacity,
             },
-        }
+        })
     }
 
     fn increment_count(&mut self, key: &SketchKey) {
@@ -271,6 +275,10 @@ pub trait Policy {
 // not object safe, so we can't use a trait object instead
 pub enum TrafficControlPolicy {
     FreqThreshold(FreqThresholdPolicy),
+    TokenBucket(TokenBucketPolicy),
+    ErrorWeighted(ErrorWeightedPolicy),
+    AnyOf(AnyOfPolicy),
+    AllOf(AllOfPolicy),
     NoOp(NoOpPolicy),
     // Test policies below this point
     TestNConnIP(TestNConnIPPolicy),
@@ -282,6 +290,10 @@ impl Policy for TrafficControlPolicy {
         match self {
             TrafficControlPolicy::NoOp(policy) => policy.handle_tally(tally),
             TrafficControlPolicy::FreqThreshold(policy) => policy.handle_tally(tally),
+            TrafficControlPolicy::TokenBucket(policy) => policy.handle_tally(tally),
+            TrafficControlPolicy::ErrorWeighted(policy) => policy.handle_tally(tally),
+            TrafficControlPolicy::AnyOf(policy) => policy.handle_tally(tally),
+            TrafficControlPolicy::AllOf(policy) => policy.handle_tally(tally),
             TrafficControlPolicy::TestNConnIP(policy) => policy.handle_tally(tally),
             TrafficControlPolicy::TestPanicOnInvocation(policy) => policy.handle_tally(tally),
         }
@@ -291,6 +303,10 @@ impl Policy for TrafficControlPolicy {
         match self {
             TrafficControlPolicy::NoOp(policy) => policy.policy_config(),
             TrafficControlPolicy::FreqThreshold(policy) => policy.policy_config(),
+            TrafficControlPolicy::TokenBucket(policy) => policy.policy_config(),
+            TrafficControlPolicy::ErrorWeighted(policy) => policy.policy_config(),
+            TrafficControlPolicy::AnyOf(policy) => policy.policy_config(),
+            TrafficControlPolicy::AllOf(policy) => policy.policy_config(),
             TrafficControlPolicy::TestNConnIP(policy) => policy.policy_config(),
             TrafficControlPolicy::TestPanicOnInvocation(policy) => policy.policy_config(),
         }
@@ -298,25 +314,64 @@ impl Policy for TrafficControlPolicy {
 }
 
 impl TrafficControlPolicy {
-    pub async fn from_spam_config(policy_config: PolicyConfig) -> Self {
+    /// Name of the policy type, as recorded in the blocklist audit log.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrafficControlPolicy::NoOp(_) => "no-op",
+            TrafficControlPolicy::FreqThreshold(_) => "freq-threshold",
+            TrafficControlPolicy::TokenBucket(_) => "token-bucket",
+            TrafficControlPolicy::ErrorWeighted(_) => "error-weighted",
+            TrafficControlPolicy::AnyOf(_) => "any-of",
+            TrafficControlPolicy::AllOf(_) => "all-of",
+            TrafficControlPolicy::TestNConnIP(_) => "test-n-conn-ip",
+            TrafficControlPolicy::TestPanicOnInvocation(_) => "test-panic-on-invocation",
+        }
+    }
+
+    pub async fn from_spam_config(policy_config: PolicyConfig) -> anyhow::Result<Self> {
         Self::from_config(policy_config.clone().spam_policy_type, policy_config).await
     }
-    pub async fn from_error_config(policy_config: PolicyConfig) -> Self {
+    pub async fn from_error_config(policy_config: PolicyConfig) -> anyhow::Result<Self> {
         Self::from_config(policy_config.clone().error_policy_type, policy_config).await
     }
-    pub async fn from_config(policy_type: PolicyType, policy_config: PolicyConfig) -> Self {
-        match policy_type {
+    /// Builds the policy, returning an error if its config is invalid.
+    pub async fn from_config(
+        policy_type: PolicyType,
+        policy_config: PolicyConfig,
+    ) -> anyhow::Result<Self> {
+        Ok(match policy_type {
             PolicyType::NoOp => Self::NoOp(NoOpPolicy::new(policy_config)),
             PolicyType::FreqThreshold(freq_threshold_config) => Self::FreqThreshold(
-                FreqThresholdPolicy::new(policy_config, freq_threshold_config),
+                FreqThresholdPolicy::new(policy_config, freq_threshold_config)?,
+            ),
+            PolicyType::TokenBucket(token_bucket_config) => {
+                Self::TokenBucket(TokenBucketPolicy::new(policy_config, token_bucket_config)?)
+            }
+            PolicyType::ErrorWeighted(error_weighted_config) => Self::ErrorWeighted(
+                ErrorWeightedPolicy::new(policy_config, error_weighted_config)?,
             ),
+            PolicyType::AnyOf(policy_types) => {
+                Self::AnyOf(AnyOfPolicy::new(policy_config, policy_types).await?)
+            }
+            PolicyType::AllOf(policy_types) => {
+                Self::AllOf(AllOfPolicy::new(policy_config, policy_types).await?)
+            }
             PolicyType::TestNConnIP(n) => {
                 Self::TestNConnIP(TestNConnIPPolicy::new(policy_config, n).await)
             }
             PolicyType::TestPanicOnInvocation => {
                 Self::TestPanicOnInvocation(TestPanicOnInvocationPolicy::new(policy_config))
             }
-        }
+        })
+    }
+
+    /// Boxed version of `from_config`, for constructing the policies nested
+    /// in a combinator policy, which would otherwise be an infinitely sized future.
+    fn from_config_boxed(
+        policy_type: PolicyType,
+        policy_config: PolicyConfig,
+    ) -> BoxFuture<'static, anyhow::Result<Self>> {
+        Self::from_config(policy_type, policy_config).boxed()
     }
 }
 
@@ -347,7 +402,7 @@ impl FreqThresholdPolicy {
             sketch_probability,
             sketch_tolerance,
         }: FreqThresholdConfig,
-    ) -> Self {
+    ) -> anyhow::Result<Self> {
         let sketch = TrafficSketch::new(
             Duration::from_secs(window_size_secs),
             Duration::from_secs(update_interval_secs),
@@ -355,14 +410,14 @@ impl FreqThresholdPolicy {
             sketch_probability,
             sketch_tolerance,
             HIGHEST_RATES_CAPACITY,
-        );
-        Self {
+        )?;
+        Ok(Self {
             config,
             sketch,
             client_threshold,
             proxied_client_threshold,
             salt: rand::random(),
-        }
+        })
     }
 
     pub fn highest_direct_rate(&self) -> Option<(u64, IpAddr)> {
@@ -420,6 +475,361 @@ impl FreqThresholdPolicy {
     }
 }
 
+/// Per client token buckets, for one type of client.
+struct TokenBuckets {
+    buckets: HashMap<IpAddr, TokenBucket>,
+    capacity: f64,
+    refill_rate: f64,
+    max_tracked_clients: usize,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBuckets {
+    fn new(capacity: u64, refill_rate: f64, max_tracked_clients: usize) -> anyhow::Result<Self> {
+        ensure!(capacity >= 1, "Token bucket capacity must be at least 1");
+        ensure!(
+            refill_rate > 0.0 && refill_rate.is_finite(),
+            "Token bucket refill rate must be positive"
+        );
+        ensure!(
+            max_tracked_clients >= 1,
+            "Token bucket policy must track at least one client"
+        );
+        Ok(Self {
+            buckets: HashMap::new(),
+            capacity: capacity as f64,
+            refill_rate,
+            max_tracked_clients,
+        })
+    }
+
+    /// Takes a token from the client's bucket, returning true if there
+    /// was none left to take, i.e. the client should be blocked.
+    fn take(&mut self, client: IpAddr, now: Instant) -> bool {
+        if !self.buckets.contains_key(&client) && self.buckets.len() >= self.max_tracked_clients {
+            self.evict(now);
+        }
+
+        let (capacity, refill_rate) = (self.capacity, self.refill_rate);
+        let bucket = self.buckets.entry(client).or_insert(TokenBucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * refill_rate).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Makes room for new clients. Buckets that have refilled are equivalent to
+    /// untracked clients, so they are dropped first. If that is not enough, the
+    /// fullest buckets are dropped until the table is half full, forgiving the
+    /// clients that were least limited, and the least recently seen among those
+    /// that are equally limited.
+    fn evict(&mut self, now: Instant) {
+        let (capacity, refill_rate) = (self.capacity, self.refill_rate);
+        let tokens_at = |bucket: &TokenBucket| {
+            let elapsed = now.saturating_duration_since(bucket.last_refill);
+            (bucket.tokens + elapsed.as_secs_f64() * refill_rate).min(capacity)
+        };
+
+        self.buckets
+            .retain(|_, bucket| tokens_at(bucket) < capacity);
+        if self.buckets.len() < self.max_tracked_clients {
+            return;
+        }
+
+        let count = self.buckets.len() - self.max_tracked_clients / 2;
+        evict_lowest(&mut self.buckets, count, |bucket| {
+            (-tokens_at(bucket), bucket.last_refill)
+        });
+    }
+}
+
+pub struct TokenBucketPolicy {
+    pub config: PolicyConfig,
+    direct: TokenBuckets,
+    proxied: TokenBuckets,
+}
+
+impl TokenBucketPolicy {
+    pub fn new(
+        config: PolicyConfig,
+        TokenBucketConfig {
+            client_capacity,
+            client_refill_rate,
+            proxied_client_capacity,
+            proxied_client_refill_rate,
+            max_tracked_clients,
+        }: TokenBucketConfig,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            config,
+            direct: TokenBuckets::new(client_capacity, client_refill_rate, max_tracked_clients)?,
+            proxied: TokenBuckets::new(
+                proxied_client_capacity,
+                proxied_client_refill_rate,
+                max_tracked_clients,
+            )?,
+        })
+    }
+
+    pub fn set_client_capacity(&mut self, capacity: u64) {
+        self.direct.capacity = capacity.max(1) as f64;
+    }
+
+    pub fn handle_tally(&mut self, tally: TrafficTally) -> PolicyResponse {
+        let now = Instant::now();
+        let block_client = tally.direct.filter(|client| self.direct.take(*client, now));
+        let block_proxied_client = tally
+            .through_fullnode
+            .filter(|client| self.proxied.take(*client, now));
+        trace!(
+            "TokenBucketPolicy handling tally -- block_client: {:?}, block_proxied_client: {:?}",
+            block_client, block_proxied_client,
+        );
+        PolicyResponse {
+            block_client,
+            block_proxied_client,
+        }
+    }
+
+    fn policy_config(&self) -> &PolicyConfig {
+        &self.config
+    }
+}
+
+/// Per client exponentially decaying scores, for one type of client.
+struct DecayingScores {
+    scores: HashMap<IpAddr, (f64, Instant)>,
+    half_life: Duration,
+    max_tracked_clients: usize,
+}
+
+impl DecayingScores {
+    fn new(half_life: Duration, max_tracked_clients: usize) -> anyhow::Result<Self> {
+        ensure!(
+            !half_life.is_zero(),
+            "Error weighted policy half life must be positive"
+        );
+        ensure!(
+            max_tracked_clients >= 1,
+            "Error weighted policy must track at least one client"
+        );
+        Ok(Self {
+            scores: HashMap::new(),
+            half_life,
+            max_tracked_clients,
+        })
+    }
+
+    fn decayed(&self, score: f64, since: Instant, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(since).as_secs_f64();
+        score * 0.5f64.powf(elapsed / self.half_life.as_secs_f64())
+    }
+
+    /// Adds `weight` to the client's decayed score, returning the new score.
+    fn add(&mut self, client: IpAddr, weight: f64, now: Instant) -> f64 {
+        if !self.scores.contains_key(&client) && self.scores.len() >= self.max_tracked_clients {
+            self.evict(now);
+        }
+
+        let (score, since) = self.scores.get(&client).copied().unwrap_or((0.0, now));
+        let score = self.decayed(score, since, now) + weight;
+        self.scores.insert(client, (score, now));
+        score
+    }
+
+    /// Makes room for new clients by dropping the lowest scoring clients, and the
+    /// least recently seen among those with equal scores, until the table is half full.
+    fn evict(&mut self, now: Instant) {
+        let count = self.scores.len() - self.max_tracked_clients / 2;
+        let half_life = self.half_life.as_secs_f64();
+        evict_lowest(&mut self.scores, count, |(score, since)| {
+            let elapsed = now.saturating_duration_since(*since).as_secs_f64();
+            (*score * 0.5f64.powf(elapsed / half_life), *since)
+        });
+    }
+}
+
+/// Removes the `count` clients with the lowest `(score, last seen)` keys. Evicting
+/// by count rather than by a score cutoff keeps the number of clients evicted the
+/// same when many of them have equal scores.
+fn evict_lowest<V>(
+    clients: &mut HashMap<IpAddr, V>,
+    count: usize,
+    key: impl Fn(&V) -> (f64, Instant),
+) {
+    let mut keys: Vec<_> = clients
+        .iter()
+        .map(|(client, value)| (*client, key(value)))
+        .collect();
+    if count < keys.len() {
+        keys.select_nth_unstable_by(count, |(_, a), (_, b)| {
+            a.0.total_cmp(&b.0).then(a.1.cmp(&b.1))
+        });
+        keys.truncate(count);
+    }
+    for (client, _) in keys {
+        clients.remove(&client);
+    }
+}
+
+pub struct ErrorWeightedPolicy {
+    pub config: PolicyConfig,
+    pub client_threshold: f64,
+    pub proxied_client_threshold: f64,
+    error_weights: BTreeMap<String, f64>,
+    default_weight: f64,
+    direct: DecayingScores,
+    proxied: DecayingScores,
+}
+
+impl ErrorWeightedPolicy {
+    pub fn new(
+        config: PolicyConfig,
+        ErrorWeightedConfig {
+            error_weights,
+            default_weight,
+            client_threshold,
+            proxied_client_threshold,
+            half_life_secs,
+            max_tracked_clients,
+        }: ErrorWeightedConfig,
+    ) -> anyhow::Result<Self> {
+        let half_life = Duration::from_secs(half_life_secs);
+        Ok(Self {
+            config,
+            client_threshold,
+            proxied_client_threshold,
+            error_weights,
+            default_weight,
+            direct: DecayingScores::new(half_life, max_tracked_clients)?,
+            proxied: DecayingScores::new(half_life, max_tracked_clients)?,
+        })
+    }
+
+    pub fn handle_tally(&mut self, tally: TrafficTally) -> PolicyResponse {
+        // Only errors count towards a client's score.
+        let Some((_, error_type)) = &tally.error_info else {
+            return PolicyResponse::default();
+        };
+        let weight = self
+            .error_weights
+            .get(error_type)
+            .copied()
+            .unwrap_or(self.default_weight);
+
+        let now = Instant::now();
+        let block_client = tally.direct.filter(|client| {
+            let score = self.direct.add(*client, weight, now);
+            trace!(
+                "ErrorWeightedPolicy handling tally -- error_type: {error_type}, score: {score}, client: {client:?}"
+            );
+            score >= self.client_threshold
+        });
+        let block_proxied_client = tally.through_fullnode.filter(|client| {
+            self.proxied.add(*client, weight, now) >= self.proxied_client_threshold
+        });
+        PolicyResponse {
+            block_client,
+            block_proxied_client,
+        }
+    }
+
+    fn policy_config(&self) -> &PolicyConfig {
+        &self.config
+    }
+}
+
+/// Builds the policies nested in a combinator, which share its config.
+async fn nested_policies(
+    config: &PolicyConfig,
+    policy_types: Vec<PolicyType>,
+) -> anyhow::Result<Vec<TrafficControlPolicy>> {
+    let mut policies = Vec::with_capacity(policy_types.len());
+    for policy_type in policy_types {
+        policies.push(TrafficControlPolicy::from_config_boxed(policy_type, config.clone()).await?);
+    }
+    Ok(policies)
+}
+
+/// Blocks a client if any of its policies would. Every policy sees every
+/// tally, so that their state does not depend on the order they are listed in.
+pub struct AnyOfPolicy {
+    pub config: PolicyConfig,
+    pub policies: Vec<TrafficControlPolicy>,
+}
+
+impl AnyOfPolicy {
+    pub async fn new(config: PolicyConfig, policy_types: Vec<PolicyType>) -> anyhow::Result<Self> {
+        let policies = nested_policies(&config, policy_types).await?;
+        Ok(Self { config, policies })
+    }
+
+    pub fn handle_tally(&mut self, tally: TrafficTally) -> PolicyResponse {
+        self.policies
+            .iter_mut()
+            .map(|policy| policy.handle_tally(tally.clone()))
+            .fold(PolicyResponse::default(), |acc, response| PolicyResponse {
+                block_client: acc.block_client.or(response.block_client),
+                block_proxied_client: acc.block_proxied_client.or(response.block_proxied_client),
+            })
+    }
+
+    fn policy_config(&self) -> &PolicyConfig {
+        &self.config
+    }
+}
+
+/// Blocks a client only if all of its policies would. Every policy sees every
+/// tally, so that their state does not depend on the order they are listed in.
+pub struct AllOfPolicy {
+    pub config: PolicyConfig,
+    pub policies: Vec<TrafficControlPolicy>,
+}
+
+impl AllOfPolicy {
+    pub async fn new(config: PolicyConfig, policy_types: Vec<PolicyType>) -> anyhow::Result<Self> {
+        let policies = nested_policies(&config, policy_types).await?;
+        Ok(Self { config, policies })
+    }
+
+    pub fn handle_tally(&mut self, tally: TrafficTally) -> PolicyResponse {
+        if self.policies.is_empty() {
+            return PolicyResponse::default();
+        }
+
+        let responses: Vec<_> = self
+            .policies
+            .iter_mut()
+            .map(|policy| policy.handle_tally(tally.clone()))
+            .collect();
+        PolicyResponse {
+            block_client: tally
+                .direct
+                .filter(|_| responses.iter().all(|r| r.block_client.is_some())),
+            block_proxied_client: tally
+                .through_fullnode
+                .filter(|_| responses.iter().all(|r| r.block_proxied_client.is_some())),
+        }
+    }
+
+    fn policy_config(&self) -> &PolicyConfig {
+        &self.config
+    }
+}
+
 ////////////// *** Test policies below this point *** //////////////
 
 #[derive(Clone)]
@@ -538,7 +948,8 @@ mod tests {
                 update_interval_secs: 1,
                 ..Default::default()
             },
-        );
+        )
+        .unwrap();
         // alice and bob connection from different IPs through the
         // same fullnode, thus have the same connection IP on
         // validator, but different proxy IPs
@@ -697,4 +1108,253 @@ mod tests {
             "Memory estimate {mem_estimate} for traffic sketch exceeds 128MB."
         );
     }
+
+    fn tally(direct: [u8; 4], proxied: [u8; 4], error_type: Option<&str>) -> TrafficTally {
+        TrafficTally {
+            direct: Some(IpAddr::V4(Ipv4Addr::from(direct))),
+            through_fullnode: Some(IpAddr::V4(Ipv4Addr::from(proxied))),
+            error_info: error_type.map(|e| (Weight::one(), e.to_string())),
+            spam_weight: Weight::one(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    #[sim_test]
+    async fn test_token_bucket_policy() {
+        // Proxied clients can burst 5 requests, refilling at 2 requests per second,
+        // and connections 10 requests, refilling at 4 requests per second.
+        let mut policy = TokenBucketPolicy::new(
+            PolicyConfig::default(),
+            TokenBucketConfig {
+                client_capacity: 10,
+                client_refill_rate: 4.0,
+                proxied_client_capacity: 5,
+                proxied_client_refill_rate: 2.0,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let alice = tally([8, 7, 6, 5], [1, 2, 3, 4], None);
+        let bob = tally([8, 7, 6, 5], [4, 3, 2, 1], None);
+
+        // alice can burst up to the bucket capacity
+        for i in 0..5 {
+            let response = policy.handle_tally(alice.clone());
+            assert_eq!(response.block_proxied_client, None, "Blocked at i = {}", i);
+            assert_eq!(response.block_client, None);
+        }
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_proxied_client, alice.through_fullnode);
+        assert_eq!(response.block_client, None);
+
+        // bob has a bucket of his own, but shares the connection IP with alice,
+        // whose bucket runs out after 10 requests between them
+        for i in 0..4 {
+            let response = policy.handle_tally(bob.clone());
+            assert_eq!(response.block_proxied_client, None, "Blocked at i = {}", i);
+            assert_eq!(response.block_client, None);
+        }
+        let response = policy.handle_tally(bob.clone());
+        assert_eq!(response.block_proxied_client, None);
+        assert_eq!(response.block_client, bob.direct);
+
+        // after a second, alice has refilled enough for two more requests
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        for _ in 0..2 {
+            let response = policy.handle_tally(alice.clone());
+            assert_eq!(response.block_proxied_client, None);
+        }
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_proxied_client, alice.through_fullnode);
+    }
+
+    #[sim_test]
+    async fn test_token_bucket_eviction() {
+        let mut buckets = TokenBuckets::new(2, 1.0, 4).unwrap();
+        let now = Instant::now();
+        let client = |i: u8| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i));
+
+        // fill the table with partially drained buckets
+        for i in 0..4 {
+            for _ in 0..=(i % 2) {
+                assert!(!buckets.take(client(i), now));
+            }
+        }
+        assert_eq!(buckets.buckets.len(), 4);
+
+        // a new client forces out the least drained half
+        assert!(!buckets.take(client(4), now));
+        assert!(buckets.buckets.len() <= 4);
+        assert!(buckets.buckets.contains_key(&client(1)));
+        assert!(buckets.buckets.contains_key(&client(3)));
+        assert!(!buckets.buckets.contains_key(&client(0)));
+    }
+
+    #[sim_test]
+    async fn test_eviction_with_equal_scores() {
+        let now = Instant::now();
+        let client = |i: u8| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i));
+
+        // all buckets are equally drained, so only enough of them to make room
+        // for new clients are evicted, rather than all of them
+        let mut buckets = TokenBuckets::new(1, 1.0, 4).unwrap();
+        for i in 0..4 {
+            assert!(!buckets.take(client(i), now));
+        }
+        assert!(!buckets.take(client(4), now));
+        assert_eq!(buckets.buckets.len(), 3);
+        assert!(buckets.buckets.contains_key(&client(4)));
+
+        let mut scores = DecayingScores::new(Duration::from_secs(1), 4).unwrap();
+        for i in 0..4 {
+            scores.add(client(i), 1.0, now);
+        }
+        scores.add(client(4), 1.0, now);
+        assert_eq!(scores.scores.len(), 3);
+        assert!(scores.scores.contains_key(&client(4)));
+    }
+
+    #[sim_test]
+    async fn test_invalid_policy_config() {
+        let invalid = [
+            PolicyType::FreqThreshold(FreqThresholdConfig {
+                update_interval_secs: 0,
+                ..Default::default()
+            }),
+            PolicyType::FreqThreshold(FreqThresholdConfig {
+                window_size_secs: 1200,
+                ..Default::default()
+            }),
+            PolicyType::TokenBucket(TokenBucketConfig {
+                client_capacity: 0,
+                ..Default::default()
+            }),
+            PolicyType::TokenBucket(TokenBucketConfig {
+                proxied_client_refill_rate: 0.0,
+                ..Default::default()
+            }),
+            PolicyType::ErrorWeighted(ErrorWeightedConfig {
+                half_life_secs: 0,
+                ..Default::default()
+            }),
+            // errors in nested policies are returned too
+            PolicyType::AnyOf(vec![PolicyType::ErrorWeighted(ErrorWeightedConfig {
+                max_tracked_clients: 0,
+                ..Default::default()
+            })]),
+        ];
+        for policy_type in invalid {
+            assert!(
+                TrafficControlPolicy::from_config(policy_type.clone(), PolicyConfig::default())
+                    .await
+                    .is_err(),
+                "{policy_type:?} should be rejected"
+            );
+        }
+    }
+
+    #[sim_test]
+    async fn test_error_weighted_policy() {
+        let mut policy = ErrorWeightedPolicy::new(
+            PolicyConfig::default(),
+            ErrorWeightedConfig {
+                error_weights: [("InvalidSignature".to_string(), 5.0)].into(),
+                default_weight: 1.0,
+                client_threshold: 9.5,
+                proxied_client_threshold: 9.5,
+                half_life_secs: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // requests without errors do not count
+        for _ in 0..20 {
+            let response = policy.handle_tally(tally([8, 7, 6, 5], [1, 2, 3, 4], None));
+            assert_eq!(response.block_client, None);
+            assert_eq!(response.block_proxied_client, None);
+        }
+
+        // two invalid signatures block alice...
+        let alice = tally([8, 7, 6, 5], [1, 2, 3, 4], Some("InvalidSignature"));
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_proxied_client, None);
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_proxied_client, alice.through_fullnode);
+
+        // ...while bob gets nine tries of other errors
+        let bob = tally([9, 9, 9, 9], [4, 3, 2, 1], Some("ObjectNotFound"));
+        for i in 0..9 {
+            let response = policy.handle_tally(bob.clone());
+            assert_eq!(response.block_proxied_client, None, "Blocked at i = {}", i);
+        }
+        let response = policy.handle_tally(bob.clone());
+        assert_eq!(response.block_proxied_client, bob.through_fullnode);
+        assert_eq!(response.block_client, bob.direct);
+
+        // scores decay, so after a few half lives alice is forgiven
+        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        let response = policy.handle_tally(alice.clone());
+        assert_eq!(response.block_proxied_client, None);
+    }
+
+    #[sim_test]
+    async fn test_combinator_policies() {
+        let config = PolicyConfig {
+            connection_blocklist_ttl_sec: 60,
+            ..Default::default()
+        };
+        let policies = vec![
+            PolicyType::TestNConnIP(2),
+            PolicyType::TokenBucket(TokenBucketConfig {
+                client_capacity: 3,
+                client_refill_rate: 0.001,
+                ..Default::default()
+            }),
+        ];
+        let mut any_of = AnyOfPolicy::new(config.clone(), policies.clone())
+            .await
+            .unwrap();
+        let mut all_of = AllOfPolicy::new(config.clone(), policies).await.unwrap();
+        let alice = tally([8, 7, 6, 5], [1, 2, 3, 4], None);
+
+        // the first tally blocks with neither policy
+        assert_eq!(any_of.handle_tally(alice.clone()).block_client, None);
+        assert_eq!(all_of.handle_tally(alice.clone()).block_client, None);
+
+        // TestNConnIP blocks from the second tally, the token bucket from the fourth
+        for _ in 0..2 {
+            assert_eq!(
+                any_of.handle_tally(alice.clone()).block_client,
+                alice.direct
+            );
+            assert_eq!(all_of.handle_tally(alice.clone()).block_client, None);
+        }
+        assert_eq!(
+            any_of.handle_tally(alice.clone()).block_client,
+            alice.direct
+        );
+        assert_eq!(
+            all_of.handle_tally(alice.clone()).block_client,
+            alice.direct
+        );
+
+        // combinators nest, and are built from config
+        let mut nested = TrafficControlPolicy::from_config(
+            PolicyType::AllOf(vec![PolicyType::AnyOf(vec![PolicyType::TestNConnIP(1)])]),
+            config,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            nested.handle_tally(alice.clone()).block_client,
+            alice.direct
+        );
+
+        // an empty AllOf never blocks
+        let mut empty = AllOfPolicy::new(PolicyConfig::default(), vec![])
+            .await
+            .unwrap();
+        assert_eq!(empty.handle_tally(alice).block_client, None);
+    }
 }
@@ -98,7 +98,28 @@ async fn basic_flow() {
         .unwrap();
     let path = temp_dir();
     for checkpoint_number in 0..20 {
-        let bytes = mock_checkpoint_data_bytes(checkpoint_number);
+        let bytes = mock_checkpoint_data_bytes(checkpoint_number, BlobEncoding::Bcs);
+        std::fs::write(path.join(format!("{}.chk", checkpoint_number)), bytes).unwrap();
+    }
+    let result = run(bundle.executor, Some(path), Some(Duration::from_secs(1))).await;
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap().get("test"), Some(&20));
+}
+
+#[tokio::test]
+async fn compressed_checkpoints() {
+    let mut bundle = create_executor_bundle();
+    add_worker_pool(&mut bundle.executor, TestWorker, 5)
+        .await
+        .unwrap();
+    let path = temp_dir();
+    for checkpoint_number in 0..20 {
+        let encoding = if checkpoint_number % 2 == 0 {
+            BlobEncoding::Zstd
+        } else {
+            BlobEncoding::Bcs
+        };
+        let bytes = mock_checkpoint_data_bytes(checkpoint_number, encoding);
         std::fs::write(path.join(format!("{}.chk", checkpoint_number)), bytes).unwrap();
     }
     let result = run(bundle.executor, Some(path), Some(Duration::from_secs(1))).await;
@@ -133,7 +154,10 @@ const RNG_SEED: [u8; 32] = [
     179, 179, 65, 9, 31, 249, 221, 123, 225, 112, 199, 247,
 ];
 
-fn mock_checkpoint_data_bytes(seq_number: CheckpointSequenceNumber) -> Vec<u8> {
+fn mock_checkpoint_data_bytes(
+    seq_number: CheckpointSequenceNumber,
+    encoding: BlobEncoding,
+) -> Vec<u8> {
     let mut rng = StdRng::from_seed(RNG_SEED);
     let (keys, committee) = make_committee_key(&mut rng);
     let contents = CheckpointContents::new_with_digests_only_for_tests(vec![]);
@@ -165,7 +189,5 @@ fn mock_checkpoint_data_bytes(seq_number: CheckpointSequenceNumber) -> Vec<u8> {
         checkpoint_contents: contents,
         transactions: vec![],
     };
-    Blob::encode(&checkpoint_data, BlobEncoding::Bcs)
-        .unwrap()
-        .to_bytes()
+    Blob::encode(&checkpoint_data, encoding).unwrap().to_bytes()
 }
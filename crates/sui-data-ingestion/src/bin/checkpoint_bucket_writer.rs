@@ -13,6 +13,7 @@ use sui_data_ingestion_core::{
     DataIngestionMetrics, FileProgressStore, IndexerExecutor, ProgressStore, ReaderOptions,
     WorkerPool, create_remote_store_client, end_of_epoch_data,
 };
+use sui_storage::blob::BlobEncoding;
 use tokio::sync::oneshot;
 
 static TASK_NAME: String = String::new();
@@ -29,6 +30,8 @@ struct Config {
     concurrency: usize,
     #[serde(default = "default_timeout_secs")]
     timeout_secs: u64,
+    #[serde(default)]
+    encoding: BlobEncoding,
 }
 
 fn default_timeout_secs() -> u64 {
@@ -97,6 +100,7 @@ async fn main() -> Result<()> {
     let worker = BlobWorker::new(BlobTaskConfig {
         url: config.target_url,
         remote_store_options: config.target_remote_store_options,
+        encoding: config.encoding,
     });
     let worker_pool = WorkerPool::new(worker, TASK_NAME.clone(), config.concurrency);
     executor.register(worker_pool).await?;
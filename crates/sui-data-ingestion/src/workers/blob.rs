@@ -16,10 +16,14 @@ use sui_types::messages_checkpoint::CheckpointSequenceNumber;
 pub struct BlobTaskConfig {
     pub url: String,
     pub remote_store_options: Vec<(String, String)>,
+    /// Encoding of the uploaded checkpoint files, `bcs` unless set.
+    #[serde(default)]
+    pub encoding: BlobEncoding,
 }
 
 pub struct BlobWorker {
     remote_store: Box<dyn ObjectStore>,
+    encoding: BlobEncoding,
 }
 
 impl BlobWorker {
@@ -27,6 +31,7 @@ impl BlobWorker {
         Self {
             remote_store: create_remote_store_client(config.url, config.remote_store_options, 10)
                 .expect("failed to create remote store client"),
+            encoding: config.encoding,
         }
     }
 }
@@ -35,7 +40,7 @@ impl BlobWorker {
 impl Worker for BlobWorker {
     type Result = ();
     async fn process_checkpoint(&self, checkpoint: &CheckpointData) -> Result<()> {
-        let bytes = Blob::encode(checkpoint, BlobEncoding::Bcs)?.to_bytes();
+        let bytes = Blob::encode(checkpoint, self.encoding)?.to_bytes();
         let location = Path::from(format!(
             "{}.chk",
             checkpoint.checkpoint_summary.sequence_number
@@ -0,0 +1,154 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{stake_with_validator, transfer_coin};
+use sui_macros::sim_test;
+use sui_rpc::field::FieldMask;
+use sui_rpc::field::FieldMaskUtil;
+use sui_rpc_api::grpc::alpha::subscription_service_proto::subscription_service_client::SubscriptionServiceClient;
+use sui_rpc_api::grpc::alpha::subscription_service_proto::{
+    EventFilter, SubscribeEventsRequest, SubscribeTransactionsRequest, TransactionFilter,
+};
+use sui_types::base_types::SuiAddress;
+use test_cluster::TestClusterBuilder;
+use tokio_stream::StreamExt;
+
+fn sender_filter(sender: String) -> TransactionFilter {
+    let mut filter = TransactionFilter::default();
+    filter.sender = Some(sender);
+    filter
+}
+
+#[sim_test]
+async fn subscribe_transactions_by_sender() {
+    let test_cluster = TestClusterBuilder::new().build().await;
+    let sender = test_cluster
+        .wallet
+        .get_all_accounts_and_gas_objects()
+        .await
+        .unwrap()[0]
+        .0
+        .to_string();
+
+    let mut client = SubscriptionServiceClient::connect(test_cluster.rpc_url().to_owned())
+        .await
+        .unwrap();
+
+    let mut request = SubscribeTransactionsRequest::default();
+    request.filters = vec![sender_filter(sender)];
+    request.read_mask = Some(FieldMask::from_str("digest,checkpoint"));
+    let mut stream = client
+        .subscribe_transactions(request.clone())
+        .await
+        .unwrap()
+        .into_inner();
+
+    let first = transfer_coin(&test_cluster.wallet).await;
+    let response = stream.next().await.unwrap().unwrap();
+    let transaction = response.transaction.unwrap();
+    assert_eq!(transaction.digest.unwrap(), first.to_string());
+    let checkpoint = transaction.checkpoint.unwrap();
+    let cursor = response.cursor.unwrap();
+    drop(stream);
+
+    // Transactions executed while disconnected are delivered after resuming from the cursor,
+    // without repeating the transaction of the cursor.
+    let second = transfer_coin(&test_cluster.wallet).await;
+    request.cursor = Some(cursor);
+    let mut stream = client
+        .subscribe_transactions(request.clone())
+        .await
+        .unwrap()
+        .into_inner();
+    let response = stream.next().await.unwrap().unwrap();
+    assert_eq!(
+        response.transaction.unwrap().digest.unwrap(),
+        second.to_string()
+    );
+
+    // Starting from a checkpoint replays all matching transactions since then.
+    request.cursor = None;
+    request.start_checkpoint = Some(checkpoint);
+    let mut stream = client
+        .subscribe_transactions(request.clone())
+        .await
+        .unwrap()
+        .into_inner();
+    for expected in [first, second] {
+        let response = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            response.transaction.unwrap().digest.unwrap(),
+            expected.to_string()
+        );
+    }
+
+    // Transactions from other senders are filtered out.
+    request.start_checkpoint = Some(0);
+    request.filters = vec![sender_filter(
+        SuiAddress::random_for_testing_only().to_string(),
+    )];
+    request.read_mask = None;
+    let mut stream = client
+        .subscribe_transactions(request)
+        .await
+        .unwrap()
+        .into_inner();
+    let _third = transfer_coin(&test_cluster.wallet).await;
+    let next = tokio::time::timeout(std::time::Duration::from_secs(10), stream.next()).await;
+    assert!(next.is_err());
+}
+
+#[sim_test]
+async fn subscribe_events_by_type() {
+    let test_cluster = TestClusterBuilder::new().build().await;
+
+    let mut client = SubscriptionServiceClient::connect(test_cluster.rpc_url().to_owned())
+        .await
+        .unwrap();
+
+    let mut filter = EventFilter::default();
+    filter.event_type = Some("0x3::validator::StakingRequestEvent".to_owned());
+    let mut request = SubscribeEventsRequest::default();
+    request.filters = vec![filter];
+    let mut stream = client.subscribe_events(request).await.unwrap().into_inner();
+
+    // Transfers don't emit events, so the first event is from staking.
+    let _transfer = transfer_coin(&test_cluster.wallet).await;
+    let digest = stake_with_validator(&test_cluster).await;
+
+    let response = stream.next().await.unwrap().unwrap();
+    assert_eq!(response.transaction_digest.unwrap(), digest.to_string());
+    let event = response.event.unwrap();
+    assert_eq!(
+        event.event_type.unwrap(),
+        "0x0000000000000000000000000000000000000000000000000000000000000003::validator::StakingRequestEvent"
+    );
+    assert_eq!(event.module.unwrap(), "sui_system");
+    assert!(event.contents.is_some());
+}
+
+#[sim_test]
+async fn subscribe_invalid_requests() {
+    let test_cluster = TestClusterBuilder::new().build().await;
+
+    let mut client = SubscriptionServiceClient::connect(test_cluster.rpc_url().to_owned())
+        .await
+        .unwrap();
+
+    let mut filter = EventFilter::default();
+    filter.module = Some("coin".to_owned());
+    let mut request = SubscribeEventsRequest::default();
+    request.filters = vec![filter];
+    let error = client.subscribe_events(request).await.unwrap_err();
+    assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+    let mut request = SubscribeTransactionsRequest::default();
+    request.cursor = Some(vec![1, 2, 3]);
+    let error = client.subscribe_transactions(request).await.unwrap_err();
+    assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+    let mut request = SubscribeTransactionsRequest::default();
+    request.filters = vec![TransactionFilter::default(); 17];
+    let error = client.subscribe_transactions(request).await.unwrap_err();
+    assert_eq!(error.code(), tonic::Code::InvalidArgument);
+}
@@ -0,0 +1,109 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use prost::Message;
+use serde_json::{Value, json};
+use sui_macros::sim_test;
+use sui_rpc::proto::sui::rpc::v2::GetServiceInfoResponse;
+use sui_sdk_types::Address;
+use test_cluster::TestClusterBuilder;
+
+async fn post_json(base: &str, path: &str, body: Value) -> (reqwest::StatusCode, Value) {
+    let response = reqwest::Client::new()
+        .post(format!("{}{path}", base.trim_end_matches('/')))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status();
+    (status, response.json().await.unwrap())
+}
+
+#[sim_test]
+async fn json_transcoding() {
+    let test_cluster = TestClusterBuilder::new().build().await;
+    let url = test_cluster.rpc_url();
+
+    let (status, info) = post_json(url, "/v2/ledger/get_service_info", json!({})).await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert!(info["chainId"].is_string());
+    assert!(info["checkpointHeight"].is_string());
+
+    let id: Address = "0x5".parse().unwrap();
+    let (status, response) = post_json(
+        url,
+        "/v2/ledger/get_object",
+        json!({ "objectId": "0x5", "readMask": "objectId,version,owner" }),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    let object = &response["object"];
+    assert_eq!(object["objectId"], id.to_string());
+    assert!(object["version"].is_string());
+    assert!(object["owner"].is_object());
+    assert!(object.get("digest").is_none());
+
+    // Errors are returned as a `google.rpc.Status` with a matching HTTP status code.
+    let (status, error) = post_json(
+        url,
+        "/v2/ledger/get_object",
+        json!({ "objectId": "not an object id" }),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(error["code"], tonic::Code::InvalidArgument as i32);
+    assert!(error["details"].is_array());
+
+    let (status, error) = post_json(
+        url,
+        "/v2/ledger/get_object",
+        json!({ "objectId": Address::ZERO.to_string() }),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+    assert_eq!(error["code"], tonic::Code::NotFound as i32);
+
+    let (status, error) =
+        post_json(url, "/v2/ledger/get_object", json!({ "unknownField": 1 })).await;
+    assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(error["code"], tonic::Code::InvalidArgument as i32);
+}
+
+#[sim_test]
+async fn grpc_web() {
+    let test_cluster = TestClusterBuilder::new().build().await;
+
+    // An empty `GetServiceInfoRequest` in a single uncompressed frame.
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/sui.rpc.v2.LedgerService/GetServiceInfo",
+            test_cluster.rpc_url().trim_end_matches('/')
+        ))
+        .header("content-type", "application/grpc-web+proto")
+        .header("x-grpc-web", "1")
+        .body(vec![0, 0, 0, 0, 0])
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/grpc-web+proto"
+    );
+
+    let body = response.bytes().await.unwrap();
+    assert_eq!(body[0], 0);
+    let len = u32::from_be_bytes(body[1..5].try_into().unwrap()) as usize;
+    let info = GetServiceInfoResponse::decode(&body[5..5 + len]).unwrap();
+    assert!(info.chain_id.is_some());
+
+    // The trailers follow in a frame of their own.
+    let trailers = &body[5 + len..];
+    assert_eq!(trailers[0], 0x80);
+    assert!(
+        String::from_utf8_lossy(&trailers[5..])
+            .to_lowercase()
+            .contains("grpc-status:0")
+    );
+}
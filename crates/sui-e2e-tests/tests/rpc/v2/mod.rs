@@ -16,6 +16,7 @@ use sui_types::base_types::{ObjectID, SuiAddress};
 use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
 use sui_types::transaction::{TransactionData, TransactionKind};
 
+mod json_transcoding;
 mod ledger_service;
 mod move_package_service;
 mod signature_verification_service;
@@ -0,0 +1,802 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! An embedded store for the indexer framework, backed by a local RocksDB database, for running
+//! pipelines on a single machine without operating a database server.
+//!
+//! Watermarks (and pipeline versions) are kept in dedicated column families. Each pipeline writes
+//! its data to one or more tables of its own, which are column families that are opened with the
+//! store, and accessed as typed maps through [EmbeddedStore::table]. Handlers write to their
+//! tables through the [Connection] they are given, so that writes made in a transaction are
+//! committed atomically, together with the watermark.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, ensure};
+use async_trait::async_trait;
+use scoped_futures::ScopedBoxFuture;
+use serde::{Deserialize, Serialize};
+use sui_indexer_alt_framework_store_traits::{
+    self as framework_traits, CommitterWatermark, PrunerWatermark, ReaderWatermark, Store,
+    TransactionalStore,
+};
+use tokio::sync::Mutex;
+use typed_store::Map;
+use typed_store::TypedStoreError;
+use typed_store::rocks::{
+    DBBatch, DBMap, Database, MetricConf, ReadWriteOptions, default_db_options, open_cf_opts,
+};
+
+/// Column family holding the watermarks of all pipelines and pipeline tasks.
+const WATERMARKS: &str = "watermarks";
+
+/// Column family mapping logical pipelines to their active version.
+const PIPELINE_VERSIONS: &str = "pipeline_versions";
+
+#[derive(Clone)]
+pub struct EmbeddedStore(Arc<Inner>);
+
+struct Inner {
+    db: Arc<Database>,
+    watermarks: DBMap<String, StoredWatermark>,
+    pipeline_versions: DBMap<String, String>,
+
+    /// Names of the pipeline tables that the store was opened with.
+    tables: BTreeSet<String>,
+
+    /// Serializes transactions with each other, and with updates to watermarks and pipeline
+    /// versions outside of transactions, which read their current value before writing it.
+    write_lock: Mutex<()>,
+}
+
+pub struct Connection<'c> {
+    store: &'c EmbeddedStore,
+
+    /// Writes staged by the transaction this connection belongs to, if there is one. They are
+    /// written to the database atomically when the transaction commits.
+    staged: Option<Staged>,
+}
+
+struct Staged {
+    batch: DBBatch,
+
+    /// Watermarks and pipeline versions written in the transaction so far, so that it can read
+    /// its own writes to them.
+    watermarks: BTreeMap<String, StoredWatermark>,
+    pipeline_versions: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+struct StoredWatermark {
+    epoch_hi_inclusive: u64,
+    checkpoint_hi_inclusive: u64,
+    tx_hi: u64,
+    timestamp_ms_hi_inclusive: u64,
+    reader_lo: u64,
+    pruner_timestamp_ms: u64,
+    pruner_hi: u64,
+}
+
+impl EmbeddedStore {
+    /// Open (or create) the store at `path`, with a table for each of the names in `tables`.
+    pub fn open(path: impl AsRef<Path>, tables: &[&str]) -> anyhow::Result<Self> {
+        let tables: BTreeSet<String> = tables.iter().map(|t| t.to_string()).collect();
+        for reserved in [WATERMARKS, PIPELINE_VERSIONS] {
+            ensure!(
+                !tables.contains(reserved),
+                "Table name {reserved:?} is reserved by the store",
+            );
+        }
+
+        let cfs: Vec<_> = [WATERMARKS, PIPELINE_VERSIONS]
+            .into_iter()
+            .chain(tables.iter().map(String::as_str))
+            .map(|cf| (cf, default_db_options().options))
+            .collect();
+
+        let path = path.as_ref();
+        let db = open_cf_opts(path, None, MetricConf::new("indexer_alt_embedded"), &cfs)
+            .with_context(|| format!("Failed to open embedded store at {}", path.display()))?;
+
+        let watermarks = DBMap::reopen(&db, Some(WATERMARKS), &read_write_options(), false)?;
+        let pipeline_versions =
+            DBMap::reopen(&db, Some(PIPELINE_VERSIONS), &read_write_options(), false)?;
+
+        Ok(Self(Arc::new(Inner {
+            db,
+            watermarks,
+            pipeline_versions,
+            tables,
+            write_lock: Mutex::new(()),
+        })))
+    }
+
+    /// A typed view of the table called `name`, which must be one of the tables the store was
+    /// opened with. Reads through this view see data that has been committed to the store, and
+    /// writes must go through a [Connection], to be included in its transaction.
+    pub fn table<K, V>(&self, name: &str) -> anyhow::Result<DBMap<K, V>> {
+        ensure!(
+            self.0.tables.contains(name),
+            "Table {name:?} was not opened with the store",
+        );
+
+        Ok(DBMap::reopen(
+            &self.0.db,
+            Some(name),
+            &read_write_options(),
+            false,
+        )?)
+    }
+}
+
+impl Connection<'_> {
+    /// Insert `entries` into `table`, overwriting any existing values for their keys.
+    pub fn insert_batch<K, V>(
+        &mut self,
+        table: &DBMap<K, V>,
+        entries: impl IntoIterator<Item = (K, V)>,
+    ) -> anyhow::Result<()>
+    where
+        K: Serialize,
+        V: Serialize,
+    {
+        self.write(table, |batch| {
+            batch.insert_batch(table, entries).map(|_| ())
+        })
+    }
+
+    /// Remove the entries for `keys` from `table`.
+    pub fn remove_batch<K, V>(
+        &mut self,
+        table: &DBMap<K, V>,
+        keys: impl IntoIterator<Item = K>,
+    ) -> anyhow::Result<()>
+    where
+        K: Serialize,
+    {
+        self.write(table, |batch| batch.delete_batch(table, keys))
+    }
+
+    /// Remove all entries from `table` whose keys are between `from` (inclusive) and
+    /// `to_exclusive`, in their serialized order. Intended for pruning, with keys that start with a
+    /// checkpoint sequence number.
+    pub fn remove_range<K, V>(
+        &mut self,
+        table: &DBMap<K, V>,
+        from: &K,
+        to_exclusive: &K,
+    ) -> anyhow::Result<()>
+    where
+        K: Serialize,
+    {
+        self.write(table, |batch| {
+            batch.schedule_delete_range(table, from, to_exclusive)
+        })
+    }
+
+    /// Apply `f` to the transaction's batch, if this connection belongs to a transaction, or to a
+    /// new batch that is written immediately, otherwise.
+    fn write<K, V>(
+        &mut self,
+        table: &DBMap<K, V>,
+        f: impl FnOnce(&mut DBBatch) -> Result<(), TypedStoreError>,
+    ) -> anyhow::Result<()> {
+        if let Some(staged) = &mut self.staged {
+            return Ok(f(&mut staged.batch)?);
+        }
+
+        let mut batch = table.batch();
+        f(&mut batch)?;
+        batch.write()?;
+        Ok(())
+    }
+
+    fn watermark(&self, pipeline_task: &str) -> anyhow::Result<Option<StoredWatermark>> {
+        if let Some(w) = self
+            .staged
+            .as_ref()
+            .and_then(|s| s.watermarks.get(pipeline_task))
+        {
+            return Ok(Some(*w));
+        }
+
+        Ok(self.store.0.watermarks.get(&pipeline_task.to_owned())?)
+    }
+
+    fn active_version(&self, pipeline: &str) -> anyhow::Result<Option<String>> {
+        if let Some(v) = self
+            .staged
+            .as_ref()
+            .and_then(|s| s.pipeline_versions.get(pipeline))
+        {
+            return Ok(Some(v.clone()));
+        }
+
+        Ok(self.store.0.pipeline_versions.get(&pipeline.to_owned())?)
+    }
+
+    /// Replace the watermark for `pipeline_task` with the result of `f`, unless `f` returns `None`.
+    /// Returns whether the watermark was updated.
+    async fn update_watermark(
+        &mut self,
+        pipeline_task: &str,
+        f: impl FnOnce(Option<StoredWatermark>) -> Option<StoredWatermark>,
+    ) -> anyhow::Result<bool> {
+        let store = self.store;
+        let _guard = match self.staged {
+            // The transaction already holds the lock.
+            Some(_) => None,
+            None => Some(store.0.write_lock.lock().await),
+        };
+
+        let Some(watermark) = f(self.watermark(pipeline_task)?) else {
+            return Ok(false);
+        };
+
+        let key = pipeline_task.to_owned();
+        match &mut self.staged {
+            Some(staged) => {
+                staged
+                    .batch
+                    .insert_batch(&store.0.watermarks, [(&key, &watermark)])?;
+                staged.watermarks.insert(key, watermark);
+            }
+            None => store.0.watermarks.insert(&key, &watermark)?,
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Store for EmbeddedStore {
+    type Connection<'c> = Connection<'c>;
+
+    async fn connect<'c>(&'c self) -> anyhow::Result<Self::Connection<'c>> {
+        Ok(Connection {
+            store: self,
+            staged: None,
+        })
+    }
+}
+
+#[async_trait]
+impl TransactionalStore for EmbeddedStore {
+    /// Transactions are serialized with each other. Reads within a transaction see the data that
+    /// was committed before it started, and its own updates to watermarks, but not its own writes
+    /// to pipeline tables.
+    async fn transaction<'a, R, F>(&self, f: F) -> anyhow::Result<R>
+    where
+        R: Send + 'a,
+        F: Send + 'a,
+        F: for<'r> FnOnce(
+            &'r mut Self::Connection<'_>,
+        ) -> ScopedBoxFuture<'a, 'r, anyhow::Result<R>>,
+    {
+        let _guard = self.0.write_lock.lock().await;
+
+        let mut conn = Connection {
+            store: self,
+            staged: Some(Staged {
+                batch: self.0.watermarks.batch(),
+                watermarks: BTreeMap::new(),
+                pipeline_versions: BTreeMap::new(),
+            }),
+        };
+
+        // On error, the staged writes are dropped without being written.
+        let result = f(&mut conn).await?;
+
+        if let Some(staged) = conn.staged.take() {
+            staged
+                .batch
+                .write()
+                .context("Failed to commit transaction")?;
+        }
+
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl framework_traits::Connection for Connection<'_> {
+    async fn committer_watermark(
+        &mut self,
+        pipeline_task: &str,
+    ) -> anyhow::Result<Option<CommitterWatermark>> {
+        Ok(self.watermark(pipeline_task)?.map(|w| CommitterWatermark {
+            epoch_hi_inclusive: w.epoch_hi_inclusive,
+            checkpoint_hi_inclusive: w.checkpoint_hi_inclusive,
+            tx_hi: w.tx_hi,
+            timestamp_ms_hi_inclusive: w.timestamp_ms_hi_inclusive,
+        }))
+    }
+
+    async fn reader_watermark(
+        &mut self,
+        pipeline: &'static str,
+    ) -> anyhow::Result<Option<ReaderWatermark>> {
+        Ok(self.watermark(pipeline)?.map(|w| ReaderWatermark {
+            checkpoint_hi_inclusive: w.checkpoint_hi_inclusive,
+            reader_lo: w.reader_lo,
+        }))
+    }
+
+    async fn pruner_watermark(
+        &mut self,
+        pipeline: &'static str,
+        delay: Duration,
+    ) -> anyhow::Result<Option<PrunerWatermark>> {
+        let now = now_ms();
+        Ok(self.watermark(pipeline)?.map(|w| PrunerWatermark {
+            wait_for_ms: (w.pruner_timestamp_ms as i64 + delay.as_millis() as i64) - now as i64,
+            reader_lo: w.reader_lo,
+            pruner_hi: w.pruner_hi,
+        }))
+    }
+
+    async fn set_committer_watermark(
+        &mut self,
+        pipeline_task: &str,
+        watermark: CommitterWatermark,
+    ) -> anyhow::Result<bool> {
+        self.update_watermark(pipeline_task, |current| {
+            if current
+                .is_some_and(|c| c.checkpoint_hi_inclusive >= watermark.checkpoint_hi_inclusive)
+            {
+                return None;
+            }
+
+            // Only the `hi` values are written over an existing entry.
+            let current = current.unwrap_or_default();
+            Some(StoredWatermark {
+                epoch_hi_inclusive: watermark.epoch_hi_inclusive,
+                checkpoint_hi_inclusive: watermark.checkpoint_hi_inclusive,
+                tx_hi: watermark.tx_hi,
+                timestamp_ms_hi_inclusive: watermark.timestamp_ms_hi_inclusive,
+                ..current
+            })
+        })
+        .await
+    }
+
+    async fn set_reader_watermark(
+        &mut self,
+        pipeline: &'static str,
+        reader_lo: u64,
+    ) -> anyhow::Result<bool> {
+        self.update_watermark(pipeline, |current| {
+            let current = current?;
+            (current.reader_lo < reader_lo).then(|| StoredWatermark {
+                reader_lo,
+                pruner_timestamp_ms: now_ms(),
+                ..current
+            })
+        })
+        .await
+    }
+
+    async fn set_pruner_watermark(
+        &mut self,
+        pipeline: &'static str,
+        pruner_hi: u64,
+    ) -> anyhow::Result<bool> {
+        self.update_watermark(pipeline, |current| {
+            Some(StoredWatermark {
+                pruner_hi,
+                ..current?
+            })
+        })
+        .await
+    }
+
+    async fn active_pipeline_version(&mut self, pipeline: &str) -> anyhow::Result<Option<String>> {
+        self.active_version(pipeline)
+    }
+
+    async fn promote_pipeline_version(
+        &mut self,
+        pipeline: &str,
+        version: &str,
+    ) -> anyhow::Result<bool> {
+        let store = self.store;
+        let _guard = match self.staged {
+            Some(_) => None,
+            None => Some(store.0.write_lock.lock().await),
+        };
+
+        let current = self
+            .active_version(pipeline)?
+            .unwrap_or_else(|| pipeline.to_owned());
+        if current == version {
+            return Ok(false);
+        }
+
+        let Some(next) = self.watermark(version)? else {
+            return Ok(false);
+        };
+
+        if let Some(current) = self.watermark(&current)?
+            && next.checkpoint_hi_inclusive < current.checkpoint_hi_inclusive
+        {
+            return Ok(false);
+        }
+
+        let (key, version) = (pipeline.to_owned(), version.to_owned());
+        match &mut self.staged {
+            Some(staged) => {
+                staged
+                    .batch
+                    .insert_batch(&store.0.pipeline_versions, [(&key, &version)])?;
+                staged.pipeline_versions.insert(key, version);
+            }
+            None => store.0.pipeline_versions.insert(&key, &version)?,
+        }
+
+        Ok(true)
+    }
+}
+
+/// Pruned ranges are removed with range deletions, which reads must not ignore.
+fn read_write_options() -> ReadWriteOptions {
+    ReadWriteOptions::default().set_ignore_range_deletions(false)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::Registry;
+    use scoped_futures::ScopedFutureExt;
+    use sui_indexer_alt_framework::ingestion::ingestion_client::IngestionClientArgs;
+    use sui_indexer_alt_framework::ingestion::{ClientArgs, IngestionConfig};
+    use sui_indexer_alt_framework::pipeline::Processor;
+    use sui_indexer_alt_framework::pipeline::sequential::{self, SequentialConfig};
+    use sui_indexer_alt_framework::types::full_checkpoint_content::Checkpoint;
+    use sui_indexer_alt_framework::{Indexer, IndexerArgs};
+    use sui_indexer_alt_framework_store_traits::Connection as _;
+    use sui_synthetic_ingestion::synthetic_ingestion;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+
+    const TABLE: &str = "checkpoints";
+
+    /// A sequential pipeline that records the number of transactions in each checkpoint.
+    struct TxCounts(DBMap<u64, u64>);
+
+    #[async_trait]
+    impl Processor for TxCounts {
+        const NAME: &'static str = "tx_counts";
+        type Value = (u64, u64);
+
+        async fn process(&self, checkpoint: &Arc<Checkpoint>) -> anyhow::Result<Vec<Self::Value>> {
+            Ok(vec![(
+                checkpoint.summary.sequence_number,
+                checkpoint.transactions.len() as u64,
+            )])
+        }
+    }
+
+    #[async_trait]
+    impl sequential::Handler for TxCounts {
+        type Store = EmbeddedStore;
+        type Batch = Vec<Self::Value>;
+
+        fn batch(&self, batch: &mut Self::Batch, values: std::vec::IntoIter<Self::Value>) {
+            batch.extend(values);
+        }
+
+        async fn commit<'a>(
+            &self,
+            batch: &Self::Batch,
+            conn: &mut Connection<'a>,
+        ) -> anyhow::Result<usize> {
+            conn.insert_batch(&self.0, batch.iter().copied())?;
+            Ok(batch.len())
+        }
+    }
+
+    /// Run the [TxCounts] pipeline on `store` until `last_checkpoint`, reading checkpoints from
+    /// `ingestion_dir`. Returns the number of checkpoints that were ingested.
+    async fn run_tx_counts(
+        store: EmbeddedStore,
+        ingestion_dir: &Path,
+        last_checkpoint: u64,
+    ) -> u64 {
+        let table = store.table(TABLE).unwrap();
+        let mut indexer = Indexer::new(
+            store,
+            IndexerArgs {
+                last_checkpoint: Some(last_checkpoint),
+                ..Default::default()
+            },
+            ClientArgs {
+                ingestion: IngestionClientArgs {
+                    local_ingestion_path: Some(ingestion_dir.to_owned()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            IngestionConfig::default(),
+            None,
+            &Registry::new(),
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        indexer
+            .sequential_pipeline(TxCounts(table), SequentialConfig::default())
+            .await
+            .unwrap();
+
+        let ingestion_metrics = indexer.ingestion_metrics().clone();
+        indexer.run().await.unwrap().await.unwrap();
+        ingestion_metrics.total_ingested_checkpoints.get()
+    }
+
+    fn watermark(checkpoint_hi_inclusive: u64) -> CommitterWatermark {
+        CommitterWatermark {
+            epoch_hi_inclusive: 0,
+            checkpoint_hi_inclusive,
+            tx_hi: checkpoint_hi_inclusive * 10,
+            timestamp_ms_hi_inclusive: 1000 + checkpoint_hi_inclusive,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_committer_watermark_only_advances() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), &[]).unwrap();
+        let mut conn = store.connect().await.unwrap();
+
+        assert!(conn.committer_watermark("p").await.unwrap().is_none());
+        assert!(
+            conn.set_committer_watermark("p", watermark(10))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !conn
+                .set_committer_watermark("p", watermark(5))
+                .await
+                .unwrap()
+        );
+        assert!(
+            conn.set_committer_watermark("p", watermark(20))
+                .await
+                .unwrap()
+        );
+
+        let w = conn.committer_watermark("p").await.unwrap().unwrap();
+        assert_eq!(w.checkpoint_hi_inclusive, 20);
+        assert_eq!(w.tx_hi, 200);
+        assert_eq!(w.timestamp_ms_hi_inclusive, 1020);
+    }
+
+    #[tokio::test]
+    async fn test_reader_and_pruner_watermarks() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), &[]).unwrap();
+        let mut conn = store.connect().await.unwrap();
+
+        // Reader and pruner watermarks can only be set on an existing watermark.
+        assert!(!conn.set_reader_watermark("p", 5).await.unwrap());
+        assert!(!conn.set_pruner_watermark("p", 5).await.unwrap());
+
+        conn.set_committer_watermark("p", watermark(100))
+            .await
+            .unwrap();
+        assert!(conn.set_reader_watermark("p", 50).await.unwrap());
+        assert!(!conn.set_reader_watermark("p", 40).await.unwrap());
+
+        let r = conn.reader_watermark("p").await.unwrap().unwrap();
+        assert_eq!(r.checkpoint_hi_inclusive, 100);
+        assert_eq!(r.reader_lo, 50);
+
+        // The pruner has to wait out the delay from when the reader watermark was set.
+        let p = conn
+            .pruner_watermark("p", Duration::from_secs(60))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(p.wait_for().is_some());
+        assert_eq!((p.pruner_hi, p.reader_lo), (0, 50));
+
+        assert!(conn.set_pruner_watermark("p", 50).await.unwrap());
+        let p = conn
+            .pruner_watermark("p", Duration::ZERO)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(p.wait_for().is_none());
+        assert_eq!(p.pruner_hi, 50);
+
+        // Committing more data does not reset the reader or pruner watermarks.
+        conn.set_committer_watermark("p", watermark(110))
+            .await
+            .unwrap();
+        let p = conn
+            .pruner_watermark("p", Duration::ZERO)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((p.pruner_hi, p.reader_lo), (50, 50));
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commits_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), &[TABLE]).unwrap();
+        let table: &DBMap<u64, String> = &store.table(TABLE).unwrap();
+
+        store
+            .transaction(move |conn| {
+                async move {
+                    conn.insert_batch(&table, [(1, "a".to_owned()), (2, "b".to_owned())])?;
+                    conn.set_committer_watermark("p", watermark(2)).await?;
+
+                    // Watermark writes are visible within the transaction, but not outside.
+                    assert_eq!(
+                        conn.committer_watermark("p")
+                            .await?
+                            .unwrap()
+                            .checkpoint_hi_inclusive,
+                        2
+                    );
+                    assert!(table.get(&1)?.is_none());
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(table.get(&2).unwrap().as_deref(), Some("b"));
+        let mut conn = store.connect().await.unwrap();
+        let w = conn.committer_watermark("p").await.unwrap().unwrap();
+        assert_eq!(w.checkpoint_hi_inclusive, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_transaction_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), &[TABLE]).unwrap();
+        let table: &DBMap<u64, String> = &store.table(TABLE).unwrap();
+
+        let result: anyhow::Result<()> = store
+            .transaction(move |conn| {
+                async move {
+                    conn.insert_batch(&table, [(1, "a".to_owned())])?;
+                    conn.set_committer_watermark("p", watermark(1)).await?;
+                    anyhow::bail!("Handler failed");
+                }
+                .scope_boxed()
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert!(table.get(&1).unwrap().is_none());
+        let mut conn = store.connect().await.unwrap();
+        assert!(conn.committer_watermark("p").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prune_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), &[TABLE]).unwrap();
+        let table: DBMap<u64, String> = store.table(TABLE).unwrap();
+
+        let mut conn = store.connect().await.unwrap();
+        conn.insert_batch(&table, (0..10).map(|i| (i, i.to_string())))
+            .unwrap();
+        conn.remove_range(&table, &0, &5).unwrap();
+        conn.remove_batch(&table, [9]).unwrap();
+
+        let keys: Vec<_> = table.safe_iter().map(|e| e.unwrap().0).collect();
+        assert_eq!(keys, vec![5, 6, 7, 8]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), &[TABLE]).unwrap();
+        assert!(store.table::<u64, u64>("other").is_err());
+        assert!(EmbeddedStore::open(dir.path().join("reserved"), &[WATERMARKS]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reopen_is_durable() {
+        let dir = tempfile::tempdir().unwrap();
+
+        {
+            let store = EmbeddedStore::open(dir.path(), &[TABLE]).unwrap();
+            let table: DBMap<u64, u64> = store.table(TABLE).unwrap();
+            let mut conn = store.connect().await.unwrap();
+            conn.insert_batch(&table, [(7, 49)]).unwrap();
+            conn.set_committer_watermark("p", watermark(7))
+                .await
+                .unwrap();
+        }
+
+        let store = EmbeddedStore::open(dir.path(), &[TABLE]).unwrap();
+        let table: DBMap<u64, u64> = store.table(TABLE).unwrap();
+        let mut conn = store.connect().await.unwrap();
+        assert_eq!(table.get(&7).unwrap(), Some(49));
+        let w = conn.committer_watermark("p").await.unwrap().unwrap();
+        assert_eq!(w.checkpoint_hi_inclusive, 7);
+    }
+
+    #[tokio::test]
+    async fn test_indexer_resumes_from_watermark() {
+        let ingestion_dir = tempfile::tempdir().unwrap();
+        synthetic_ingestion::generate_ingestion(synthetic_ingestion::Config {
+            ingestion_dir: ingestion_dir.path().to_owned(),
+            starting_checkpoint: 0,
+            num_checkpoints: 10,
+            checkpoint_size: 2,
+        })
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        {
+            let store = EmbeddedStore::open(dir.path(), &[TABLE]).unwrap();
+            let ingested = run_tx_counts(store.clone(), ingestion_dir.path(), 4).await;
+            assert_eq!(ingested, 5);
+
+            let mut conn = store.connect().await.unwrap();
+            let w = conn.committer_watermark(TxCounts::NAME).await.unwrap();
+            assert_eq!(w.unwrap().checkpoint_hi_inclusive, 4);
+        }
+
+        // After a restart, the indexer picks up from the checkpoint after the watermark.
+        let store = EmbeddedStore::open(dir.path(), &[TABLE]).unwrap();
+        let ingested = run_tx_counts(store.clone(), ingestion_dir.path(), 9).await;
+        assert_eq!(ingested, 5);
+
+        let mut conn = store.connect().await.unwrap();
+        let w = conn.committer_watermark(TxCounts::NAME).await.unwrap();
+        assert_eq!(w.unwrap().checkpoint_hi_inclusive, 9);
+
+        let table: DBMap<u64, u64> = store.table(TABLE).unwrap();
+        let keys: Vec<_> = table.safe_iter().map(|e| e.unwrap().0).collect();
+        assert_eq!(keys, (0..10).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_promote_pipeline_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = EmbeddedStore::open(dir.path(), &[]).unwrap();
+        let mut conn = store.connect().await.unwrap();
+
+        conn.set_committer_watermark("p", watermark(100))
+            .await
+            .unwrap();
+        conn.set_committer_watermark("p_v2", watermark(50))
+            .await
+            .unwrap();
+
+        assert!(!conn.promote_pipeline_version("p", "p_v2").await.unwrap());
+        assert!(conn.active_pipeline_version("p").await.unwrap().is_none());
+
+        conn.set_committer_watermark("p_v2", watermark(100))
+            .await
+            .unwrap();
+        assert!(conn.promote_pipeline_version("p", "p_v2").await.unwrap());
+        assert!(!conn.promote_pipeline_version("p", "p_v2").await.unwrap());
+        assert_eq!(
+            conn.active_pipeline_version("p").await.unwrap().as_deref(),
+            Some("p_v2")
+        );
+    }
+}
@@ -74,6 +74,27 @@ pub trait Connection: Send {
         pipeline: &'static str,
         pruner_hi: u64,
     ) -> anyhow::Result<bool>;
+
+    /// Given a `pipeline` that readers refer to, return the name of the pipeline version that
+    /// currently serves reads for it, if a new version has been promoted in its place. Stores that
+    /// do not support pipeline versions never have a promoted version.
+    async fn active_pipeline_version(&mut self, _pipeline: &str) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Atomically promote `version` to serve reads for `pipeline`, as long as the committer
+    /// watermark of `version` has caught up with the committer watermark of the version currently
+    /// serving reads (`pipeline` itself, if no version has been promoted yet). Returns a boolean
+    /// indicating whether the version was promoted or not.
+    async fn promote_pipeline_version(
+        &mut self,
+        pipeline: &str,
+        version: &str,
+    ) -> anyhow::Result<bool> {
+        anyhow::bail!(
+            "Store does not support promoting version '{version}' of pipeline '{pipeline}'"
+        )
+    }
 }
 
 /// A storage-agnostic interface that provides database connections for both watermark management
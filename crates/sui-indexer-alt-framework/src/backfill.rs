@@ -0,0 +1,445 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parallel range backfills for concurrent pipelines.
+//!
+//! A checkpoint range is split into shards, and each shard is backfilled by its own tasked indexer
+//! (in the same process, or in separate processes), which records its progress in the shard's task
+//! watermark (`{pipeline}{Store::DELIMITER}backfill_{first}_{last}`). Once shards are complete,
+//! [merge_backfill] advances the pipeline's main watermark over them, as long as they extend it
+//! without gaps. [run_backfill] drives a whole backfill in one process: it runs an indexer for
+//! every shard concurrently, and merges them once they have all finished.
+//!
+//! Sequential pipelines cannot be attached to a tasked indexer, and so cannot be backfilled this
+//! way.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, bail, ensure};
+use tracing::{info, warn};
+
+use crate::{
+    Indexer, IndexerArgs, TaskArgs,
+    store::{CommitterWatermark, Connection, Store, pipeline_task},
+};
+
+/// Command-line arguments for a sharded backfill.
+#[derive(clap::Args, Debug, Clone)]
+pub struct BackfillArgs {
+    /// The first checkpoint to backfill (inclusive).
+    #[arg(long)]
+    pub backfill_first_checkpoint: u64,
+
+    /// The last checkpoint to backfill (inclusive).
+    #[arg(long)]
+    pub backfill_last_checkpoint: u64,
+
+    /// The number of shards to split the range into. Each shard is backfilled independently, and
+    /// tracks its progress in its own task watermark.
+    #[arg(long, default_value_t = 1)]
+    pub backfill_shards: u64,
+
+    /// The interval in milliseconds at which each shard's pipelines refetch their main pipeline's
+    /// reader watermark.
+    #[arg(long, default_value_t = 5000)]
+    pub backfill_reader_interval_ms: u64,
+}
+
+/// A contiguous range of checkpoints that is backfilled by its own tasked indexer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackfillShard {
+    pub first_checkpoint: u64,
+    pub last_checkpoint: u64,
+}
+
+impl BackfillArgs {
+    /// Split the backfill range into shards of roughly equal size, in checkpoint order. There are
+    /// never more shards than checkpoints in the range.
+    pub fn shards(&self) -> anyhow::Result<Vec<BackfillShard>> {
+        let first = self.backfill_first_checkpoint;
+        let last = self.backfill_last_checkpoint;
+        ensure!(
+            first <= last,
+            "Backfill range is empty: first checkpoint {first} is after last checkpoint {last}",
+        );
+        ensure!(
+            self.backfill_shards > 0,
+            "Backfill needs at least one shard"
+        );
+
+        let total = last - first + 1;
+        let num_shards = self.backfill_shards.min(total);
+        let (size, rem) = (total / num_shards, total % num_shards);
+
+        let mut shards = Vec::with_capacity(num_shards as usize);
+        let mut next = first;
+        for i in 0..num_shards {
+            // The first `rem` shards take one extra checkpoint each.
+            let len = size + u64::from(i < rem);
+            shards.push(BackfillShard {
+                first_checkpoint: next,
+                last_checkpoint: next + len - 1,
+            });
+            next += len;
+        }
+
+        Ok(shards)
+    }
+}
+
+impl BackfillShard {
+    /// The task name under which the shard records its watermarks.
+    pub fn task(&self) -> String {
+        format!(
+            "backfill_{}_{}",
+            self.first_checkpoint, self.last_checkpoint
+        )
+    }
+
+    /// Arguments for an indexer that backfills this shard, running only `pipelines` (or all the
+    /// pipelines added to it, if empty). Tasked pipelines refetch their main pipeline's reader
+    /// watermark every `reader_interval_ms`.
+    pub fn indexer_args(&self, pipelines: Vec<String>, reader_interval_ms: u64) -> IndexerArgs {
+        IndexerArgs {
+            first_checkpoint: Some(self.first_checkpoint),
+            last_checkpoint: Some(self.last_checkpoint),
+            pipeline: pipelines,
+            task: TaskArgs::tasked(self.task(), reader_interval_ms),
+        }
+    }
+
+    /// Whether the shard's task watermark shows that all of its checkpoints have been committed.
+    pub async fn is_complete<S: Store>(
+        &self,
+        conn: &mut S::Connection<'_>,
+        pipeline: &'static str,
+    ) -> anyhow::Result<bool> {
+        Ok(self
+            .watermark::<S>(conn, pipeline)
+            .await?
+            .is_some_and(|w| w.checkpoint_hi_inclusive >= self.last_checkpoint))
+    }
+
+    async fn watermark<S: Store>(
+        &self,
+        conn: &mut S::Connection<'_>,
+        pipeline: &'static str,
+    ) -> anyhow::Result<Option<CommitterWatermark>> {
+        let pipeline_task = pipeline_task::<S>(pipeline, Some(&self.task()))?;
+        conn.committer_watermark(&pipeline_task)
+            .await
+            .with_context(|| format!("Failed to get watermark for {pipeline_task}"))
+    }
+}
+
+/// Run a sharded backfill of the range in `args`, in this process. `build` is called with the
+/// [IndexerArgs] for each shard, and should return an indexer with the pipelines to backfill added
+/// to it (only `pipelines` are run, or all of them if it is empty). The indexers for all the shards
+/// run concurrently, and once they have all finished, the shards are merged into the main
+/// watermark of each pipeline that was run.
+///
+/// Returns the main watermark of each pipeline after the merge. A backfill that was interrupted can
+/// be resumed by running it again with the same arguments: shards resume from their task
+/// watermarks, and completed shards finish immediately.
+pub async fn run_backfill<S, F, Fut>(
+    args: &BackfillArgs,
+    pipelines: Vec<String>,
+    mut build: F,
+) -> anyhow::Result<BTreeMap<&'static str, Option<CommitterWatermark>>>
+where
+    S: Store,
+    F: FnMut(IndexerArgs) -> Fut,
+    Fut: Future<Output = anyhow::Result<Indexer<S>>>,
+{
+    let shards = args.shards()?;
+
+    let mut indexers = Vec::with_capacity(shards.len());
+    for shard in &shards {
+        let indexer =
+            build(shard.indexer_args(pipelines.clone(), args.backfill_reader_interval_ms))
+                .await
+                .with_context(|| format!("Failed to set up indexer for {}", shard.task()))?;
+        indexers.push(indexer);
+    }
+
+    // Every shard's indexer is built the same way, so they share a store and pipelines.
+    let store = indexers[0].store().clone();
+    let backfilled: BTreeSet<&'static str> = indexers[0]
+        .added_pipelines
+        .iter()
+        .copied()
+        .filter(|p| pipelines.is_empty() || pipelines.iter().any(|q| q == p))
+        .collect();
+
+    let mut handles = Vec::with_capacity(indexers.len());
+    for (shard, indexer) in shards.iter().zip(indexers) {
+        info!(
+            first_checkpoint = shard.first_checkpoint,
+            last_checkpoint = shard.last_checkpoint,
+            "Starting backfill shard"
+        );
+        let handle = indexer
+            .run()
+            .await
+            .with_context(|| format!("Failed to start indexer for {}", shard.task()))?;
+        handles.push(handle);
+    }
+
+    for handle in handles {
+        handle.await.context("Backfill indexer panicked")?;
+    }
+
+    let mut watermarks = BTreeMap::new();
+    for pipeline in backfilled {
+        let watermark = merge_backfill(&store, pipeline, &shards).await?;
+        info!(
+            pipeline,
+            checkpoint_hi_inclusive = ?watermark.map(|w| w.checkpoint_hi_inclusive),
+            "Merged backfill"
+        );
+        watermarks.insert(pipeline, watermark);
+    }
+
+    Ok(watermarks)
+}
+
+/// Advance the main committer watermark of `pipeline` over the completed `shards` that extend it
+/// contiguously, stopping at the first shard that is incomplete or leaves a gap. Shards must not
+/// overlap each other.
+///
+/// Shards that are already covered by the main watermark are skipped, and only contribute the part
+/// of their range above it. Unless the shard was merged by an earlier call, this means the
+/// pipeline's main task had already written (some of) the shard's range, so a warning is logged.
+///
+/// This is safe to call at any time, and repeatedly: the main watermark only moves over ranges
+/// that have been committed in full, and is never lowered. Returns the main watermark after the
+/// merge, if there is one.
+pub async fn merge_backfill<S: Store>(
+    store: &S,
+    pipeline: &'static str,
+    shards: &[BackfillShard],
+) -> anyhow::Result<Option<CommitterWatermark>> {
+    let mut conn = store
+        .connect()
+        .await
+        .context("Failed to establish connection to store")?;
+
+    let main_task = pipeline_task::<S>(pipeline, None)?;
+    let main = conn
+        .committer_watermark(&main_task)
+        .await
+        .with_context(|| format!("Failed to get watermark for {main_task}"))?;
+
+    let mut shards = shards.to_vec();
+    shards.sort_by_key(|s| s.first_checkpoint);
+    for pair in shards.windows(2) {
+        if pair[0].last_checkpoint >= pair[1].first_checkpoint {
+            bail!(
+                "Backfill shards {} and {} overlap",
+                pair[0].task(),
+                pair[1].task()
+            );
+        }
+    }
+
+    let mut next_checkpoint = main.map_or(0, |w| w.checkpoint_hi_inclusive + 1);
+    let mut merged = None;
+    for shard in shards {
+        if shard.first_checkpoint > next_checkpoint {
+            break;
+        }
+
+        let watermark = shard.watermark::<S>(&mut conn, pipeline).await?;
+        let complete =
+            watermark.is_some_and(|w| w.checkpoint_hi_inclusive >= shard.last_checkpoint);
+
+        if shard.last_checkpoint < next_checkpoint {
+            if !complete {
+                warn!(
+                    pipeline,
+                    shard = shard.task(),
+                    main_checkpoint_hi_inclusive = next_checkpoint - 1,
+                    "Backfill shard is already covered by the main watermark, skipping"
+                );
+            }
+            continue;
+        }
+
+        if shard.first_checkpoint < next_checkpoint {
+            warn!(
+                pipeline,
+                shard = shard.task(),
+                main_checkpoint_hi_inclusive = next_checkpoint - 1,
+                "Backfill shard overlaps the main watermark"
+            );
+        }
+
+        match watermark {
+            Some(w) if complete => {
+                next_checkpoint = w.checkpoint_hi_inclusive + 1;
+                merged = Some(w);
+            }
+            _ => break,
+        }
+    }
+
+    let Some(watermark) = merged else {
+        return Ok(main);
+    };
+
+    conn.set_committer_watermark(&main_task, watermark)
+        .await
+        .with_context(|| format!("Failed to set watermark for {main_task}"))?;
+
+    Ok(Some(watermark))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mocks::store::{MockStore, MockWatermark};
+
+    use super::*;
+
+    const PIPELINE: &str = "pipeline";
+
+    fn args(first: u64, last: u64, shards: u64) -> BackfillArgs {
+        BackfillArgs {
+            backfill_first_checkpoint: first,
+            backfill_last_checkpoint: last,
+            backfill_shards: shards,
+            backfill_reader_interval_ms: 10,
+        }
+    }
+
+    fn shard(first_checkpoint: u64, last_checkpoint: u64) -> BackfillShard {
+        BackfillShard {
+            first_checkpoint,
+            last_checkpoint,
+        }
+    }
+
+    fn watermark(checkpoint_hi_inclusive: u64) -> MockWatermark {
+        MockWatermark {
+            checkpoint_hi_inclusive,
+            ..Default::default()
+        }
+    }
+
+    fn with_shard(store: MockStore, shard: BackfillShard, hi: u64) -> MockStore {
+        let task = pipeline_task::<MockStore>(PIPELINE, Some(&shard.task())).unwrap();
+        store.with_watermark(&task, watermark(hi))
+    }
+
+    async fn main_hi(store: &MockStore) -> Option<u64> {
+        let mut conn = store.connect().await.unwrap();
+        conn.committer_watermark(PIPELINE)
+            .await
+            .unwrap()
+            .map(|w| w.checkpoint_hi_inclusive)
+    }
+
+    #[test]
+    fn test_shards_cover_range() {
+        let shards = args(10, 19, 3).shards().unwrap();
+        assert_eq!(shards, vec![shard(10, 13), shard(14, 16), shard(17, 19)]);
+
+        // Never more shards than checkpoints
+        let shards = args(5, 6, 10).shards().unwrap();
+        assert_eq!(shards, vec![shard(5, 5), shard(6, 6)]);
+
+        assert!(args(5, 4, 1).shards().is_err());
+        assert!(args(0, 10, 0).shards().is_err());
+    }
+
+    #[test]
+    fn test_shard_indexer_args() {
+        let args = shard(100, 199).indexer_args(vec![PIPELINE.to_string()], 1000);
+        assert_eq!(args.first_checkpoint, Some(100));
+        assert_eq!(args.last_checkpoint, Some(199));
+        assert_eq!(args.pipeline, vec![PIPELINE.to_string()]);
+        assert_eq!(args.task.into_task().unwrap().task, "backfill_100_199");
+    }
+
+    #[tokio::test]
+    async fn test_merge_complete_shards() {
+        let shards = args(0, 29, 3).shards().unwrap();
+        let store = MockStore::default();
+        let store = with_shard(store, shards[0], 9);
+        let store = with_shard(store, shards[1], 19);
+        let store = with_shard(store, shards[2], 29);
+
+        let merged = merge_backfill(&store, PIPELINE, &shards).await.unwrap();
+        assert_eq!(merged.unwrap().checkpoint_hi_inclusive, 29);
+        assert_eq!(main_hi(&store).await, Some(29));
+    }
+
+    #[tokio::test]
+    async fn test_merge_stops_at_incomplete_shard() {
+        let shards = args(0, 29, 3).shards().unwrap();
+        let store = MockStore::default();
+        let store = with_shard(store, shards[0], 9);
+        let store = with_shard(store, shards[1], 15);
+        let store = with_shard(store, shards[2], 29);
+
+        // Only the first shard can be merged, as the second is still in progress.
+        let merged = merge_backfill(&store, PIPELINE, &shards).await.unwrap();
+        assert_eq!(merged.unwrap().checkpoint_hi_inclusive, 9);
+
+        // Once the second shard completes, the rest is merged.
+        let store = with_shard(store, shards[1], 19);
+        let merged = merge_backfill(&store, PIPELINE, &shards).await.unwrap();
+        assert_eq!(merged.unwrap().checkpoint_hi_inclusive, 29);
+    }
+
+    #[tokio::test]
+    async fn test_merge_does_not_skip_gaps() {
+        // The main watermark is at 4, but the backfill starts at 10.
+        let shards = args(10, 29, 2).shards().unwrap();
+        let store = MockStore::default().with_watermark(PIPELINE, watermark(4));
+        let store = with_shard(store, shards[0], 19);
+        let store = with_shard(store, shards[1], 29);
+
+        let merged = merge_backfill(&store, PIPELINE, &shards).await.unwrap();
+        assert_eq!(merged.unwrap().checkpoint_hi_inclusive, 4);
+        assert_eq!(main_hi(&store).await, Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_merge_skips_covered_shards() {
+        // The main watermark is already in the middle of the second shard.
+        let shards = args(0, 29, 3).shards().unwrap();
+        let store = MockStore::default().with_watermark(PIPELINE, watermark(14));
+        let store = with_shard(store, shards[1], 19);
+        let store = with_shard(store, shards[2], 29);
+
+        let merged = merge_backfill(&store, PIPELINE, &shards).await.unwrap();
+        assert_eq!(merged.unwrap().checkpoint_hi_inclusive, 29);
+        assert!(
+            shards[2]
+                .is_complete::<MockStore>(&mut store.connect().await.unwrap(), PIPELINE)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_rejects_overlapping_shards() {
+        let shards = [shard(0, 9), shard(5, 19)];
+        let store = MockStore::default();
+        let store = with_shard(store, shards[0], 9);
+        let store = with_shard(store, shards[1], 19);
+
+        assert!(merge_backfill(&store, PIPELINE, &shards).await.is_err());
+        assert_eq!(main_hi(&store).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_merge_without_shards_started() {
+        let shards = args(0, 29, 3).shards().unwrap();
+        let store = MockStore::default();
+
+        let merged = merge_backfill(&store, PIPELINE, &shards).await.unwrap();
+        assert!(merged.is_none());
+        assert_eq!(main_hi(&store).await, None);
+    }
+}
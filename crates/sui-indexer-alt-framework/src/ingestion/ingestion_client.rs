@@ -325,6 +325,7 @@ mod tests {
     use tokio_util::bytes::Bytes;
 
     use crate::ingestion::test_utils::test_checkpoint_data;
+    use sui_storage::blob::BlobEncoding;
 
     use super::*;
 
@@ -400,6 +401,23 @@ mod tests {
         assert_eq!(result.summary.sequence_number(), &1);
     }
 
+    #[tokio::test]
+    async fn test_fetch_compressed_raw_bytes_success() {
+        let (client, mock) = setup_test();
+
+        // Re-encode the test checkpoint with zstd
+        let checkpoint: CheckpointData = Blob::from_bytes(&test_checkpoint_data(1)).unwrap();
+        let bytes = Blob::encode(&checkpoint, BlobEncoding::Zstd)
+            .unwrap()
+            .to_bytes();
+        mock.checkpoints
+            .insert(1, FetchData::Raw(Bytes::from(bytes)));
+
+        // Fetch and verify
+        let result = client.fetch(1).await.unwrap();
+        assert_eq!(result.summary.sequence_number(), &1);
+    }
+
     #[tokio::test]
     async fn test_fetch_checkpoint_success() {
         let (client, mock) = setup_test();
@@ -7,6 +7,7 @@ use anyhow::{Context, bail, ensure};
 use futures::future;
 use ingestion::{ClientArgs, IngestionConfig, IngestionService, ingestion_client::IngestionClient};
 use metrics::IndexerMetrics;
+use migration::MigrationConfig;
 use pipeline::{
     Processor,
     concurrent::{self, ConcurrentConfig},
@@ -28,10 +29,12 @@ pub use sui_types as types;
 
 use crate::metrics::IngestionMetrics;
 
+pub mod backfill;
 #[cfg(feature = "cluster")]
 pub mod cluster;
 pub mod ingestion;
 pub mod metrics;
+pub mod migration;
 pub mod pipeline;
 #[cfg(feature = "postgres")]
 pub mod postgres;
@@ -305,6 +308,61 @@ impl<S: Store> Indexer<S> {
         Ok(())
     }
 
+    /// Adds a concurrent pipeline to this indexer as a new version of the pipeline named in
+    /// `migration`, and starts it up, like [Self::concurrent_pipeline].
+    ///
+    /// The new version records its progress under its own watermark, so it backfills from its
+    /// first checkpoint while the version it replaces keeps running. Once it has caught up, it is
+    /// promoted to be the active version of the pipeline, which readers switch over to. See
+    /// [migration] for how the new version's tables are laid out.
+    pub async fn concurrent_pipeline_migration<H>(
+        &mut self,
+        handler: H,
+        config: ConcurrentConfig,
+        migration: MigrationConfig,
+    ) -> Result<()>
+    where
+        H: concurrent::Handler<Store = S> + Send + Sync + 'static,
+    {
+        ensure!(
+            migration.replaces != H::NAME,
+            "Pipeline {:?} cannot replace itself",
+            H::NAME,
+        );
+
+        if self.task.is_some() {
+            bail!(
+                "Pipeline migrations do not support pipeline tasks. \
+                Only a pipeline's main watermark can be promoted."
+            );
+        }
+
+        let Some(next_checkpoint) = self.add_pipeline::<H>().await? else {
+            return Ok(());
+        };
+
+        let pipeline = concurrent::pipeline::<H>(
+            handler,
+            next_checkpoint,
+            config,
+            self.store.clone(),
+            None,
+            self.ingestion_service.subscribe().0,
+            self.metrics.clone(),
+            self.cancel.clone(),
+        );
+
+        self.handles.push(migration::promoter(
+            H::NAME,
+            migration,
+            self.store.clone(),
+            pipeline,
+            self.cancel.clone(),
+        ));
+
+        Ok(())
+    }
+
     /// Start ingesting checkpoints from `first_ingestion_checkpoint`. Individual pipelines
     /// will start processing and committing once the ingestion service has caught up to their
     /// respective watermarks.
@@ -2094,4 +2152,197 @@ mod tests {
             500
         );
     }
+
+    /// A new version of a pipeline backfills alongside the version it replaces, and is promoted
+    /// once it has caught up with it.
+    #[tokio::test]
+    async fn test_pipeline_migration_promoted_once_caught_up() {
+        let cancel = CancellationToken::new();
+        let registry = Registry::new();
+        let store = MockStore::default();
+
+        test_pipeline!(V1, "pipeline_v1");
+        test_pipeline!(V2, "pipeline_v2");
+
+        let mut conn = store.connect().await.unwrap();
+        conn.set_committer_watermark(
+            V1::NAME,
+            CommitterWatermark {
+                checkpoint_hi_inclusive: 20,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        synthetic_ingestion::generate_ingestion(synthetic_ingestion::Config {
+            ingestion_dir: temp_dir.path().to_owned(),
+            starting_checkpoint: 0,
+            num_checkpoints: 30,
+            checkpoint_size: 1,
+        })
+        .await;
+
+        let indexer_args = IndexerArgs {
+            last_checkpoint: Some(29),
+            ..Default::default()
+        };
+
+        let client_args = ClientArgs {
+            ingestion: IngestionClientArgs {
+                local_ingestion_path: Some(temp_dir.path().to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut indexer = Indexer::new(
+            store.clone(),
+            indexer_args,
+            client_args,
+            IngestionConfig::default(),
+            None,
+            &registry,
+            cancel,
+        )
+        .await
+        .unwrap();
+
+        indexer
+            .concurrent_pipeline::<V1>(V1, ConcurrentConfig::default())
+            .await
+            .unwrap();
+        indexer
+            .concurrent_pipeline_migration::<V2>(
+                V2,
+                ConcurrentConfig::default(),
+                MigrationConfig {
+                    replaces: V1::NAME.to_owned(),
+                    promote_interval_ms: 10,
+                },
+            )
+            .await
+            .unwrap();
+
+        // The new version backfills from genesis, while the old one resumes from its watermark.
+        assert_eq!(indexer.first_ingestion_checkpoint, 0);
+        indexer.run().await.unwrap().await.unwrap();
+
+        assert_eq!(
+            store.watermark(V1::NAME).unwrap().checkpoint_hi_inclusive,
+            29
+        );
+        assert_eq!(
+            store.watermark(V2::NAME).unwrap().checkpoint_hi_inclusive,
+            29
+        );
+        assert_eq!(store.data.get(V2::NAME).unwrap().len(), 30);
+        assert_eq!(
+            migration::active_version(&store, V1::NAME).await.unwrap(),
+            V2::NAME
+        );
+    }
+
+    /// A sharded backfill runs an indexer per shard, and merges them into the main watermark.
+    #[tokio::test]
+    async fn test_run_backfill() {
+        let store = MockStore::default();
+        test_pipeline!(A, "pipeline_a");
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        synthetic_ingestion::generate_ingestion(synthetic_ingestion::Config {
+            ingestion_dir: temp_dir.path().to_owned(),
+            starting_checkpoint: 0,
+            num_checkpoints: 30,
+            checkpoint_size: 1,
+        })
+        .await;
+
+        let args = backfill::BackfillArgs {
+            backfill_first_checkpoint: 0,
+            backfill_last_checkpoint: 29,
+            backfill_shards: 3,
+            backfill_reader_interval_ms: 10,
+        };
+
+        let watermarks = backfill::run_backfill(&args, vec![], |indexer_args| {
+            let store = store.clone();
+            let client_args = ClientArgs {
+                ingestion: IngestionClientArgs {
+                    local_ingestion_path: Some(temp_dir.path().to_owned()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            async move {
+                let mut indexer = Indexer::new(
+                    store,
+                    indexer_args,
+                    client_args,
+                    IngestionConfig::default(),
+                    None,
+                    &Registry::new(),
+                    CancellationToken::new(),
+                )
+                .await?;
+                indexer
+                    .concurrent_pipeline::<A>(A, ConcurrentConfig::default())
+                    .await?;
+                Ok(indexer)
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(watermarks[A::NAME].unwrap().checkpoint_hi_inclusive, 29);
+        assert_eq!(
+            store.watermark(A::NAME).unwrap().checkpoint_hi_inclusive,
+            29
+        );
+        assert_eq!(store.data.get(A::NAME).unwrap().len(), 30);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_migration_rejects_tasked_indexer() {
+        let registry = Registry::new();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let client_args = ClientArgs {
+            ingestion: IngestionClientArgs {
+                local_ingestion_path: Some(temp_dir.path().to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut indexer = Indexer::new(
+            MockStore::default(),
+            IndexerArgs {
+                task: TaskArgs::tasked("task".to_string(), 10),
+                ..Default::default()
+            },
+            client_args,
+            IngestionConfig::default(),
+            None,
+            &registry,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        test_pipeline!(V2, "pipeline_v2");
+        let err = indexer
+            .concurrent_pipeline_migration::<V2>(
+                V2,
+                ConcurrentConfig::default(),
+                MigrationConfig {
+                    replaces: "pipeline_v1".to_owned(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("pipeline tasks"));
+    }
 }
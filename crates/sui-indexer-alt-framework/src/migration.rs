@@ -0,0 +1,299 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Side-by-side versions of concurrent pipelines.
+//!
+//! When a pipeline's output changes shape, a new version of it can be added to the indexer under a
+//! new name, next to the version it replaces. The new version backfills under its own watermark,
+//! while the old version keeps writing, and once the new version's committer watermark has caught
+//! up with the old one's, it is promoted to be the active version of the (logical) pipeline, in
+//! the store's `pipeline_versions` table.
+//!
+//! A new version writes its tables to a database schema named after it, with the same table names
+//! as the version it replaces. Readers resolve each logical pipeline to its active version, and
+//! read both its watermark and its tables from that version, so they switch over without ever
+//! seeing a gap, or data and watermarks from different versions.
+//!
+//! The old version continues to write after the promotion, so that readers that have not switched
+//! yet are still served, until it is removed from the indexer.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::store::{Connection, Store};
+
+/// Configuration for a pipeline that is added as a new version of an existing pipeline.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrationConfig {
+    /// The logical pipeline that this pipeline is a new version of.
+    pub replaces: String,
+
+    /// How often to check whether the new version has caught up and can be promoted.
+    pub promote_interval_ms: u64,
+}
+
+impl MigrationConfig {
+    pub fn promote_interval(&self) -> Duration {
+        Duration::from_millis(self.promote_interval_ms)
+    }
+}
+
+impl Default for MigrationConfig {
+    fn default() -> Self {
+        Self {
+            replaces: String::new(),
+            promote_interval_ms: 10_000,
+        }
+    }
+}
+
+/// The active version of `pipeline`. This is `pipeline` itself, unless another version of it has
+/// been promoted.
+pub async fn active_version<S: Store>(store: &S, pipeline: &str) -> anyhow::Result<String> {
+    let mut conn = store
+        .connect()
+        .await
+        .context("Failed to establish connection to store")?;
+
+    Ok(conn
+        .active_pipeline_version(pipeline)
+        .await
+        .with_context(|| format!("Failed to get active version of {pipeline}"))?
+        .unwrap_or_else(|| pipeline.to_owned()))
+}
+
+/// Try to promote `version` to be the active version of `pipeline`. Promotion only succeeds if the
+/// committer watermark of `version` is at or above the committer watermark of the version that is
+/// currently active. Returns whether `version` is active after the call (including if it already
+/// was).
+pub async fn promote<S: Store>(store: &S, pipeline: &str, version: &str) -> anyhow::Result<bool> {
+    let mut conn = store
+        .connect()
+        .await
+        .context("Failed to establish connection to store")?;
+
+    if conn
+        .promote_pipeline_version(pipeline, version)
+        .await
+        .with_context(|| format!("Failed to promote {version} for {pipeline}"))?
+    {
+        info!(pipeline, version, "Promoted pipeline version");
+        return Ok(true);
+    }
+
+    let active = conn
+        .active_pipeline_version(pipeline)
+        .await
+        .with_context(|| format!("Failed to get active version of {pipeline}"))?;
+
+    Ok(active.as_deref() == Some(version))
+}
+
+/// The promoter task periodically tries to promote `version` as the active version of the pipeline
+/// it replaces, until it succeeds. It wraps the `pipeline` task of the new version, so that if the
+/// pipeline finishes first (e.g. because the indexer was given a last checkpoint), a final attempt
+/// is made before the promoter exits.
+///
+/// The task exits once the promotion succeeds and the pipeline finishes, or when the provided
+/// cancellation token is triggered.
+pub(crate) fn promoter<S: Store>(
+    version: &'static str,
+    config: MigrationConfig,
+    store: S,
+    mut pipeline: JoinHandle<()>,
+    cancel: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut poll = interval(config.promote_interval());
+        let replaces = config.replaces.as_str();
+
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => {
+                    info!(pipeline = version, replaces, "Shutdown received");
+                    break;
+                }
+
+                _ = &mut pipeline => {
+                    if let Err(e) = promote(&store, replaces, version).await {
+                        warn!(pipeline = version, replaces, "Failed to promote version: {e}");
+                    }
+
+                    info!(pipeline = version, replaces, "Stopping promoter task");
+                    return;
+                }
+
+                _ = poll.tick() => {
+                    match promote(&store, replaces, version).await {
+                        Ok(true) => break,
+                        Ok(false) => {}
+                        Err(e) => {
+                            warn!(pipeline = version, replaces, "Failed to promote version: {e}");
+                        }
+                    }
+                }
+            }
+        }
+
+        info!(pipeline = version, replaces, "Stopping promoter task");
+
+        // The pipeline keeps running after its promotion, until it is shut down.
+        let _ = pipeline.await;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mocks::store::{MockStore, MockWatermark};
+
+    use super::*;
+
+    fn watermark(checkpoint_hi_inclusive: u64) -> MockWatermark {
+        MockWatermark {
+            checkpoint_hi_inclusive,
+            ..Default::default()
+        }
+    }
+
+    fn config(replaces: &str) -> MigrationConfig {
+        MigrationConfig {
+            replaces: replaces.to_owned(),
+            promote_interval_ms: 10,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_promote_when_caught_up() {
+        let store = MockStore::default()
+            .with_watermark("objects", watermark(100))
+            .with_watermark("objects_v2", watermark(50));
+
+        assert_eq!(active_version(&store, "objects").await.unwrap(), "objects");
+
+        // The new version is still behind the version it replaces.
+        assert!(!promote(&store, "objects", "objects_v2").await.unwrap());
+        assert_eq!(active_version(&store, "objects").await.unwrap(), "objects");
+
+        // Once it has caught up, it is promoted, and stays promoted.
+        let store = store.with_watermark("objects_v2", watermark(100));
+        assert!(promote(&store, "objects", "objects_v2").await.unwrap());
+        assert!(promote(&store, "objects", "objects_v2").await.unwrap());
+        assert_eq!(
+            active_version(&store, "objects").await.unwrap(),
+            "objects_v2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_promote_compares_against_active_version() {
+        let store = MockStore::default()
+            .with_watermark("objects", watermark(100))
+            .with_watermark("objects_v2", watermark(200))
+            .with_watermark("objects_v3", watermark(150));
+
+        assert!(promote(&store, "objects", "objects_v2").await.unwrap());
+
+        // The third version has overtaken the original, but not the active version.
+        assert!(!promote(&store, "objects", "objects_v3").await.unwrap());
+        assert_eq!(
+            active_version(&store, "objects").await.unwrap(),
+            "objects_v2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_promote_without_watermark() {
+        // A version that has not committed anything yet can't be promoted.
+        let store = MockStore::default().with_watermark("objects", watermark(100));
+        assert!(!promote(&store, "objects", "objects_v2").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_promoter_promotes_once_caught_up() {
+        let store = MockStore::default()
+            .with_watermark("objects", watermark(100))
+            .with_watermark("objects_v2", watermark(50));
+
+        let cancel = CancellationToken::new();
+        let pipeline = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { cancel.cancelled().await }
+        });
+
+        let handle = promoter(
+            "objects_v2",
+            config("objects"),
+            store.clone(),
+            pipeline,
+            cancel.clone(),
+        );
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(active_version(&store, "objects").await.unwrap(), "objects");
+
+        store
+            .watermarks
+            .get_mut("objects_v2")
+            .unwrap()
+            .checkpoint_hi_inclusive = 100;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            active_version(&store, "objects").await.unwrap(),
+            "objects_v2"
+        );
+
+        // The promoter waits for the pipeline to shut down.
+        assert!(!handle.is_finished());
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_promoter_final_attempt_when_pipeline_finishes() {
+        let store = MockStore::default()
+            .with_watermark("objects", watermark(100))
+            .with_watermark("objects_v2", watermark(50));
+
+        // The promoter's first tick happens before the pipeline has caught up, and the next one is
+        // too far off to matter.
+        let config = MigrationConfig {
+            replaces: "objects".to_owned(),
+            promote_interval_ms: 60_000,
+        };
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let pipeline = tokio::spawn(async move {
+            let _ = rx.await;
+        });
+
+        let handle = promoter(
+            "objects_v2",
+            config,
+            store.clone(),
+            pipeline,
+            CancellationToken::new(),
+        );
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(active_version(&store, "objects").await.unwrap(), "objects");
+
+        // The pipeline catches up and finishes before the next tick.
+        store
+            .watermarks
+            .get_mut("objects_v2")
+            .unwrap()
+            .checkpoint_hi_inclusive = 100;
+        tx.send(()).unwrap();
+        handle.await.unwrap();
+
+        assert_eq!(
+            active_version(&store, "objects").await.unwrap(),
+            "objects_v2"
+        );
+    }
+}
@@ -10,6 +10,7 @@ use std::{
 };
 
 use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
 
 use anyhow::ensure;
 use async_trait::async_trait;
@@ -56,6 +57,8 @@ pub struct Failures {
 pub struct MockStore {
     /// Maps each pipeline's name to its watermark.
     pub watermarks: Arc<DashMap<String, MockWatermark>>,
+    /// Maps each logical pipeline's name to the name of its active version.
+    pub pipeline_versions: Arc<DashMap<String, String>>,
     /// Maps each pipeline's name to a map of checkpoint sequence numbers to a vector of numbers.
     pub data: Arc<DashMap<String, DashMap<u64, Vec<u64>>>>,
     /// Tracks the order of checkpoint processing for testing sequential processing
@@ -197,6 +200,49 @@ impl Connection for MockConnection<'_> {
         curr.pruner_hi = pruner_hi;
         Ok(true)
     }
+
+    async fn active_pipeline_version(&mut self, pipeline: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.0.pipeline_versions.get(pipeline).map(|v| v.clone()))
+    }
+
+    async fn promote_pipeline_version(
+        &mut self,
+        pipeline: &str,
+        version: &str,
+    ) -> anyhow::Result<bool> {
+        // Hold the entry for the duration of the check, so that promotions are atomic.
+        let active = self.0.pipeline_versions.entry(pipeline.to_string());
+        let current = match &active {
+            Entry::Occupied(e) => e.get().clone(),
+            Entry::Vacant(_) => pipeline.to_string(),
+        };
+
+        if current == version {
+            return Ok(false);
+        }
+
+        let Some(next_hi) = self
+            .0
+            .watermarks
+            .get(version)
+            .map(|w| w.checkpoint_hi_inclusive)
+        else {
+            return Ok(false);
+        };
+
+        let current_hi = self
+            .0
+            .watermarks
+            .get(&current)
+            .map(|w| w.checkpoint_hi_inclusive);
+        if current_hi.is_some_and(|hi| next_hi < hi) {
+            return Ok(false);
+        }
+
+        active.insert(version.to_string());
+
+        Ok(true)
+    }
 }
 
 #[async_trait]
@@ -405,11 +405,14 @@ async fn watermarks_from_pg(
         .await
         .context("Failed to connect to database")?;
 
+    // Each pipeline's watermark is read from its active version (see `pipeline_versions`), which is
+    // also the version whose tables the reader reads from, but is reported under the pipeline's
+    // logical name, so promoting a new version is transparent here.
     let rows: Vec<WatermarkRow> = conn
         .results(query!(
             r#"
             SELECT
-                w.pipeline,
+                p.pipeline,
                 w.epoch_hi_inclusive,
                 w.checkpoint_hi_inclusive,
                 w.tx_hi,
@@ -418,12 +421,16 @@ async fn watermarks_from_pg(
                 w.reader_lo AS checkpoint_lo,
                 c.tx_lo AS tx_lo
             FROM
+                UNNEST({Array<Text>}) AS p (pipeline)
+            LEFT JOIN
+                pipeline_versions v
+            ON (p.pipeline = v.pipeline)
+            INNER JOIN
                 watermarks w
+            ON (w.pipeline = COALESCE(v.active, p.pipeline))
             INNER JOIN
                 cp_sequence_numbers c
             ON (w.reader_lo = c.cp_sequence_number)
-            WHERE
-                pipeline = ANY({Array<Text>})
             "#,
             pg_pipelines,
         ))
@@ -475,3 +482,105 @@ async fn watermark_from_consistent(
         Err(e) => Err(anyhow!(e).context("Failed to get consistent store watermarks")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use diesel::sql_types::Bytea;
+    use diesel_async::RunQueryDsl;
+    use prometheus::Registry;
+    use sui_indexer_alt_reader::object_versions::VersionBoundedObjectVersionKey;
+    use sui_indexer_alt_schema::{MIGRATIONS, objects::StoredObjVersion, schema::obj_versions};
+    use sui_pg_db::{Db, DbArgs, temp::TempDb};
+    use sui_types::base_types::ObjectID;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_readers_switch_on_promotion() {
+        let temp_db = TempDb::new().unwrap();
+        let url = temp_db.database().url();
+        let writer = Db::for_write(url.clone(), DbArgs::default()).await.unwrap();
+        writer.run_migrations(Some(&MIGRATIONS)).await.unwrap();
+        let pg_reader = PgReader::new(
+            None,
+            Some(url.clone()),
+            DbArgs::default(),
+            &Registry::new(),
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        let id = ObjectID::random();
+        let mut conn = writer.connect().await.unwrap();
+        for statement in [
+            "INSERT INTO cp_sequence_numbers VALUES (0, 0, 0)",
+            // `obj_versions` has indexed up to checkpoint 10, and a new version of it, writing to
+            // tables in its own schema, has indexed up to checkpoint 20.
+            "INSERT INTO watermarks VALUES
+                ('obj_versions', 0, 10, 100, 1000, 0, NOW(), 0),
+                ('obj_versions_v2', 0, 20, 200, 2000, 0, NOW(), 0)",
+            "CREATE SCHEMA obj_versions_v2",
+            "CREATE TABLE obj_versions_v2.obj_versions (LIKE public.obj_versions INCLUDING ALL)",
+        ] {
+            diesel::sql_query(statement)
+                .execute(&mut conn)
+                .await
+                .unwrap();
+        }
+
+        diesel::insert_into(obj_versions::table)
+            .values(StoredObjVersion {
+                object_id: id.to_vec(),
+                object_version: 1,
+                object_digest: None,
+                cp_sequence_number: 10,
+            })
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        diesel::sql_query(
+            "INSERT INTO obj_versions_v2.obj_versions (object_id, object_version, cp_sequence_number)
+             VALUES ($1, 1, 20)",
+        )
+        .bind::<Bytea, _>(id.to_vec())
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        // Reports the watermark of the version of the pipeline whose tables data is loaded from.
+        let assert_reads_from = |checkpoint: i64| {
+            let pg_reader = pg_reader.clone();
+            async move {
+                let rows = watermarks_from_pg(&pg_reader, &["obj_versions".to_owned()])
+                    .await
+                    .unwrap();
+                assert_eq!(rows.len(), 1);
+                assert_eq!(rows[0].pipeline, "obj_versions");
+                assert_eq!(rows[0].checkpoint_hi_inclusive, checkpoint);
+                assert_eq!(rows[0].tx_hi, checkpoint * 10);
+
+                let loaded = pg_reader
+                    .as_data_loader()
+                    .load_one(VersionBoundedObjectVersionKey(id, 1))
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(loaded.cp_sequence_number, checkpoint);
+            }
+        };
+
+        // Before the promotion, both come from the old version, and after it, from the new one.
+        assert_reads_from(10).await;
+
+        diesel::sql_query(
+            "INSERT INTO pipeline_versions VALUES ('obj_versions', 'obj_versions_v2', NOW())",
+        )
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        assert_reads_from(20).await;
+    }
+}
@@ -19,6 +19,7 @@ pub mod objects;
 pub mod package_resolver;
 pub mod packages;
 pub mod pg_reader;
+pub mod pipeline_versions;
 pub mod system_package_task;
 pub mod transactions;
 pub mod tx_balance_changes;
@@ -22,6 +22,26 @@ use crate::metrics::DbReaderMetrics;
 
 pub use sui_pg_db as db;
 
+/// Puts the database schemas of the active versions of pipelines that have been migrated (see
+/// `pipeline_versions`) at the front of the connection's `search_path`. A new version of a pipeline
+/// writes its tables to a schema named after it, so the unqualified table names in queries resolve
+/// to the tables of the active version of each pipeline. Postgres skips schemas that don't exist.
+const SET_ACTIVE_VERSIONS: &str = r#"
+    SELECT set_config(
+        'search_path',
+        CONCAT_WS(
+            ', ',
+            (
+                SELECT STRING_AGG(QUOTE_IDENT(active), ', ' ORDER BY pipeline)
+                FROM pipeline_versions
+                WHERE active <> pipeline
+            ),
+            (SELECT reset_val FROM pg_settings WHERE name = 'search_path')
+        ),
+        false
+    )
+"#;
+
 /// This wrapper type exists to perform error conversion between the data fetching layer and the
 /// RPC layer, metrics collection, and debug logging of database queries.
 #[derive(Clone)]
@@ -86,6 +106,10 @@ impl PgReader {
 
     /// Acquire a connection to the database. This can potentially fail if the service is cancelled
     /// while the connection is being acquired.
+    ///
+    /// Queries on the connection read from the tables of the active version of each pipeline, as of
+    /// when the connection was acquired, so that readers switch over to a new version of a
+    /// pipeline as soon as it has been promoted.
     pub async fn connect(&self) -> anyhow::Result<Connection<'_>> {
         let Some(db) = &self.db else {
             bail!("No database to connect to");
@@ -97,8 +121,14 @@ impl PgReader {
             }
 
             conn = db.connect() => {
+                let mut conn = conn.context("Failed to connect to database")?;
+                diesel::sql_query(SET_ACTIVE_VERSIONS)
+                    .execute(&mut conn)
+                    .await
+                    .context("Failed to resolve active pipeline versions")?;
+
                 Ok(Connection {
-                    conn: conn.context("Failed to connect to database")?,
+                    conn,
                     metrics: self.metrics.clone(),
                 })
             }
@@ -0,0 +1,52 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{error::Error, pg_reader::PgReader};
+use async_graphql::dataloader::Loader;
+use diesel::{ExpressionMethods, QueryDsl};
+use std::collections::HashMap;
+
+use sui_pg_db::schema::pipeline_versions;
+
+/// Key for resolving a logical pipeline to its active version: the pipeline whose watermark (and
+/// tables) readers should read from. A pipeline that has never been migrated is its own active
+/// version.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActivePipelineVersionKey(pub String);
+
+#[async_trait::async_trait]
+impl Loader<ActivePipelineVersionKey> for PgReader {
+    type Value = String;
+    type Error = Error;
+
+    async fn load(
+        &self,
+        keys: &[ActivePipelineVersionKey],
+    ) -> Result<HashMap<ActivePipelineVersionKey, Self::Value>, Error> {
+        use pipeline_versions::dsl as v;
+
+        if keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut conn = self.connect().await?;
+
+        let pipelines: Vec<_> = keys.iter().map(|k| k.0.clone()).collect();
+        let versions: Vec<(String, String)> = conn
+            .results(
+                v::pipeline_versions
+                    .select((v::pipeline, v::active))
+                    .filter(v::pipeline.eq_any(pipelines)),
+            )
+            .await?;
+
+        let mut active: HashMap<_, _> = versions.into_iter().collect();
+        Ok(keys
+            .iter()
+            .map(|k| {
+                let version = active.remove(&k.0).unwrap_or_else(|| k.0.clone());
+                (k.clone(), version)
+            })
+            .collect())
+    }
+}
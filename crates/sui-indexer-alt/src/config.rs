@@ -265,6 +265,17 @@ impl PipelineLayer {
             tx_kinds: Some(Default::default()),
         }
     }
+
+    /// Disable all sequential pipelines, returning the names of those that were enabled.
+    /// Sequential pipelines can't run under a pipeline task, so they need to be removed from
+    /// configurations that are used for backfills.
+    pub fn take_sequential(&mut self) -> Vec<&'static str> {
+        let mut taken = vec![];
+        if self.sum_displays.take().is_some() {
+            taken.push("sum_displays");
+        }
+        taken
+    }
 }
 
 impl Merge for IndexerConfig {
@@ -680,6 +691,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn take_sequential() {
+        let mut layer = PipelineLayer::example();
+        assert_eq!(layer.take_sequential(), vec!["sum_displays"]);
+        assert!(layer.sum_displays.is_none());
+        assert!(layer.obj_info.is_some());
+        assert!(layer.take_sequential().is_empty());
+    }
+
     #[test]
     fn detect_unrecognized_fields() {
         let err = toml::from_str::<IndexerConfig>(
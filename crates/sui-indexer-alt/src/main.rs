@@ -13,6 +13,7 @@ use sui_indexer_alt::args::Command;
 use sui_indexer_alt::config::IndexerConfig;
 use sui_indexer_alt::config::Merge;
 use sui_indexer_alt::setup_indexer;
+use sui_indexer_alt_framework::backfill::run_backfill;
 use sui_indexer_alt_framework::postgres::reset_database;
 use sui_indexer_alt_metrics::MetricsService;
 use sui_indexer_alt_metrics::uptime;
@@ -21,6 +22,7 @@ use tokio::fs;
 use tokio::signal;
 use tokio_util::sync::CancellationToken;
 use tracing::info;
+use tracing::warn;
 
 // Define the `GIT_REVISION` const
 bin_version::git_revision!();
@@ -106,6 +108,70 @@ async fn main() -> Result<()> {
             let _ = h_ctrl_c.await;
         }
 
+        Command::Backfill {
+            database_url,
+            db_args,
+            client_args,
+            backfill_args,
+            pipeline,
+            config,
+        } => {
+            let mut indexer_config = read_config(&config).await?;
+
+            // Sequential pipelines can't be backfilled, because they can't run under a task. Only
+            // fail on them if they were asked for explicitly.
+            if pipeline.is_empty() {
+                for sequential in indexer_config.pipeline.take_sequential() {
+                    warn!(
+                        pipeline = sequential,
+                        "Skipping sequential pipeline, which can't be backfilled"
+                    );
+                }
+            }
+
+            info!("Starting backfill with config: {:#?}", indexer_config);
+
+            let cancel = CancellationToken::new();
+            let h_ctrl_c = tokio::spawn({
+                let cancel = cancel.clone();
+                async move {
+                    tokio::select! {
+                        _ = cancel.cancelled() => {}
+                        _ = signal::ctrl_c() => {
+                            info!("Received Ctrl-C, shutting down...");
+                            cancel.cancel();
+                        }
+                    }
+                }
+            });
+
+            // Each shard gets its own registry, as they all register the same metrics.
+            run_backfill(&backfill_args, pipeline, |indexer_args| {
+                let database_url = database_url.clone();
+                let db_args = db_args.clone();
+                let client_args = client_args.clone();
+                let indexer_config = indexer_config.clone();
+                let cancel = cancel.child_token();
+                async move {
+                    setup_indexer(
+                        database_url,
+                        db_args,
+                        indexer_args,
+                        client_args,
+                        indexer_config,
+                        None,
+                        &Registry::new(),
+                        cancel,
+                    )
+                    .await
+                }
+            })
+            .await?;
+
+            cancel.cancel();
+            let _ = h_ctrl_c.await;
+        }
+
         Command::GenerateConfig => {
             let config = IndexerConfig::example();
             let config_toml = toml::to_string_pretty(&config)
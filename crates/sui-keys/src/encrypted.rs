@@ -0,0 +1,753 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A file-based keystore whose private keys are encrypted at rest, with a key derived from a
+//! passphrase (Argon2id) and an AEAD cipher (XChaCha20-Poly1305).
+//!
+//! Aliases and public keys are stored in the clear (in the same `.aliases` file as the plain
+//! file-based keystore), so addresses can be listed without the passphrase. Private keys are only
+//! decrypted when they are first needed, at which point the keystore is unlocked by (in order):
+//!
+//! - A session created by [`EncryptedFileKeystore::unlock`], until it expires or
+//!   [`EncryptedFileKeystore::lock`] is called. The session file next to the keystore only holds
+//!   the derived key encrypted with a random session token, which is handed to the caller and never
+//!   written to disk. Other processes join the session with the token in [`SESSION_ENV_VAR`].
+//! - The passphrase in the [`PASSPHRASE_ENV_VAR`] environment variable, for automation.
+//! - The passphrase typed in at a prompt, if there is a terminal.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, anyhow, bail, ensure};
+use argon2::{Algorithm, Argon2, Params, Version};
+use async_trait::async_trait;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use fastcrypto::encoding::{Base64, Encoding};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use shared_crypto::intent::{Intent, IntentMessage};
+use sui_types::base_types::SuiAddress;
+use sui_types::crypto::{EncodeDecodeBase64, PublicKey, Signature, SuiKeyPair};
+use zeroize::Zeroizing;
+
+use crate::keystore::{
+    ALIASES_FILE_EXTENSION, AccountKeystore, Alias, FileBasedKeystore, validate_alias,
+};
+use crate::random_names::random_name;
+use crate::signing_policy::{self, SigningPolicy};
+
+/// Environment variable that the passphrase of an encrypted keystore is read from, if set.
+pub const PASSPHRASE_ENV_VAR: &str = "SUI_KEYSTORE_PASSPHRASE";
+
+/// Environment variable that the new passphrase is read from when changing passphrases, if set.
+pub const NEW_PASSPHRASE_ENV_VAR: &str = "SUI_KEYSTORE_NEW_PASSPHRASE";
+
+/// Environment variable that the token of a session started by [`EncryptedFileKeystore::unlock`]
+/// is read from.
+pub const SESSION_ENV_VAR: &str = "SUI_KEYSTORE_SESSION";
+
+/// Extension of the file holding the session of an unlocked keystore.
+pub const SESSION_FILE_EXTENSION: &str = "session";
+
+const ENVELOPE_VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const KEY_LENGTH: usize = 32;
+
+/// Argon2id parameters, following the OWASP recommendation for Argon2id (19 MiB, 2 iterations).
+const ARGON2_M_COST: u32 = 19 * 1024;
+const ARGON2_T_COST: u32 = 2;
+const ARGON2_P_COST: u32 = 1;
+
+type DerivedKey = Zeroizing<[u8; KEY_LENGTH]>;
+
+/// The contents of an encrypted keystore file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Envelope {
+    version: u8,
+    kdf: KdfParams,
+    /// Base64 encoded nonce.
+    nonce: String,
+    /// Base64 encoded encryption of the JSON array of Base64 encoded keys, in the same format as
+    /// the plain file-based keystore.
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct KdfParams {
+    /// Base64 encoded salt.
+    salt: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+/// The cached key of an unlocked keystore.
+#[derive(Serialize, Deserialize)]
+struct Session {
+    /// The KDF parameters the key was derived with, to detect stale sessions after the
+    /// passphrase has changed.
+    kdf: KdfParams,
+    /// Base64 encoded nonce.
+    nonce: String,
+    /// Base64 encoded encryption of the derived key with the session token, authenticating the
+    /// expiry.
+    wrapped_key: String,
+    expires_at_ms: u64,
+}
+
+/// A session started by [`EncryptedFileKeystore::unlock`].
+pub struct UnlockSession {
+    /// Token that other processes need (in [`SESSION_ENV_VAR`]) to use the session.
+    pub token: Zeroizing<String>,
+    pub expires_at: SystemTime,
+}
+
+struct Unlocked {
+    keys: BTreeMap<SuiAddress, SuiKeyPair>,
+    key: DerivedKey,
+}
+
+pub struct EncryptedFileKeystore {
+    path: PathBuf,
+    envelope: Envelope,
+    aliases: BTreeMap<SuiAddress, Alias>,
+    /// Decrypted keys, set once the keystore has been unlocked.
+    unlocked: OnceLock<Unlocked>,
+    /// Token of the session to unlock the keystore with, from [`SESSION_ENV_VAR`] by default.
+    session_token: Option<Zeroizing<String>>,
+    signing_policy: Option<SigningPolicy>,
+}
+
+impl EncryptedFileKeystore {
+    /// Create a new encrypted keystore at `path`, holding `keys`.
+    pub fn create(
+        path: &Path,
+        passphrase: &str,
+        keys: BTreeMap<SuiAddress, SuiKeyPair>,
+        mut aliases: BTreeMap<SuiAddress, Alias>,
+    ) -> Result<Self, anyhow::Error> {
+        ensure!(!passphrase.is_empty(), "Passphrase cannot be empty");
+
+        // Every key needs an alias, as that is where its public key is read from while locked.
+        for (address, key) in &keys {
+            if !aliases.contains_key(address) {
+                let taken = aliases.values().map(|a| a.alias.clone()).collect();
+                aliases.insert(
+                    *address,
+                    Alias {
+                        alias: random_name(&taken),
+                        public_key_base64: key.public().encode_base64(),
+                    },
+                );
+            }
+        }
+        aliases.retain(|address, _| keys.contains_key(address));
+
+        let kdf = KdfParams::new();
+        let key = kdf.derive(passphrase)?;
+        let envelope = Envelope::seal(kdf, &key, &keys)?;
+
+        let keystore = Self {
+            path: path.to_path_buf(),
+            envelope,
+            aliases,
+            unlocked: OnceLock::from(Unlocked { keys, key }),
+            session_token: session_token_from_env(),
+            signing_policy: SigningPolicy::load_for_keystore(path)?,
+        };
+
+        keystore.save_aliases()?;
+        keystore.save_envelope()?;
+        Ok(keystore)
+    }
+
+    /// Encrypt the keys in a plain file-based keystore, replacing its file.
+    pub fn migrate(file: &FileBasedKeystore, passphrase: &str) -> Result<Self, anyhow::Error> {
+        let path = file
+            .path()
+            .ok_or_else(|| anyhow!("Cannot migrate a keystore that is not backed by a file"))?;
+
+        let keys = file
+            .key_pairs()
+            .into_iter()
+            .map(|kp| (SuiAddress::from(&kp.public()), kp.copy()))
+            .collect();
+
+        let aliases = file
+            .addresses_with_alias()
+            .into_iter()
+            .map(|(address, alias)| (*address, alias.clone()))
+            .collect();
+
+        Self::create(path, passphrase, keys, aliases)
+    }
+
+    /// Load the encrypted keystore at `path`. The keystore starts off locked.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read the keystore file: {}", path.display()))?;
+        let envelope: Envelope = serde_json::from_str(&contents).with_context(|| {
+            format!(
+                "Cannot deserialize the encrypted keystore: {}",
+                path.display()
+            )
+        })?;
+        ensure!(
+            envelope.version == ENVELOPE_VERSION,
+            "Unsupported encrypted keystore version {} in {}",
+            envelope.version,
+            path.display(),
+        );
+
+        let mut aliases_path = path.to_path_buf();
+        aliases_path.set_extension(ALIASES_FILE_EXTENSION);
+        let aliases = if aliases_path.exists() {
+            let contents = std::fs::read_to_string(&aliases_path)
+                .with_context(|| format!("Cannot read aliases file: {}", aliases_path.display()))?;
+            let aliases: Vec<Alias> = serde_json::from_str(&contents).with_context(|| {
+                format!(
+                    "Cannot deserialize aliases file: {}",
+                    aliases_path.display()
+                )
+            })?;
+            aliases
+                .into_iter()
+                .map(|alias| {
+                    let key = PublicKey::decode_base64(&alias.public_key_base64);
+                    key.map(|k| (SuiAddress::from(&k), alias))
+                })
+                .collect::<Result<BTreeMap<_, _>, _>>()
+                .map_err(|e| anyhow!("Invalid aliases file: {}. {}", aliases_path.display(), e))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            envelope,
+            aliases,
+            unlocked: OnceLock::new(),
+            session_token: session_token_from_env(),
+            signing_policy: SigningPolicy::load_for_keystore(path)?,
+        })
+    }
+
+    /// Whether the file at `path` holds an encrypted keystore.
+    pub fn is_encrypted(path: &Path) -> bool {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Envelope>(&contents).ok())
+            .is_some()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the private keys have not been decrypted (yet).
+    pub fn is_locked(&self) -> bool {
+        self.unlocked.get().is_none()
+    }
+
+    /// Unlock the keystore with `passphrase`. If a `duration` is given, the keystore stays
+    /// unlocked for other processes that have the returned session token for that long. The
+    /// derived key is stored next to the keystore (readable only by its owner), encrypted with the
+    /// token, which is not stored anywhere.
+    pub fn unlock(
+        &mut self,
+        passphrase: &str,
+        duration: Option<Duration>,
+    ) -> Result<Option<UnlockSession>, anyhow::Error> {
+        let key = self.envelope.kdf.derive(passphrase)?;
+        let keys = self.envelope.open(&key)?;
+
+        let session = if let Some(duration) = duration {
+            let mut token = Zeroizing::new([0u8; KEY_LENGTH]);
+            OsRng.fill_bytes(&mut token[..]);
+            let expires_at_ms = (SystemTime::now() + duration)
+                .duration_since(UNIX_EPOCH)?
+                .as_millis() as u64;
+            let expires_at = UNIX_EPOCH + Duration::from_millis(expires_at_ms);
+
+            let mut nonce = [0u8; NONCE_LENGTH];
+            OsRng.fill_bytes(&mut nonce);
+            let wrapped_key = cipher(&token)
+                .encrypt(
+                    XNonce::from_slice(&nonce),
+                    Payload {
+                        msg: &key[..],
+                        aad: &expires_at_ms.to_le_bytes(),
+                    },
+                )
+                .map_err(|_| anyhow!("Cannot encrypt session key"))?;
+
+            let session = Session {
+                kdf: self.envelope.kdf.clone(),
+                nonce: Base64::encode(nonce),
+                wrapped_key: Base64::encode(wrapped_key),
+                expires_at_ms,
+            };
+            write_private_file(&self.session_path(), &serde_json::to_string(&session)?)?;
+
+            let token = Zeroizing::new(Base64::encode(&token[..]));
+            self.session_token = Some(token.clone());
+            Some(UnlockSession { token, expires_at })
+        } else {
+            None
+        };
+
+        self.unlocked = OnceLock::from(Unlocked { keys, key });
+        Ok(session)
+    }
+
+    /// Use the session with `token` to unlock the keystore, instead of the one in
+    /// [`SESSION_ENV_VAR`].
+    pub fn set_session_token(&mut self, token: &str) {
+        self.session_token = Some(Zeroizing::new(token.to_owned()));
+    }
+
+    /// Forget the decrypted keys, and end any session started by [`Self::unlock`].
+    pub fn lock(&mut self) -> Result<(), anyhow::Error> {
+        self.unlocked = OnceLock::new();
+        self.session_token = None;
+        self.remove_session()
+    }
+
+    fn remove_session(&self) -> Result<(), anyhow::Error> {
+        let session = self.session_path();
+        match std::fs::remove_file(&session) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Cannot remove session file: {}", session.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Re-encrypt the keystore with a key derived from `new_passphrase`. Ends any existing
+    /// session, and leaves the keystore unlocked in this process.
+    pub fn change_passphrase(
+        &mut self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+    ) -> Result<(), anyhow::Error> {
+        ensure!(!new_passphrase.is_empty(), "Passphrase cannot be empty");
+
+        let old_key = self.envelope.kdf.derive(old_passphrase)?;
+        let keys = self.envelope.open(&old_key)?;
+
+        let kdf = KdfParams::new();
+        let key = kdf.derive(new_passphrase)?;
+        self.envelope = Envelope::seal(kdf, &key, &keys)?;
+        self.save_envelope()?;
+
+        self.lock()?;
+        self.unlocked = OnceLock::from(Unlocked { keys, key });
+        Ok(())
+    }
+
+    /// When the session started by [`Self::unlock`] expires, if there is a live session.
+    pub fn session_expiry(&self) -> Option<SystemTime> {
+        self.session_key()
+            .map(|(_, expires_at_ms)| UNIX_EPOCH + Duration::from_millis(expires_at_ms))
+    }
+
+    /// The decrypted keys, unlocking the keystore if necessary.
+    fn unlocked(&self) -> Result<&Unlocked, anyhow::Error> {
+        if let Some(unlocked) = self.unlocked.get() {
+            return Ok(unlocked);
+        }
+
+        let key = match self.session_key() {
+            Some((key, _)) => key,
+            None => {
+                let passphrase = read_passphrase(&format!(
+                    "Enter passphrase for keystore {}: ",
+                    self.path.display()
+                ))?;
+                self.envelope.kdf.derive(&passphrase)?
+            }
+        };
+
+        let keys = self.envelope.open(&key)?;
+        Ok(self.unlocked.get_or_init(|| Unlocked { keys, key }))
+    }
+
+    fn unlocked_mut(&mut self) -> Result<&mut Unlocked, anyhow::Error> {
+        self.unlocked()?;
+        Ok(self.unlocked.get_mut().expect("Keystore was just unlocked"))
+    }
+
+    fn key(&self, address: &SuiAddress) -> Result<&SuiKeyPair, signature::Error> {
+        self.unlocked()
+            .map_err(|e| signature::Error::from_source(e.to_string()))?
+            .keys
+            .get(address)
+            .ok_or_else(|| {
+                signature::Error::from_source(format!("Cannot find key for address: [{address}]"))
+            })
+    }
+
+    /// The derived key cached by a live session, and when that session expires. Sessions that
+    /// have expired, or that are for a previous passphrase, are removed.
+    fn session_key(&self) -> Option<(DerivedKey, u64)> {
+        let contents = std::fs::read_to_string(self.session_path()).ok()?;
+        let session: Session = serde_json::from_str(&contents).ok()?;
+
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_millis() as u64;
+        if session.kdf != self.envelope.kdf || session.expires_at_ms <= now_ms {
+            let _ = self.remove_session();
+            return None;
+        }
+
+        let token = Zeroizing::new(Base64::decode(self.session_token.as_ref()?).ok()?);
+        let token: &[u8; KEY_LENGTH] = token.as_slice().try_into().ok()?;
+        let nonce = Base64::decode(&session.nonce).ok()?;
+        if nonce.len() != NONCE_LENGTH {
+            return None;
+        }
+        let wrapped_key = Base64::decode(&session.wrapped_key).ok()?;
+
+        let bytes = Zeroizing::new(
+            cipher(&Zeroizing::new(*token))
+                .decrypt(
+                    XNonce::from_slice(&nonce),
+                    Payload {
+                        msg: &wrapped_key,
+                        aad: &session.expires_at_ms.to_le_bytes(),
+                    },
+                )
+                .ok()?,
+        );
+        let key: &[u8; KEY_LENGTH] = bytes.as_slice().try_into().ok()?;
+        Some((Zeroizing::new(*key), session.expires_at_ms))
+    }
+
+    fn session_path(&self) -> PathBuf {
+        let mut path = self.path.clone();
+        path.set_extension(SESSION_FILE_EXTENSION);
+        path
+    }
+
+    /// Re-encrypt the (unlocked) keys with the current derived key, and write them out.
+    fn save_keys(&mut self) -> Result<(), anyhow::Error> {
+        let kdf = self.envelope.kdf.clone();
+        let unlocked = self.unlocked()?;
+        self.envelope = Envelope::seal(kdf, &unlocked.key, &unlocked.keys)?;
+        self.save_envelope()
+    }
+
+    fn save_envelope(&self) -> Result<(), anyhow::Error> {
+        let contents = serde_json::to_string_pretty(&self.envelope).with_context(|| {
+            format!("Cannot serialize keystore to file: {}", self.path.display())
+        })?;
+        write_private_file(&self.path, &contents)
+    }
+
+    fn save_aliases(&self) -> Result<(), anyhow::Error> {
+        let contents = serde_json::to_string_pretty(&self.aliases.values().collect::<Vec<_>>())
+            .with_context(|| {
+                format!(
+                    "Cannot serialize aliases to file in keystore: {}",
+                    self.path.display()
+                )
+            })?;
+
+        let mut aliases_path = self.path.clone();
+        aliases_path.set_extension(ALIASES_FILE_EXTENSION);
+        std::fs::write(&aliases_path, contents)
+            .with_context(|| format!("Cannot write aliases to file: {}", aliases_path.display()))
+    }
+}
+
+impl KdfParams {
+    fn new() -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            salt: Base64::encode(salt),
+            m_cost: ARGON2_M_COST,
+            t_cost: ARGON2_T_COST,
+            p_cost: ARGON2_P_COST,
+        }
+    }
+
+    fn derive(&self, passphrase: &str) -> Result<DerivedKey, anyhow::Error> {
+        let salt = Base64::decode(&self.salt).map_err(|e| anyhow!("Invalid salt: {e}"))?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(KEY_LENGTH))
+            .map_err(|e| anyhow!("Invalid key derivation parameters: {e}"))?;
+
+        let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key[..])
+            .map_err(|e| anyhow!("Cannot derive key from passphrase: {e}"))?;
+        Ok(key)
+    }
+}
+
+impl Envelope {
+    fn seal(
+        kdf: KdfParams,
+        key: &DerivedKey,
+        keys: &BTreeMap<SuiAddress, SuiKeyPair>,
+    ) -> Result<Self, anyhow::Error> {
+        let encoded = Zeroizing::new(keys.values().map(|k| k.encode_base64()).collect::<Vec<_>>());
+        let plaintext = Zeroizing::new(serde_json::to_vec(&*encoded)?);
+
+        let mut nonce = [0u8; NONCE_LENGTH];
+        OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = cipher(key)
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| anyhow!("Cannot encrypt keystore"))?;
+
+        Ok(Self {
+            version: ENVELOPE_VERSION,
+            kdf,
+            nonce: Base64::encode(nonce),
+            ciphertext: Base64::encode(ciphertext),
+        })
+    }
+
+    fn open(&self, key: &DerivedKey) -> Result<BTreeMap<SuiAddress, SuiKeyPair>, anyhow::Error> {
+        let nonce = Base64::decode(&self.nonce).map_err(|e| anyhow!("Invalid nonce: {e}"))?;
+        ensure!(nonce.len() == NONCE_LENGTH, "Invalid nonce length");
+        let ciphertext =
+            Base64::decode(&self.ciphertext).map_err(|e| anyhow!("Invalid ciphertext: {e}"))?;
+
+        let plaintext = Zeroizing::new(
+            cipher(key)
+                .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+                .map_err(|_| anyhow!("Incorrect passphrase, or the keystore is corrupted"))?,
+        );
+
+        let encoded: Zeroizing<Vec<String>> = Zeroizing::new(
+            serde_json::from_slice(&plaintext)
+                .context("Cannot deserialize the decrypted keystore")?,
+        );
+        encoded
+            .iter()
+            .map(|k| {
+                let key = SuiKeyPair::decode_base64(k)?;
+                Ok((SuiAddress::from(&key.public()), key))
+            })
+            .collect::<Result<_, anyhow::Error>>()
+            .context("Invalid keys in the decrypted keystore")
+    }
+}
+
+fn cipher(key: &DerivedKey) -> XChaCha20Poly1305 {
+    XChaCha20Poly1305::new(Key::from_slice(&key[..]))
+}
+
+fn session_token_from_env() -> Option<Zeroizing<String>> {
+    std::env::var(SESSION_ENV_VAR).ok().map(Zeroizing::new)
+}
+
+/// Read a passphrase from [`PASSPHRASE_ENV_VAR`], or from the terminal (without echoing it).
+pub fn read_passphrase(prompt: &str) -> Result<Zeroizing<String>, anyhow::Error> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV_VAR) {
+        return Ok(Zeroizing::new(passphrase));
+    }
+
+    if !std::io::stdin().is_terminal() {
+        bail!(
+            "Keystore is locked: unlock it with `sui keytool unlock`, or set {PASSPHRASE_ENV_VAR}"
+        );
+    }
+
+    Ok(Zeroizing::new(rpassword::prompt_password(prompt)?))
+}
+
+/// Read a new passphrase from the `env_var` environment variable, or from the terminal, asking for
+/// it twice.
+pub fn read_new_passphrase(env_var: &str) -> Result<Zeroizing<String>, anyhow::Error> {
+    if let Ok(passphrase) = std::env::var(env_var) {
+        return Ok(Zeroizing::new(passphrase));
+    }
+
+    ensure!(
+        std::io::stdin().is_terminal(),
+        "Cannot read a new passphrase: set {env_var}, or run from a terminal"
+    );
+
+    let passphrase = Zeroizing::new(rpassword::prompt_password("Enter new passphrase: ")?);
+    let confirm = Zeroizing::new(rpassword::prompt_password("Confirm new passphrase: ")?);
+    ensure!(passphrase == confirm, "Passphrases do not match");
+    Ok(passphrase)
+}
+
+/// Atomically replace the file at `path` with `contents`, readable only by its owner: the
+/// contents are written and synced to a temporary file in the same directory, which is then renamed
+/// over `path`, so a crash leaves either the old or the new file in place.
+fn write_private_file(path: &Path, contents: &str) -> Result<(), anyhow::Error> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("Cannot create a temporary file in: {}", dir.display()))?;
+    #[cfg(unix)]
+    crate::keystore::set_reduced_file_permissions(file.path())?;
+    file.write_all(contents.as_bytes())
+        .and_then(|()| file.as_file().sync_all())
+        .with_context(|| format!("Cannot write to file: {}", file.path().display()))?;
+    file.persist(path)
+        .with_context(|| format!("Cannot write to file: {}", path.display()))?;
+    Ok(())
+}
+
+impl Serialize for EncryptedFileKeystore {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.path.to_str().unwrap_or(""))
+    }
+}
+
+impl<'de> Deserialize<'de> for EncryptedFileKeystore {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        EncryptedFileKeystore::load(&PathBuf::from(String::deserialize(deserializer)?))
+            .map_err(D::Error::custom)
+    }
+}
+
+#[async_trait]
+impl AccountKeystore for EncryptedFileKeystore {
+    async fn import(
+        &mut self,
+        alias: Option<String>,
+        keypair: SuiKeyPair,
+    ) -> Result<(), anyhow::Error> {
+        let address: SuiAddress = (&keypair.public()).into();
+        let alias = self.create_alias(alias)?;
+        let public_key_base64 = keypair.public().encode_base64();
+
+        self.unlocked_mut()?.keys.insert(address, keypair);
+        self.aliases.insert(
+            address,
+            Alias {
+                alias,
+                public_key_base64,
+            },
+        );
+
+        self.save_keys()?;
+        self.save_aliases()
+    }
+
+    async fn remove(&mut self, address: SuiAddress) -> Result<(), anyhow::Error> {
+        self.unlocked_mut()?.keys.remove(&address);
+        self.aliases.remove(&address);
+        self.save_keys()?;
+        self.save_aliases()
+    }
+
+    /// Public keys are read from the aliases, so they are available while the keystore is
+    /// locked.
+    fn entries(&self) -> Vec<PublicKey> {
+        self.aliases
+            .values()
+            .filter_map(|alias| PublicKey::decode_base64(&alias.public_key_base64).ok())
+            .collect()
+    }
+
+    fn export(&self, address: &SuiAddress) -> Result<&SuiKeyPair, anyhow::Error> {
+        match self.unlocked()?.keys.get(address) {
+            Some(key) => Ok(key),
+            None => Err(anyhow!("Cannot find key for address: [{address}]")),
+        }
+    }
+
+    fn signing_policy(&self) -> Option<&SigningPolicy> {
+        self.signing_policy.as_ref()
+    }
+
+    fn set_signing_policy(&mut self, policy: Option<SigningPolicy>) {
+        self.signing_policy = policy;
+    }
+
+    async fn sign_hashed(
+        &self,
+        address: &SuiAddress,
+        msg: &[u8],
+    ) -> Result<Signature, signature::Error> {
+        signing_policy::enforce_hashed(self.signing_policy(), address)?;
+        Ok(Signature::new_hashed(msg, self.key(address)?))
+    }
+
+    async fn sign_secure<T>(
+        &self,
+        address: &SuiAddress,
+        msg: &T,
+        intent: Intent,
+    ) -> Result<Signature, signature::Error>
+    where
+        T: Serialize + Sync,
+    {
+        signing_policy::enforce(self.signing_policy(), address, msg, &intent)?;
+        Ok(Signature::new_secure(
+            &IntentMessage::new(intent, msg),
+            self.key(address)?,
+        ))
+    }
+
+    fn addresses_with_alias(&self) -> Vec<(&SuiAddress, &Alias)> {
+        self.aliases.iter().collect()
+    }
+
+    fn aliases(&self) -> Vec<&Alias> {
+        self.aliases.values().collect()
+    }
+
+    fn aliases_mut(&mut self) -> Vec<&mut Alias> {
+        self.aliases.values_mut().collect()
+    }
+
+    fn get_alias(&self, address: &SuiAddress) -> Result<String, anyhow::Error> {
+        match self.aliases.get(address) {
+            Some(alias) => Ok(alias.alias.clone()),
+            None => bail!("Cannot find alias for address {address}"),
+        }
+    }
+
+    fn create_alias(&self, alias: Option<String>) -> Result<String, anyhow::Error> {
+        match alias {
+            Some(a) if self.alias_exists(&a) => {
+                bail!("Alias {a} already exists. Please choose another alias.")
+            }
+            Some(a) => validate_alias(&a),
+            None => Ok(random_name(
+                &self
+                    .aliases()
+                    .iter()
+                    .map(|x| x.alias.to_string())
+                    .collect::<HashSet<_>>(),
+            )),
+        }
+    }
+
+    async fn update_alias(
+        &mut self,
+        old_alias: &str,
+        new_alias: Option<&str>,
+    ) -> Result<String, anyhow::Error> {
+        let new_alias_name = self.update_alias_value(old_alias, new_alias)?;
+        self.save_aliases()?;
+        Ok(new_alias_name)
+    }
+}
@@ -5,6 +5,7 @@ use crate::keystore::{
     ALIASES_FILE_EXTENSION, AccountKeystore, Alias, GenerateOptions, GeneratedKey, validate_alias,
 };
 use crate::random_names::random_name;
+use crate::signing_policy::{self, SigningPolicy};
 
 use anyhow::{Context, Error};
 use anyhow::{anyhow, bail};
@@ -35,6 +36,7 @@ pub struct External {
     pub keys: BTreeMap<SuiAddress, StoredKey>,
     command_runner: Box<dyn CommandRunner>,
     path: Option<PathBuf>,
+    signing_policy: Option<SigningPolicy>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -144,11 +146,14 @@ impl External {
             BTreeMap::default()
         };
 
+        let signing_policy = SigningPolicy::load_for_keystore(path)?;
+
         Ok(Self {
             aliases,
             keys,
             command_runner: Box::new(StdCommandRunner),
             path: Some(path.clone()),
+            signing_policy,
         })
     }
 
@@ -158,6 +163,7 @@ impl External {
             keys: old.keys.clone(),
             command_runner: Box::new(StdCommandRunner),
             path: old.path.clone(),
+            signing_policy: old.signing_policy.clone(),
         }
     }
 
@@ -168,6 +174,7 @@ impl External {
             keys: BTreeMap::default(),
             command_runner,
             path,
+            signing_policy: None,
         }
     }
 
@@ -362,11 +369,20 @@ impl AccountKeystore for External {
         Err(anyhow!("Export not supported for external keys."))
     }
 
+    fn signing_policy(&self) -> Option<&SigningPolicy> {
+        self.signing_policy.as_ref()
+    }
+
+    fn set_signing_policy(&mut self, policy: Option<SigningPolicy>) {
+        self.signing_policy = policy;
+    }
+
     async fn sign_hashed(
         &self,
         address: &SuiAddress,
         msg: &[u8],
     ) -> Result<Signature, signature::Error> {
+        signing_policy::enforce_hashed(self.signing_policy(), address)?;
         let StoredKey {
             key_id,
             ext_signer,
@@ -478,6 +494,7 @@ impl AccountKeystore for External {
     where
         T: Serialize + Sync,
     {
+        signing_policy::enforce(self.signing_policy(), address, msg, &intent)?;
         let StoredKey {
             key_id,
             ext_signer,
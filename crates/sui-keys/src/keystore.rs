@@ -1,10 +1,12 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+pub use crate::encrypted::EncryptedFileKeystore;
 pub use crate::external::External;
 use crate::key_derive::{derive_key_pair_from_path, generate_new_key};
 use crate::key_identity::KeyIdentity;
 use crate::random_names::{random_name, random_names};
+use crate::signing_policy::{self, SigningPolicy};
 
 use anyhow::{Context, anyhow, bail, ensure};
 use async_trait::async_trait;
@@ -38,6 +40,7 @@ pub enum Keystore {
     File(FileBasedKeystore),
     InMem(InMemKeystore),
     External(External),
+    Encrypted(EncryptedFileKeystore),
 }
 
 pub struct LocalGenerate {
@@ -112,6 +115,11 @@ pub trait AccountKeystore: Send + Sync {
     /// Return `SuiKeyPair` for the given address.
     fn export(&self, address: &SuiAddress) -> Result<&SuiKeyPair, anyhow::Error>;
 
+    /// The signing policy checked before signing with this keystore, if any.
+    fn signing_policy(&self) -> Option<&SigningPolicy>;
+    /// Replace the signing policy checked before signing with this keystore.
+    fn set_signing_policy(&mut self, policy: Option<SigningPolicy>);
+
     /// Sign a hash with the keypair corresponding to the given address.
     async fn sign_hashed(
         &self,
@@ -228,6 +236,16 @@ impl Display for Keystore {
             Keystore::External(_external) => {
                 writeln!(writer, "Keystore Type : External")
             }
+            Keystore::Encrypted(encrypted) => {
+                writeln!(writer, "Keystore Type : Encrypted")?;
+                writeln!(writer, "Keystore Path : {:?}", encrypted.path())?;
+                write!(
+                    writer,
+                    "Locked        : {}",
+                    encrypted.is_locked() && encrypted.session_expiry().is_none()
+                )?;
+                write!(f, "{}", writer)
+            }
         }
     }
 }
@@ -243,6 +261,7 @@ pub struct FileBasedKeystore {
     keys: BTreeMap<SuiAddress, SuiKeyPair>,
     aliases: BTreeMap<SuiAddress, Alias>,
     path: Option<PathBuf>,
+    signing_policy: Option<SigningPolicy>,
 }
 
 impl Serialize for FileBasedKeystore {
@@ -278,6 +297,7 @@ impl AccountKeystore for FileBasedKeystore {
         address: &SuiAddress,
         msg: &[u8],
     ) -> Result<Signature, signature::Error> {
+        signing_policy::enforce_hashed(self.signing_policy(), address)?;
         Ok(Signature::new_hashed(
             msg,
             self.keys.get(address).ok_or_else(|| {
@@ -294,6 +314,7 @@ impl AccountKeystore for FileBasedKeystore {
     where
         T: Serialize + Sync,
     {
+        signing_policy::enforce(self.signing_policy(), address, msg, &intent)?;
         Ok(Signature::new_secure(
             &IntentMessage::new(intent, msg),
             self.keys.get(address).ok_or_else(|| {
@@ -380,6 +401,14 @@ impl AccountKeystore for FileBasedKeystore {
         }
     }
 
+    fn signing_policy(&self) -> Option<&SigningPolicy> {
+        self.signing_policy.as_ref()
+    }
+
+    fn set_signing_policy(&mut self, policy: Option<SigningPolicy>) {
+        self.signing_policy = policy;
+    }
+
     /// Updates an old alias to the new alias and saves it to the alias file.
     /// If the new_alias is None, it will generate a new random alias.
     async fn update_alias(
@@ -393,8 +422,26 @@ impl AccountKeystore for FileBasedKeystore {
     }
 }
 
+impl Keystore {
+    /// Load the keystore at `path`, detecting whether it is encrypted. A plain file-based
+    /// keystore is created if nothing exists at `path`.
+    pub fn load_or_create_from_path(path: &PathBuf) -> Result<Self, anyhow::Error> {
+        if EncryptedFileKeystore::is_encrypted(path) {
+            Ok(Keystore::Encrypted(EncryptedFileKeystore::load(path)?))
+        } else {
+            Ok(Keystore::File(FileBasedKeystore::load_or_create(path)?))
+        }
+    }
+}
+
 impl FileBasedKeystore {
     pub fn load_or_create(path: &PathBuf) -> Result<Self, anyhow::Error> {
+        ensure!(
+            !EncryptedFileKeystore::is_encrypted(path),
+            "The keystore file {} is encrypted, and cannot be loaded as a plain file-based keystore",
+            path.display(),
+        );
+
         let keys = if path.exists() {
             #[cfg(unix)]
             let _ = set_reduced_file_permissions(path).inspect_err(|error| {
@@ -488,10 +535,13 @@ impl FileBasedKeystore {
             aliases
         };
 
+        let signing_policy = SigningPolicy::load_for_keystore(path)?;
+
         Ok(Self {
             keys,
             aliases,
             path: Some(path.to_path_buf()),
+            signing_policy,
         })
     }
 
@@ -499,6 +549,10 @@ impl FileBasedKeystore {
         self.path = Some(path.to_path_buf());
     }
 
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
     pub async fn save_aliases(&self) -> Result<(), anyhow::Error> {
         if let Some(path) = &self.path {
             let aliases_store =
@@ -567,7 +621,7 @@ impl FileBasedKeystore {
 }
 
 #[cfg(unix)]
-fn set_reduced_file_permissions(path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+pub(crate) fn set_reduced_file_permissions(path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
     let path = path.as_ref();
     let metadata = fs::metadata(path)?;
     let mode = metadata.permissions().mode();
@@ -586,6 +640,8 @@ fn set_reduced_file_permissions(path: impl AsRef<Path>) -> Result<(), anyhow::Er
 pub struct InMemKeystore {
     aliases: BTreeMap<SuiAddress, Alias>,
     keys: BTreeMap<SuiAddress, SuiKeyPair>,
+    #[serde(skip)]
+    signing_policy: Option<SigningPolicy>,
 }
 
 #[async_trait]
@@ -595,6 +651,7 @@ impl AccountKeystore for InMemKeystore {
         address: &SuiAddress,
         msg: &[u8],
     ) -> Result<Signature, signature::Error> {
+        signing_policy::enforce_hashed(self.signing_policy(), address)?;
         Ok(Signature::new_hashed(
             msg,
             self.keys.get(address).ok_or_else(|| {
@@ -611,6 +668,7 @@ impl AccountKeystore for InMemKeystore {
     where
         T: Serialize + Sync,
     {
+        signing_policy::enforce(self.signing_policy(), address, msg, &intent)?;
         Ok(Signature::new_secure(
             &IntentMessage::new(intent, msg),
             self.keys.get(address).ok_or_else(|| {
@@ -671,6 +729,14 @@ impl AccountKeystore for InMemKeystore {
         }
     }
 
+    fn signing_policy(&self) -> Option<&SigningPolicy> {
+        self.signing_policy.as_ref()
+    }
+
+    fn set_signing_policy(&mut self, policy: Option<SigningPolicy>) {
+        self.signing_policy = policy;
+    }
+
     /// Get alias of address
     fn get_alias(&self, address: &SuiAddress) -> Result<String, anyhow::Error> {
         match self.aliases.get(address) {
@@ -736,7 +802,11 @@ impl InMemKeystore {
             })
             .collect::<BTreeMap<_, _>>();
 
-        Self { aliases, keys }
+        Self {
+            aliases,
+            keys,
+            signing_policy: None,
+        }
     }
 }
 
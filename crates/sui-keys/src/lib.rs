@@ -1,9 +1,12 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod encrypted;
 pub mod external;
 pub mod key_derive;
 pub mod key_identity;
 pub mod keypair_file;
 pub mod keystore;
 pub mod random_names;
+pub mod shamir;
+pub mod signing_policy;
@@ -0,0 +1,490 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Shamir secret sharing of keypairs and mnemonics, for M-of-N backups.
+//!
+//! A secret is split byte-wise over GF(256) into `N` shares, any `M` of which recover it. Each
+//! share is encoded as a list of words from the BIP39 English word list (11 bits per word), and
+//! carries the parameters of its split and a checksum, so that typos and shares from different
+//! splits are detected before recovery. Every share also carries a digest of the secret, which the
+//! recovered secret is checked against, so that a corrupted share, or one from another split that
+//! happens to share its identifier, fails recovery rather than silently producing the wrong secret.
+
+use std::collections::{BTreeSet, HashMap};
+use std::iter;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, ensure};
+use bip39::{Language, Mnemonic};
+use fastcrypto::hash::HashFunction;
+use rand::RngCore;
+use rand::rngs::OsRng;
+use sui_types::crypto::{DefaultHash, SuiKeyPair};
+use zeroize::Zeroizing;
+
+const SHARE_VERSION: u8 = 1;
+const DIGEST_LENGTH: usize = 8;
+const HEADER_LENGTH: usize = 13 + DIGEST_LENGTH;
+const CHECKSUM_LENGTH: usize = 4;
+const BITS_PER_WORD: usize = 11;
+
+/// A secret that can be split into shares.
+pub enum Secret {
+    KeyPair(SuiKeyPair),
+    /// A BIP39 mnemonic phrase, shared as its entropy.
+    Mnemonic(Zeroizing<String>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum SecretKind {
+    KeyPair = 0,
+    Mnemonic = 1,
+}
+
+/// One share of a split secret.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Share {
+    kind: SecretKind,
+    /// Random identifier of the split, shared by all its shares.
+    id: u64,
+    /// Digest of the secret, shared by all shares of its splits.
+    digest: [u8; DIGEST_LENGTH],
+    threshold: u8,
+    /// The x-coordinate of this share, never zero.
+    index: u8,
+    /// The y-coordinates of this share, one per byte of the secret.
+    value: Vec<u8>,
+}
+
+/// Split `secret` into `shares` shares, any `threshold` of which can recover it.
+pub fn split(secret: &Secret, threshold: u8, shares: u8) -> Result<Vec<Share>, anyhow::Error> {
+    ensure!(threshold >= 1, "Threshold must be at least 1");
+    ensure!(
+        threshold <= shares,
+        "Threshold ({threshold}) cannot be greater than the number of shares ({shares})"
+    );
+
+    let (kind, bytes) = secret.to_bytes()?;
+    let digest = secret_digest(kind, &bytes);
+    let mut rng = OsRng;
+    let id = rng.next_u64();
+
+    // One random polynomial of degree `threshold - 1` per byte of the secret, whose constant
+    // term is that byte.
+    let mut coefficients = Zeroizing::new(vec![0u8; bytes.len() * (threshold as usize - 1)]);
+    rng.fill_bytes(&mut coefficients);
+
+    Ok((1..=shares)
+        .map(|index| {
+            let value = bytes
+                .iter()
+                .enumerate()
+                .map(|(i, secret_byte)| {
+                    let degree = threshold as usize - 1;
+                    // Horner's method, from the highest degree coefficient down to the secret.
+                    let coeffs = &coefficients[i * degree..(i + 1) * degree];
+                    coeffs
+                        .iter()
+                        .rev()
+                        .chain(iter::once(secret_byte))
+                        .fold(0u8, |acc, c| gf_mul(acc, index) ^ c)
+                })
+                .collect();
+
+            Share {
+                kind,
+                id,
+                digest,
+                threshold,
+                index,
+                value,
+            }
+        })
+        .collect())
+}
+
+/// Recover a secret from at least `threshold` of its shares.
+pub fn combine(shares: &[Share]) -> Result<Secret, anyhow::Error> {
+    let Some(first) = shares.first() else {
+        bail!("No shares to recover from");
+    };
+
+    for share in shares {
+        ensure!(
+            share.id == first.id
+                && share.digest == first.digest
+                && share.kind == first.kind
+                && share.threshold == first.threshold,
+            "Shares {} and {} are from different backups",
+            first.index,
+            share.index,
+        );
+        ensure!(
+            share.value.len() == first.value.len(),
+            "Shares {} and {} have different lengths",
+            first.index,
+            share.index,
+        );
+    }
+
+    let indices: BTreeSet<_> = shares.iter().map(|s| s.index).collect();
+    ensure!(
+        indices.len() == shares.len(),
+        "The same share was provided more than once"
+    );
+    ensure!(
+        shares.len() >= first.threshold as usize,
+        "Need {} shares to recover this backup, but only {} were provided",
+        first.threshold,
+        shares.len(),
+    );
+
+    // Lagrange interpolation at x = 0, using only as many shares as needed.
+    let shares = &shares[..first.threshold as usize];
+    let mut bytes = Zeroizing::new(vec![0u8; first.value.len()]);
+    for (i, share) in shares.iter().enumerate() {
+        let mut basis = 1u8;
+        for (j, other) in shares.iter().enumerate() {
+            if i != j {
+                // In GF(256), subtraction is XOR, so (0 - x_j) / (x_i - x_j) is this.
+                basis = gf_mul(basis, gf_div(other.index, share.index ^ other.index));
+            }
+        }
+
+        for (byte, y) in bytes.iter_mut().zip(&share.value) {
+            *byte ^= gf_mul(basis, *y);
+        }
+    }
+
+    ensure!(
+        secret_digest(first.kind, &bytes) == first.digest,
+        "The recovered secret does not match its backup: a share is corrupted, or from a \
+         different backup",
+    );
+
+    Secret::from_bytes(first.kind, &bytes)
+}
+
+impl Secret {
+    fn to_bytes(&self) -> Result<(SecretKind, Zeroizing<Vec<u8>>), anyhow::Error> {
+        Ok(match self {
+            Secret::KeyPair(kp) => (SecretKind::KeyPair, Zeroizing::new(kp.to_bytes())),
+            Secret::Mnemonic(phrase) => {
+                let mnemonic = Mnemonic::from_phrase(phrase, Language::English)
+                    .map_err(|e| anyhow!("Invalid mnemonic phrase: {e}"))?;
+                (
+                    SecretKind::Mnemonic,
+                    Zeroizing::new(mnemonic.entropy().to_vec()),
+                )
+            }
+        })
+    }
+
+    fn from_bytes(kind: SecretKind, bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        Ok(match kind {
+            SecretKind::KeyPair => Secret::KeyPair(
+                SuiKeyPair::from_bytes(bytes)
+                    .map_err(|e| anyhow!("Recovered an invalid keypair: {e}"))?,
+            ),
+            SecretKind::Mnemonic => {
+                let mnemonic = Mnemonic::from_entropy(bytes, Language::English)
+                    .map_err(|e| anyhow!("Recovered an invalid mnemonic: {e}"))?;
+                Secret::Mnemonic(Zeroizing::new(mnemonic.phrase().to_string()))
+            }
+        })
+    }
+}
+
+impl Share {
+    /// The x-coordinate of this share, from 1 to the number of shares in its split.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Encode this share as space separated words.
+    pub fn to_words(&self) -> String {
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH + self.value.len() + CHECKSUM_LENGTH);
+        bytes.push(SHARE_VERSION);
+        bytes.push(self.kind as u8);
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        bytes.extend_from_slice(&self.digest);
+        bytes.push(self.threshold);
+        bytes.push(self.index);
+        bytes.push(self.value.len() as u8);
+        bytes.extend_from_slice(&self.value);
+        bytes.extend_from_slice(&checksum(&bytes));
+
+        let words = wordlist();
+        let mut encoded = vec![];
+        let (mut acc, mut bits) = (0u32, 0usize);
+        for byte in bytes {
+            acc = (acc << 8) | byte as u32;
+            bits += 8;
+            while bits >= BITS_PER_WORD {
+                bits -= BITS_PER_WORD;
+                encoded.push(words[((acc >> bits) & 0x7ff) as usize].as_str());
+            }
+        }
+        if bits > 0 {
+            encoded.push(words[((acc << (BITS_PER_WORD - bits)) & 0x7ff) as usize].as_str());
+        }
+
+        encoded.join(" ")
+    }
+
+    /// Decode a share from the words produced by [`Share::to_words`], verifying its checksum.
+    pub fn from_words(phrase: &str) -> Result<Self, anyhow::Error> {
+        let index = word_index();
+        let mut bytes = vec![];
+        let (mut acc, mut bits) = (0u32, 0usize);
+        for word in phrase.split_whitespace() {
+            let Some(i) = index.get(word.to_lowercase().as_str()) else {
+                bail!("Unknown word in share: {word:?}");
+            };
+            acc = (acc << BITS_PER_WORD) | *i as u32;
+            bits += BITS_PER_WORD;
+            while bits >= 8 {
+                bits -= 8;
+                bytes.push((acc >> bits) as u8);
+            }
+        }
+
+        ensure!(bytes.len() >= HEADER_LENGTH, "Share is too short");
+        let length = HEADER_LENGTH + bytes[HEADER_LENGTH - 1] as usize + CHECKSUM_LENGTH;
+        ensure!(
+            bytes.len() >= length && bytes[length..].iter().all(|b| *b == 0),
+            "Share has the wrong number of words"
+        );
+
+        let (payload, sum) = bytes[..length].split_at(length - CHECKSUM_LENGTH);
+        ensure!(
+            sum == checksum(payload),
+            "Share checksum does not match, check it for typos"
+        );
+        ensure!(
+            payload[0] == SHARE_VERSION,
+            "Unsupported share version {}",
+            payload[0]
+        );
+
+        let kind = match payload[1] {
+            0 => SecretKind::KeyPair,
+            1 => SecretKind::Mnemonic,
+            kind => bail!("Unknown kind of secret in share: {kind}"),
+        };
+
+        let (id, rest) = payload[2..].split_at(8);
+        let (digest, rest) = rest.split_at(DIGEST_LENGTH);
+        let share = Share {
+            kind,
+            id: u64::from_be_bytes(id.try_into().expect("Split at 8 bytes")),
+            digest: digest.try_into().expect("Split at DIGEST_LENGTH bytes"),
+            threshold: rest[0],
+            index: rest[1],
+            value: payload[HEADER_LENGTH..].to_vec(),
+        };
+
+        ensure!(share.index != 0, "Invalid share index 0");
+        ensure!(share.threshold != 0, "Invalid share threshold 0");
+        Ok(share)
+    }
+}
+
+/// A digest of the secret's `bytes`, to check recovered secrets against. It is truncated, as it
+/// only needs to catch mistakes.
+fn secret_digest(kind: SecretKind, bytes: &[u8]) -> [u8; DIGEST_LENGTH] {
+    let mut hasher = DefaultHash::default();
+    hasher.update(b"sui-shamir-secret");
+    hasher.update([kind as u8]);
+    hasher.update(bytes);
+    let digest = hasher.finalize().digest;
+
+    let mut truncated = [0u8; DIGEST_LENGTH];
+    truncated.copy_from_slice(&digest[..DIGEST_LENGTH]);
+    truncated
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LENGTH] {
+    let digest = DefaultHash::digest(bytes).digest;
+    let mut sum = [0u8; CHECKSUM_LENGTH];
+    sum.copy_from_slice(&digest[..CHECKSUM_LENGTH]);
+    sum
+}
+
+/// The BIP39 English word list. `bip39` does not expose it, so it is read off the first word of
+/// the mnemonics of entropies starting with each possible 11-bit prefix.
+fn wordlist() -> &'static [String] {
+    static WORDS: OnceLock<Vec<String>> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        (0..1u16 << BITS_PER_WORD)
+            .map(|i| {
+                let mut entropy = [0u8; 16];
+                entropy[0] = (i >> 3) as u8;
+                entropy[1] = ((i & 0x7) << 5) as u8;
+                let mnemonic = Mnemonic::from_entropy(&entropy, Language::English)
+                    .expect("16 bytes is a valid entropy length");
+                let first = mnemonic.phrase().split(' ').next();
+                first.expect("Mnemonics are not empty").to_string()
+            })
+            .collect()
+    })
+}
+
+fn word_index() -> &'static HashMap<&'static str, u16> {
+    static INDEX: OnceLock<HashMap<&'static str, u16>> = OnceLock::new();
+    INDEX.get_or_init(|| {
+        wordlist()
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (w.as_str(), i as u16))
+            .collect()
+    })
+}
+
+/// Multiplication in GF(256), with the AES reduction polynomial.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// Division in GF(256), as multiplication by `b^254`, the inverse of `b`.
+fn gf_div(a: u8, b: u8) -> u8 {
+    assert_ne!(b, 0, "Division by zero in GF(256)");
+    let mut inverse = 1u8;
+    let mut power = b;
+    let mut exponent = 254u8;
+    while exponent != 0 {
+        if exponent & 1 != 0 {
+            inverse = gf_mul(inverse, power);
+        }
+        power = gf_mul(power, power);
+        exponent >>= 1;
+    }
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_derive::generate_new_key;
+    use sui_types::crypto::SignatureScheme;
+
+    #[test]
+    fn gf_arithmetic_test() {
+        for a in 1..=255u8 {
+            assert_eq!(1, gf_div(a, a));
+            for b in 1..=255u8 {
+                assert_eq!(a, gf_div(gf_mul(a, b), b));
+            }
+        }
+    }
+
+    #[test]
+    fn split_and_combine_keypair_test() {
+        let (_, kp, _, _) = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
+        let bytes = kp.to_bytes();
+
+        let shares = split(&Secret::KeyPair(kp), 3, 5).unwrap();
+        let shares: Vec<_> = shares
+            .iter()
+            .map(|s| Share::from_words(&s.to_words()).unwrap())
+            .collect();
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<_> = subset.iter().map(|i| shares[*i].clone()).collect();
+            let Secret::KeyPair(recovered) = combine(&subset).unwrap() else {
+                panic!("Expected a keypair");
+            };
+            assert_eq!(bytes, recovered.to_bytes());
+        }
+
+        assert!(combine(&shares[..2]).is_err());
+        assert!(combine(&[shares[0].clone(), shares[0].clone(), shares[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn split_and_combine_mnemonic_test() {
+        let (_, _, _, phrase) = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
+
+        let shares = split(&Secret::Mnemonic(Zeroizing::new(phrase.clone())), 2, 2).unwrap();
+        let Secret::Mnemonic(recovered) = combine(&shares).unwrap() else {
+            panic!("Expected a mnemonic");
+        };
+        assert_eq!(phrase, *recovered);
+    }
+
+    #[test]
+    fn share_checksum_test() {
+        let (_, kp, _, _) = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
+        let shares = split(&Secret::KeyPair(kp), 2, 3).unwrap();
+        let words = shares[0].to_words();
+
+        let mut typo: Vec<_> = words.split(' ').collect();
+        typo[10] = if typo[10] == "abandon" {
+            "ability"
+        } else {
+            "abandon"
+        };
+        assert!(Share::from_words(&typo.join(" ")).is_err());
+        assert!(Share::from_words(&words[..words.rfind(' ').unwrap()]).is_err());
+
+        // Shares of different splits of the same secret cannot be mixed.
+        let (_, kp, _, _) = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
+        let other = split(&Secret::KeyPair(kp), 2, 3).unwrap();
+        assert!(combine(&[shares[0].clone(), other[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn mixed_splits_test() {
+        let (_, kp, _, _) = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
+        let secret = Secret::KeyPair(kp);
+        let shares = split(&secret, 2, 3).unwrap();
+
+        // Shares of another split of the same secret, whose identifier happens to collide.
+        let mut other = split(&secret, 2, 3).unwrap();
+        for share in &mut other {
+            share.id = shares[0].id;
+        }
+
+        let err = combine(&[shares[0].clone(), other[1].clone()])
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("does not match"), "{err}");
+
+        // Shares of a different secret, whose identifier happens to collide.
+        let (_, kp, _, _) = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
+        let mut other = split(&Secret::KeyPair(kp), 2, 3).unwrap();
+        other[1].id = shares[0].id;
+        assert!(combine(&[shares[0].clone(), other[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn tampered_share_test() {
+        let (_, _, _, phrase) = generate_new_key(SignatureScheme::ED25519, None, None).unwrap();
+        let shares = split(&Secret::Mnemonic(Zeroizing::new(phrase)), 2, 3).unwrap();
+
+        // A share whose value was changed, but whose checksum still matches.
+        let mut tampered = shares[0].clone();
+        tampered.value[0] ^= 1;
+        let tampered = Share::from_words(&tampered.to_words()).unwrap();
+
+        let err = combine(&[tampered, shares[1].clone()]).err().unwrap();
+        assert!(err.to_string().contains("does not match"), "{err}");
+    }
+}
@@ -0,0 +1,506 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Signing policies are a guardrail that keystores evaluate before signing a transaction. A policy
+//! is a list of rules, each of which denies signing, or requires interactive confirmation before
+//! signing, when a transaction matches all of the rule's conditions:
+//!
+//! ```yaml
+//! rules:
+//!   - name: treasury-outflow
+//!     signers: ["0x4e2d..."]
+//!     sui_outflow_above: 1000000000000
+//!     action: deny
+//!   - name: treasury-recipients
+//!     signers: ["0x4e2d..."]
+//!     recipients_not_in: ["0x7a1c...", "0x93bb..."]
+//!     action: deny
+//!   - name: upgrades
+//!     functions: ["0x2::package::*"]
+//!     action: confirm
+//! ```
+//!
+//! File-based and external keystores pick up the policy in [`SIGNING_POLICY_FILE_NAME`], next to
+//! the keystore file, if there is one.
+//!
+//! Keystores only see the transaction, not the objects it uses, so they assume that any owned
+//! object input could be a `Coin<SUI>` of unbounded value, and they deny transactions that need
+//! confirmation. Callers that can read the inputs and ask for confirmation call
+//! [`SigningPolicy::authorize`] before signing instead, which evaluates the policy with what is
+//! known about the inputs, and lets the keystore sign the transaction if it is allowed or confirmed.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, anyhow, bail, ensure};
+use serde::{Deserialize, Serialize};
+use shared_crypto::intent::{Intent, IntentScope};
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::digests::TransactionDigest;
+use sui_types::transaction::{
+    Argument, CallArg, Command, ObjectArg, TransactionData, TransactionDataAPI, TransactionKind,
+};
+
+/// Name of the file holding the signing policy for the keystores in a directory.
+pub const SIGNING_POLICY_FILE_NAME: &str = "signing_policy.yaml";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SigningPolicy {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+    /// Transactions that the caller has evaluated and approved for signing.
+    #[serde(skip)]
+    approved: Arc<Mutex<BTreeSet<(SuiAddress, TransactionDigest)>>>,
+}
+
+/// A rule applies to a transaction if the transaction matches all of its conditions. A rule
+/// without any conditions applies to every transaction.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Name of the rule, reported when it applies.
+    pub name: String,
+    /// What to do when the rule applies.
+    pub action: PolicyAction,
+    /// The transaction is being signed by one of these addresses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signers: Option<Vec<SuiAddress>>,
+    /// The transaction calls a function in one of these packages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packages: Option<Vec<ObjectID>>,
+    /// The transaction calls one of these functions, written as `<package>::<module>::<function>`,
+    /// where the function name can be `*` to match any function in the module.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functions: Option<Vec<String>>,
+    /// The transaction moves more than this much SUI (in MIST) out of its gas coin and other
+    /// `Coin<SUI>` inputs, not counting gas.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sui_outflow_above: Option<u64>,
+    /// The transaction transfers objects to an address that is not in this allow-list, or to an
+    /// address that can't be determined without executing it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipients_not_in: Option<Vec<SuiAddress>>,
+    /// The transaction's gas budget is above this amount (in MIST).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_budget_above: Option<u64>,
+}
+
+/// Actions are ordered by severity: when several rules apply, the most severe action wins.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Ask for confirmation before signing. Keystores deny signing transactions that need
+    /// confirmation unless the caller has approved them.
+    Confirm,
+    /// Refuse to sign.
+    Deny,
+}
+
+/// Outcome of evaluating a signing policy against a transaction, with the names of the rules
+/// that led to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    Confirm(Vec<String>),
+    Deny(Vec<String>),
+}
+
+/// The parts of a transaction that signing policies inspect.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionSummary {
+    /// `(package, module, function)` for every Move call in the transaction.
+    pub move_calls: Vec<(ObjectID, String, String)>,
+    /// Upper bound on the SUI moved out of the gas coin and other `Coin<SUI>` inputs, or `None`
+    /// if it is unbounded: when the whole gas coin may be moved, or an owned object input that is
+    /// not known to not be a `Coin<SUI>` is used. Shared objects do not belong to the signer, so
+    /// they are not counted.
+    pub sui_outflow: Option<u64>,
+    /// Recipients of objects transferred by the transaction, or `None` for recipients that are
+    /// only known once the transaction runs.
+    pub recipients: Vec<Option<SuiAddress>>,
+    pub gas_budget: u64,
+}
+
+/// What is known about the object inputs of a transaction: whether they are `Coin<SUI>`s and if so,
+/// their balance. Owned object inputs that are not resolved are assumed to be `Coin<SUI>`s of
+/// unbounded value.
+#[derive(Clone, Debug, Default)]
+pub struct ResolvedInputs {
+    /// The balance of inputs that are `Coin<SUI>`s, or `None` for other objects.
+    objects: BTreeMap<ObjectID, Option<u64>>,
+}
+
+impl ResolvedInputs {
+    /// Record that `id` is a `Coin<SUI>` holding `balance` MIST.
+    pub fn insert_sui_coin(&mut self, id: ObjectID, balance: u64) {
+        self.objects.insert(id, Some(balance));
+    }
+
+    /// Record that `id` is not a `Coin<SUI>`.
+    pub fn insert_other(&mut self, id: ObjectID) {
+        self.objects.insert(id, None);
+    }
+}
+
+impl SigningPolicy {
+    /// Load a policy from a YAML file.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read signing policy: {}", path.display()))?;
+        let policy: SigningPolicy = serde_yaml::from_str(&contents)
+            .with_context(|| format!("Cannot parse signing policy: {}", path.display()))?;
+        policy
+            .validate()
+            .with_context(|| format!("Invalid signing policy: {}", path.display()))?;
+        Ok(policy)
+    }
+
+    /// Load the policy for the keystore stored at `keystore_path`, if there is one.
+    pub fn load_for_keystore(keystore_path: &Path) -> Result<Option<Self>, anyhow::Error> {
+        let path = keystore_path.with_file_name(SIGNING_POLICY_FILE_NAME);
+        if path.exists() {
+            Self::load(&path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Check that every rule is well-formed.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        let mut names = BTreeSet::new();
+        for rule in &self.rules {
+            if !names.insert(&rule.name) {
+                bail!("Rule {:?} is defined more than once", rule.name);
+            }
+
+            for function in rule.functions.iter().flatten() {
+                FunctionPattern::parse(function)
+                    .with_context(|| format!("In rule {:?}", rule.name))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decide whether `signer` may sign `tx`, assuming that every object input could be a
+    /// `Coin<SUI>` of unbounded value.
+    pub fn evaluate(&self, signer: &SuiAddress, tx: &TransactionData) -> PolicyDecision {
+        self.evaluate_with_inputs(signer, tx, &ResolvedInputs::default())
+    }
+
+    /// Decide whether `signer` may sign `tx`, given what is known about its object inputs.
+    pub fn evaluate_with_inputs(
+        &self,
+        signer: &SuiAddress,
+        tx: &TransactionData,
+        inputs: &ResolvedInputs,
+    ) -> PolicyDecision {
+        let summary = TransactionSummary::with_inputs(tx, inputs);
+
+        let mut action = None;
+        let mut reasons = vec![];
+        for rule in &self.rules {
+            if !rule.applies(signer, &summary) {
+                continue;
+            }
+
+            match action.cmp(&Some(rule.action)) {
+                std::cmp::Ordering::Less => {
+                    action = Some(rule.action);
+                    reasons = vec![rule.name.clone()];
+                }
+                std::cmp::Ordering::Equal => reasons.push(rule.name.clone()),
+                std::cmp::Ordering::Greater => {}
+            }
+        }
+
+        match action {
+            None => PolicyDecision::Allow,
+            Some(PolicyAction::Confirm) => PolicyDecision::Confirm(reasons),
+            Some(PolicyAction::Deny) => PolicyDecision::Deny(reasons),
+        }
+    }
+
+    /// Decide whether `signer` may sign `tx`, given what is known about its object inputs, calling
+    /// `confirm` if the policy requires confirmation. If the transaction is allowed or confirmed,
+    /// keystores sign it once without evaluating the policy again, otherwise this errors.
+    pub fn authorize(
+        &self,
+        signer: SuiAddress,
+        tx: &TransactionData,
+        inputs: &ResolvedInputs,
+        confirm: impl FnOnce(&PolicyDecision) -> Result<bool, anyhow::Error>,
+    ) -> Result<(), anyhow::Error> {
+        match self.evaluate_with_inputs(&signer, tx, inputs) {
+            PolicyDecision::Allow => {}
+            decision @ PolicyDecision::Deny(_) => {
+                bail!("Signing policy denied signing for {signer}: {decision}")
+            }
+            decision @ PolicyDecision::Confirm(_) => {
+                ensure!(
+                    confirm(&decision)?,
+                    "Signing for {signer} {decision}, and was not confirmed"
+                );
+            }
+        }
+
+        self.approve(signer, tx.digest());
+        Ok(())
+    }
+
+    fn approve(&self, signer: SuiAddress, digest: TransactionDigest) {
+        self.approved.lock().unwrap().insert((signer, digest));
+    }
+
+    fn take_approval(&self, signer: SuiAddress, digest: TransactionDigest) -> bool {
+        self.approved.lock().unwrap().remove(&(signer, digest))
+    }
+}
+
+impl PolicyRule {
+    fn applies(&self, signer: &SuiAddress, tx: &TransactionSummary) -> bool {
+        let calls_function = |functions: &Vec<String>| {
+            let patterns: Vec<_> = functions
+                .iter()
+                .filter_map(|f| FunctionPattern::parse(f).ok())
+                .collect();
+            tx.move_calls
+                .iter()
+                .any(|call| patterns.iter().any(|p| p.matches(call)))
+        };
+
+        self.signers.as_ref().is_none_or(|s| s.contains(signer))
+            && self
+                .packages
+                .as_ref()
+                .is_none_or(|packages| tx.move_calls.iter().any(|(p, _, _)| packages.contains(p)))
+            && self.functions.as_ref().is_none_or(calls_function)
+            && self
+                .sui_outflow_above
+                .is_none_or(|limit| tx.sui_outflow.is_none_or(|outflow| outflow > limit))
+            && self.recipients_not_in.as_ref().is_none_or(|allowed| {
+                tx.recipients
+                    .iter()
+                    .any(|r| r.is_none_or(|r| !allowed.contains(&r)))
+            })
+            && self
+                .gas_budget_above
+                .is_none_or(|limit| tx.gas_budget > limit)
+    }
+}
+
+impl TransactionSummary {
+    pub fn new(tx: &TransactionData) -> Self {
+        Self::with_inputs(tx, &ResolvedInputs::default())
+    }
+
+    pub fn with_inputs(tx: &TransactionData, inputs: &ResolvedInputs) -> Self {
+        let mut summary = TransactionSummary {
+            gas_budget: tx.gas_budget(),
+            sui_outflow: Some(0),
+            ..Default::default()
+        };
+
+        summary.move_calls = tx
+            .move_calls()
+            .into_iter()
+            .map(|(p, m, f)| (*p, m.to_string(), f.to_string()))
+            .collect();
+
+        let TransactionKind::ProgrammableTransaction(pt) = tx.kind() else {
+            return summary;
+        };
+
+        let pure_input = |arg: &Argument| match arg {
+            Argument::Input(ix) => match pt.inputs.get(*ix as usize) {
+                Some(CallArg::Pure(bytes)) => Some(bytes.as_slice()),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        // The SUI that moving `arg` in its entirety could move out: nothing for values created by
+        // the transaction (coins split off another coin are accounted for when splitting) or for
+        // shared objects, the balance of `Coin<SUI>` inputs, and an unbounded amount for the gas
+        // coin and unresolved owned object inputs.
+        let coin_value = |arg: &Argument| -> Option<u64> {
+            match arg {
+                Argument::GasCoin => None,
+                Argument::Input(ix) => match pt.inputs.get(*ix as usize) {
+                    Some(CallArg::Object(object)) => {
+                        let id = match object {
+                            ObjectArg::ImmOrOwnedObject((id, _, _))
+                            | ObjectArg::Receiving((id, _, _)) => *id,
+                            ObjectArg::SharedObject { .. } => return Some(0),
+                        };
+                        inputs.objects.get(&id).copied()?.or(Some(0))
+                    }
+                    _ => Some(0),
+                },
+                Argument::Result(_) | Argument::NestedResult(..) => Some(0),
+            }
+        };
+
+        let mut outflow = Some(0u64);
+        let mut add_outflow = |amount: Option<u64>| {
+            outflow = outflow
+                .zip(amount)
+                .map(|(total, amount)| total.saturating_add(amount));
+        };
+
+        for command in &pt.commands {
+            match command {
+                // Splitting a known amount off a coin only moves that amount (or the balance of
+                // the coin, if that is lower).
+                Command::SplitCoins(coin, amounts) => {
+                    let split = amounts.iter().try_fold(0u64, |total, amount| {
+                        let amount: u64 = bcs::from_bytes(pure_input(amount)?).ok()?;
+                        Some(total.saturating_add(amount))
+                    });
+                    add_outflow(match (coin, coin_value(coin)) {
+                        (Argument::GasCoin, _) => split,
+                        (_, Some(balance)) => Some(split.map_or(balance, |s| s.min(balance))),
+                        (_, None) => None,
+                    });
+                }
+
+                Command::TransferObjects(objs, recipient) => {
+                    for obj in objs {
+                        add_outflow(coin_value(obj));
+                    }
+
+                    summary
+                        .recipients
+                        .push(pure_input(recipient).and_then(|b| bcs::from_bytes(b).ok()));
+                }
+
+                Command::MoveCall(call) => {
+                    for arg in &call.arguments {
+                        add_outflow(coin_value(arg));
+                    }
+                }
+
+                Command::MakeMoveVec(_, elems) => {
+                    for elem in elems {
+                        add_outflow(coin_value(elem));
+                    }
+                }
+
+                // Merging coins into the gas coin keeps their SUI with the sender, unless the gas
+                // coin is moved, which is unbounded anyway.
+                Command::MergeCoins(into, coins) => {
+                    if *into != Argument::GasCoin {
+                        for coin in coins {
+                            add_outflow(coin_value(coin));
+                        }
+                    }
+                }
+
+                Command::Publish(..) | Command::Upgrade(..) => {}
+            }
+        }
+
+        summary.sui_outflow = outflow;
+        summary
+    }
+}
+
+impl fmt::Display for PolicyDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyDecision::Allow => write!(f, "allowed"),
+            PolicyDecision::Confirm(rules) => {
+                write!(f, "requires confirmation by rule(s): {}", rules.join(", "))
+            }
+            PolicyDecision::Deny(rules) => write!(f, "denied by rule(s): {}", rules.join(", ")),
+        }
+    }
+}
+
+/// A `<package>::<module>::<function>` pattern from a policy rule.
+struct FunctionPattern {
+    package: ObjectID,
+    module: String,
+    function: Option<String>,
+}
+
+impl FunctionPattern {
+    fn parse(s: &str) -> Result<Self, anyhow::Error> {
+        let [package, module, function] = s.split("::").collect::<Vec<_>>()[..] else {
+            bail!("Expected a function as '<package>::<module>::<function>', got {s:?}");
+        };
+
+        let package = ObjectID::from_hex_literal(package)
+            .map_err(|e| anyhow!("Invalid package in function {s:?}: {e}"))?;
+
+        Ok(Self {
+            package,
+            module: module.to_string(),
+            function: (function != "*").then(|| function.to_string()),
+        })
+    }
+
+    fn matches(&self, (package, module, function): &(ObjectID, String, String)) -> bool {
+        self.package == *package
+            && self.module == *module
+            && self.function.as_ref().is_none_or(|f| f == function)
+    }
+}
+
+/// Apply `policy` (if there is one) before `signer` signs `msg` with `intent`. Only transactions
+/// are subject to the policy.
+pub(crate) fn enforce<T: Serialize>(
+    policy: Option<&SigningPolicy>,
+    signer: &SuiAddress,
+    msg: &T,
+    intent: &Intent,
+) -> Result<(), signature::Error> {
+    let Some(policy) = policy else {
+        return Ok(());
+    };
+
+    if intent.scope != IntentScope::TransactionData {
+        return Ok(());
+    }
+
+    // Signing is generic over the message, so recover the transaction from its serialized form.
+    let tx: TransactionData = bcs::to_bytes(msg)
+        .ok()
+        .and_then(|bytes| bcs::from_bytes(&bytes).ok())
+        .ok_or_else(|| {
+            signature::Error::from_source(
+                "Signing policy denied signing: message is not a valid transaction",
+            )
+        })?;
+
+    let digest = tx.digest();
+    if policy.take_approval(*signer, digest) {
+        return Ok(());
+    }
+
+    match policy.evaluate(signer, &tx) {
+        PolicyDecision::Allow => Ok(()),
+        decision => Err(signature::Error::from_source(format!(
+            "Signing policy denied signing transaction {digest} for {signer}: {decision}",
+        ))),
+    }
+}
+
+/// Apply `policy` (if there is one) before `signer` signs a pre-hashed message. A hash cannot be
+/// inspected, so this is denied whenever there is a policy.
+pub(crate) fn enforce_hashed(
+    policy: Option<&SigningPolicy>,
+    signer: &SuiAddress,
+) -> Result<(), signature::Error> {
+    if policy.is_some() {
+        return Err(signature::Error::from_source(format!(
+            "Signing policy denied signing a hashed message for {signer}: hashed messages \
+             cannot be checked against the policy"
+        )));
+    }
+
+    Ok(())
+}
@@ -3,15 +3,17 @@
 
 use std::fs;
 use std::str::FromStr;
+use std::time::Duration;
 
 use fastcrypto::hash::HashFunction;
 use fastcrypto::traits::EncodeDecodeBase64;
+use sui_keys::encrypted::SESSION_FILE_EXTENSION;
 use sui_keys::key_derive::generate_new_key;
 use tempfile::TempDir;
 
 use sui_keys::keystore::{
-    ALIASES_FILE_EXTENSION, AccountKeystore, Alias, FileBasedKeystore, GenerateOptions,
-    GeneratedKey, InMemKeystore, Keystore,
+    ALIASES_FILE_EXTENSION, AccountKeystore, Alias, EncryptedFileKeystore, FileBasedKeystore,
+    GenerateOptions, GeneratedKey, InMemKeystore, Keystore,
 };
 use sui_types::crypto::{DefaultHash, SignatureScheme, SuiSignatureInner};
 use sui_types::{
@@ -372,3 +374,296 @@ async fn keystore_file_permissions_test() {
         "Keystore file permissions should remain 0o600 after operations"
     );
 }
+
+#[tokio::test]
+async fn signing_policy_test() {
+    use shared_crypto::intent::Intent;
+    use sui_keys::signing_policy::{
+        PolicyDecision, ResolvedInputs, SIGNING_POLICY_FILE_NAME, SigningPolicy,
+    };
+    use sui_types::Identifier;
+    use sui_types::base_types::{ObjectID, random_object_ref};
+    use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
+    use sui_types::transaction::{CallArg, TransactionData};
+
+    let temp_dir = TempDir::new().unwrap();
+    let keystore_path = temp_dir.path().join("sui.keystore");
+    let mut keystore = Keystore::from(FileBasedKeystore::load_or_create(&keystore_path).unwrap());
+    let GeneratedKey { address, .. } = keystore
+        .generate(None, GenerateOptions::Default)
+        .await
+        .unwrap();
+
+    let friend = SuiAddress::random_for_testing_only();
+    let stranger = SuiAddress::random_for_testing_only();
+    let policy = format!(
+        r#"
+rules:
+  - name: outflow
+    signers: ["{address}"]
+    sui_outflow_above: 1000
+    action: deny
+  - name: recipients
+    recipients_not_in: ["{friend}"]
+    action: deny
+"#
+    );
+    fs::write(temp_dir.path().join(SIGNING_POLICY_FILE_NAME), policy).unwrap();
+
+    let transfer = |recipient: SuiAddress, amount: u64| {
+        let mut builder = ProgrammableTransactionBuilder::new();
+        builder.transfer_sui(recipient, Some(amount));
+        TransactionData::new_programmable(
+            address,
+            vec![random_object_ref()],
+            builder.finish(),
+            10_000_000,
+            1000,
+        )
+    };
+
+    // The policy is picked up when the keystore is loaded.
+    let keystore = Keystore::from(FileBasedKeystore::load_or_create(&keystore_path).unwrap());
+    assert!(keystore.signing_policy().is_some());
+
+    keystore
+        .sign_secure(&address, &transfer(friend, 1000), Intent::sui_transaction())
+        .await
+        .unwrap();
+
+    let err = keystore
+        .sign_secure(&address, &transfer(friend, 1001), Intent::sui_transaction())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("outflow"), "{err}");
+
+    let err = keystore
+        .sign_secure(&address, &transfer(stranger, 10), Intent::sui_transaction())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("recipients"), "{err}");
+
+    // Coins other than the gas coin count towards the outflow too. Without knowing their balance,
+    // the keystore assumes that it is unbounded.
+    let coin = random_object_ref();
+    let transfer_coin = {
+        let mut builder = ProgrammableTransactionBuilder::new();
+        builder.transfer_object(friend, coin).unwrap();
+        TransactionData::new_programmable(
+            address,
+            vec![random_object_ref()],
+            builder.finish(),
+            10_000_000,
+            1000,
+        )
+    };
+    let err = keystore
+        .sign_secure(&address, &transfer_coin, Intent::sui_transaction())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("outflow"), "{err}");
+
+    let policy = keystore.signing_policy().unwrap();
+    let mut inputs = ResolvedInputs::default();
+    inputs.insert_sui_coin(coin.0, 5000);
+    assert_eq!(
+        PolicyDecision::Deny(vec!["outflow".to_owned()]),
+        policy.evaluate_with_inputs(&address, &transfer_coin, &inputs)
+    );
+    inputs.insert_sui_coin(coin.0, 500);
+    assert_eq!(
+        PolicyDecision::Allow,
+        policy.evaluate_with_inputs(&address, &transfer_coin, &inputs)
+    );
+    inputs.insert_other(coin.0);
+    assert_eq!(
+        PolicyDecision::Allow,
+        policy.evaluate_with_inputs(&address, &transfer_coin, &inputs)
+    );
+
+    // Once the caller has authorized a transaction, the keystore signs it, once.
+    let unresolved = ResolvedInputs::default();
+    let err = policy
+        .authorize(address, &transfer_coin, &unresolved, |_| Ok(true))
+        .unwrap_err();
+    assert!(err.to_string().contains("outflow"), "{err}");
+    policy
+        .authorize(address, &transfer_coin, &inputs, |_| Ok(true))
+        .unwrap();
+    keystore
+        .sign_secure(&address, &transfer_coin, Intent::sui_transaction())
+        .await
+        .unwrap();
+    assert!(
+        keystore
+            .sign_secure(&address, &transfer_coin, Intent::sui_transaction())
+            .await
+            .is_err()
+    );
+
+    // Shared objects do not belong to the signer, so they do not count towards its outflow.
+    let use_shared = {
+        let mut builder = ProgrammableTransactionBuilder::new();
+        builder
+            .move_call(
+                ObjectID::random(),
+                Identifier::new("m").unwrap(),
+                Identifier::new("f").unwrap(),
+                vec![],
+                vec![CallArg::CLOCK_MUT],
+            )
+            .unwrap();
+        TransactionData::new_programmable(
+            address,
+            vec![random_object_ref()],
+            builder.finish(),
+            10_000_000,
+            1000,
+        )
+    };
+    assert_eq!(
+        PolicyDecision::Allow,
+        policy.evaluate(&address, &use_shared)
+    );
+
+    // Transactions that need confirmation are only authorized once they are confirmed.
+    let confirming: SigningPolicy = serde_yaml::from_str(
+        r#"
+rules:
+  - name: big-budget
+    gas_budget_above: 1000
+    action: confirm
+"#,
+    )
+    .unwrap();
+    let tx = transfer(friend, 10);
+    assert!(
+        confirming
+            .authorize(address, &tx, &unresolved, |_| Ok(false))
+            .is_err()
+    );
+    confirming
+        .authorize(address, &tx, &unresolved, |decision| {
+            Ok(*decision == PolicyDecision::Confirm(vec!["big-budget".to_owned()]))
+        })
+        .unwrap();
+
+    // Hashed messages cannot be inspected, so they are refused.
+    assert!(keystore.sign_hashed(&address, b"hello").await.is_err());
+
+    // Messages that are not transactions are not subject to the policy.
+    keystore
+        .sign_secure(&address, &b"hello".to_vec(), Intent::personal_message())
+        .await
+        .unwrap();
+
+    // Without a policy, anything goes.
+    let mut keystore = keystore;
+    keystore.set_signing_policy(None);
+    keystore
+        .sign_secure(
+            &address,
+            &transfer(stranger, 1001),
+            Intent::sui_transaction(),
+        )
+        .await
+        .unwrap();
+
+    let policy = SigningPolicy::load(&temp_dir.path().join(SIGNING_POLICY_FILE_NAME)).unwrap();
+    assert_eq!(policy.rules.len(), 2);
+}
+
+#[tokio::test]
+async fn encrypted_keystore_test() {
+    let temp_dir = TempDir::new().unwrap();
+    let keystore_path = temp_dir.path().join("sui.keystore");
+    let mut file = FileBasedKeystore::load_or_create(&keystore_path).unwrap();
+    let GeneratedKey { address, .. } = file
+        .generate(Some("alice".to_string()), GenerateOptions::default())
+        .await
+        .unwrap();
+    let private_key = file.export(&address).unwrap().encode_base64();
+
+    // Migrating replaces the keystore file with an encrypted one, keeping aliases readable.
+    EncryptedFileKeystore::migrate(&file, "correct horse").unwrap();
+    assert!(EncryptedFileKeystore::is_encrypted(&keystore_path));
+    assert!(
+        !fs::read_to_string(&keystore_path)
+            .unwrap()
+            .contains(&private_key)
+    );
+    assert!(FileBasedKeystore::load_or_create(&keystore_path).is_err());
+
+    let Keystore::Encrypted(mut encrypted) =
+        Keystore::load_or_create_from_path(&keystore_path).unwrap()
+    else {
+        panic!("Expected an encrypted keystore");
+    };
+    assert!(encrypted.is_locked());
+    assert_eq!(vec![address], encrypted.addresses());
+    assert_eq!("alice", encrypted.get_alias(&address).unwrap());
+
+    assert!(encrypted.unlock("wrong", None).is_err());
+    assert!(encrypted.is_locked());
+
+    // A session is shared with other instances of the keystore that have its token, until it is
+    // locked.
+    let session = encrypted
+        .unlock("correct horse", Some(Duration::from_secs(60)))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        private_key,
+        encrypted.export(&address).unwrap().encode_base64()
+    );
+
+    // The session file does not hold the key in the clear.
+    let session_path = keystore_path.with_extension(SESSION_FILE_EXTENSION);
+    let session_file = fs::read_to_string(&session_path).unwrap();
+    assert!(!session_file.contains(&*session.token));
+
+    let mut other = EncryptedFileKeystore::load(&keystore_path).unwrap();
+    assert!(other.is_locked());
+    other.set_session_token(&session.token);
+    assert_eq!(Some(session.expires_at), other.session_expiry());
+    assert_eq!(private_key, other.export(&address).unwrap().encode_base64());
+
+    let mut wrong_token = EncryptedFileKeystore::load(&keystore_path).unwrap();
+    wrong_token.set_session_token("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=");
+    assert!(wrong_token.session_expiry().is_none());
+
+    encrypted.lock().unwrap();
+    assert!(encrypted.is_locked());
+    assert!(other.session_expiry().is_none());
+    assert!(!session_path.exists());
+
+    // Expired sessions are removed.
+    encrypted
+        .unlock("correct horse", Some(Duration::ZERO))
+        .unwrap();
+    assert!(session_path.exists());
+    assert!(encrypted.session_expiry().is_none());
+    assert!(!session_path.exists());
+
+    // Changing the passphrase re-encrypts the keys.
+    encrypted
+        .change_passphrase("correct horse", "battery staple")
+        .unwrap();
+    let mut reloaded = EncryptedFileKeystore::load(&keystore_path).unwrap();
+    assert!(reloaded.unlock("correct horse", None).is_err());
+    reloaded.unlock("battery staple", None).unwrap();
+    assert_eq!(
+        private_key,
+        reloaded.export(&address).unwrap().encode_base64()
+    );
+
+    // Keys added while unlocked are encrypted with the same passphrase.
+    let GeneratedKey { address: bob, .. } = reloaded
+        .generate(Some("bob".to_string()), GenerateOptions::default())
+        .await
+        .unwrap();
+    let mut reloaded = EncryptedFileKeystore::load(&keystore_path).unwrap();
+    assert_eq!(2, reloaded.addresses().len());
+    reloaded.unlock("battery staple", None).unwrap();
+    assert!(reloaded.export(&bob).is_ok());
+}
@@ -0,0 +1,201 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Self-contained proof bundles, which can be verified offline.
+//!
+//! A bundle carries a proof together with the end-of-epoch checkpoints that link a trusted epoch
+//! to the epoch of the proof. Verifying it only needs the committee of the trusted epoch, taken
+//! either from a genesis file or from a trusted end-of-epoch checkpoint, and no network access.
+
+use crate::config::Config;
+use crate::light_client::LightClient;
+use crate::object_store::SuiObjectStore;
+use crate::proof::base::{Proof, ProofBuilder, ProofTarget, ProofVerifier};
+use crate::proof::committee::extract_new_committee_info;
+use anyhow::{Result, anyhow, ensure};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use sui_config::genesis::Genesis;
+use sui_json_rpc_types::{SuiObjectDataOptions, SuiTransactionBlockResponseOptions};
+use sui_sdk::SuiClientBuilder;
+use sui_types::base_types::{ObjectID, TransactionDigest};
+use sui_types::committee::{Committee, EpochId};
+use sui_types::effects::TransactionEffectsAPI;
+use sui_types::messages_checkpoint::{CertifiedCheckpointSummary, CheckpointSequenceNumber};
+use sui_types::object::Object;
+
+/// Version of the bundle encoding produced by [`ProofBundle::to_bytes`].
+pub const BUNDLE_FORMAT_VERSION: u8 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProofBundle {
+    /// The end-of-epoch checkpoints from the trusted epoch up to the epoch before the proof's,
+    /// oldest first. Each is signed by the committee that the previous one certifies.
+    pub committee_chain: Vec<CertifiedCheckpointSummary>,
+
+    /// The proof, whose checkpoint is signed by the committee the last link certifies.
+    pub proof: Proof,
+}
+
+impl ProofBundle {
+    /// Bundles `proof` with the end-of-epoch checkpoints of the light client chain that link
+    /// `trusted_epoch` to the epoch of the proof.
+    pub fn new(light_client: &LightClient, trusted_epoch: EpochId, proof: Proof) -> Result<Self> {
+        let epoch = proof.checkpoint_summary.epoch();
+        ensure!(
+            trusted_epoch <= epoch,
+            "Trusted epoch {trusted_epoch} is after the epoch of the proof {epoch}"
+        );
+
+        let committee_chain = (trusted_epoch..epoch)
+            .map(|epoch| {
+                light_client
+                    .end_of_epoch_checkpoint(epoch)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("No end-of-epoch checkpoint for epoch {epoch}"))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            committee_chain,
+            proof,
+        })
+    }
+
+    /// The epoch whose committee has to be trusted to verify the bundle.
+    pub fn trusted_epoch(&self) -> EpochId {
+        self.committee_chain
+            .first()
+            .unwrap_or(&self.proof.checkpoint_summary)
+            .epoch()
+    }
+
+    /// Verifies the bundle starting from `trusted`, the committee of the trusted epoch.
+    pub fn verify(self, trusted: &Committee) -> Result<()> {
+        ensure!(
+            trusted.epoch == self.trusted_epoch(),
+            "The bundle starts from epoch {}, but the trusted committee is for epoch {}",
+            self.trusted_epoch(),
+            trusted.epoch
+        );
+
+        let mut committee = trusted.clone();
+        for summary in self.committee_chain {
+            ensure!(
+                summary.epoch() == committee.epoch,
+                "Expected an end-of-epoch checkpoint for epoch {}, got one for epoch {}",
+                committee.epoch,
+                summary.epoch()
+            );
+            let verified = summary.try_into_verified(&committee)?;
+            committee = extract_new_committee_info(&verified)?;
+        }
+        // MILESTONE: Committee of the proof's epoch is correct
+
+        self.proof.verify(&committee)?;
+        Ok(())
+    }
+
+    /// Encodes the bundle as a version byte followed by the BCS encoded bundle.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![BUNDLE_FORMAT_VERSION];
+        bytes.extend(bcs::to_bytes(self)?);
+        Ok(bytes)
+    }
+
+    /// Decodes a bundle encoded by [`ProofBundle::to_bytes`]. The bundle still needs to be
+    /// verified.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (version, bundle) = bytes
+            .split_first()
+            .ok_or_else(|| anyhow!("Empty proof bundle"))?;
+        ensure!(
+            *version == BUNDLE_FORMAT_VERSION,
+            "Unsupported proof bundle version {version}"
+        );
+        Ok(bcs::from_bytes(bundle)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_bytes()?)
+            .map_err(|e| anyhow!("Cannot write {}: {e}", path.display()))
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).map_err(|e| anyhow!("Cannot read {}: {e}", path.display()))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// The genesis committee, trusted as the committee of epoch 0.
+pub fn trusted_committee_from_genesis(path: &Path) -> Result<Committee> {
+    Genesis::load(path)?
+        .committee()
+        .map_err(|e| anyhow!(format!("Cannot load Genesis: {e}")))
+}
+
+/// The committee certified by a trusted end-of-epoch checkpoint summary, in the BCS format of
+/// the synced checkpoint summaries. The summary is trusted as is, and its signatures are not
+/// checked.
+pub fn trusted_committee_from_checkpoint(path: &Path) -> Result<Committee> {
+    let bytes = fs::read(path).map_err(|e| anyhow!("Cannot read {}: {e}", path.display()))?;
+    let summary: CertifiedCheckpointSummary = bcs::from_bytes(&bytes)
+        .map_err(|e| anyhow!("Cannot parse checkpoint summary {}: {e}", path.display()))?;
+    Ok(extract_new_committee_info(&summary)?)
+}
+
+/// Proves that a transaction executed, with its effects, in its checkpoint.
+pub async fn prove_transaction(config: &Config, digest: TransactionDigest) -> Result<Proof> {
+    let seq = transaction_checkpoint(config, digest).await?;
+    let checkpoint = SuiObjectStore::new(config)?
+        .get_full_checkpoint(seq)
+        .await?;
+
+    let effects = checkpoint
+        .transactions
+        .iter()
+        .find(|tx| *tx.transaction.digest() == digest)
+        .map(|tx| tx.effects.execution_digests())
+        .ok_or_else(|| anyhow!("Transaction not found in checkpoint"))?;
+
+    Ok(ProofTarget::new_transactions(vec![effects]).construct(&checkpoint)?)
+}
+
+/// Proves the latest version of an object, as written by the transaction that last modified it.
+pub async fn prove_object(config: &Config, id: ObjectID) -> Result<Proof> {
+    let sui_client = SuiClientBuilder::default()
+        .build(config.full_node_url.as_str())
+        .await?;
+    let object: Object = sui_client
+        .read_api()
+        .get_object_with_options(id, SuiObjectDataOptions::bcs_lossless())
+        .await?
+        .into_object()?
+        .try_into()?;
+
+    let seq = transaction_checkpoint(config, object.previous_transaction).await?;
+    let checkpoint = SuiObjectStore::new(config)?
+        .get_full_checkpoint(seq)
+        .await?;
+
+    let target = ProofTarget::new_objects(vec![(object.compute_object_reference(), object)]);
+    Ok(target.construct(&checkpoint)?)
+}
+
+/// The checkpoint in which a transaction was executed.
+async fn transaction_checkpoint(
+    config: &Config,
+    digest: TransactionDigest,
+) -> Result<CheckpointSequenceNumber> {
+    let sui_client = SuiClientBuilder::default()
+        .build(config.full_node_url.as_str())
+        .await?;
+    sui_client
+        .read_api()
+        .get_transaction_with_options(digest, SuiTransactionBlockResponseOptions::new())
+        .await
+        .map_err(|e| anyhow!(format!("Cannot get transaction: {e}")))?
+        .checkpoint
+        .ok_or(anyhow!("Transaction not found"))
+}
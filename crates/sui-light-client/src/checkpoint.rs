@@ -1,16 +1,15 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::committee::extract_new_committee_info;
 use crate::config::Config;
 use crate::graphql::query_last_checkpoint_of_epoch;
+use crate::light_client::LightClient;
 use crate::object_store::SuiObjectStore;
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::io::Read;
 use std::{fs, io::Write};
-use sui_config::genesis::Genesis;
 use sui_data_ingestion_core::end_of_epoch_data;
 use sui_sdk::SuiClientBuilder;
 use sui_types::{
@@ -235,48 +234,55 @@ pub async fn check_and_sync_checkpoints(config: &Config) -> anyhow::Result<()> {
     // Write the fetched checkpoint list to disk
     write_checkpoint_list(config, &checkpoints_list)?;
 
-    // Load the genesis committee
-    let mut genesis_path = config.checkpoint_summary_dir.clone();
-    genesis_path.push(&config.genesis_filename);
-    let genesis_committee = Genesis::load(&genesis_path)?
-        .committee()
-        .map_err(|e| anyhow!(format!("Cannot load Genesis: {e}")))?;
+    // Resume the verified committee chain from the last trusted epoch, starting
+    // from the genesis committee if there is none yet
+    let mut light_client = LightClient::from_config(config)?;
+    info!(
+        "Resuming light client chain from epoch {}",
+        light_client.latest_epoch()
+    );
 
-    // Check the signatures of all checkpoints
+    // Check the signatures of all checkpoints not yet in the chain
     // And download any missing ones
-
-    let mut prev_committee = genesis_committee;
     let object_store = SuiObjectStore::new(config)?;
     for ckp_id in &checkpoints_list.checkpoints {
+        if light_client.contains_checkpoint(*ckp_id) {
+            continue;
+        }
+
         // check if there is a file with this name ckp_id.yaml in the checkpoint_summary_dir
-        let mut checkpoint_path = config.checkpoint_summary_dir.clone();
-        checkpoint_path.push(format!("{}.yaml", ckp_id));
+        let checkpoint_path = config.checkpoint_path(*ckp_id, None);
 
         // If file exists read the file otherwise download it from the server
-        let summary = if checkpoint_path.exists() {
-            read_checkpoint(config, *ckp_id)
-                .map_err(|e| anyhow!(format!("Cannot read checkpoint: {e}")))?
+        let (summary, downloaded) = if checkpoint_path.exists() {
+            let summary = read_checkpoint(config, *ckp_id)
+                .map_err(|e| anyhow!(format!("Cannot read checkpoint: {e}")))?;
+            (summary, false)
         } else {
             // Download the checkpoint from the server
             let summary = object_store
                 .download_checkpoint_summary(*ckp_id)
                 .await
                 .map_err(|e| anyhow!(format!("Cannot download summary: {e}")))?;
-            summary.clone().try_into_verified(&prev_committee)?;
-            // Write the checkpoint summary to a file
-            write_checkpoint(config, &summary)?;
-            summary
+            (summary, true)
         };
 
+        // Verify the checkpoint and extend the chain with the new committee
+        light_client
+            .append(summary.clone())
+            .map_err(|e| anyhow!(format!("Cannot verify checkpoint {ckp_id}: {e}")))?;
+
+        // Write the checkpoint summary to a file
+        if downloaded {
+            write_checkpoint(config, &summary)?;
+        }
+
         // Print the id of the checkpoint and the epoch number
         info!(
             "Epoch: {} Checkpoint ID: {}",
             summary.epoch(),
             summary.digest()
         );
-
-        // Extract the new committee information
-        prev_committee = extract_new_committee_info(&summary)?;
     }
 
     Ok(())
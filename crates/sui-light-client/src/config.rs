@@ -58,6 +58,11 @@ impl Config {
     pub fn genesis_path(&self) -> PathBuf {
         self.checkpoint_summary_dir.join(&self.genesis_filename)
     }
+
+    /// Directory holding the verified committee chain, see [`crate::light_client`].
+    pub fn chain_dir(&self) -> PathBuf {
+        self.checkpoint_summary_dir.join("chain")
+    }
 }
 
 #[cfg(test)]
@@ -109,5 +114,8 @@ mod tests {
         let (config, _temp_dir) = create_test_config();
         let genesis_path = config.genesis_path();
         assert_eq!(genesis_path.file_name().unwrap(), "genesis.blob");
+
+        let chain_dir = config.chain_dir();
+        assert_eq!(chain_dir.file_name().unwrap(), "chain");
     }
 }
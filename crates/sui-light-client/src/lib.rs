@@ -3,6 +3,8 @@
 
 pub mod proof;
 
+pub mod bundle;
+
 pub mod checkpoint;
 
 pub mod config;
@@ -12,8 +14,12 @@ pub mod package_store;
 
 pub mod graphql;
 
+pub mod light_client;
+
 pub mod mmr;
 
+pub mod stream_follower;
+
 pub mod verifier;
 
 #[doc(inline)]
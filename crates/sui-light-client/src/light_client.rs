@@ -0,0 +1,422 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A persistent chain of verified committees.
+//!
+//! Starting from a trusted committee (normally the genesis committee), each end-of-epoch
+//! checkpoint is verified against the committee of its epoch, and in turn certifies the
+//! committee of the next epoch. Every verified link is written to disk as soon as it is
+//! verified, so that the light client can resume from the last trusted epoch instead of
+//! re-syncing from genesis on every start.
+//!
+//! On-disk layout, under the chain directory:
+//! - `root.bcs`: the trusted committee the chain starts from.
+//! - `<epoch>.bcs`: the end-of-epoch checkpoint of `<epoch>` and the committee it certifies.
+
+use crate::committee::extract_new_committee_info;
+use crate::config::Config;
+use anyhow::{Result, anyhow, bail, ensure};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sui_config::genesis::Genesis;
+use sui_types::committee::{Committee, EpochId};
+use sui_types::messages_checkpoint::{
+    CertifiedCheckpointSummary, CheckpointSequenceNumber, VerifiedCheckpoint,
+};
+
+const ROOT_FILE_NAME: &str = "root.bcs";
+
+/// An end-of-epoch checkpoint, and the next epoch's committee that it certifies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochLink {
+    pub end_of_epoch_checkpoint: CertifiedCheckpointSummary,
+    pub next_committee: Committee,
+}
+
+pub struct LightClient {
+    dir: PathBuf,
+    root: Committee,
+    /// `links[i]` is the link out of epoch `root.epoch + i`.
+    links: Vec<EpochLink>,
+    /// Sequence numbers of the end-of-epoch checkpoints in `links`.
+    checkpoints: HashSet<CheckpointSequenceNumber>,
+}
+
+impl LightClient {
+    /// Opens the chain stored in the configured checkpoint directory, starting a new one from
+    /// the genesis committee if there is none yet.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let dir = config.chain_dir();
+        if dir.join(ROOT_FILE_NAME).exists() {
+            return Self::open(&dir);
+        }
+
+        let genesis_committee = Genesis::load(config.genesis_path())?
+            .committee()
+            .map_err(|e| anyhow!(format!("Cannot load Genesis: {e}")))?;
+        Self::create(&dir, genesis_committee)
+    }
+
+    /// Starts a new chain in `dir`, trusting `root` as the committee of its epoch.
+    pub fn create(dir: &Path, root: Committee) -> Result<Self> {
+        let root_path = dir.join(ROOT_FILE_NAME);
+        ensure!(
+            !root_path.exists(),
+            "A light client chain already exists in {}",
+            dir.display()
+        );
+        fs::create_dir_all(dir)?;
+        write_bcs(&root_path, &root)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            root,
+            links: vec![],
+            checkpoints: HashSet::new(),
+        })
+    }
+
+    /// Opens an existing chain. The links were verified when they were added, so they are only
+    /// checked to follow on from each other here.
+    pub fn open(dir: &Path) -> Result<Self> {
+        let root: Committee = read_bcs(&dir.join(ROOT_FILE_NAME))?;
+        let mut light_client = Self {
+            dir: dir.to_path_buf(),
+            root,
+            links: vec![],
+            checkpoints: HashSet::new(),
+        };
+
+        loop {
+            let epoch = light_client.latest_epoch();
+            let path = light_client.link_path(epoch);
+            if !path.exists() {
+                break;
+            }
+            let link: EpochLink = read_bcs(&path)?;
+            ensure!(
+                link.end_of_epoch_checkpoint.epoch() == epoch
+                    && link.next_committee.epoch == epoch + 1,
+                "Light client chain is corrupted at epoch {epoch}"
+            );
+            light_client.push(link);
+        }
+
+        Ok(light_client)
+    }
+
+    /// The epoch of the trusted committee the chain starts from.
+    pub fn root_epoch(&self) -> EpochId {
+        self.root.epoch
+    }
+
+    /// The epoch of the most recent committee in the chain.
+    pub fn latest_epoch(&self) -> EpochId {
+        self.latest_committee().epoch
+    }
+
+    pub fn latest_committee(&self) -> &Committee {
+        self.links
+            .last()
+            .map(|link| &link.next_committee)
+            .unwrap_or(&self.root)
+    }
+
+    /// The committee of `epoch`, if the chain has reached it.
+    pub fn committee_at(&self, epoch: EpochId) -> Result<&Committee> {
+        if epoch == self.root.epoch {
+            return Ok(&self.root);
+        }
+        epoch
+            .checked_sub(1)
+            .and_then(|prev| self.link(prev))
+            .map(|link| &link.next_committee)
+            .ok_or_else(|| {
+                anyhow!(
+                    "No committee for epoch {epoch}: the light client chain covers epochs {} to {}. Need to Sync.",
+                    self.root.epoch,
+                    self.latest_epoch()
+                )
+            })
+    }
+
+    /// The verified end-of-epoch checkpoint of `epoch`, if the chain has passed it.
+    pub fn end_of_epoch_checkpoint(&self, epoch: EpochId) -> Option<&CertifiedCheckpointSummary> {
+        self.link(epoch).map(|link| &link.end_of_epoch_checkpoint)
+    }
+
+    /// Whether `seq` is an end-of-epoch checkpoint that is already in the chain.
+    pub fn contains_checkpoint(&self, seq: CheckpointSequenceNumber) -> bool {
+        self.checkpoints.contains(&seq)
+    }
+
+    /// Verifies a checkpoint summary against the committee of its epoch.
+    pub fn verify_checkpoint(
+        &self,
+        summary: &CertifiedCheckpointSummary,
+    ) -> Result<VerifiedCheckpoint> {
+        let committee = self.committee_at(summary.epoch())?;
+        Ok(summary.clone().try_into_verified(committee)?)
+    }
+
+    /// Verifies the end-of-epoch checkpoint of the latest epoch, and extends the chain with the
+    /// committee that it certifies. Returns the new latest committee.
+    pub fn append(&mut self, summary: CertifiedCheckpointSummary) -> Result<&Committee> {
+        let epoch = self.latest_epoch();
+        if summary.epoch() != epoch {
+            bail!(
+                "Expected an end-of-epoch checkpoint for epoch {epoch}, got one for epoch {}",
+                summary.epoch()
+            );
+        }
+
+        let verified = self.verify_checkpoint(&summary)?;
+        let next_committee = extract_new_committee_info(&verified)?;
+        let link = EpochLink {
+            end_of_epoch_checkpoint: summary,
+            next_committee,
+        };
+        write_bcs(&self.link_path(epoch), &link)?;
+        self.push(link);
+
+        Ok(self.latest_committee())
+    }
+
+    fn link(&self, epoch: EpochId) -> Option<&EpochLink> {
+        let index = epoch.checked_sub(self.root.epoch)?;
+        self.links.get(usize::try_from(index).ok()?)
+    }
+
+    fn push(&mut self, link: EpochLink) {
+        self.checkpoints
+            .insert(*link.end_of_epoch_checkpoint.sequence_number());
+        self.links.push(link);
+    }
+
+    fn link_path(&self, epoch: EpochId) -> PathBuf {
+        self.dir.join(format!("{epoch}.bcs"))
+    }
+}
+
+fn read_bcs<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let bytes = fs::read(path).map_err(|e| anyhow!("Cannot read {}: {e}", path.display()))?;
+    bcs::from_bytes(&bytes).map_err(|e| anyhow!("Cannot parse {}: {e}", path.display()))
+}
+
+/// Writes to a temporary file first, so that an interrupted write never leaves behind a
+/// truncated link.
+fn write_bcs<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, bcs::to_bytes(value)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundle::ProofBundle;
+    use crate::proof::base::{Proof, ProofBuilder, ProofTarget};
+    use sui_types::crypto::AuthorityKeyPair;
+    use sui_types::effects::TransactionEffectsAPI;
+    use sui_types::full_checkpoint_content::CheckpointData;
+    use sui_types::gas::GasCostSummary;
+    use sui_types::messages_checkpoint::{CheckpointContents, CheckpointSummary, EndOfEpochData};
+    use sui_types::supported_protocol_versions::{ProtocolConfig, ProtocolVersion};
+    use sui_types::test_checkpoint_data_builder::TestCheckpointBuilder;
+    use tempfile::TempDir;
+
+    /// A checkpoint of `committee`'s epoch, signed by `keys`, and certifying `next` if given.
+    fn checkpoint(
+        seq: CheckpointSequenceNumber,
+        committee: &Committee,
+        keys: &[AuthorityKeyPair],
+        next: Option<&Committee>,
+    ) -> CertifiedCheckpointSummary {
+        let contents = CheckpointContents::new_with_digests_only_for_tests(vec![]);
+        let summary = CheckpointSummary::new(
+            &ProtocolConfig::get_for_max_version_UNSAFE(),
+            committee.epoch,
+            seq,
+            0,
+            &contents,
+            None,
+            GasCostSummary::default(),
+            next.map(|next| EndOfEpochData {
+                next_epoch_committee: next.voting_rights.clone(),
+                next_epoch_protocol_version: ProtocolVersion::MAX,
+                epoch_commitments: vec![],
+            }),
+            0,
+            Vec::new(),
+            Vec::new(),
+        );
+        CertifiedCheckpointSummary::new_from_keypairs_for_testing(summary, keys, committee)
+    }
+
+    /// A chain of `n` committees starting at epoch 0, with their keys.
+    fn committees(n: usize) -> Vec<(Committee, Vec<AuthorityKeyPair>)> {
+        (0..n)
+            .map(|epoch| {
+                let (mut committee, keys) = Committee::new_simple_test_committee();
+                committee.epoch = epoch as EpochId;
+                (committee, keys)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_append_and_resume() {
+        let dir = TempDir::new().unwrap();
+        let committees = committees(3);
+
+        let mut light_client = LightClient::create(dir.path(), committees[0].0.clone()).unwrap();
+        assert_eq!(light_client.latest_epoch(), 0);
+        assert!(light_client.committee_at(1).is_err());
+
+        for (epoch, seq) in [(0, 10), (1, 20)] {
+            let (committee, keys) = &committees[epoch];
+            let next = &committees[epoch + 1].0;
+            let summary = checkpoint(seq, committee, keys, Some(next));
+            assert_eq!(light_client.append(summary).unwrap(), next);
+        }
+
+        // Resume from disk without re-verifying.
+        let light_client = LightClient::open(dir.path()).unwrap();
+        assert_eq!(light_client.latest_epoch(), 2);
+        assert_eq!(light_client.committee_at(1).unwrap(), &committees[1].0);
+        assert!(light_client.contains_checkpoint(20));
+        assert_eq!(
+            *light_client
+                .end_of_epoch_checkpoint(0)
+                .unwrap()
+                .sequence_number(),
+            10
+        );
+
+        // Checkpoints of any epoch in the chain can be verified.
+        let (committee, keys) = &committees[1];
+        light_client
+            .verify_checkpoint(&checkpoint(15, committee, keys, None))
+            .unwrap();
+        let (committee, keys) = &committees[2];
+        light_client
+            .verify_checkpoint(&checkpoint(25, committee, keys, None))
+            .unwrap();
+
+        // But not if they were signed by the wrong committee.
+        let (mut committee, keys) = committees[1].clone();
+        committee.epoch = 2;
+        assert!(
+            light_client
+                .verify_checkpoint(&checkpoint(25, &committee, &keys, None))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_append_rejects_bad_links() {
+        let dir = TempDir::new().unwrap();
+        let committees = committees(3);
+        let mut light_client = LightClient::create(dir.path(), committees[0].0.clone()).unwrap();
+
+        // Not an end-of-epoch checkpoint.
+        let (committee, keys) = &committees[0];
+        assert!(
+            light_client
+                .append(checkpoint(10, committee, keys, None))
+                .is_err()
+        );
+
+        // Skips an epoch.
+        let (committee, keys) = &committees[1];
+        assert!(
+            light_client
+                .append(checkpoint(20, committee, keys, Some(&committees[2].0)))
+                .is_err()
+        );
+
+        // Signed by a committee other than the trusted one.
+        let (mut committee, keys) = committees[1].clone();
+        committee.epoch = 0;
+        assert!(
+            light_client
+                .append(checkpoint(10, &committee, &keys, Some(&committees[1].0)))
+                .is_err()
+        );
+
+        assert_eq!(light_client.latest_epoch(), 0);
+        assert_eq!(LightClient::open(dir.path()).unwrap().latest_epoch(), 0);
+    }
+
+    #[test]
+    fn test_proof_bundle_walks_committee_chain() {
+        // Committees of epochs 0 to 2, each of a different size so that no two are alike.
+        let committees: Vec<_> = [6, 5, 4]
+            .into_iter()
+            .enumerate()
+            .map(|(epoch, size)| {
+                let (mut committee, keys) = Committee::new_simple_test_committee_of_size(size);
+                committee.epoch = epoch as EpochId;
+                (committee, keys)
+            })
+            .collect();
+
+        let dir = TempDir::new().unwrap();
+        let mut light_client = LightClient::create(dir.path(), committees[0].0.clone()).unwrap();
+        for (epoch, seq) in [(0, 10), (1, 20)] {
+            let (committee, keys) = &committees[epoch];
+            let next = &committees[epoch + 1].0;
+            light_client
+                .append(checkpoint(seq, committee, keys, Some(next)))
+                .unwrap();
+        }
+
+        // A checkpoint of epoch 2 with a transaction, certified by the committee of epoch 2.
+        let mut full_checkpoint: CheckpointData = TestCheckpointBuilder::new(25)
+            .with_epoch(2)
+            .start_transaction(0)
+            .create_owned_object(0)
+            .finish_transaction()
+            .build_checkpoint()
+            .into();
+        let (committee, keys) = &committees[2];
+        full_checkpoint.checkpoint_summary =
+            CertifiedCheckpointSummary::new_from_keypairs_for_testing(
+                full_checkpoint.checkpoint_summary.into_data(),
+                keys,
+                committee,
+            );
+        let target = ProofTarget::new_transactions(vec![
+            full_checkpoint.transactions[0].effects.execution_digests(),
+        ]);
+        let proof = target
+            .construct(&full_checkpoint)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+
+        // Trusting the committee of epoch 0, the bundle walks both links of the chain.
+        let bundle =
+            ProofBundle::new(&light_client, 0, Proof::from_bytes(&proof).unwrap()).unwrap();
+        assert_eq!(bundle.committee_chain.len(), 2);
+        assert_eq!(bundle.trusted_epoch(), 0);
+        let bundle = ProofBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        bundle.verify(&committees[0].0).unwrap();
+
+        // Trusting the committee of epoch 1, only the last link is needed.
+        let bundle =
+            ProofBundle::new(&light_client, 1, Proof::from_bytes(&proof).unwrap()).unwrap();
+        assert_eq!(bundle.committee_chain.len(), 1);
+        bundle.verify(&committees[1].0).unwrap();
+
+        // A committee with the right epoch but the wrong members cannot start the chain.
+        let (mut impostor, _) = Committee::new_simple_test_committee_of_size(7);
+        impostor.epoch = 0;
+        let bundle =
+            ProofBundle::new(&light_client, 0, Proof::from_bytes(&proof).unwrap()).unwrap();
+        assert!(bundle.verify(&impostor).is_err());
+    }
+}
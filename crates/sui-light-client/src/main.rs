@@ -10,10 +10,16 @@ use sui_types::{
 use sui_package_resolver::Resolver;
 
 use clap::{Parser, Subcommand};
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{fs, path::PathBuf, str::FromStr, time::Duration};
+use sui_light_client::bundle::{
+    ProofBundle, prove_object, prove_transaction, trusted_committee_from_checkpoint,
+    trusted_committee_from_genesis,
+};
 use sui_light_client::checkpoint::check_and_sync_checkpoints;
 use sui_light_client::config::Config;
+use sui_light_client::light_client::LightClient;
 use sui_light_client::package_store::RemotePackageStore;
+use sui_light_client::stream_follower::follow_event_stream;
 use sui_light_client::verifier::{get_verified_effects_and_events, get_verified_object};
 
 use tracing::info;
@@ -48,6 +54,55 @@ enum SCommands {
         #[arg(short, long, value_name = "OID")]
         oid: String,
     },
+
+    /// Follows an authenticated event stream, verifying each new batch of events
+    Follow {
+        /// Object ID of the event stream head
+        #[arg(short, long, value_name = "OID")]
+        oid: String,
+
+        /// Checkpoint to start following from
+        #[arg(short, long, value_name = "SEQ")]
+        start: u64,
+
+        /// Milliseconds to wait for a checkpoint that is not available yet
+        #[arg(long, default_value_t = 1000)]
+        poll_interval_ms: u64,
+    },
+
+    /// Writes a proof bundle for a transaction or an object, which can be verified offline
+    Prove {
+        /// Transaction hash
+        #[arg(short, long, value_name = "TID", conflicts_with = "oid")]
+        tid: Option<String>,
+
+        /// Object ID
+        #[arg(short, long, value_name = "OID", required_unless_present = "tid")]
+        oid: Option<String>,
+
+        /// Epoch whose committee the verifier trusts, by default the genesis epoch
+        #[arg(long, value_name = "EPOCH")]
+        trusted_epoch: Option<u64>,
+
+        /// File to write the bundle to
+        #[arg(long, value_name = "FILE")]
+        output: PathBuf,
+    },
+
+    /// Verifies a proof bundle offline, without a config file
+    Verify {
+        /// The proof bundle
+        #[arg(short, long, value_name = "FILE")]
+        bundle: PathBuf,
+
+        /// Genesis file to take the trusted committee from
+        #[arg(long, value_name = "FILE", conflicts_with = "trusted_checkpoint")]
+        genesis: Option<PathBuf>,
+
+        /// End-of-epoch checkpoint summary to take the trusted committee from
+        #[arg(long, value_name = "FILE", required_unless_present = "genesis")]
+        trusted_checkpoint: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
@@ -57,6 +112,37 @@ pub async fn main() {
     // Command line arguments and config loading
     let args = Args::parse();
 
+    // Verifying a bundle only needs what is on the command line
+    if let Some(SCommands::Verify {
+        bundle,
+        genesis,
+        trusted_checkpoint,
+    }) = &args.command
+    {
+        let trusted = match (genesis, trusted_checkpoint) {
+            (Some(genesis), _) => trusted_committee_from_genesis(genesis),
+            (None, Some(checkpoint)) => trusted_committee_from_checkpoint(checkpoint),
+            (None, None) => unreachable!("clap requires a trusted committee"),
+        }
+        .expect("Cannot load the trusted committee");
+
+        let bundle = ProofBundle::read(bundle).expect("Cannot read the proof bundle");
+        let summary = &bundle.proof.checkpoint_summary;
+        println!(
+            "Checkpoint: {} Epoch: {} Trusted epoch: {}\n{:#?}",
+            summary.sequence_number(),
+            summary.epoch(),
+            bundle.trusted_epoch(),
+            bundle.proof.targets
+        );
+
+        bundle
+            .verify(&trusted)
+            .expect("Proof bundle verification failed");
+        println!("Proof bundle is valid");
+        return;
+    }
+
     let path = args
         .config
         .unwrap_or_else(|| panic!("Need a config file path"));
@@ -141,6 +227,77 @@ pub async fn main() {
             }
         }
 
+        Some(SCommands::Follow {
+            oid,
+            start,
+            poll_interval_ms,
+        }) => {
+            let oid = ObjectID::from_str(&oid).unwrap();
+            follow_event_stream(
+                &config,
+                oid,
+                start,
+                Duration::from_millis(poll_interval_ms),
+                |batch| {
+                    println!(
+                        "Checkpoint: {} Events: {} Stream events: {}",
+                        batch.checkpoint,
+                        batch.events.len(),
+                        batch.head.num_events
+                    );
+                    for (commitment, event) in &batch.events {
+                        println!(
+                            " - Transaction index: {} Event index: {} Digest: {}\n   Type: {} Sender: {}",
+                            commitment.transaction_idx,
+                            commitment.event_idx,
+                            commitment.digest,
+                            event.type_,
+                            event.sender
+                        );
+                    }
+                },
+            )
+            .await
+            .expect("Failed to follow event stream");
+        }
+
+        Some(SCommands::Prove {
+            tid,
+            oid,
+            trusted_epoch,
+            output,
+        }) => {
+            let proof = match (tid, oid) {
+                (Some(tid), _) => {
+                    prove_transaction(&config, TransactionDigest::from_str(&tid).unwrap()).await
+                }
+                (None, Some(oid)) => prove_object(&config, ObjectID::from_str(&oid).unwrap()).await,
+                (None, None) => unreachable!("clap requires a target"),
+            }
+            .expect("Cannot construct proof");
+
+            let light_client = LightClient::from_config(&config).unwrap();
+            let trusted_epoch = trusted_epoch.unwrap_or(light_client.root_epoch());
+            let bundle = ProofBundle::new(&light_client, trusted_epoch, proof)
+                .expect("Cannot bundle proof, the light client may need to sync");
+
+            // Check the bundle as an offline verifier would before handing it out
+            ProofBundle::from_bytes(&bundle.to_bytes().unwrap())
+                .unwrap()
+                .verify(light_client.committee_at(trusted_epoch).unwrap())
+                .expect("Proof bundle verification failed");
+
+            bundle.write(&output).unwrap();
+            println!(
+                "Wrote proof bundle for checkpoint {} trusting epoch {} to {}",
+                bundle.proof.checkpoint_summary.sequence_number(),
+                trusted_epoch,
+                output.display()
+            );
+        }
+
+        Some(SCommands::Verify { .. }) => unreachable!("handled before loading the config"),
+
         Some(SCommands::Sync {}) => {
             check_and_sync_checkpoints(&config)
                 .await
@@ -1,9 +1,20 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+//! Merkle Mountain Range (MMR) accumulator of authenticated event streams.
+//!
+//! Each leaf of a stream's MMR is the merkle root of the event commitments of one checkpoint,
+//! and `mmr[i]` of the stream head is the peak over `2^i` consecutive leaves (or zero if there
+//! is no such peak). Older leaves sit to the left, so the peaks from the highest to the lowest
+//! cover the leaves from the oldest to the newest.
+
+use crate::proof::error::{ProofError, ProofResult};
 use fastcrypto::hash::{Blake2b256, HashFunction};
+use fastcrypto::merkle::{MerkleProof, MerkleTree, Node};
 use move_core_types::u256::U256;
+use serde::{Deserialize, Serialize};
 use sui_types::accumulator_root::{EventCommitment, EventStreamHead, build_event_merkle_root};
+use sui_types::digests::Digest;
 
 const U256_ZERO: U256 = U256::zero();
 
@@ -48,18 +59,190 @@ pub fn apply_stream_updates(
 
         // TODO: checkpoint_seq in EventCommitment is always 0, so we don't validate it
 
-        let digest = build_event_merkle_root(&cp_events);
-        let merkle_root = U256::from_le_bytes(&digest.into_inner());
-        add_to_stream(&mut new_head.mmr, merkle_root);
+        add_to_stream(&mut new_head.mmr, event_merkle_leaf(&cp_events));
         new_head.num_events += cp_events.len() as u64;
     }
     new_head
 }
 
+/// The MMR leaf of a checkpoint's event commitments.
+fn event_merkle_leaf(cp_events: &[EventCommitment]) -> U256 {
+    U256::from_le_bytes(&build_event_merkle_root(cp_events).into_inner())
+}
+
+/// Returns the height of the peak covering the leaf at `leaf_index`, and the position of the
+/// leaf under that peak.
+fn locate_leaf(mmr: &[U256], leaf_index: u64) -> Option<(usize, u64)> {
+    let mut start = 0u64;
+    for height in (0..mmr.len()).rev() {
+        if mmr[height] == U256_ZERO {
+            continue;
+        }
+        let size = 1u64.checked_shl(height as u32)?;
+        if leaf_index < start + size {
+            return Some((height, leaf_index - start));
+        }
+        start += size;
+    }
+    None
+}
+
+/// A proof that a leaf is in the MMR of a stream head.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MmrProof {
+    /// The position of the leaf among all the leaves of the stream, oldest first.
+    pub leaf_index: u64,
+    /// The siblings on the path from the leaf up to its peak, lowest first.
+    pub siblings: Vec<U256>,
+}
+
+impl MmrProof {
+    /// Checks that `leaf` is the leaf at `leaf_index` of the MMR of `head`.
+    pub fn verify(&self, head: &EventStreamHead, leaf: U256) -> ProofResult<()> {
+        let (height, mut index) =
+            locate_leaf(&head.mmr, self.leaf_index).ok_or(ProofError::EventSequenceOutOfBounds)?;
+        if self.siblings.len() != height {
+            return Err(ProofError::InvalidProof);
+        }
+
+        let mut node = leaf;
+        for sibling in &self.siblings {
+            node = if index & 1 == 0 {
+                hash_two_to_one_u256(node, *sibling)
+            } else {
+                hash_two_to_one_u256(*sibling, node)
+            };
+            index >>= 1;
+        }
+
+        if node != head.mmr[height] {
+            return Err(ProofError::InvalidProof);
+        }
+        Ok(())
+    }
+}
+
+/// A proof that an event commitment is in an authenticated event stream: the commitment is in
+/// the merkle tree of its checkpoint's events, whose root is in turn a leaf of the stream's MMR.
+///
+/// Proofs are against a specific stream head, since the peaks of the MMR change as the stream
+/// grows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventStreamProof {
+    /// The merkle root of the event commitments of the checkpoint.
+    pub checkpoint_root: Digest,
+    /// The position of the commitment among the event commitments of the checkpoint.
+    pub event_position: usize,
+    pub merkle_proof: MerkleProof<Blake2b256>,
+    pub mmr_proof: MmrProof,
+}
+
+impl EventStreamProof {
+    /// Checks that `commitment` is in the stream with the given head.
+    pub fn verify(&self, head: &EventStreamHead, commitment: &EventCommitment) -> ProofResult<()> {
+        self.merkle_proof
+            .verify_proof_with_unserialized_leaf(
+                &Node::from(self.checkpoint_root.into_inner()),
+                commitment,
+                self.event_position,
+            )
+            .map_err(|_| ProofError::InvalidProof)?;
+        // MILESTONE: Commitment is in the checkpoint's events
+
+        self.mmr_proof.verify(
+            head,
+            U256::from_le_bytes(&self.checkpoint_root.into_inner()),
+        )
+        // MILESTONE: Checkpoint's events are in the stream
+    }
+}
+
+/// An authenticated event stream replayed from the event commitments of each checkpoint.
+/// Unlike the stream head, it keeps all the commitments, so it can prove that any of its events
+/// is in the stream.
+#[derive(Debug, Clone, Default)]
+pub struct EventStream {
+    head: EventStreamHead,
+    /// The event commitments of each checkpoint, oldest first.
+    batches: Vec<Vec<EventCommitment>>,
+    /// The MMR leaf of each batch.
+    leaves: Vec<U256>,
+}
+
+impl EventStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The head of the stream, as `apply_stream_updates` would compute it.
+    pub fn head(&self) -> &EventStreamHead {
+        &self.head
+    }
+
+    /// Appends the events of one checkpoint, which must be ordered.
+    pub fn append(&mut self, cp_events: Vec<EventCommitment>) {
+        debug_assert!(!cp_events.is_empty());
+
+        let leaf = event_merkle_leaf(&cp_events);
+        add_to_stream(&mut self.head.mmr, leaf);
+        self.head.num_events += cp_events.len() as u64;
+        self.batches.push(cp_events);
+        self.leaves.push(leaf);
+    }
+
+    /// Proves that `commitment` is in the stream, against the current head.
+    pub fn prove(&self, commitment: &EventCommitment) -> ProofResult<EventStreamProof> {
+        let (batch_index, event_position) = self
+            .batches
+            .iter()
+            .enumerate()
+            .find_map(|(i, batch)| {
+                let position = batch.binary_search(commitment).ok()?;
+                (batch[position] == *commitment).then_some((i, position))
+            })
+            .ok_or(ProofError::EventContentsMismatch)?;
+        let batch = &self.batches[batch_index];
+
+        let merkle_proof = MerkleTree::<Blake2b256>::build_from_unserialized(batch.iter())
+            .map_err(|e| ProofError::GeneralError(e.to_string()))?
+            .get_proof(event_position)
+            .map_err(|e| ProofError::GeneralError(e.to_string()))?;
+
+        Ok(EventStreamProof {
+            checkpoint_root: build_event_merkle_root(batch),
+            event_position,
+            merkle_proof,
+            mmr_proof: self.prove_leaf(batch_index as u64)?,
+        })
+    }
+
+    fn prove_leaf(&self, leaf_index: u64) -> ProofResult<MmrProof> {
+        let (height, mut index) =
+            locate_leaf(&self.head.mmr, leaf_index).ok_or(ProofError::EventSequenceOutOfBounds)?;
+        let start = (leaf_index - index) as usize;
+
+        // Rebuild the peak level by level, collecting the siblings of the path.
+        let mut level = self.leaves[start..start + (1 << height)].to_vec();
+        let mut siblings = Vec::with_capacity(height);
+        while level.len() > 1 {
+            siblings.push(level[(index ^ 1) as usize]);
+            level = level
+                .chunks(2)
+                .map(|pair| hash_two_to_one_u256(pair[0], pair[1]))
+                .collect();
+            index >>= 1;
+        }
+
+        Ok(MmrProof {
+            leaf_index,
+            siblings,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
-    use sui_types::digests::Digest;
 
     use super::*;
 
@@ -102,4 +285,72 @@ mod tests {
         let new_head = apply_stream_updates(&old_head, events);
         println!("{:?}", new_head);
     }
+
+    /// A stream of `num_checkpoints` checkpoints, with a varying number of events in each.
+    fn event_stream(num_checkpoints: u64) -> EventStream {
+        let mut stream = EventStream::new();
+        for cp in 0..num_checkpoints {
+            let cp_events = (0..cp % 3 + 1)
+                .map(|i| EventCommitment::new(cp, i, 0, Digest::new([(cp * 3 + i) as u8; 32])))
+                .collect();
+            stream.append(cp_events);
+        }
+        stream
+    }
+
+    #[test]
+    fn test_event_stream_matches_stream_updates() {
+        let stream = event_stream(11);
+        let new_head = apply_stream_updates(&EventStreamHead::new(), stream.batches.clone());
+        assert_eq!(stream.head(), &new_head);
+        assert_eq!(locate_leaf(&new_head.mmr, 10), Some((0, 0)));
+        assert_eq!(locate_leaf(&new_head.mmr, 11), None);
+    }
+
+    #[test]
+    fn test_event_stream_proofs() {
+        for num_checkpoints in [1, 2, 5, 8, 13] {
+            let stream = event_stream(num_checkpoints);
+            for commitment in stream.batches.iter().flatten() {
+                let proof = stream.prove(commitment).unwrap();
+                proof.verify(stream.head(), commitment).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_event_stream_proofs_reject_tampering() {
+        let mut stream = event_stream(6);
+        let commitment = stream.batches[4][1].clone();
+        let proof = stream.prove(&commitment).unwrap();
+        proof.verify(stream.head(), &commitment).unwrap();
+
+        // A different event at the same position.
+        let mut other = commitment.clone();
+        other.digest = Digest::new([0xff; 32]);
+        assert!(stream.prove(&other).is_err());
+        assert!(proof.verify(stream.head(), &other).is_err());
+
+        // The leaf claimed to be at another position in the stream.
+        let mut bad_proof = proof.clone();
+        bad_proof.mmr_proof.leaf_index = 5;
+        assert!(bad_proof.verify(stream.head(), &commitment).is_err());
+        bad_proof.mmr_proof.leaf_index = 6;
+        assert!(bad_proof.verify(stream.head(), &commitment).is_err());
+
+        // A tampered path.
+        let mut bad_proof = proof.clone();
+        bad_proof.mmr_proof.siblings[0] = U256::from(1u64);
+        assert!(bad_proof.verify(stream.head(), &commitment).is_err());
+
+        // Once the stream grows, the proof has to be regenerated against the new head.
+        stream.append(vec![EventCommitment::new(6, 0, 0, Digest::new([6; 32]))]);
+        stream.append(vec![EventCommitment::new(7, 0, 0, Digest::new([7; 32]))]);
+        assert!(proof.verify(stream.head(), &commitment).is_err());
+        stream
+            .prove(&commitment)
+            .unwrap()
+            .verify(stream.head(), &commitment)
+            .unwrap();
+    }
 }
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::config::Config;
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use object_store::{ObjectStore, path::Path};
 use sui_types::full_checkpoint_content::CheckpointData;
@@ -38,11 +38,9 @@ impl SuiObjectStore {
     pub async fn get_full_checkpoint(&self, checkpoint_number: u64) -> Result<CheckpointData> {
         let path = Path::from(format!("{}.chk", checkpoint_number));
         info!("Request full checkpoint: {}", path);
-        let response = self
-            .store
-            .get(&path)
-            .await
-            .map_err(|_| anyhow!("Cannot get full checkpoint from object store"))?;
+        let response = self.store.get(&path).await.with_context(|| {
+            format!("Cannot get full checkpoint {checkpoint_number} from object store")
+        })?;
         let bytes = response.bytes().await?;
         let (_, full_checkpoint) = bcs::from_bytes::<(u8, CheckpointData)>(&bytes)?;
         Ok(full_checkpoint)
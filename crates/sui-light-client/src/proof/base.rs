@@ -3,7 +3,7 @@
 
 use serde::{Deserialize, Serialize};
 use sui_types::{
-    base_types::{ObjectID, ObjectRef},
+    base_types::{ExecutionDigests, ObjectID, ObjectRef},
     committee::Committee,
     event::{Event, EventID},
     full_checkpoint_content::CheckpointData,
@@ -18,8 +18,12 @@ use crate::proof::{
     objects::ObjectsTarget,
     ocs::{OCSProof, OCSTarget},
     transaction_proof::TransactionProof,
+    transactions::{TransactionsProof, TransactionsTarget},
 };
 
+/// Version of the standalone proof encoding produced by [`Proof::to_bytes`].
+pub const PROOF_FORMAT_VERSION: u8 = 1;
+
 pub trait ProofBuilder {
     fn construct(self, checkpoint: &CheckpointData) -> ProofResult<Proof>;
 }
@@ -38,6 +42,7 @@ pub enum ProofTarget {
     Events(EventsTarget),
     Committee(CommitteeTarget),
     ObjectCheckpointState(OCSTarget),
+    Transactions(TransactionsTarget),
 }
 
 impl ProofTarget {
@@ -60,6 +65,10 @@ impl ProofTarget {
     pub fn new_ocs_non_inclusion(object_id: ObjectID) -> Self {
         ProofTarget::ObjectCheckpointState(OCSTarget::new_non_inclusion_target(object_id))
     }
+
+    pub fn new_transactions(transactions: Vec<ExecutionDigests>) -> Self {
+        ProofTarget::Transactions(TransactionsTarget { transactions })
+    }
 }
 
 impl ProofBuilder for ProofTarget {
@@ -69,6 +78,7 @@ impl ProofBuilder for ProofTarget {
             ProofTarget::Events(target) => target.construct(checkpoint),
             ProofTarget::Committee(target) => target.construct(checkpoint),
             ProofTarget::ObjectCheckpointState(target) => target.construct(checkpoint),
+            ProofTarget::Transactions(target) => target.construct(checkpoint),
         }
     }
 }
@@ -77,7 +87,7 @@ impl ProofBuilder for ProofTarget {
 /// evidence to certify objects and events.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Proof {
-    /// Targets of the proof are a committee, objects, or events that need to be certified.
+    /// Targets of the proof are a committee, objects, events, or transactions that need to be certified.
     pub targets: ProofTarget,
 
     /// A summary of the checkpoint being certified.
@@ -87,6 +97,30 @@ pub struct Proof {
     pub proof_contents: ProofContents,
 }
 
+impl Proof {
+    /// Encodes the proof in a standalone format: a version byte followed by the BCS encoded
+    /// proof. Together with a trusted committee for its epoch, this is all a third party needs
+    /// to verify the proof.
+    pub fn to_bytes(&self) -> ProofResult<Vec<u8>> {
+        let mut bytes = vec![PROOF_FORMAT_VERSION];
+        bytes.extend(
+            bcs::to_bytes(self).map_err(|e| ProofError::InvalidProofEncoding(e.to_string()))?,
+        );
+        Ok(bytes)
+    }
+
+    /// Decodes a proof encoded by [`Proof::to_bytes`]. The proof still needs to be verified.
+    pub fn from_bytes(bytes: &[u8]) -> ProofResult<Self> {
+        let (version, proof) = bytes
+            .split_first()
+            .ok_or_else(|| ProofError::InvalidProofEncoding("empty proof".to_string()))?;
+        if *version != PROOF_FORMAT_VERSION {
+            return Err(ProofError::UnsupportedProofVersion(*version));
+        }
+        bcs::from_bytes(proof).map_err(|e| ProofError::InvalidProofEncoding(e.to_string()))
+    }
+}
+
 /// Different types of proofs that can be constructed.
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize, Deserialize)]
@@ -99,6 +133,9 @@ pub enum ProofContents {
 
     /// Used by ObjectCheckpointStatesTarget.
     ObjectCheckpointStateProof(OCSProof),
+
+    /// Used by TransactionsTarget.
+    TransactionsProof(TransactionsProof),
 }
 
 impl ProofVerifier for Proof {
@@ -129,6 +166,11 @@ impl ProofVerifier for Proof {
                     return Err(ProofError::MismatchedTargetAndProofType);
                 }
             }
+            ProofTarget::Transactions(_) => {
+                if !matches!(self.proof_contents, ProofContents::TransactionsProof(_)) {
+                    return Err(ProofError::MismatchedTargetAndProofType);
+                }
+            }
         }
 
         self.proof_contents.verify(&self.targets, &verified_summary)
@@ -141,6 +183,7 @@ impl ProofContentsVerifier for ProofContents {
             ProofContents::TransactionProof(proof) => proof.verify(targets, summary),
             ProofContents::CommitteeProof(proof) => proof.verify(targets, summary),
             ProofContents::ObjectCheckpointStateProof(proof) => proof.verify(targets, summary),
+            ProofContents::TransactionsProof(proof) => proof.verify(targets, summary),
         }
     }
 }
@@ -53,6 +53,9 @@ pub enum ProofError {
     #[error("Transaction digest not found in the checkpoint contents")]
     TransactionDigestNotFound,
 
+    #[error("Effects do not match the transaction's execution digests")]
+    EffectsMismatch,
+
     #[error("Epoch overflow when calculating next epoch")]
     EpochAddOverflow,
 
@@ -68,6 +71,12 @@ pub enum ProofError {
     #[error("Invalid proof")]
     InvalidProof,
 
+    #[error("Unsupported proof format version {0}")]
+    UnsupportedProofVersion(u8),
+
+    #[error("Invalid proof encoding: {0}")]
+    InvalidProofEncoding(String),
+
     #[error("Artifact digest mismatch")]
     ArtifactDigestMismatch,
 
@@ -117,12 +117,20 @@ impl TransactionProof {
     }
 }
 
+/// Check that the checkpoint contents are the ones certified by the summary.
+pub(crate) fn verify_contents(
+    contents: &CheckpointContents,
+    summary: &VerifiedCheckpoint,
+) -> ProofResult<()> {
+    if *contents.digest() != summary.data().content_digest {
+        return Err(ProofError::ContentsDigestMismatch);
+    }
+    Ok(())
+}
+
 impl ProofContentsVerifier for TransactionProof {
     fn verify(self, targets: &ProofTarget, summary: &VerifiedCheckpoint) -> ProofResult<()> {
-        let contents_digest = *self.checkpoint_contents.digest();
-        if contents_digest != summary.data().content_digest {
-            return Err(ProofError::ContentsDigestMismatch);
-        }
+        verify_contents(&self.checkpoint_contents, summary)?;
         // MILESTONE: Contents is correct
 
         // Extract Transaction Digests and check they are in contents
@@ -0,0 +1,99 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+use sui_types::{
+    base_types::ExecutionDigests,
+    effects::TransactionEffects,
+    full_checkpoint_content::CheckpointData,
+    messages_checkpoint::{CheckpointContents, VerifiedCheckpoint},
+};
+
+use crate::proof::{
+    base::{Proof, ProofBuilder, ProofContents, ProofContentsVerifier, ProofTarget},
+    error::{ProofError, ProofResult},
+    transaction_proof::verify_contents,
+};
+
+/// Transactions that executed with the given effects in the checkpoint, e.g. the payment a
+/// receipt is for.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionsTarget {
+    pub transactions: Vec<ExecutionDigests>,
+}
+
+impl ProofBuilder for TransactionsTarget {
+    fn construct(self, checkpoint: &CheckpointData) -> ProofResult<Proof> {
+        if self.transactions.is_empty() {
+            return Err(ProofError::NoTargetsFound);
+        }
+
+        let effects = self
+            .transactions
+            .iter()
+            .map(|digests| {
+                checkpoint
+                    .transactions
+                    .iter()
+                    .find(|tx| tx.effects.execution_digests() == *digests)
+                    .map(|tx| tx.effects.clone())
+                    .ok_or(ProofError::TransactionNotFound)
+            })
+            .collect::<ProofResult<_>>()?;
+
+        Ok(Proof {
+            targets: ProofTarget::Transactions(self),
+            checkpoint_summary: checkpoint.checkpoint_summary.clone(),
+            proof_contents: ProofContents::TransactionsProof(TransactionsProof {
+                checkpoint_contents: checkpoint.checkpoint_contents.clone(),
+                effects,
+            }),
+        })
+    }
+}
+
+/// A proof that transactions executed with the given effects.
+/// Implements the execution digests -> contents -> summary pathway, and carries the full
+/// effects of each target transaction so that they can be inspected once verified.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionsProof {
+    /// Checkpoint contents including the transactions.
+    pub checkpoint_contents: CheckpointContents,
+
+    /// The effects of each target transaction, in the order of the targets.
+    pub effects: Vec<TransactionEffects>,
+}
+
+impl ProofContentsVerifier for TransactionsProof {
+    fn verify(self, targets: &ProofTarget, summary: &VerifiedCheckpoint) -> ProofResult<()> {
+        let ProofTarget::Transactions(target) = targets else {
+            return Err(ProofError::MismatchedTargetAndProofType);
+        };
+
+        verify_contents(&self.checkpoint_contents, summary)?;
+        // MILESTONE: Contents is correct
+
+        if target.transactions.is_empty() {
+            return Err(ProofError::NoTargetsFound);
+        }
+        if target.transactions.len() != self.effects.len() {
+            return Err(ProofError::EffectsMismatch);
+        }
+
+        for (digests, effects) in target.transactions.iter().zip(&self.effects) {
+            // The effects are those the transaction executed with
+            if effects.execution_digests() != *digests {
+                return Err(ProofError::EffectsMismatch);
+            }
+
+            // And the transaction executed with them in this checkpoint
+            if !self.checkpoint_contents.iter().any(|d| d == digests) {
+                return Err(ProofError::TransactionDigestNotFound);
+            }
+        }
+        // MILESTONE: Transactions & Effects correct and in contents
+
+        Ok(())
+    }
+}
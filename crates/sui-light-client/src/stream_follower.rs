@@ -0,0 +1,539 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Follows an authenticated event stream checkpoint by checkpoint.
+//!
+//! Every checkpoint is verified against the committee chain of the light client, and the events
+//! it adds to the stream are checked against the stream head that the same checkpoint writes:
+//! applying the batch of events to the previous head must give the new head.
+
+use crate::config::Config;
+use crate::light_client::LightClient;
+use crate::mmr::apply_stream_updates;
+use crate::object_store::SuiObjectStore;
+use anyhow::{Result, anyhow, ensure};
+use std::time::Duration;
+use sui_types::accumulator_root::{AccumulatorKey, EventCommitment, EventStreamHead};
+use sui_types::base_types::ObjectID;
+use sui_types::dynamic_field::Field;
+use sui_types::effects::{AccumulatorValue, TransactionEffectsAPI};
+use sui_types::event::Event;
+use sui_types::full_checkpoint_content::CheckpointData;
+use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+use tracing::info;
+
+/// The events a checkpoint added to the stream, and the stream head after them.
+#[derive(Debug)]
+pub struct EventBatch {
+    pub checkpoint: CheckpointSequenceNumber,
+    pub events: Vec<(EventCommitment, Event)>,
+    pub head: EventStreamHead,
+}
+
+pub struct StreamFollower {
+    light_client: LightClient,
+    /// The object holding the stream head.
+    stream_head_id: ObjectID,
+    /// The verified stream head, once a checkpoint has written one.
+    head: Option<EventStreamHead>,
+    next_checkpoint: CheckpointSequenceNumber,
+}
+
+impl StreamFollower {
+    /// Follows the stream whose head is held by `stream_head_id`, from `start_checkpoint`.
+    ///
+    /// The events of a batch can only be checked against the stream head once the previous head
+    /// is known, so the first head written at or after `start_checkpoint` is trusted as it was
+    /// certified by its checkpoint, and batches are checked against the stream head from there.
+    pub fn new(
+        light_client: LightClient,
+        stream_head_id: ObjectID,
+        start_checkpoint: CheckpointSequenceNumber,
+    ) -> Self {
+        Self {
+            light_client,
+            stream_head_id,
+            head: None,
+            next_checkpoint: start_checkpoint,
+        }
+    }
+
+    /// The latest verified stream head.
+    pub fn head(&self) -> Option<&EventStreamHead> {
+        self.head.as_ref()
+    }
+
+    /// Downloads and processes checkpoints until one adds events to the stream, waiting for
+    /// `poll_interval` whenever the next checkpoint is not available yet. Any other failure to
+    /// download a checkpoint is returned.
+    pub async fn next_batch(
+        &mut self,
+        store: &SuiObjectStore,
+        poll_interval: Duration,
+    ) -> Result<EventBatch> {
+        loop {
+            let checkpoint = match store.get_full_checkpoint(self.next_checkpoint).await {
+                Ok(checkpoint) => checkpoint,
+                // Only a checkpoint that is not available yet is worth waiting for
+                Err(e) if is_not_found(&e) => {
+                    tokio::time::sleep(poll_interval).await;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(batch) = self.process_checkpoint(&checkpoint)? {
+                return Ok(batch);
+            }
+        }
+    }
+
+    /// Verifies the next checkpoint, and returns the events it adds to the stream, if any.
+    pub fn process_checkpoint(
+        &mut self,
+        checkpoint: &CheckpointData,
+    ) -> Result<Option<EventBatch>> {
+        let summary = &checkpoint.checkpoint_summary;
+        let seq = *summary.sequence_number();
+        ensure!(
+            seq == self.next_checkpoint,
+            "Expected checkpoint {}, got {seq}",
+            self.next_checkpoint
+        );
+
+        // Verify that the committee signed this checkpoint and its contents
+        let committee = self.light_client.committee_at(summary.epoch())?;
+        summary.verify_with_contents(committee, Some(&checkpoint.checkpoint_contents))?;
+        ensure!(
+            checkpoint.checkpoint_contents.size() == checkpoint.transactions.len(),
+            "Transactions of checkpoint {seq} do not match its contents"
+        );
+
+        let mut events = vec![];
+        let mut written_head = None;
+        for (idx, (digests, tx)) in checkpoint
+            .checkpoint_contents
+            .iter()
+            .zip(&checkpoint.transactions)
+            .enumerate()
+        {
+            // The effects and events are those certified by the checkpoint
+            ensure!(
+                tx.effects.execution_digests() == *digests,
+                "Effects of transaction {} do not match the checkpoint contents",
+                digests.transaction
+            );
+            ensure!(
+                tx.effects.events_digest() == tx.events.as_ref().map(|e| e.digest()).as_ref(),
+                "Events of transaction {} do not match its effects",
+                digests.transaction
+            );
+
+            for acc_event in tx.effects.accumulator_events() {
+                if *acc_event.accumulator_obj.inner() != self.stream_head_id {
+                    continue;
+                }
+                let AccumulatorValue::EventDigest(event_digests) = &acc_event.write.value else {
+                    continue;
+                };
+                for (event_idx, digest) in event_digests {
+                    let event = tx
+                        .events
+                        .as_ref()
+                        .and_then(|e| e.data.get(*event_idx as usize))
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Event {event_idx} of {} is missing",
+                                tx.effects.transaction_digest()
+                            )
+                        })?;
+                    ensure!(
+                        event.digest() == *digest,
+                        "Event {event_idx} of {} does not match its commitment",
+                        tx.effects.transaction_digest()
+                    );
+                    events.push((
+                        EventCommitment::new(seq, idx as u64, *event_idx, *digest),
+                        event.clone(),
+                    ));
+                }
+            }
+
+            // The settlement of the checkpoint writes the new stream head
+            if let Some(object) = tx
+                .output_objects
+                .iter()
+                .find(|o| o.id() == self.stream_head_id)
+            {
+                let object_ref = object.compute_object_reference();
+                ensure!(
+                    tx.effects
+                        .all_changed_objects()
+                        .iter()
+                        .any(|(r, _, _)| *r == object_ref),
+                    "Stream head {} is not in the effects",
+                    self.stream_head_id
+                );
+                let field = object
+                    .data
+                    .try_as_move()
+                    .and_then(|o| o.to_rust::<Field<AccumulatorKey, EventStreamHead>>())
+                    .ok_or_else(|| anyhow!("{} is not a stream head", self.stream_head_id))?;
+                written_head = Some(field.value);
+            }
+        }
+        events.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let batch = match (&self.head, written_head) {
+            (_, None) => {
+                ensure!(
+                    events.is_empty(),
+                    "Checkpoint {seq} adds events to the stream without updating its head"
+                );
+                None
+            }
+            (None, Some(written)) => {
+                info!("Starting from the stream head at checkpoint {seq}");
+                Some(EventBatch {
+                    checkpoint: seq,
+                    events,
+                    head: written,
+                })
+            }
+            (Some(head), Some(written)) => {
+                ensure!(
+                    !events.is_empty(),
+                    "Checkpoint {seq} updates the stream head without adding events"
+                );
+                let commitments = events.iter().map(|(c, _)| c.clone()).collect();
+                let expected = apply_stream_updates(head, vec![commitments]);
+                ensure!(
+                    expected.mmr == written.mmr && expected.num_events == written.num_events,
+                    "Events of checkpoint {seq} do not match the stream head"
+                );
+                Some(EventBatch {
+                    checkpoint: seq,
+                    events,
+                    head: written,
+                })
+            }
+        };
+        if let Some(batch) = &batch {
+            self.head = Some(batch.head.clone());
+        }
+
+        // Keep the committee chain up to date across epochs
+        if summary.is_last_checkpoint_of_epoch() && !self.light_client.contains_checkpoint(seq) {
+            self.light_client.append(summary.clone())?;
+        }
+
+        self.next_checkpoint = seq + 1;
+        Ok(batch)
+    }
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<object_store::Error>(),
+        Some(object_store::Error::NotFound { .. })
+    )
+}
+
+/// Follows the stream from `start_checkpoint`, calling `on_batch` with each verified batch.
+pub async fn follow_event_stream(
+    config: &Config,
+    stream_head_id: ObjectID,
+    start_checkpoint: CheckpointSequenceNumber,
+    poll_interval: Duration,
+    mut on_batch: impl FnMut(&EventBatch),
+) -> Result<()> {
+    let store = SuiObjectStore::new(config)?;
+    let mut follower = StreamFollower::new(
+        LightClient::from_config(config)?,
+        stream_head_id,
+        start_checkpoint,
+    );
+    loop {
+        let batch = follower.next_batch(&store, poll_interval).await?;
+        on_batch(&batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_core_types::ident_str;
+    use move_core_types::language_storage::StructTag;
+    use nonempty::NonEmpty;
+    use std::collections::{BTreeMap, BTreeSet};
+    use sui_types::base_types::{SequenceNumber, SuiAddress};
+    use sui_types::committee::Committee;
+    use sui_types::crypto::AuthorityKeyPair;
+    use sui_types::digests::ObjectDigest;
+    use sui_types::effects::{
+        AccumulatorAddress, AccumulatorOperation, AccumulatorWriteV1, EffectsObjectChange,
+        IDOperation, ObjectIn, ObjectOut, TransactionEffects, TransactionEvents,
+    };
+    use sui_types::execution_status::ExecutionStatus;
+    use sui_types::full_checkpoint_content::CheckpointTransaction;
+    use sui_types::gas::GasCostSummary;
+    use sui_types::id::UID;
+    use sui_types::messages_checkpoint::{
+        CertifiedCheckpointSummary, CheckpointContents, CheckpointSummary,
+    };
+    use sui_types::object::{MoveObject, Object, Owner};
+    use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
+    use sui_types::supported_protocol_versions::ProtocolConfig;
+    use sui_types::transaction::{SenderSignedData, Transaction, TransactionData, TransactionKind};
+    use tempfile::TempDir;
+
+    const STREAM_HEAD_TYPE: &str = "0x2::dynamic_field::Field<0x2::accumulator::Key<\
+        0x2::accumulator_settlement::EventStreamHead>, 0x2::accumulator_settlement::EventStreamHead>";
+
+    /// Writes the checkpoints of a single event stream to an object store directory.
+    struct TestStream {
+        dir: TempDir,
+        committee: Committee,
+        keys: Vec<AuthorityKeyPair>,
+        head_id: ObjectID,
+        head: EventStreamHead,
+    }
+
+    impl TestStream {
+        fn new() -> Self {
+            let (committee, keys) = Committee::new_simple_test_committee();
+            Self {
+                dir: TempDir::new().unwrap(),
+                committee,
+                keys,
+                head_id: ObjectID::random(),
+                head: EventStreamHead::new(),
+            }
+        }
+
+        fn store(&self) -> SuiObjectStore {
+            SuiObjectStore::new(&Config {
+                checkpoint_summary_dir: self.dir.path().to_path_buf(),
+                full_node_url: String::new(),
+                object_store_url: format!("file://{}", self.dir.path().display()),
+                archive_store_config: None,
+                graphql_url: None,
+                genesis_filename: String::new(),
+            })
+            .unwrap()
+        }
+
+        fn follower(&self, start_checkpoint: CheckpointSequenceNumber) -> StreamFollower {
+            let chain_dir = self.dir.path().join("chain");
+            std::fs::create_dir(&chain_dir).unwrap();
+            let light_client = LightClient::create(&chain_dir, self.committee.clone()).unwrap();
+            StreamFollower::new(light_client, self.head_id, start_checkpoint)
+        }
+
+        /// Writes checkpoint `seq`, which adds `num_events` events to the stream, if any.
+        fn write_checkpoint(&mut self, seq: CheckpointSequenceNumber, num_events: u64) {
+            let mut transactions = vec![];
+            if num_events > 0 {
+                let events: Vec<_> = (0..num_events).map(|i| event(seq, i)).collect();
+                let digests = events
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| (i as u64, e.digest()));
+                let write = AccumulatorWriteV1 {
+                    address: AccumulatorAddress::new(
+                        SuiAddress::ZERO,
+                        "0x2::accumulator_settlement::EventStreamHead"
+                            .parse()
+                            .unwrap(),
+                    ),
+                    operation: AccumulatorOperation::Merge,
+                    value: AccumulatorValue::EventDigest(
+                        NonEmpty::from_vec(digests.collect()).unwrap(),
+                    ),
+                };
+                let commitments = events
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| EventCommitment::new(seq, 0, i as u64, e.digest()))
+                    .collect();
+                self.head = apply_stream_updates(&self.head, vec![commitments]);
+                self.head.checkpoint_seq = seq;
+
+                transactions.push(self.transaction(
+                    seq,
+                    ObjectOut::AccumulatorWriteV1(write),
+                    Some(TransactionEvents { data: events }),
+                    None,
+                ));
+
+                // The settlement writes the new stream head
+                let version = SequenceNumber::from_u64(seq + 1);
+                let field = Field {
+                    id: UID::new(self.head_id),
+                    name: AccumulatorKey {
+                        owner: SuiAddress::ZERO,
+                    },
+                    value: self.head.clone(),
+                };
+                let type_: StructTag = STREAM_HEAD_TYPE.parse().unwrap();
+                let object = Object::new_move(
+                    unsafe {
+                        MoveObject::new_from_execution_with_limit(
+                            type_.into(),
+                            false,
+                            version,
+                            bcs::to_bytes(&field).unwrap(),
+                            u64::MAX,
+                        )
+                    }
+                    .unwrap(),
+                    Owner::ObjectOwner(SuiAddress::ZERO),
+                    Default::default(),
+                );
+                let output_state =
+                    ObjectOut::ObjectWrite((object.digest(), object.owner().clone()));
+                transactions.push(self.transaction(seq, output_state, None, Some(object)));
+            }
+
+            let contents = CheckpointContents::new_with_digests_only_for_tests(
+                transactions.iter().map(|tx| tx.effects.execution_digests()),
+            );
+            let summary = CheckpointSummary::new(
+                &ProtocolConfig::get_for_max_version_UNSAFE(),
+                0,
+                seq,
+                0,
+                &contents,
+                None,
+                GasCostSummary::default(),
+                None,
+                0,
+                Vec::new(),
+                Vec::new(),
+            );
+            let checkpoint = CheckpointData {
+                checkpoint_summary: CertifiedCheckpointSummary::new_from_keypairs_for_testing(
+                    summary,
+                    &self.keys,
+                    &self.committee,
+                ),
+                checkpoint_contents: contents,
+                transactions,
+            };
+            self.write_file(seq, &bcs::to_bytes(&(1u8, checkpoint)).unwrap());
+        }
+
+        fn write_file(&self, seq: CheckpointSequenceNumber, bytes: &[u8]) {
+            std::fs::write(self.dir.path().join(format!("{seq}.chk")), bytes).unwrap();
+        }
+
+        /// A transaction of checkpoint `seq` that changes the stream head object.
+        fn transaction(
+            &self,
+            seq: CheckpointSequenceNumber,
+            output_state: ObjectOut,
+            events: Option<TransactionEvents>,
+            output_object: Option<Object>,
+        ) -> CheckpointTransaction {
+            let gas = (
+                ObjectID::random(),
+                SequenceNumber::new(),
+                ObjectDigest::random(),
+            );
+            let data = TransactionData::new(
+                TransactionKind::ProgrammableTransaction(
+                    ProgrammableTransactionBuilder::new().finish(),
+                ),
+                SuiAddress::ZERO,
+                gas,
+                1,
+                1,
+            );
+            let transaction = Transaction::new(SenderSignedData::new(data, vec![]));
+            let change = EffectsObjectChange {
+                input_state: ObjectIn::Exist((
+                    (SequenceNumber::from_u64(seq), ObjectDigest::MIN),
+                    Owner::ObjectOwner(SuiAddress::ZERO),
+                )),
+                output_state,
+                id_operation: IDOperation::None,
+            };
+            let effects = TransactionEffects::new_from_execution_v2(
+                ExecutionStatus::Success,
+                0,
+                GasCostSummary::default(),
+                vec![],
+                BTreeSet::new(),
+                *transaction.digest(),
+                SequenceNumber::from_u64(seq + 1),
+                BTreeMap::from([(self.head_id, change)]),
+                None,
+                events.as_ref().map(|e| e.digest()),
+                vec![],
+            );
+            CheckpointTransaction {
+                transaction,
+                effects,
+                events,
+                input_objects: vec![],
+                output_objects: output_object.into_iter().collect(),
+            }
+        }
+    }
+
+    fn event(seq: CheckpointSequenceNumber, idx: u64) -> Event {
+        Event {
+            package_id: ObjectID::ZERO,
+            transaction_module: ident_str!("stream").to_owned(),
+            sender: SuiAddress::ZERO,
+            type_: "0x0::stream::Event".parse().unwrap(),
+            contents: bcs::to_bytes(&(seq, idx)).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follow_stream() {
+        let mut stream = TestStream::new();
+        let store = stream.store();
+        let mut follower = stream.follower(1);
+        let poll_interval = Duration::from_millis(10);
+
+        // The first head is trusted, checkpoints without events are skipped, and later batches
+        // are checked against the head.
+        for (seq, num_events) in [(1, 2), (2, 0), (3, 1), (4, 3)] {
+            stream.write_checkpoint(seq, num_events);
+        }
+        for (seq, num_events) in [(1, 2), (3, 1), (4, 3)] {
+            let batch = follower.next_batch(&store, poll_interval).await.unwrap();
+            assert_eq!(batch.checkpoint, seq);
+            assert_eq!(batch.events.len(), num_events);
+        }
+        assert_eq!(follower.head(), Some(&stream.head));
+        assert_eq!(follower.head().unwrap().num_events, 6);
+
+        // Waits for a checkpoint that is not available yet.
+        assert!(
+            tokio::time::timeout(
+                Duration::from_millis(100),
+                follower.next_batch(&store, poll_interval)
+            )
+            .await
+            .is_err()
+        );
+        stream.write_checkpoint(5, 1);
+        let batch = follower.next_batch(&store, poll_interval).await.unwrap();
+        assert_eq!(batch.checkpoint, 5);
+        assert_eq!(follower.head(), Some(&stream.head));
+
+        // But fails on a checkpoint that cannot be read.
+        stream.write_file(6, b"not a checkpoint");
+        assert!(
+            tokio::time::timeout(
+                Duration::from_secs(5),
+                follower.next_batch(&store, poll_interval)
+            )
+            .await
+            .unwrap()
+            .is_err()
+        );
+    }
+}
@@ -3,8 +3,9 @@
 
 use anyhow::anyhow;
 
+use sui_light_client::bundle::{BUNDLE_FORMAT_VERSION, ProofBundle};
 use sui_light_client::proof::{
-    base::{Proof, ProofBuilder, ProofContents, ProofTarget, ProofVerifier},
+    base::{PROOF_FORMAT_VERSION, Proof, ProofBuilder, ProofContents, ProofTarget, ProofVerifier},
     committee::{CommitteeProof, extract_new_committee_info},
     objects::ObjectsTarget,
 };
@@ -215,3 +216,134 @@ async fn test_event_target_fail_bad_event() {
 
     assert!(event_proof.verify(&committee).is_err());
 }
+
+#[tokio::test]
+async fn test_transactions_target_success() {
+    let (committee, full_checkpoint) = read_data(15918264, 16005062).await;
+
+    let digests = vec![
+        full_checkpoint.transactions[0].effects.execution_digests(),
+        full_checkpoint.transactions[1].effects.execution_digests(),
+    ];
+    let target = ProofTarget::new_transactions(digests);
+    let transactions_proof = target.construct(&full_checkpoint).unwrap();
+
+    // The proof survives a round trip through its standalone encoding
+    let bytes = transactions_proof.to_bytes().unwrap();
+    assert_eq!(bytes[0], PROOF_FORMAT_VERSION);
+    let transactions_proof = Proof::from_bytes(&bytes).unwrap();
+
+    assert!(transactions_proof.verify(&committee).is_ok());
+}
+
+#[tokio::test]
+async fn test_transactions_target_fail_wrong_effects() {
+    let (committee, full_checkpoint) = read_data(15918264, 16005062).await;
+
+    let mut digests = full_checkpoint.transactions[0].effects.execution_digests();
+    digests.effects = full_checkpoint.transactions[1]
+        .effects
+        .execution_digests()
+        .effects; // WRONG
+
+    // Cannot be constructed, as no transaction executed with these effects
+    let target = ProofTarget::new_transactions(vec![digests]);
+    assert!(target.construct(&full_checkpoint).is_err());
+
+    // Nor verified if the target is swapped out
+    let target = ProofTarget::new_transactions(vec![
+        full_checkpoint.transactions[0].effects.execution_digests(),
+    ]);
+    let mut transactions_proof = target.construct(&full_checkpoint).unwrap();
+    transactions_proof.targets = ProofTarget::new_transactions(vec![digests]);
+    assert!(transactions_proof.verify(&committee).is_err());
+}
+
+#[tokio::test]
+async fn test_transactions_target_fail_no_data() {
+    let (committee, full_checkpoint) = read_data(15918264, 16005062).await;
+
+    let bad_proof = Proof {
+        checkpoint_summary: full_checkpoint.checkpoint_summary.clone(),
+        proof_contents: ProofContents::CommitteeProof(CommitteeProof {}), // WRONG
+        targets: ProofTarget::new_transactions(vec![
+            full_checkpoint.transactions[0].effects.execution_digests(),
+        ]),
+    };
+
+    assert!(bad_proof.verify(&committee).is_err());
+}
+
+#[tokio::test]
+async fn test_proof_encoding_version() {
+    let (_committee, full_checkpoint) = read_data(15918264, 16005062).await;
+
+    let target = ProofTarget::new_transactions(vec![
+        full_checkpoint.transactions[0].effects.execution_digests(),
+    ]);
+    let mut bytes = target
+        .construct(&full_checkpoint)
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+    bytes[0] = PROOF_FORMAT_VERSION + 1; // WRONG
+
+    assert!(Proof::from_bytes(&bytes).is_err());
+    assert!(Proof::from_bytes(&[]).is_err());
+}
+
+#[tokio::test]
+async fn test_proof_bundle_success() {
+    let (committee, full_checkpoint) = read_data(15918264, 16005062).await;
+
+    let target = ProofTarget::new_transactions(vec![
+        full_checkpoint.transactions[0].effects.execution_digests(),
+    ]);
+    let bundle = ProofBundle {
+        committee_chain: vec![],
+        proof: target.construct(&full_checkpoint).unwrap(),
+    };
+    assert_eq!(bundle.trusted_epoch(), committee.epoch);
+
+    let bundle = ProofBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+    assert!(bundle.verify(&committee).is_ok());
+}
+
+#[tokio::test]
+async fn test_proof_bundle_with_committee_chain() {
+    let (committee, full_checkpoint) = read_data(15918264, 16005062).await;
+    let (_, committee_checkpoint) = read_data(15918264, 15918264).await;
+
+    // The end-of-epoch checkpoint certifying the proof's committee is in the chain, so the
+    // bundle starts from the epoch before.
+    let target = ProofTarget::new_committee(
+        extract_new_committee_info(&full_checkpoint.checkpoint_summary).unwrap(),
+    );
+    let bundle = ProofBundle {
+        committee_chain: vec![committee_checkpoint.checkpoint_summary.clone()],
+        proof: target.construct(&full_checkpoint).unwrap(),
+    };
+    assert_eq!(bundle.trusted_epoch(), committee.epoch - 1);
+
+    // Only the committee of the trusted epoch is accepted
+    assert!(bundle.verify(&committee).is_err());
+}
+
+#[tokio::test]
+async fn test_proof_bundle_fail_wrong_chain() {
+    let (committee, full_checkpoint) = read_data(15918264, 16005062).await;
+
+    // A link that does not lead to the proof's epoch
+    let target = ProofTarget::new_transactions(vec![
+        full_checkpoint.transactions[0].effects.execution_digests(),
+    ]);
+    let bundle = ProofBundle {
+        committee_chain: vec![full_checkpoint.checkpoint_summary.clone()], // WRONG
+        proof: target.construct(&full_checkpoint).unwrap(),
+    };
+    let mut bytes = bundle.to_bytes().unwrap();
+    assert!(bundle.verify(&committee).is_err());
+
+    bytes[0] = BUNDLE_FORMAT_VERSION + 1; // WRONG
+    assert!(ProofBundle::from_bytes(&bytes).is_err());
+}
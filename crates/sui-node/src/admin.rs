@@ -16,6 +16,7 @@ use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
 };
+use sui_core::traffic_controller::{TrafficController, blocklist_store::BlocklistKind};
 use sui_types::{
     base_types::AuthorityName,
     crypto::{RandomnessPartialSignature, RandomnessRound, RandomnessSignature},
@@ -77,6 +78,18 @@ use tracing::info;
 // Reconfigure traffic control policy
 //
 //  $ curl 'http://127.0.0.1:1337/traffic-control?error_threshold=100&spam_threshold=100&dry_run=true'
+//
+// List the traffic control blocklists
+//
+//  $ curl 'http://127.0.0.1:1337/traffic-control/blocklist'
+//
+// Inspect the blocklist entries and audit log history of an IP
+//
+//  $ curl 'http://127.0.0.1:1337/traffic-control/blocklist/inspect?ip=1.2.3.4'
+//
+// Lift the block on an IP, from both blocklists or only from `client` or `proxied-client`
+//
+//  $ curl -X POST 'http://127.0.0.1:1337/traffic-control/blocklist/lift?ip=1.2.3.4&kind=client'
 
 const LOGGING_ROUTE: &str = "/logging";
 const TRACING_ROUTE: &str = "/enable-tracing";
@@ -92,6 +105,9 @@ const RANDOMNESS_INJECT_FULL_SIG_ROUTE: &str = "/randomness-inject-full-sig";
 const GET_TX_COST_ROUTE: &str = "/get-tx-cost";
 const DUMP_CONSENSUS_TX_COST_ESTIMATES_ROUTE: &str = "/dump-consensus-tx-cost-estimates";
 const TRAFFIC_CONTROL: &str = "/traffic-control";
+const TRAFFIC_CONTROL_BLOCKLIST: &str = "/traffic-control/blocklist";
+const TRAFFIC_CONTROL_BLOCKLIST_INSPECT: &str = "/traffic-control/blocklist/inspect";
+const TRAFFIC_CONTROL_BLOCKLIST_LIFT: &str = "/traffic-control/blocklist/lift";
 
 struct AppState {
     node: Arc<SuiNode>,
@@ -137,6 +153,15 @@ pub async fn run_admin_server(node: Arc<SuiNode>, port: u16, tracing_handle: Tra
             get(dump_consensus_tx_cost_estimates),
         )
         .route(TRAFFIC_CONTROL, post(traffic_control))
+        .route(TRAFFIC_CONTROL_BLOCKLIST, get(traffic_control_blocklist))
+        .route(
+            TRAFFIC_CONTROL_BLOCKLIST_INSPECT,
+            get(traffic_control_blocklist_inspect),
+        )
+        .route(
+            TRAFFIC_CONTROL_BLOCKLIST_LIFT,
+            post(traffic_control_blocklist_lift),
+        )
         .with_state(Arc::new(app_state));
 
     let socket_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
@@ -523,3 +548,65 @@ async fn traffic_control(
         Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
     }
 }
+
+fn traffic_controller(state: &AppState) -> Result<Arc<TrafficController>, (StatusCode, String)> {
+    state.node.state().traffic_controller.clone().ok_or((
+        StatusCode::BAD_REQUEST,
+        "Traffic controller is not configured on this node".to_string(),
+    ))
+}
+
+async fn traffic_control_blocklist(State(state): State<Arc<AppState>>) -> (StatusCode, String) {
+    match traffic_controller(&state) {
+        Ok(traffic_controller) => (
+            StatusCode::OK,
+            format!("{:#?}\n", traffic_controller.blocklist_entries()),
+        ),
+        Err(err) => err,
+    }
+}
+
+#[derive(Deserialize)]
+struct BlocklistInspect {
+    ip: IpAddr,
+}
+
+async fn traffic_control_blocklist_inspect(
+    State(state): State<Arc<AppState>>,
+    args: Query<BlocklistInspect>,
+) -> (StatusCode, String) {
+    let Query(BlocklistInspect { ip }) = args;
+    let traffic_controller = match traffic_controller(&state) {
+        Ok(traffic_controller) => traffic_controller,
+        Err(err) => return err,
+    };
+    match traffic_controller.inspect_blocklist(ip) {
+        Ok(inspection) => (StatusCode::OK, format!("{:#?}\n", inspection)),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct BlocklistLift {
+    ip: IpAddr,
+    kind: Option<BlocklistKind>,
+}
+
+async fn traffic_control_blocklist_lift(
+    State(state): State<Arc<AppState>>,
+    args: Query<BlocklistLift>,
+) -> (StatusCode, String) {
+    let Query(BlocklistLift { ip, kind }) = args;
+    let traffic_controller = match traffic_controller(&state) {
+        Ok(traffic_controller) => traffic_controller,
+        Err(err) => return err,
+    };
+    match traffic_controller.lift_block(ip, kind) {
+        Ok(lifted) if lifted.is_empty() => (
+            StatusCode::NOT_FOUND,
+            format!("{ip} is not in the blocklist\n"),
+        ),
+        Ok(lifted) => (StatusCode::OK, format!("Lifted:\n{:#?}\n", lifted)),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    }
+}
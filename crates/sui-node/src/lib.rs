@@ -1020,6 +1020,9 @@ impl SuiNode {
                 checkpoint_store,
                 chain_identifier,
                 config.state_snapshot_write_config.archive_interval_epochs,
+                config
+                    .state_snapshot_write_config
+                    .full_snapshot_interval_epochs,
             )?;
             Ok(Some(snapshot_uploader.start()))
         } else {
@@ -2630,7 +2633,9 @@ async fn build_http_servers(
             tower_http::cors::CorsLayer::new()
                 .allow_methods([http::Method::GET, http::Method::POST])
                 .allow_origin(tower_http::cors::Any)
-                .allow_headers(tower_http::cors::Any),
+                .allow_headers(tower_http::cors::Any)
+                // gRPC-Web clients read `grpc-status` and `grpc-message` from the response headers
+                .expose_headers(tower_http::cors::Any),
         );
 
     router = router.merge(rpc_router).layer(layers);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS pipeline_versions
+(
+    -- The pipeline that readers refer to, i.e `tx_digests`. This is the name
+    -- of the first version of the pipeline.
+    pipeline                    TEXT          PRIMARY KEY,
+    -- The version of the pipeline that currently serves reads for it. This is
+    -- the name of the pipeline that writes it, of its watermark, and of the
+    -- database schema that its tables are in.
+    active                      TEXT          NOT NULL,
+    -- When the active version was promoted.
+    promoted_at                 TIMESTAMP     NOT NULL
+);
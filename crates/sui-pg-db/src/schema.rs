@@ -2,6 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    pipeline_versions (pipeline) {
+        pipeline -> Text,
+        active -> Text,
+        promoted_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     watermarks (pipeline) {
         pipeline -> Text,
@@ -14,3 +22,5 @@ diesel::table! {
         pruner_hi -> Int8,
     }
 }
+
+diesel::allow_tables_to_appear_in_same_query!(pipeline_versions, watermarks,);
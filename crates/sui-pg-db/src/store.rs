@@ -8,14 +8,14 @@ use chrono::NaiveDateTime;
 use diesel::ExpressionMethods;
 use diesel::OptionalExtension;
 use diesel::prelude::*;
-use diesel::sql_types::BigInt;
+use diesel::sql_types::{BigInt, Text};
 use diesel_async::{AsyncConnection, RunQueryDsl};
 use scoped_futures::ScopedBoxFuture;
 use sui_indexer_alt_framework_store_traits as store;
 use sui_sql_macro::sql;
 
 use crate::model::StoredWatermark;
-use crate::schema::watermarks;
+use crate::schema::{pipeline_versions, watermarks};
 use crate::{Connection, Db};
 
 pub use sui_indexer_alt_framework_store_traits::Store;
@@ -171,6 +171,52 @@ impl store::Connection for Connection<'_> {
             .await?
             > 0)
     }
+
+    async fn active_pipeline_version(&mut self, pipeline: &str) -> anyhow::Result<Option<String>> {
+        Ok(pipeline_versions::table
+            .select(pipeline_versions::active)
+            .filter(pipeline_versions::pipeline.eq(pipeline))
+            .first(self)
+            .await
+            .optional()?)
+    }
+
+    async fn promote_pipeline_version(
+        &mut self,
+        pipeline: &str,
+        version: &str,
+    ) -> anyhow::Result<bool> {
+        // The comparison with the currently active version's watermark and the switch happen in
+        // a single statement, so readers never see a version that is behind the one it replaced.
+        Ok(diesel::sql_query(
+            r#"
+            INSERT INTO pipeline_versions (pipeline, active, promoted_at)
+            SELECT
+                $1, $2, NOW()
+            FROM
+                watermarks n
+            LEFT JOIN
+                watermarks o
+            ON (o.pipeline = COALESCE(
+                (SELECT active FROM pipeline_versions WHERE pipeline = $1),
+                $1
+            ))
+            WHERE
+                n.pipeline = $2
+            AND (o.pipeline IS NULL OR n.checkpoint_hi_inclusive >= o.checkpoint_hi_inclusive)
+            ON CONFLICT (pipeline) DO UPDATE SET
+                active = EXCLUDED.active,
+                promoted_at = EXCLUDED.promoted_at
+            WHERE
+                pipeline_versions.active <> EXCLUDED.active
+            "#,
+        )
+        .bind::<Text, _>(pipeline)
+        .bind::<Text, _>(version)
+        .execute(self)
+        .await?
+            > 0)
+    }
 }
 
 #[async_trait]
@@ -38,6 +38,11 @@ impl RpcError {
         }
     }
 
+    pub fn with_details(mut self, details: ErrorDetails) -> Self {
+        self.details = Some(Box::new(details));
+        self
+    }
+
     pub fn into_status_proto(self) -> crate::proto::google::rpc::Status {
         crate::proto::google::rpc::Status {
             code: self.code.into(),
@@ -282,6 +287,11 @@ impl ErrorDetails {
         &[]
     }
 
+    pub fn with_error_info(mut self, error_info: ErrorInfo) -> Self {
+        self.error_info = Some(error_info);
+        self
+    }
+
     pub fn with_bad_request(mut self, bad_request: BadRequest) -> Self {
         self.bad_request = Some(bad_request);
         self
@@ -4,6 +4,8 @@
 pub mod event_service;
 pub mod list_authenticated_events;
 pub mod proof_service;
+mod subscription_filter;
+pub mod subscription_service;
 
 pub mod event_service_proto {
     include!("../../proto/generated/sui.rpc.alpha.rs");
@@ -12,3 +14,7 @@ pub mod event_service_proto {
 pub mod proof_service_proto {
     include!("../../proto/generated/sui.rpc.alpha.rs");
 }
+
+pub mod subscription_service_proto {
+    include!("../../proto/generated/sui.rpc.alpha.rs");
+}
@@ -0,0 +1,294 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::str::FromStr;
+
+use crate::ErrorReason;
+use crate::grpc::alpha::subscription_service_proto::{EventFilter, TransactionFilter};
+use move_core_types::language_storage::StructTag;
+use sui_rpc::proto::google::rpc::bad_request::FieldViolation;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::effects::TransactionEffectsAPI;
+use sui_types::event::Event;
+use sui_types::full_checkpoint_content::ExecutedTransaction;
+use sui_types::object::Owner;
+use sui_types::parse_sui_struct_tag;
+use sui_types::transaction::TransactionDataAPI;
+
+/// A parsed `EventFilter`. An event matches if it matches all of the criteria that are set.
+#[derive(Debug)]
+pub(crate) struct EventMatcher {
+    sender: Option<SuiAddress>,
+    event_type: Option<StructTag>,
+    package: Option<ObjectID>,
+    module: Option<String>,
+}
+
+/// A parsed `TransactionFilter`. A transaction matches if it matches all of the criteria that
+/// are set.
+///
+/// `affected_address` is checked against the sender and the owners of the objects in the
+/// transaction's post-state. The owners of input objects are not considered, so an address that
+/// only transferred away, wrapped or deleted an object it owned is matched only if it was also the
+/// sender.
+#[derive(Debug)]
+pub(crate) struct TransactionMatcher {
+    sender: Option<SuiAddress>,
+    package: Option<ObjectID>,
+    module: Option<String>,
+    affected_object: Option<ObjectID>,
+    affected_address: Option<SuiAddress>,
+}
+
+impl EventMatcher {
+    pub(crate) fn matches(&self, event: &Event) -> bool {
+        self.sender.is_none_or(|sender| event.sender == sender)
+            && self
+                .event_type
+                .as_ref()
+                .is_none_or(|event_type| type_matches(event_type, &event.type_))
+            && self
+                .package
+                .is_none_or(|package| event.package_id == package)
+            && self
+                .module
+                .as_deref()
+                .is_none_or(|module| event.transaction_module.as_str() == module)
+    }
+}
+
+impl TransactionMatcher {
+    pub(crate) fn matches(&self, transaction: &ExecutedTransaction) -> bool {
+        let sender = transaction.transaction.sender();
+
+        self.sender.is_none_or(|s| s == sender)
+            && self.package.is_none_or(|package| {
+                transaction
+                    .transaction
+                    .move_calls()
+                    .into_iter()
+                    .any(|(p, m, _)| {
+                        *p == package && self.module.as_deref().is_none_or(|module| m == module)
+                    })
+            })
+            && self.affected_object.is_none_or(|object_id| {
+                transaction
+                    .effects
+                    .object_changes()
+                    .iter()
+                    .any(|change| change.id == object_id)
+            })
+            && self.affected_address.is_none_or(|address| {
+                address == sender
+                    || transaction
+                        .effects
+                        .all_changed_objects()
+                        .iter()
+                        .any(|(_, owner, _)| owner_address(owner) == Some(address))
+            })
+    }
+}
+
+impl TryFrom<&EventFilter> for EventMatcher {
+    type Error = FieldViolation;
+
+    fn try_from(filter: &EventFilter) -> Result<Self, Self::Error> {
+        check_module_has_package(filter.module.as_deref(), filter.package.as_deref())?;
+
+        Ok(Self {
+            sender: parse_field("sender", filter.sender.as_deref(), SuiAddress::from_str)?,
+            event_type: parse_field(
+                "event_type",
+                filter.event_type.as_deref(),
+                parse_sui_struct_tag,
+            )?,
+            package: parse_field("package", filter.package.as_deref(), ObjectID::from_str)?,
+            module: filter.module.clone(),
+        })
+    }
+}
+
+impl TryFrom<&TransactionFilter> for TransactionMatcher {
+    type Error = FieldViolation;
+
+    fn try_from(filter: &TransactionFilter) -> Result<Self, Self::Error> {
+        check_module_has_package(filter.module.as_deref(), filter.package.as_deref())?;
+
+        Ok(Self {
+            sender: parse_field("sender", filter.sender.as_deref(), SuiAddress::from_str)?,
+            package: parse_field("package", filter.package.as_deref(), ObjectID::from_str)?,
+            module: filter.module.clone(),
+            affected_object: parse_field(
+                "affected_object",
+                filter.affected_object.as_deref(),
+                ObjectID::from_str,
+            )?,
+            affected_address: parse_field(
+                "affected_address",
+                filter.affected_address.as_deref(),
+                SuiAddress::from_str,
+            )?,
+        })
+    }
+}
+
+/// A type without type parameters matches all instantiations of the type.
+fn type_matches(filter: &StructTag, event_type: &StructTag) -> bool {
+    if filter.type_params.is_empty() {
+        filter.address == event_type.address
+            && filter.module == event_type.module
+            && filter.name == event_type.name
+    } else {
+        filter == event_type
+    }
+}
+
+fn owner_address(owner: &Owner) -> Option<SuiAddress> {
+    match owner {
+        Owner::AddressOwner(address) | Owner::ConsensusAddressOwner { owner: address, .. } => {
+            Some(*address)
+        }
+        Owner::ObjectOwner(_) | Owner::Shared { .. } | Owner::Immutable => None,
+    }
+}
+
+fn check_module_has_package(
+    module: Option<&str>,
+    package: Option<&str>,
+) -> Result<(), FieldViolation> {
+    if module.is_some() && package.is_none() {
+        return Err(FieldViolation::new("module")
+            .with_description("module requires package to be set")
+            .with_reason(ErrorReason::FieldInvalid));
+    }
+    Ok(())
+}
+
+fn parse_field<T, E: std::fmt::Display>(
+    name: &str,
+    value: Option<&str>,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<Option<T>, FieldViolation> {
+    value
+        .map(|value| {
+            parse(value).map_err(|e| {
+                FieldViolation::new(name)
+                    .with_description(format!("invalid {name}: {e}"))
+                    .with_reason(ErrorReason::FieldInvalid)
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod tests {
+    use super::*;
+    use move_core_types::identifier::Identifier;
+    use sui_types::test_checkpoint_data_builder::TestCheckpointBuilder;
+
+    fn struct_tag(s: &str) -> StructTag {
+        parse_sui_struct_tag(s).unwrap()
+    }
+
+    fn event(package: ObjectID, module: &str, type_: &str) -> Event {
+        Event {
+            package_id: package,
+            transaction_module: Identifier::new(module).unwrap(),
+            sender: TestCheckpointBuilder::derive_address(0),
+            type_: struct_tag(type_),
+            contents: vec![],
+        }
+    }
+
+    #[test]
+    fn type_matches_generic_and_concrete() {
+        let sui = struct_tag("0x2::coin::Coin<0x2::sui::SUI>");
+        let other = struct_tag("0x2::coin::Coin<0x3::foo::FOO>");
+
+        // A filter without type parameters matches every instantiation.
+        let generic = struct_tag("0x2::coin::Coin");
+        assert!(type_matches(&generic, &sui));
+        assert!(type_matches(&generic, &other));
+        assert!(!type_matches(
+            &generic,
+            &struct_tag("0x2::coin::TreasuryCap<0x2::sui::SUI>")
+        ));
+
+        // A filter with type parameters only matches the same instantiation.
+        assert!(type_matches(&sui, &sui));
+        assert!(!type_matches(&sui, &other));
+    }
+
+    #[test]
+    fn event_package_and_module() {
+        let package = ObjectID::from_single_byte(0xa);
+        let mut filter = EventFilter::default();
+        filter.package = Some(package.to_string());
+        filter.module = Some("foo".to_owned());
+        let matcher = EventMatcher::try_from(&filter).unwrap();
+
+        assert!(matcher.matches(&event(package, "foo", "0xa::foo::Event")));
+        assert!(!matcher.matches(&event(package, "bar", "0xa::bar::Event")));
+        assert!(!matcher.matches(&event(
+            ObjectID::from_single_byte(0xb),
+            "foo",
+            "0xb::foo::Event"
+        )));
+    }
+
+    #[test]
+    fn module_requires_package() {
+        let mut filter = EventFilter::default();
+        filter.module = Some("foo".to_owned());
+        assert!(EventMatcher::try_from(&filter).is_err());
+
+        let mut filter = TransactionFilter::default();
+        filter.module = Some("foo".to_owned());
+        assert!(TransactionMatcher::try_from(&filter).is_err());
+    }
+
+    #[test]
+    fn transaction_affected_object_and_address() {
+        let mut builder = TestCheckpointBuilder::new(1)
+            .start_transaction(0)
+            .create_owned_object(0)
+            .finish_transaction()
+            .start_transaction(0)
+            .transfer_object(0, 1)
+            .finish_transaction()
+            .start_transaction(2)
+            .create_owned_object(1)
+            .finish_transaction();
+        let transactions = builder.build_checkpoint().transactions;
+
+        let object = |idx| {
+            let mut filter = TransactionFilter::default();
+            filter.affected_object = Some(TestCheckpointBuilder::derive_object_id(idx).to_string());
+            TransactionMatcher::try_from(&filter).unwrap()
+        };
+        let address = |idx| {
+            let mut filter = TransactionFilter::default();
+            filter.affected_address = Some(TestCheckpointBuilder::derive_address(idx).to_string());
+            TransactionMatcher::try_from(&filter).unwrap()
+        };
+        let matching = |matcher: TransactionMatcher| {
+            transactions
+                .iter()
+                .enumerate()
+                .filter(|(_, tx)| matcher.matches(tx))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matching(object(0)), vec![0, 1]);
+        assert_eq!(matching(object(1)), vec![2]);
+
+        // Address 0 sends the first two transactions, address 1 receives the object in the second
+        // and address 2 sends and owns the object created by the third.
+        assert_eq!(matching(address(0)), vec![0, 1]);
+        assert_eq!(matching(address(1)), vec![1]);
+        assert_eq!(matching(address(2)), vec![2]);
+        assert_eq!(matching(address(3)), Vec::<usize>::new());
+    }
+}
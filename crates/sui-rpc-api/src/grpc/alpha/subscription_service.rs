@@ -0,0 +1,531 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+#![allow(clippy::field_reassign_with_default)]
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::ErrorDetails;
+use crate::ErrorReason;
+use crate::RpcError;
+use crate::RpcService;
+use crate::error::CheckpointNotFoundError;
+use crate::grpc::alpha::subscription_filter::{EventMatcher, TransactionMatcher};
+use crate::grpc::alpha::subscription_service_proto::subscription_service_server::SubscriptionService;
+use crate::grpc::alpha::subscription_service_proto::{
+    SubscribeEventsRequest, SubscribeEventsResponse, SubscribeTransactionsRequest,
+    SubscribeTransactionsResponse,
+};
+use crate::proto::google::rpc::ErrorInfo;
+use crate::reader::StateReader;
+use crate::subscription::SubscriptionServiceHandle;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use prost_types::FieldMask;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sui_rpc::field::FieldMaskTree;
+use sui_rpc::field::FieldMaskUtil;
+use sui_rpc::merge::Merge;
+use sui_rpc::proto::google::rpc::bad_request::FieldViolation;
+use sui_rpc::proto::sui::rpc::v2::Event;
+use sui_rpc::proto::sui::rpc::v2::ExecutedTransaction;
+use sui_rpc::proto::sui::rpc::v2::TransactionEvents;
+use sui_types::full_checkpoint_content::Checkpoint;
+use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::StreamExt;
+
+const MAX_FILTERS: usize = 16;
+/// Maximum number of already executed checkpoints that a subscription can start behind the latest
+/// checkpoint.
+const MAX_CATCH_UP_CHECKPOINTS: u64 = 10_000;
+const EVENTS_READ_MASK_DEFAULT: &str = "package_id,module,sender,event_type,contents";
+const TRANSACTIONS_READ_MASK_DEFAULT: &str = "digest";
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, tonic::Status>> + Send>>;
+type CheckpointStream = Pin<Box<dyn Stream<Item = Result<Arc<Checkpoint>, RpcError>> + Send>>;
+
+/// Position of an event in the chain, which is encoded into the cursors of `SubscribeEvents`.
+#[derive(PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
+struct EventCursor {
+    checkpoint: CheckpointSequenceNumber,
+    transaction_index: u64,
+    event_index: u32,
+}
+
+impl EventCursor {
+    /// Position after all the events of `checkpoint`.
+    fn end_of_checkpoint(checkpoint: CheckpointSequenceNumber) -> Self {
+        Self {
+            checkpoint,
+            transaction_index: u64::MAX,
+            event_index: u32::MAX,
+        }
+    }
+}
+
+/// Position of a transaction in the chain, which is encoded into the cursors of
+/// `SubscribeTransactions`.
+#[derive(PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
+struct TransactionCursor {
+    checkpoint: CheckpointSequenceNumber,
+    transaction_index: u64,
+}
+
+impl TransactionCursor {
+    /// Position after all the transactions of `checkpoint`.
+    fn end_of_checkpoint(checkpoint: CheckpointSequenceNumber) -> Self {
+        Self {
+            checkpoint,
+            transaction_index: u64::MAX,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SubscriptionService for RpcService {
+    type SubscribeEventsStream = ResponseStream<SubscribeEventsResponse>;
+    type SubscribeTransactionsStream = ResponseStream<SubscribeTransactionsResponse>;
+
+    async fn subscribe_events(
+        &self,
+        request: tonic::Request<SubscribeEventsRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeEventsStream>, tonic::Status> {
+        subscribe_events(self, request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(tonic::Status::from)
+    }
+
+    async fn subscribe_transactions(
+        &self,
+        request: tonic::Request<SubscribeTransactionsRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeTransactionsStream>, tonic::Status> {
+        subscribe_transactions(self, request.into_inner())
+            .await
+            .map(tonic::Response::new)
+            .map_err(tonic::Status::from)
+    }
+}
+
+#[tracing::instrument(skip(service))]
+async fn subscribe_events(
+    service: &RpcService,
+    request: SubscribeEventsRequest,
+) -> Result<ResponseStream<SubscribeEventsResponse>, RpcError> {
+    if request.filters.len() > MAX_FILTERS {
+        return Err(too_many_filters());
+    }
+    let filters = request
+        .filters
+        .iter()
+        .enumerate()
+        .map(|(idx, filter)| {
+            EventMatcher::try_from(filter).map_err(|e| e.nested_at("filters", idx))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let cursor = request
+        .cursor
+        .as_deref()
+        .map(decode_cursor::<EventCursor>)
+        .transpose()?;
+    let start = start_checkpoint(
+        service,
+        cursor.as_ref().map(|c| c.checkpoint),
+        request.start_checkpoint,
+    )?;
+
+    let read_mask = {
+        let read_mask = request
+            .read_mask
+            .unwrap_or_else(|| FieldMask::from_str(EVENTS_READ_MASK_DEFAULT));
+        read_mask.validate::<Event>().map_err(|path| {
+            FieldViolation::new("read_mask")
+                .with_description(format!("invalid read_mask path: {path}"))
+                .with_reason(ErrorReason::FieldInvalid)
+        })?;
+        FieldMaskTree::from(read_mask)
+    };
+
+    let mut checkpoints = checkpoint_stream(service, start).await?;
+    let service = service.clone();
+    let mut last_cursor = request.cursor;
+    let response = Box::pin(async_stream::stream! {
+        while let Some(checkpoint) = checkpoints.next().await {
+            let checkpoint = match checkpoint {
+                Ok(checkpoint) => checkpoint,
+                Err(e) => {
+                    yield Err(e.into());
+                    return;
+                }
+            };
+            let sequence_number = checkpoint.summary.sequence_number;
+
+            for (transaction_index, transaction) in checkpoint.transactions.iter().enumerate() {
+                let Some(events) = &transaction.events else {
+                    continue;
+                };
+
+                for (event_index, event) in events.data.iter().enumerate() {
+                    let position = EventCursor {
+                        checkpoint: sequence_number,
+                        transaction_index: transaction_index as u64,
+                        event_index: event_index as u32,
+                    };
+                    if cursor.as_ref().is_some_and(|cursor| position <= *cursor)
+                        || (!filters.is_empty() && !filters.iter().any(|f| f.matches(event)))
+                    {
+                        continue;
+                    }
+
+                    let mut message = Event::merge_from(event, &read_mask);
+                    if read_mask.contains(Event::JSON_FIELD) {
+                        message.json =
+                            crate::grpc::v2::render_json(&service, &event.type_, &event.contents)
+                                .map(Box::new);
+                    }
+
+                    let mut response = SubscribeEventsResponse::default();
+                    response.cursor = Some(encode_cursor(&position));
+                    response.checkpoint = Some(sequence_number);
+                    response.transaction_digest =
+                        Some(transaction.transaction.digest().to_string());
+                    response.event_index = Some(position.event_index);
+                    response.event = Some(message);
+
+                    yield Ok(response);
+                }
+            }
+
+            last_cursor = Some(encode_cursor(&EventCursor::end_of_checkpoint(sequence_number)));
+        }
+
+        yield Err(subscription_lagged(last_cursor.as_deref()).into());
+    });
+
+    Ok(response)
+}
+
+#[tracing::instrument(skip(service))]
+async fn subscribe_transactions(
+    service: &RpcService,
+    request: SubscribeTransactionsRequest,
+) -> Result<ResponseStream<SubscribeTransactionsResponse>, RpcError> {
+    if request.filters.len() > MAX_FILTERS {
+        return Err(too_many_filters());
+    }
+    let filters = request
+        .filters
+        .iter()
+        .enumerate()
+        .map(|(idx, filter)| {
+            TransactionMatcher::try_from(filter).map_err(|e| e.nested_at("filters", idx))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let cursor = request
+        .cursor
+        .as_deref()
+        .map(decode_cursor::<TransactionCursor>)
+        .transpose()?;
+    let start = start_checkpoint(
+        service,
+        cursor.as_ref().map(|c| c.checkpoint),
+        request.start_checkpoint,
+    )?;
+
+    let read_mask = {
+        let read_mask = request
+            .read_mask
+            .unwrap_or_else(|| FieldMask::from_str(TRANSACTIONS_READ_MASK_DEFAULT));
+        read_mask
+            .validate::<ExecutedTransaction>()
+            .map_err(|path| {
+                FieldViolation::new("read_mask")
+                    .with_description(format!("invalid read_mask path: {path}"))
+                    .with_reason(ErrorReason::FieldInvalid)
+            })?;
+        FieldMaskTree::from(read_mask)
+    };
+
+    let mut checkpoints = checkpoint_stream(service, start).await?;
+    let service = service.clone();
+    let mut last_cursor = request.cursor;
+    let response = Box::pin(async_stream::stream! {
+        while let Some(checkpoint) = checkpoints.next().await {
+            let checkpoint = match checkpoint {
+                Ok(checkpoint) => checkpoint,
+                Err(e) => {
+                    yield Err(e.into());
+                    return;
+                }
+            };
+            let sequence_number = checkpoint.summary.sequence_number;
+            let timestamp_ms = checkpoint.summary.timestamp_ms;
+
+            for (transaction_index, transaction) in checkpoint.transactions.iter().enumerate() {
+                let position = TransactionCursor {
+                    checkpoint: sequence_number,
+                    transaction_index: transaction_index as u64,
+                };
+                if cursor.as_ref().is_some_and(|cursor| position <= *cursor)
+                    || (!filters.is_empty() && !filters.iter().any(|f| f.matches(transaction)))
+                {
+                    continue;
+                }
+
+                let mut message = ExecutedTransaction::merge_from(transaction, &read_mask);
+                message.checkpoint = read_mask
+                    .contains(ExecutedTransaction::CHECKPOINT_FIELD)
+                    .then_some(sequence_number);
+                message.timestamp = read_mask
+                    .contains(ExecutedTransaction::TIMESTAMP_FIELD)
+                    .then(|| sui_rpc::proto::timestamp_ms_to_proto(timestamp_ms));
+
+                if read_mask.contains(ExecutedTransaction::BALANCE_CHANGES_FIELD)
+                    && let Some(info) = service
+                        .reader
+                        .get_transaction_info(&transaction.transaction.digest())
+                {
+                    message.balance_changes = info
+                        .balance_changes
+                        .into_iter()
+                        .map(sui_rpc::proto::sui::rpc::v2::BalanceChange::from)
+                        .collect();
+                }
+
+                if let Some(events_mask) = read_mask.subtree(ExecutedTransaction::EVENTS_FIELD.name)
+                    && let Some(event_mask) =
+                        events_mask.subtree(TransactionEvents::EVENTS_FIELD.name)
+                    && event_mask.contains(Event::JSON_FIELD.name)
+                    && let Some(events) = message.events.as_mut()
+                    && let Some(sdk_events) = &transaction.events
+                {
+                    for (message, event) in events.events.iter_mut().zip(&sdk_events.data) {
+                        message.json =
+                            crate::grpc::v2::render_json(&service, &event.type_, &event.contents)
+                                .map(Box::new);
+                    }
+                }
+
+                let mut response = SubscribeTransactionsResponse::default();
+                response.cursor = Some(encode_cursor(&position));
+                response.transaction = Some(message);
+
+                yield Ok(response);
+            }
+
+            last_cursor = Some(encode_cursor(&TransactionCursor::end_of_checkpoint(
+                sequence_number,
+            )));
+        }
+
+        yield Err(subscription_lagged(last_cursor.as_deref()).into());
+    });
+
+    Ok(response)
+}
+
+/// Streams executed checkpoints in order. Without a `start` checkpoint, the stream begins with
+/// the next checkpoint to be executed. Otherwise checkpoints that were already executed are read
+/// from the store, before continuing with newly executed checkpoints. `start` can be at most
+/// `MAX_CATCH_UP_CHECKPOINTS` behind the latest checkpoint.
+///
+/// The stream ends, like `SubscribeCheckpoints`, when the subscriber falls too far behind newly
+/// executed checkpoints, and clients are expected to resume from their last cursor.
+async fn checkpoint_stream(
+    service: &RpcService,
+    start: Option<CheckpointSequenceNumber>,
+) -> Result<CheckpointStream, RpcError> {
+    let subscription_service_handle =
+        service.subscription_service_handle.clone().ok_or_else(|| {
+            RpcError::new(
+                tonic::Code::Unimplemented,
+                "subscription service not enabled",
+            )
+        })?;
+
+    let reader = service.reader.clone();
+    let receiver = match start {
+        // Nothing to catch up on, so subscribe right away.
+        None => Some(register_subscription(&subscription_service_handle).await?),
+        Some(start) => {
+            let latest = reader.inner().get_latest_checkpoint_sequence_number()?;
+            if latest.saturating_sub(start) >= MAX_CATCH_UP_CHECKPOINTS {
+                return Err(RpcError::new(
+                    tonic::Code::OutOfRange,
+                    format!(
+                        "checkpoint {start} is too far behind the latest checkpoint {latest}, \
+                        subscriptions can start at most {MAX_CATCH_UP_CHECKPOINTS} checkpoints behind"
+                    ),
+                ));
+            }
+            None
+        }
+    };
+
+    Ok(Box::pin(async_stream::stream! {
+        let mut next = start;
+
+        // Catch up from the store before subscribing, so that newly executed checkpoints don't
+        // pile up in the subscription's channel in the meantime. Checkpoints executed between the
+        // end of the catch up and the subscription are read from the store once the first newly
+        // executed checkpoint is received.
+        if let Some(start) = start {
+            let latest = match reader.inner().get_latest_checkpoint_sequence_number() {
+                Ok(latest) => latest,
+                Err(e) => {
+                    yield Err(e.into());
+                    return;
+                }
+            };
+
+            for sequence_number in start..=latest {
+                let checkpoint = load_checkpoint(&reader, sequence_number);
+                let failed = checkpoint.is_err();
+                yield checkpoint.map(Arc::new);
+                if failed {
+                    return;
+                }
+            }
+
+            next = Some(start.max(latest + 1));
+        }
+
+        let mut receiver = match receiver {
+            Some(receiver) => receiver,
+            None => match register_subscription(&subscription_service_handle).await {
+                Ok(receiver) => receiver,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            },
+        };
+
+        // Ends when the subscription service drops the subscription, because its channel is full.
+        while let Some(checkpoint) = receiver.recv().await {
+            let sequence_number = checkpoint.summary.sequence_number;
+
+            if let Some(next) = next {
+                // Already read from the store.
+                if sequence_number < next {
+                    continue;
+                }
+
+                // Executed while catching up, but before the first checkpoint of the subscription.
+                for sequence_number in next..sequence_number {
+                    let checkpoint = load_checkpoint(&reader, sequence_number);
+                    let failed = checkpoint.is_err();
+                    yield checkpoint.map(Arc::new);
+                    if failed {
+                        return;
+                    }
+                }
+            }
+
+            next = Some(sequence_number + 1);
+            yield Ok(checkpoint);
+        }
+    }))
+}
+
+async fn register_subscription(
+    subscription_service_handle: &SubscriptionServiceHandle,
+) -> Result<mpsc::Receiver<Arc<Checkpoint>>, RpcError> {
+    subscription_service_handle
+        .register_subscription()
+        .await
+        .ok_or_else(|| RpcError::new(tonic::Code::Unavailable, "too many existing subscriptions"))
+}
+
+fn load_checkpoint(
+    reader: &StateReader,
+    sequence_number: CheckpointSequenceNumber,
+) -> Result<Checkpoint, RpcError> {
+    let summary = reader
+        .inner()
+        .get_checkpoint_by_sequence_number(sequence_number)
+        .ok_or(CheckpointNotFoundError::sequence_number(sequence_number))?;
+    let contents = reader
+        .inner()
+        .get_checkpoint_contents_by_sequence_number(sequence_number)
+        .ok_or(CheckpointNotFoundError::sequence_number(sequence_number))?;
+
+    Ok(reader.inner().get_checkpoint_data(summary, contents)?)
+}
+
+/// Checks the requested starting point of a subscription, from either a cursor or a checkpoint.
+fn start_checkpoint(
+    service: &RpcService,
+    cursor_checkpoint: Option<CheckpointSequenceNumber>,
+    start_checkpoint: Option<CheckpointSequenceNumber>,
+) -> Result<Option<CheckpointSequenceNumber>, RpcError> {
+    if cursor_checkpoint.is_some() && start_checkpoint.is_some() {
+        return Err(FieldViolation::new("start_checkpoint")
+            .with_description("start_checkpoint cannot be set together with cursor")
+            .with_reason(ErrorReason::FieldInvalid)
+            .into());
+    }
+
+    let Some(start) = cursor_checkpoint.or(start_checkpoint) else {
+        return Ok(None);
+    };
+
+    let lowest_available = service
+        .reader
+        .inner()
+        .get_lowest_available_checkpoint_objects()?;
+    if start < lowest_available {
+        return Err(RpcError::new(
+            tonic::Code::OutOfRange,
+            format!(
+                "checkpoint {start} has been pruned, the lowest available checkpoint is {lowest_available}"
+            ),
+        ));
+    }
+
+    Ok(Some(start))
+}
+
+/// The error ending a subscription that fell too far behind newly executed checkpoints. Its
+/// `ErrorInfo` carries the cursor to resume from, if there is one, base64 encoded under `cursor`.
+fn subscription_lagged(cursor: Option<&[u8]>) -> RpcError {
+    let mut error_info = ErrorInfo::default();
+    error_info.reason = "SUBSCRIPTION_LAGGED".to_owned();
+    if let Some(cursor) = cursor {
+        error_info
+            .metadata
+            .insert("cursor".to_owned(), STANDARD.encode(cursor));
+    }
+
+    RpcError::new(
+        tonic::Code::Aborted,
+        "subscription fell too far behind, resume from the last cursor",
+    )
+    .with_details(ErrorDetails::new().with_error_info(error_info))
+}
+
+fn too_many_filters() -> RpcError {
+    FieldViolation::new("filters")
+        .with_description(format!("at most {MAX_FILTERS} filters are allowed"))
+        .with_reason(ErrorReason::FieldInvalid)
+        .into()
+}
+
+fn decode_cursor<T: DeserializeOwned>(cursor: &[u8]) -> Result<T, RpcError> {
+    bcs::from_bytes(cursor).map_err(|_| {
+        FieldViolation::new("cursor")
+            .with_description("invalid cursor")
+            .with_reason(ErrorReason::FieldInvalid)
+            .into()
+    })
+}
+
+fn encode_cursor<T: Serialize>(cursor: &T) -> Vec<u8> {
+    bcs::to_bytes(cursor).unwrap()
+}
@@ -6,6 +6,7 @@ use tonic::server::NamedService;
 use tower::Service;
 
 pub mod alpha;
+pub mod transcoding;
 pub mod v2;
 
 pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
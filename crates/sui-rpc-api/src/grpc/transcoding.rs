@@ -0,0 +1,340 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! HTTP/JSON transcoding for gRPC services.
+//!
+//! Every unary method of a transcoded service is served as `POST /{version}/{service}/{method}`,
+//! e.g. `LedgerService.GetObject` of package `sui.rpc.v2` as `POST /v2/ledger/get_object`. The
+//! request body is the proto3 JSON encoding of the request message, which is converted to protobuf
+//! using the service's descriptors and dispatched to the gRPC router in-process. Successful
+//! responses are returned as proto3 JSON, errors as the JSON encoding of `google.rpc.Status` with
+//! the closest matching HTTP status code.
+
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use http::{HeaderMap, HeaderValue, StatusCode, header};
+use http_body_util::BodyExt;
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor};
+use tonic::Code;
+use tower::ServiceExt;
+
+const GRPC_FRAME_HEADER_LEN: usize = 5;
+
+/// Request headers which are specific to the JSON request and are not forwarded to the gRPC
+/// service.
+const STRIPPED_REQUEST_HEADERS: [&str; 6] = [
+    "content-type",
+    "content-length",
+    "accept-encoding",
+    "grpc-encoding",
+    "grpc-accept-encoding",
+    "te",
+];
+
+/// Build a router serving the unary methods of `services` as JSON endpoints, calling into the gRPC
+/// `router`.
+pub fn router(services: &[&str], grpc: axum::Router) -> axum::Router {
+    let pool = descriptor_pool();
+    let status = pool
+        .get_message_by_name("google.rpc.Status")
+        .expect("google.rpc.Status is registered");
+
+    let mut router = axum::Router::new();
+    for service in services {
+        let service = pool
+            .get_service_by_name(service)
+            .unwrap_or_else(|| panic!("missing descriptor for service {service}"));
+
+        for method in service
+            .methods()
+            .filter(|method| !method.is_client_streaming() && !method.is_server_streaming())
+        {
+            let transcoder = Transcoder {
+                grpc: grpc.clone(),
+                path: format!("/{}/{}", service.full_name(), method.name()),
+                input: method.input(),
+                output: method.output(),
+                status: status.clone(),
+            };
+            router = router.route(
+                &route(&method),
+                axum::routing::post(transcode).with_state(transcoder),
+            );
+        }
+    }
+    router
+}
+
+fn descriptor_pool() -> DescriptorPool {
+    let mut pool = DescriptorPool::new();
+    for file_descriptor_set in [
+        crate::proto::google::protobuf::FILE_DESCRIPTOR_SET,
+        crate::proto::google::rpc::FILE_DESCRIPTOR_SET,
+        sui_rpc::proto::sui::rpc::v2::FILE_DESCRIPTOR_SET,
+    ] {
+        pool.decode_file_descriptor_set(file_descriptor_set)
+            .expect("valid file descriptor set");
+    }
+    pool
+}
+
+/// The REST-style route of a method: the last component of the package, the service name without
+/// its `Service` suffix, and the method name, the latter two in snake case.
+fn route(method: &MethodDescriptor) -> String {
+    let service = method.parent_service();
+    let version = service
+        .package_name()
+        .rsplit('.')
+        .next()
+        .unwrap_or_default();
+    let name = service.name();
+    let name = name.strip_suffix("Service").unwrap_or(name);
+
+    format!(
+        "/{version}/{}/{}",
+        snake_case(name),
+        snake_case(method.name())
+    )
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.char_indices() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[derive(Clone)]
+struct Transcoder {
+    grpc: axum::Router,
+    /// Path of the gRPC method, `/{service}/{method}`.
+    path: String,
+    input: MessageDescriptor,
+    output: MessageDescriptor,
+    /// Descriptor of `google.rpc.Status`, for rendering error details.
+    status: MessageDescriptor,
+}
+
+async fn transcode(
+    State(transcoder): State<Transcoder>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    match transcoder.call(headers, body).await {
+        Ok(message) => json_response(StatusCode::OK, &message),
+        Err(status) => transcoder.error_response(status),
+    }
+}
+
+impl Transcoder {
+    async fn call(&self, headers: HeaderMap, body: Bytes) -> Result<DynamicMessage, tonic::Status> {
+        let request = if body.iter().all(u8::is_ascii_whitespace) {
+            DynamicMessage::new(self.input.clone())
+        } else {
+            let mut deserializer = serde_json::Deserializer::from_slice(&body);
+            DynamicMessage::deserialize(self.input.clone(), &mut deserializer)
+                .and_then(|request| deserializer.end().map(|()| request))
+                .map_err(|e| {
+                    tonic::Status::invalid_argument(format!("invalid JSON request body: {e}"))
+                })?
+        };
+
+        let response = self
+            .grpc
+            .clone()
+            .oneshot(self.grpc_request(headers, request))
+            .await
+            .unwrap_or_else(|infallible| match infallible {});
+
+        let (parts, body) = response.into_parts();
+        let body = body
+            .collect()
+            .await
+            .map_err(|e| tonic::Status::internal(format!("failed to read response: {e}")))?;
+
+        // Errors are returned as trailers-only responses, with the status in the headers.
+        if let Some(status) = tonic::Status::from_header_map(&parts.headers)
+            .or_else(|| body.trailers().and_then(tonic::Status::from_header_map))
+            && status.code() != Code::Ok
+        {
+            return Err(status);
+        }
+
+        let body = body.to_bytes();
+        let message = decode_frame(&body)?;
+        DynamicMessage::decode(self.output.clone(), message)
+            .map_err(|e| tonic::Status::internal(format!("failed to decode response: {e}")))
+    }
+
+    fn grpc_request(&self, mut headers: HeaderMap, request: DynamicMessage) -> http::Request<Body> {
+        for name in STRIPPED_REQUEST_HEADERS {
+            headers.remove(name);
+        }
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc"),
+        );
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+
+        let message = request.encode_to_vec();
+        let mut frame = Vec::with_capacity(GRPC_FRAME_HEADER_LEN + message.len());
+        // Uncompressed message.
+        frame.push(0);
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&message);
+
+        let mut request = http::Request::post(&self.path)
+            .body(Body::from(frame))
+            .expect("valid request");
+        *request.headers_mut() = headers;
+        request
+    }
+
+    fn error_response(&self, status: tonic::Status) -> Response {
+        let http_status = http_status(status.code());
+
+        // Prefer the `google.rpc.Status` in the details, which includes the error details.
+        if !status.details().is_empty()
+            && let Ok(details) = DynamicMessage::decode(self.status.clone(), status.details())
+            && let Ok(body) = serde_json::to_value(&details)
+        {
+            return json_response(http_status, &body);
+        }
+
+        let body = serde_json::json!({
+            "code": status.code() as i32,
+            "message": status.message(),
+        });
+        json_response(http_status, &body)
+    }
+}
+
+fn decode_frame(body: &[u8]) -> Result<&[u8], tonic::Status> {
+    let (header, message) = body
+        .split_at_checked(GRPC_FRAME_HEADER_LEN)
+        .ok_or_else(|| tonic::Status::internal("missing response message"))?;
+
+    if header[0] != 0 {
+        return Err(tonic::Status::internal("unexpected compressed response"));
+    }
+    let len = u32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+
+    message
+        .get(..len)
+        .ok_or_else(|| tonic::Status::internal("truncated response message"))
+}
+
+fn json_response<T: serde::Serialize>(status: StatusCode, body: &T) -> Response {
+    match serde_json::to_vec(body) {
+        Ok(body) => (
+            status,
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            )],
+            body,
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to render response: {e}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Map a gRPC status code to an HTTP status code, following `google.rpc.Code`.
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        // 499 Client Closed Request
+        Code::Cancelled => StatusCode::from_u16(499).unwrap(),
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes() {
+        let pool = descriptor_pool();
+        let routes = |service: &str| {
+            pool.get_service_by_name(service)
+                .unwrap()
+                .methods()
+                .map(|method| route(&method))
+                .collect::<Vec<_>>()
+        };
+
+        let ledger = routes("sui.rpc.v2.LedgerService");
+        assert!(ledger.contains(&"/v2/ledger/get_service_info".to_owned()));
+        assert!(ledger.contains(&"/v2/ledger/batch_get_objects".to_owned()));
+
+        let execution = routes("sui.rpc.v2.TransactionExecutionService");
+        assert!(execution.contains(&"/v2/transaction_execution/execute_transaction".to_owned()));
+
+        let packages = routes("sui.rpc.v2.MovePackageService");
+        assert!(packages.contains(&"/v2/move_package/get_package".to_owned()));
+    }
+
+    #[test]
+    fn grpc_frames() {
+        let mut body = vec![0, 0, 0, 0, 3];
+        body.extend_from_slice(b"abc");
+        assert_eq!(decode_frame(&body).unwrap(), b"abc");
+
+        body[0] = 1;
+        assert!(decode_frame(&body).is_err());
+        assert!(decode_frame(&[0, 0, 0, 0, 4, 1]).is_err());
+        assert!(decode_frame(&[]).is_err());
+    }
+
+    #[test]
+    fn json_request() {
+        let pool = descriptor_pool();
+        let input = pool
+            .get_message_by_name("sui.rpc.v2.GetObjectRequest")
+            .unwrap();
+
+        let message = DynamicMessage::deserialize(
+            input,
+            serde_json::json!({
+                "objectId": "0x5",
+                "readMask": "objectId,version",
+            }),
+        )
+        .unwrap();
+        let request =
+            sui_rpc::proto::sui::rpc::v2::GetObjectRequest::decode(&*message.encode_to_vec())
+                .unwrap();
+
+        assert_eq!(request.object_id.as_deref(), Some("0x5"));
+        assert_eq!(
+            request.read_mask.unwrap().paths,
+            vec!["object_id".to_owned(), "version".to_owned()]
+        );
+    }
+}
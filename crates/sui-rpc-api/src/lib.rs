@@ -174,6 +174,13 @@ impl RpcService {
                 S::NAME
             }
 
+            let json_services = [
+                service_name(&ledger_service),
+                service_name(&transaction_execution_service),
+                service_name(&state_service),
+                service_name(&move_package_service),
+            ];
+
             for service_name in [
                 service_name(&ledger_service),
                 service_name(&transaction_execution_service),
@@ -217,9 +224,26 @@ sui_rpc::proto::sui::rpc::v2::subscription_service_server::SubscriptionServiceSe
                     .await;
 
                 services = services.add_service(subscription_service);
+
+                let subscription_service_alpha =
+                    crate::grpc::alpha::subscription_service_proto::subscription_service_server::SubscriptionServiceServer::new(
+                        self.clone(),
+                    );
+                health_reporter
+                    .set_service_status(
+                        service_name(&subscription_service_alpha),
+                        tonic_health::ServingStatus::Serving,
+                    )
+                    .await;
+
+                services = services.add_service(subscription_service_alpha);
             }
 
-            services.add_service(health_service).into_router()
+            let grpc = services.add_service(health_service).into_router();
+
+            // Unary methods of the v2 services are also served as JSON over plain HTTP.
+            let json = grpc::transcoding::router(&json_services, grpc.clone());
+            grpc.merge(json)
         };
 
         let health_endpoint = axum::Router::new()
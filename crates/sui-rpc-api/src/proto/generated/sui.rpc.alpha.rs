@@ -721,3 +721,535 @@ pub mod proof_service_server {
         const NAME: &'static str = SERVICE_NAME;
     }
 }
+/// Matches events on all of the criteria that are set.
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct EventFilter {
+    /// Sender of the transaction that emitted the event.
+    #[prost(string, optional, tag = "1")]
+    pub sender: ::core::option::Option<::prost::alloc::string::String>,
+    /// Move type of the event, e.g. `0x2::coin::CoinMetadata`. A type without
+    /// type parameters matches every instantiation of the type.
+    #[prost(string, optional, tag = "2")]
+    pub event_type: ::core::option::Option<::prost::alloc::string::String>,
+    /// Package of the module that emitted the event.
+    #[prost(string, optional, tag = "3")]
+    pub package: ::core::option::Option<::prost::alloc::string::String>,
+    /// Name of the module that emitted the event. Requires `package`.
+    #[prost(string, optional, tag = "4")]
+    pub module: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// Matches transactions on all of the criteria that are set.
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct TransactionFilter {
+    /// Sender of the transaction.
+    #[prost(string, optional, tag = "1")]
+    pub sender: ::core::option::Option<::prost::alloc::string::String>,
+    /// Package of a function called by the transaction.
+    #[prost(string, optional, tag = "2")]
+    pub package: ::core::option::Option<::prost::alloc::string::String>,
+    /// Name of the module of a function called by the transaction. Requires
+    /// `package`.
+    #[prost(string, optional, tag = "3")]
+    pub module: ::core::option::Option<::prost::alloc::string::String>,
+    /// Object created, mutated, unwrapped, wrapped or deleted by the transaction.
+    #[prost(string, optional, tag = "4")]
+    pub affected_object: ::core::option::Option<::prost::alloc::string::String>,
+    /// Sender of the transaction, or address owning an object created, mutated
+    /// or unwrapped by the transaction. Only owners after the transaction are
+    /// considered: an address whose object was transferred away, wrapped or
+    /// deleted by a transaction it did not send is not matched.
+    #[prost(string, optional, tag = "5")]
+    pub affected_address: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SubscribeEventsRequest {
+    /// Events matching any of the filters are returned. All events are returned
+    /// when no filter is given. (max: 16)
+    #[prost(message, repeated, tag = "1")]
+    pub filters: ::prost::alloc::vec::Vec<EventFilter>,
+    /// Resume the subscription after the event with this cursor, taken from a
+    /// previous response. Mutually exclusive with `start_checkpoint`.
+    ///
+    /// Events that were already executed are read from the store, so the
+    /// checkpoint of the cursor must not be pruned yet.
+    #[prost(bytes = "vec", optional, tag = "2")]
+    pub cursor: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// Inclusive checkpoint to start from. Must not be pruned yet, nor more than
+    /// 10,000 checkpoints behind the latest checkpoint. When neither `cursor`
+    /// nor `start_checkpoint` is set, the subscription starts with the next
+    /// executed checkpoint.
+    #[prost(uint64, optional, tag = "3")]
+    pub start_checkpoint: ::core::option::Option<u64>,
+    /// Mask specifying which fields of the events to return.
+    /// (default: "package_id,module,sender,event_type,contents")
+    #[prost(message, optional, tag = "4")]
+    pub read_mask: ::core::option::Option<::prost_types::FieldMask>,
+}
+#[non_exhaustive]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeEventsResponse {
+    /// Cursor to resume the subscription after this event.
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub cursor: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// Checkpoint that includes the transaction which emitted the event.
+    #[prost(uint64, optional, tag = "2")]
+    pub checkpoint: ::core::option::Option<u64>,
+    /// Digest of the transaction which emitted the event.
+    #[prost(string, optional, tag = "3")]
+    pub transaction_digest: ::core::option::Option<::prost::alloc::string::String>,
+    /// Index of the event within the events of the transaction.
+    #[prost(uint32, optional, tag = "4")]
+    pub event_index: ::core::option::Option<u32>,
+    #[prost(message, optional, tag = "5")]
+    pub event: ::core::option::Option<::sui_rpc::proto::sui::rpc::v2::Event>,
+}
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct SubscribeTransactionsRequest {
+    /// Transactions matching any of the filters are returned. All transactions
+    /// are returned when no filter is given. (max: 16)
+    #[prost(message, repeated, tag = "1")]
+    pub filters: ::prost::alloc::vec::Vec<TransactionFilter>,
+    /// Resume the subscription after the transaction with this cursor, taken
+    /// from a previous response. Mutually exclusive with `start_checkpoint`.
+    ///
+    /// Transactions that were already executed are read from the store, so the
+    /// checkpoint of the cursor must not be pruned yet.
+    #[prost(bytes = "vec", optional, tag = "2")]
+    pub cursor: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// Inclusive checkpoint to start from. Must not be pruned yet, nor more than
+    /// 10,000 checkpoints behind the latest checkpoint. When neither `cursor`
+    /// nor `start_checkpoint` is set, the subscription starts with the next
+    /// executed checkpoint.
+    #[prost(uint64, optional, tag = "3")]
+    pub start_checkpoint: ::core::option::Option<u64>,
+    /// Mask specifying which fields of the transactions to return.
+    /// (default: "digest")
+    #[prost(message, optional, tag = "4")]
+    pub read_mask: ::core::option::Option<::prost_types::FieldMask>,
+}
+#[non_exhaustive]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeTransactionsResponse {
+    /// Cursor to resume the subscription after this transaction.
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub cursor: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(message, optional, tag = "2")]
+    pub transaction: ::core::option::Option<
+        ::sui_rpc::proto::sui::rpc::v2::ExecutedTransaction,
+    >,
+}
+/// Generated client implementations.
+pub mod subscription_service_client {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// SubscriptionService streams events and transactions from newly executed
+    /// checkpoints, filtered on the server.
+    ///
+    /// A subscription that falls too far behind newly executed checkpoints ends
+    /// with an ABORTED status, whose `google.rpc.ErrorInfo` carries the cursor to
+    /// resume from, base64 encoded, under the `cursor` metadata key.
+    #[derive(Debug, Clone)]
+    pub struct SubscriptionServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl SubscriptionServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> SubscriptionServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::Body>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> SubscriptionServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::Body>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::Body>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::Body>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
+        {
+            SubscriptionServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// Subscribe to the events matching any of the given filters.
+        pub async fn subscribe_events(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeEventsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SubscribeEventsResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/sui.rpc.alpha.SubscriptionService/SubscribeEvents",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("sui.rpc.alpha.SubscriptionService", "SubscribeEvents"),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Subscribe to the transactions matching any of the given filters.
+        pub async fn subscribe_transactions(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeTransactionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SubscribeTransactionsResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic_prost::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/sui.rpc.alpha.SubscriptionService/SubscribeTransactions",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("sui.rpc.alpha.SubscriptionService", "SubscribeTransactions"),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod subscription_service_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with SubscriptionServiceServer.
+    #[async_trait]
+    pub trait SubscriptionService: std::marker::Send + std::marker::Sync + 'static {
+        /// Server streaming response type for the SubscribeEvents method.
+        type SubscribeEventsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::SubscribeEventsResponse, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Subscribe to the events matching any of the given filters.
+        async fn subscribe_events(
+            &self,
+            request: tonic::Request<super::SubscribeEventsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SubscribeEventsStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the SubscribeTransactions method.
+        type SubscribeTransactionsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::SubscribeTransactionsResponse, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Subscribe to the transactions matching any of the given filters.
+        async fn subscribe_transactions(
+            &self,
+            request: tonic::Request<super::SubscribeTransactionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SubscribeTransactionsStream>,
+            tonic::Status,
+        >;
+    }
+    /// SubscriptionService streams events and transactions from newly executed
+    /// checkpoints, filtered on the server.
+    ///
+    /// A subscription that falls too far behind newly executed checkpoints ends
+    /// with an ABORTED status, whose `google.rpc.ErrorInfo` carries the cursor to
+    /// resume from, base64 encoded, under the `cursor` metadata key.
+    #[derive(Debug)]
+    pub struct SubscriptionServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> SubscriptionServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for SubscriptionServiceServer<T>
+    where
+        T: SubscriptionService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/sui.rpc.alpha.SubscriptionService/SubscribeEvents" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeEventsSvc<T: SubscriptionService>(pub Arc<T>);
+                    impl<
+                        T: SubscriptionService,
+                    > tonic::server::ServerStreamingService<super::SubscribeEventsRequest>
+                    for SubscribeEventsSvc<T> {
+                        type Response = super::SubscribeEventsResponse;
+                        type ResponseStream = T::SubscribeEventsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeEventsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SubscriptionService>::subscribe_events(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SubscribeEventsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sui.rpc.alpha.SubscriptionService/SubscribeTransactions" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeTransactionsSvc<T: SubscriptionService>(pub Arc<T>);
+                    impl<
+                        T: SubscriptionService,
+                    > tonic::server::ServerStreamingService<super::SubscribeTransactionsRequest>
+                    for SubscribeTransactionsSvc<T> {
+                        type Response = super::SubscribeTransactionsResponse;
+                        type ResponseStream = T::SubscribeTransactionsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeTransactionsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SubscriptionService>::subscribe_transactions(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SubscribeTransactionsSvc(inner);
+                        let codec = tonic_prost::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(
+                            tonic::body::Body::default(),
+                        );
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
+            }
+        }
+    }
+    impl<T> Clone for SubscriptionServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "sui.rpc.alpha.SubscriptionService";
+    impl<T> tonic::server::NamedService for SubscriptionServiceServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Object filters for partial restores from formal snapshots.
+//!
+//! A filtered restore still downloads every object file of the snapshot, and still checks each
+//! of them against its reference file and the references against the root state hash, but only
+//! writes the objects that match the filter to the local store.
+
+use move_core_types::language_storage::StructTag;
+use std::collections::BTreeSet;
+use sui_core::authority::authority_store_tables::LiveObject;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::object::{Object, Owner};
+
+/// Selects the objects to restore. An object is restored if it matches any of the types, owners
+/// or packages of the filter.
+#[derive(Clone, Debug, Default)]
+pub struct ObjectFilter {
+    /// Types of Move objects to restore. A type without type parameters matches all of its
+    /// instantiations.
+    types: Vec<StructTag>,
+    /// Owners whose objects to restore. For objects owned by another object, e.g. dynamic
+    /// fields, the owner is the ID of the parent object.
+    owners: BTreeSet<SuiAddress>,
+    /// Packages to restore, together with all the Move objects whose type they define.
+    packages: BTreeSet<ObjectID>,
+}
+
+impl ObjectFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_type(mut self, type_: StructTag) -> Self {
+        self.types.push(type_);
+        self
+    }
+
+    pub fn with_owner(mut self, owner: SuiAddress) -> Self {
+        self.owners.insert(owner);
+        self
+    }
+
+    pub fn with_package(mut self, package: ObjectID) -> Self {
+        self.packages.insert(package);
+        self
+    }
+
+    /// Whether the filter has no criteria, and so matches no object.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.owners.is_empty() && self.packages.is_empty()
+    }
+
+    pub fn matches(&self, object: &Object) -> bool {
+        if object.is_package() {
+            return self.packages.contains(&object.id());
+        }
+
+        let owner = match &object.owner {
+            Owner::AddressOwner(address)
+            | Owner::ObjectOwner(address)
+            | Owner::ConsensusAddressOwner { owner: address, .. } => Some(address),
+            Owner::Shared { .. } | Owner::Immutable => None,
+        };
+        if owner.is_some_and(|owner| self.owners.contains(owner)) {
+            return true;
+        }
+
+        let Some(tag) = object.struct_tag() else {
+            return false;
+        };
+        self.packages.contains(&ObjectID::from(tag.address))
+            || self.types.iter().any(|type_| {
+                if type_.type_params.is_empty() {
+                    type_.address == tag.address
+                        && type_.module == tag.module
+                        && type_.name == tag.name
+                } else {
+                    *type_ == tag
+                }
+            })
+    }
+
+    /// Wrapped object tombstones carry no type or owner, and never match.
+    pub fn matches_live_object(&self, object: &LiveObject) -> bool {
+        match object {
+            LiveObject::Normal(object) => self.matches(object),
+            LiveObject::Wrapped(_) => false,
+        }
+    }
+}
@@ -5,10 +5,13 @@
 #[cfg(test)]
 mod tests;
 
+pub mod filter;
 pub mod reader;
 pub mod uploader;
 mod writer;
 
+pub use writer::StateSnapshotWriterV1;
+
 use anyhow::Result;
 use fastcrypto::hash::MultisetHash;
 use indicatif::MultiProgress;
@@ -72,6 +75,12 @@ use tokio::time::Instant;
 ///     - epoch_1/
 ///       - 1_1.obj
 ///       - ...
+///     - delta/
+///       - epoch_2/
+///         - 1_1.obj
+///         - 1_1.ref
+///         - 1_1.del
+///         - MANIFEST
 ///
 /// Object File Disk Format
 ///┌──────────────────────────────┐
@@ -115,8 +124,25 @@ use tokio::time::Instant;
 ///├──────────────────────────────┤
 ///│      sha3 <32 bytes>         │
 ///└──────────────────────────────┘
+///
+/// Delta Snapshots
+/// A delta snapshot of an epoch only holds the objects that were created or mutated since a base
+/// snapshot, whose epoch is recorded in its MANIFEST. The base can itself be a delta, so restoring
+/// a delta means restoring the full snapshot at the root of its chain, then every delta in order.
+/// Delta snapshots are stored under delta/epoch_<N>/ rather than epoch_<N>/, so that consumers of
+/// full snapshots never come across one. The base of a delta is the full snapshot of its base
+/// epoch if there is one, and the delta snapshot of that epoch otherwise.
+/// On top of the usual *.obj and *.ref files, a delta has DELETION files, named
+/// 1_<partition_number>.del, listing the object references of the base that are no longer live,
+/// because the object was deleted, wrapped or mutated. They have the same format as a REFERENCE
+/// file, apart from their magic (0xDE1E7ED0), and are split into partitions like object files.
+/// The root state hash of a delta is the one of its base, minus the deleted references, plus the
+/// references of the delta. Object references are written in ascending order of object ID, in
+/// REFERENCE and DELETION files alike, which is what allows a delta to be computed against its
+/// base without holding the base in memory.
 const OBJECT_FILE_MAGIC: u32 = 0x00B7EC75;
 const REFERENCE_FILE_MAGIC: u32 = 0xDEADBEEF;
+const DELETION_FILE_MAGIC: u32 = 0xDE1E7ED0;
 const MANIFEST_FILE_MAGIC: u32 = 0x00C0FFEE;
 const MAGIC_BYTES: usize = 4;
 const SNAPSHOT_VERSION_BYTES: usize = 1;
@@ -125,6 +151,7 @@ const PADDING_BYTES: usize = 3;
 const MANIFEST_FILE_HEADER_BYTES: usize =
     MAGIC_BYTES + SNAPSHOT_VERSION_BYTES + ADDRESS_LENGTH_BYTES + PADDING_BYTES;
 const FILE_MAX_BYTES: usize = 128 * 1024 * 1024;
+const DELTA_DIR: &str = "delta";
 const OBJECT_ID_BYTES: usize = ObjectID::LENGTH;
 const SEQUENCE_NUM_BYTES: usize = 8;
 const OBJECT_DIGEST_BYTES: usize = 32;
@@ -143,6 +170,7 @@ const FILE_METADATA_BYTES: usize =
 pub enum FileType {
     Object = 0,
     Reference,
+    Deletion,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -163,6 +191,9 @@ impl FileMetadata {
             FileType::Reference => {
                 dir_path.child(&*format!("{}_{}.ref", self.bucket_num, self.part_num))
             }
+            FileType::Deletion => {
+                dir_path.child(&*format!("{}_{}.del", self.bucket_num, self.part_num))
+            }
         }
     }
     pub fn local_file_path(&self, root_path: &std::path::Path, dir_path: &Path) -> Result<PathBuf> {
@@ -178,30 +209,52 @@ pub struct ManifestV1 {
     pub epoch: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct DeltaManifestV1 {
+    pub snapshot_version: u8,
+    pub address_length: u64,
+    pub file_metadata: Vec<FileMetadata>,
+    pub epoch: u64,
+    /// Epoch of the snapshot this delta applies to.
+    pub base_epoch: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Manifest {
     V1(ManifestV1),
+    DeltaV1(DeltaManifestV1),
 }
 
 impl Manifest {
     pub fn snapshot_version(&self) -> u8 {
         match self {
             Self::V1(manifest) => manifest.snapshot_version,
+            Self::DeltaV1(manifest) => manifest.snapshot_version,
         }
     }
     pub fn address_length(&self) -> u64 {
         match self {
             Self::V1(manifest) => manifest.address_length,
+            Self::DeltaV1(manifest) => manifest.address_length,
         }
     }
     pub fn file_metadata(&self) -> &Vec<FileMetadata> {
         match self {
             Self::V1(manifest) => &manifest.file_metadata,
+            Self::DeltaV1(manifest) => &manifest.file_metadata,
         }
     }
     pub fn epoch(&self) -> u64 {
         match self {
             Self::V1(manifest) => manifest.epoch,
+            Self::DeltaV1(manifest) => manifest.epoch,
+        }
+    }
+    /// Epoch of the snapshot a delta snapshot applies to, or `None` for a full snapshot.
+    pub fn base_epoch(&self) -> Option<u64> {
+        match self {
+            Self::V1(_) => None,
+            Self::DeltaV1(manifest) => Some(manifest.base_epoch),
         }
     }
 }
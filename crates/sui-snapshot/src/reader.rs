@@ -1,24 +1,26 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::filter::ObjectFilter;
 use crate::{
-    FileMetadata, FileType, MAGIC_BYTES, MANIFEST_FILE_MAGIC, Manifest, OBJECT_FILE_MAGIC,
-    OBJECT_ID_BYTES, OBJECT_REF_BYTES, REFERENCE_FILE_MAGIC, SEQUENCE_NUM_BYTES, SHA3_BYTES,
+    DELETION_FILE_MAGIC, DELTA_DIR, FileMetadata, FileType, MAGIC_BYTES, MANIFEST_FILE_MAGIC,
+    Manifest, OBJECT_FILE_MAGIC, OBJECT_ID_BYTES, OBJECT_REF_BYTES, REFERENCE_FILE_MAGIC,
+    SEQUENCE_NUM_BYTES, SHA3_BYTES,
 };
 use anyhow::{Context, Result, anyhow};
 use byteorder::{BigEndian, ReadBytesExt};
 use bytes::{Buf, Bytes};
 use fastcrypto::hash::MultisetHash;
 use fastcrypto::hash::{HashFunction, Sha3_256};
-use futures::future::{AbortRegistration, Abortable};
+use futures::future::{AbortHandle, AbortRegistration, Abortable};
 use futures::{StreamExt, TryStreamExt};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use integer_encoding::VarIntReader;
+use itertools::Itertools;
 use object_store::path::Path;
 use std::collections::BTreeMap;
 use std::fs;
-use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::io::Read;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -29,11 +31,13 @@ use sui_core::authority::AuthorityStore;
 use sui_core::authority::authority_store_tables::{AuthorityPerpetualTables, LiveObject};
 use sui_indexer_alt_framework::task::TrySpawnStreamExt;
 use sui_storage::blob::{Blob, BlobEncoding};
+use sui_storage::compute_sha3_checksum_for_bytes;
 use sui_storage::object_store::http::HttpDownloaderBuilder;
 use sui_storage::object_store::util::{copy_files, path_to_filesystem};
 use sui_storage::object_store::{ObjectStoreGetExt, ObjectStoreListExt, ObjectStorePutExt};
 use sui_types::base_types::{ObjectDigest, ObjectID, ObjectRef, SequenceNumber};
 use sui_types::global_state_hash::GlobalStateHash;
+use sui_types::messages_checkpoint::ECMHLiveObjectSetDigest;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
 use tokio::time::Instant;
@@ -50,6 +54,12 @@ pub struct StateSnapshotReaderV1 {
     local_object_store: Arc<dyn ObjectStorePutExt>,
     ref_files: BTreeMap<u32, BTreeMap<u32, FileMetadata>>,
     object_files: BTreeMap<u32, BTreeMap<u32, FileMetadata>>,
+    /// Only present in delta snapshots.
+    deletion_files: BTreeMap<u32, BTreeMap<u32, FileMetadata>>,
+    /// Epoch of the snapshot this one applies to, if it is a delta snapshot.
+    base_epoch: Option<u64>,
+    /// If set, only the objects matching the filter are restored.
+    filter: Option<ObjectFilter>,
     m: MultiProgress,
     concurrency: usize,
     max_retries: usize,
@@ -129,11 +139,35 @@ impl StateSnapshotReaderV1 {
         skip_reset_local_store: bool,
         max_retries: usize,
     ) -> Result<Self> {
-        let remote_object_store = if remote_store_config.no_sign_request {
-            remote_store_config.make_http()?
-        } else {
-            remote_store_config.make().map(Arc::new)?
-        };
+        // Try to download MANIFEST from standard location first, then archive
+        Self::new_with_dirs(
+            epoch,
+            vec![
+                Path::from(format!("epoch_{}", epoch)),
+                Path::from(format!("archive/epoch_{}", epoch)),
+            ],
+            remote_store_config,
+            local_store_config,
+            download_concurrency,
+            m,
+            skip_reset_local_store,
+            max_retries,
+        )
+        .await
+    }
+
+    /// Reads the snapshot of `epoch` from the first of `remote_epoch_dirs` that has a MANIFEST.
+    async fn new_with_dirs(
+        epoch: u64,
+        remote_epoch_dirs: Vec<Path>,
+        remote_store_config: &ObjectStoreConfig,
+        local_store_config: &ObjectStoreConfig,
+        download_concurrency: NonZeroUsize,
+        m: MultiProgress,
+        skip_reset_local_store: bool,
+        max_retries: usize,
+    ) -> Result<Self> {
+        let remote_object_store = Self::remote_object_store(remote_store_config)?;
         let local_object_store: Arc<dyn ObjectStorePutExt> =
             local_store_config.make().map(Arc::new)?;
         let local_object_store_list: Arc<dyn ObjectStoreListExt> =
@@ -155,45 +189,33 @@ impl StateSnapshotReaderV1 {
             fs::create_dir_all(&local_epoch_dir_absolute_path)?;
         }
 
-        // Try to download MANIFEST from standard location first, then archive
-        let standard_epoch_dir = Path::from(format!("epoch_{}", epoch));
-        let archive_epoch_dir = Path::from(format!("archive/epoch_{}", epoch));
-
-        let standard_manifest_path = standard_epoch_dir.child("MANIFEST");
-        let archive_manifest_path = archive_epoch_dir.child("MANIFEST");
-
         // We always download to local epoch dir's MANIFEST
         let local_manifest_path = local_epoch_dir_path.child("MANIFEST");
 
-        let (remote_epoch_prefix, manifest_download_result) = match Self::copy_file_with_retry(
-            &standard_manifest_path,
-            &local_manifest_path,
-            &remote_object_store,
-            &local_object_store,
-            max_retries,
-        )
-        .await
-        {
-            Ok(_) => (standard_epoch_dir, Ok(())),
-            Err(_) => {
-                // Try archive
-                match Self::copy_file_with_retry(
-                    &archive_manifest_path,
-                    &local_manifest_path,
-                    &remote_object_store,
-                    &local_object_store,
-                    max_retries,
-                )
-                .await
-                {
-                    Ok(_) => (archive_epoch_dir, Ok(())),
-                    Err(e) => (standard_epoch_dir, Err(e)), // Return standard dir but error
+        let mut remote_epoch_prefix = None;
+        let mut manifest_download_error = None;
+        for remote_epoch_dir in remote_epoch_dirs {
+            match Self::copy_file_with_retry(
+                &remote_epoch_dir.child("MANIFEST"),
+                &local_manifest_path,
+                &remote_object_store,
+                &local_object_store,
+                max_retries,
+            )
+            .await
+            {
+                Ok(_) => {
+                    remote_epoch_prefix = Some(remote_epoch_dir);
+                    break;
                 }
+                Err(e) => manifest_download_error = Some(e),
             }
+        }
+        let Some(remote_epoch_prefix) = remote_epoch_prefix else {
+            return Err(manifest_download_error
+                .unwrap_or_else(|| anyhow!("No directory to read snapshot for epoch {}", epoch)));
         };
 
-        manifest_download_result?;
-
         let manifest = Self::read_manifest(path_to_filesystem(
             local_staging_dir_root.clone(),
             &local_manifest_path,
@@ -213,6 +235,7 @@ impl StateSnapshotReaderV1 {
         }
         let mut object_files = BTreeMap::new();
         let mut ref_files = BTreeMap::new();
+        let mut deletion_files = BTreeMap::new();
         for file_metadata in manifest.file_metadata() {
             match file_metadata.file_type {
                 FileType::Object => {
@@ -227,6 +250,12 @@ impl StateSnapshotReaderV1 {
                         .or_insert_with(BTreeMap::new);
                     entry.insert(file_metadata.part_num, file_metadata.clone());
                 }
+                FileType::Deletion => {
+                    let entry = deletion_files
+                        .entry(file_metadata.bucket_num)
+                        .or_insert_with(BTreeMap::new);
+                    entry.insert(file_metadata.part_num, file_metadata.clone());
+                }
             }
         }
 
@@ -246,7 +275,7 @@ impl StateSnapshotReaderV1 {
             None
         };
 
-        for entry in ref_files.values() {
+        for entry in ref_files.values().chain(deletion_files.values()) {
             for file_metadata in entry.values() {
                 let dest = file_metadata.file_path(&local_epoch_dir_path);
                 if existing_files
@@ -285,6 +314,9 @@ impl StateSnapshotReaderV1 {
             local_object_store,
             ref_files,
             object_files,
+            deletion_files,
+            base_epoch: manifest.base_epoch(),
+            filter: None,
             m,
             concurrency: download_concurrency.get(),
             max_retries,
@@ -292,11 +324,157 @@ impl StateSnapshotReaderV1 {
         })
     }
 
+    fn remote_object_store(
+        remote_store_config: &ObjectStoreConfig,
+    ) -> Result<Arc<dyn ObjectStoreGetExt>> {
+        Ok(if remote_store_config.no_sign_request {
+            remote_store_config.make_http()?
+        } else {
+            remote_store_config.make().map(Arc::new)?
+        })
+    }
+
+    /// Epoch of the snapshot this one applies to, if it is a delta snapshot.
+    pub fn base_epoch(&self) -> Option<u64> {
+        self.base_epoch
+    }
+
+    /// Restores only the objects matching `filter`. All object files are still downloaded and
+    /// checked against the reference files, which are accumulated in full, so the root state
+    /// hash of the snapshot can be verified as for a full restore.
+    pub fn with_filter(mut self, filter: ObjectFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
     pub async fn read(
         &mut self,
         perpetual_db: &AuthorityPerpetualTables,
         abort_registration: AbortRegistration,
         sender: Option<tokio::sync::mpsc::Sender<(GlobalStateHash, u64)>>,
+    ) -> Result<()> {
+        if let Some(base_epoch) = self.base_epoch {
+            return Err(anyhow!(
+                "Snapshot for epoch {} is a delta over epoch {}, restore it with restore_with_deltas",
+                self.epoch,
+                base_epoch
+            ));
+        }
+        self.read_objects(perpetual_db, abort_registration, sender)
+            .await
+    }
+
+    /// Restores the snapshot of `epoch` into `perpetual_db`, which is the full snapshot of the
+    /// epoch if there is one, and its delta snapshot otherwise. For a delta snapshot, the full
+    /// snapshot at the root of its chain is restored first, then every delta in order, deleting
+    /// the objects that each delta no longer has.
+    ///
+    /// Returns the root state hash of the restored live object set, computed from the object
+    /// references of the snapshots, and the number of live objects. If `expected_root_state_hash`
+    /// is given, e.g. the epoch's state commitment, the restore fails when the two don't match.
+    pub async fn restore_with_deltas(
+        epoch: u64,
+        remote_store_config: &ObjectStoreConfig,
+        local_store_config: &ObjectStoreConfig,
+        download_concurrency: NonZeroUsize,
+        m: MultiProgress,
+        max_retries: usize,
+        perpetual_db: &AuthorityPerpetualTables,
+        expected_root_state_hash: Option<ECMHLiveObjectSetDigest>,
+    ) -> Result<(GlobalStateHash, u64)> {
+        // Walk back from the requested epoch to the full snapshot at the root of the chain
+        let remote_object_store = Self::remote_object_store(remote_store_config)?;
+        let mut chain = vec![];
+        let mut next_epoch = Some(epoch);
+        while let Some(epoch) = next_epoch {
+            let (remote_epoch_dir, _) = find_snapshot_dir(&remote_object_store, epoch).await?;
+            let reader = Self::new_with_dirs(
+                epoch,
+                vec![remote_epoch_dir],
+                remote_store_config,
+                local_store_config,
+                download_concurrency,
+                m.clone(),
+                false, // skip_reset_local_store
+                max_retries,
+            )
+            .await?;
+            next_epoch = reader.base_epoch;
+            if let Some(base_epoch) = next_epoch
+                && base_epoch >= epoch
+            {
+                return Err(anyhow!(
+                    "Delta snapshot for epoch {epoch} has a base epoch {base_epoch} that is not before it"
+                ));
+            }
+            chain.push(reader);
+        }
+
+        // Verify the combined root state hash from the reference files before restoring any object
+        chain.reverse();
+        let mut root_state_hash = GlobalStateHash::default();
+        let mut num_live_objects = 0u64;
+        for reader in &chain {
+            for object_ref in reader.deletion_refs()? {
+                root_state_hash.remove(object_ref.2);
+                num_live_objects = num_live_objects.saturating_sub(1);
+            }
+            for (bucket, parts) in &reader.ref_files {
+                for part in parts.keys() {
+                    for object_ref in reader.ref_iter(*bucket, *part)? {
+                        root_state_hash.insert(object_ref.2);
+                        num_live_objects += 1;
+                    }
+                }
+            }
+        }
+        if let Some(expected) = expected_root_state_hash {
+            let restored = ECMHLiveObjectSetDigest::from(root_state_hash.digest());
+            if restored != expected {
+                return Err(anyhow!(
+                    "End of epoch {} root state digest {} does not match root state hash {} \
+                    of the base and delta snapshots",
+                    epoch,
+                    expected.digest,
+                    restored.digest
+                ));
+            }
+        }
+
+        for mut reader in chain {
+            info!(
+                "Restoring state snapshot for epoch {} (base epoch: {:?})",
+                reader.epoch, reader.base_epoch
+            );
+            AuthorityStore::bulk_remove_live_objects(perpetual_db, reader.deletion_refs()?)?;
+            let (_abort_handle, abort_registration) = AbortHandle::new_pair();
+            reader
+                .read_objects(perpetual_db, abort_registration, None)
+                .await?;
+        }
+        Ok((root_state_hash, num_live_objects))
+    }
+
+    /// Object references of the base that are no longer live, for a delta snapshot.
+    fn deletion_refs(&self) -> Result<impl Iterator<Item = ObjectRef> + use<>> {
+        let mut deleted_refs = vec![];
+        for parts in self.deletion_files.values() {
+            for file_metadata in parts.values() {
+                deleted_refs.push(ObjectRefIter::new(
+                    file_metadata,
+                    self.local_staging_dir_root.clone(),
+                    self.epoch_dir(),
+                )?);
+            }
+        }
+        Ok(deleted_refs.into_iter().flatten())
+    }
+
+    async fn read_objects(
+        &mut self,
+        perpetual_db: &AuthorityPerpetualTables,
+        abort_registration: AbortRegistration,
+        sender: Option<tokio::sync::mpsc::Sender<(GlobalStateHash, u64)>>,
     ) -> Result<()> {
         // This computes and stores the sha3 digest of object references in REFERENCE file for each
         // bucket partition. When downloading objects, we will match sha3 digest of object references
@@ -506,6 +684,7 @@ impl StateSnapshotReaderV1 {
             ),
         );
         let obj_progress_bar_clone = obj_progress_bar.clone();
+        let filter = self.filter.clone();
         let instant = Instant::now();
         let downloaded_bytes = AtomicUsize::new(0);
 
@@ -541,10 +720,15 @@ impl StateSnapshotReaderV1 {
                         let bytes_len = bytes.len();
                         let result: Result<(), anyhow::Error> =
                             LiveObjectIter::new(&file_metadata, bytes).map(|obj_iter| {
-                                AuthorityStore::bulk_insert_live_objects(
+                                AuthorityStore::bulk_insert_filtered_live_objects(
                                     perpetual_db,
                                     obj_iter,
                                     &sha3_digest,
+                                    |object| {
+                                        filter
+                                            .as_ref()
+                                            .is_none_or(|filter| filter.matches_live_object(object))
+                                    },
                                 )
                                 .expect("Failed to insert live objects");
                             });
@@ -620,23 +804,21 @@ impl StateSnapshotReaderV1 {
         Path::from(format!("epoch_{}", self.epoch))
     }
 
-    fn read_manifest(path: PathBuf) -> anyhow::Result<Manifest> {
-        let manifest_file = File::open(path)?;
-        let manifest_file_size = manifest_file.metadata()?.len() as usize;
-        let mut manifest_reader = BufReader::new(manifest_file);
-        manifest_reader.rewind()?;
-        let magic = manifest_reader.read_u32::<BigEndian>()?;
+    pub(crate) fn read_manifest(path: PathBuf) -> anyhow::Result<Manifest> {
+        Self::parse_manifest(&fs::read(path)?)
+    }
+
+    pub(crate) fn parse_manifest(bytes: &[u8]) -> anyhow::Result<Manifest> {
+        if bytes.len() < MAGIC_BYTES + SHA3_BYTES {
+            return Err(anyhow!("Manifest is too short: {} bytes", bytes.len()));
+        }
+        let (content_buf, sha3_digest) = bytes.split_at(bytes.len() - SHA3_BYTES);
+        let magic = (&content_buf[..MAGIC_BYTES]).read_u32::<BigEndian>()?;
         if magic != MANIFEST_FILE_MAGIC {
             return Err(anyhow!("Unexpected magic byte: {}", magic));
         }
-        manifest_reader.seek(SeekFrom::End(-(SHA3_BYTES as i64)))?;
-        let mut sha3_digest = [0u8; SHA3_BYTES];
-        manifest_reader.read_exact(&mut sha3_digest)?;
-        manifest_reader.rewind()?;
-        let mut content_buf = vec![0u8; manifest_file_size - SHA3_BYTES];
-        manifest_reader.read_exact(&mut content_buf)?;
         let mut hasher = Sha3_256::default();
-        hasher.update(&content_buf);
+        hasher.update(content_buf);
         let computed_digest = hasher.finalize().digest;
         if computed_digest != sha3_digest {
             return Err(anyhow!(
@@ -645,8 +827,6 @@ impl StateSnapshotReaderV1 {
                 sha3_digest
             ));
         }
-        manifest_reader.rewind()?;
-        manifest_reader.seek(SeekFrom::Start(MAGIC_BYTES as u64))?;
         let manifest = bcs::from_bytes(&content_buf[MAGIC_BYTES..])?;
         Ok(manifest)
     }
@@ -656,6 +836,124 @@ impl StateSnapshotReaderV1 {
     }
 }
 
+/// Reads a formal snapshot which has already been downloaded in full to a local directory, without
+/// access to the remote object store it originally came from.
+///
+/// The directory is expected to have the same layout as the one `StateSnapshotReaderV1` downloads
+/// into, i.e. `<root>/epoch_<epoch>/MANIFEST` alongside all of the `.ref` and `.obj` files it lists.
+pub struct LocalStateSnapshotReaderV1 {
+    epoch: u64,
+    local_staging_dir_root: PathBuf,
+    ref_files: BTreeMap<u32, BTreeMap<u32, FileMetadata>>,
+    object_files: BTreeMap<u32, BTreeMap<u32, FileMetadata>>,
+}
+
+impl LocalStateSnapshotReaderV1 {
+    pub fn new(epoch: u64, local_staging_dir_root: PathBuf) -> Result<Self> {
+        let epoch_dir = Path::from(format!("epoch_{}", epoch));
+        let manifest = StateSnapshotReaderV1::read_manifest(path_to_filesystem(
+            local_staging_dir_root.clone(),
+            &epoch_dir.child("MANIFEST"),
+        )?)?;
+        let snapshot_version = manifest.snapshot_version();
+        if snapshot_version != 1u8 {
+            return Err(anyhow!("Unexpected snapshot version: {}", snapshot_version));
+        }
+        if manifest.epoch() != epoch {
+            return Err(anyhow!("Local manifest is not for epoch: {}", epoch));
+        }
+        if let Some(base_epoch) = manifest.base_epoch() {
+            return Err(anyhow!(
+                "Local snapshot for epoch {} is a delta over epoch {}",
+                epoch,
+                base_epoch
+            ));
+        }
+
+        let mut object_files = BTreeMap::new();
+        let mut ref_files = BTreeMap::new();
+        for file_metadata in manifest.file_metadata() {
+            let files = match file_metadata.file_type {
+                FileType::Object => &mut object_files,
+                FileType::Reference => &mut ref_files,
+                FileType::Deletion => continue,
+            };
+            files
+                .entry(file_metadata.bucket_num)
+                .or_insert_with(BTreeMap::new)
+                .insert(file_metadata.part_num, file_metadata.clone());
+        }
+
+        Ok(Self {
+            epoch,
+            local_staging_dir_root,
+            ref_files,
+            object_files,
+        })
+    }
+
+    /// Calls `f` on every live object in the snapshot, and returns the root state hash of the
+    /// snapshot's live object set.
+    ///
+    /// Each `.obj` file is checked against the object references in its corresponding `.ref` file
+    /// before any of its objects are passed to `f`. The returned hash is computed from the `.ref`
+    /// files, so it can be compared against the end of epoch state commitment to verify the
+    /// snapshot as a whole.
+    pub fn for_each_live_object(
+        &self,
+        mut f: impl FnMut(LiveObject) -> Result<()>,
+    ) -> Result<GlobalStateHash> {
+        let epoch_dir = Path::from(format!("epoch_{}", self.epoch));
+        let mut root_state_hash = GlobalStateHash::default();
+
+        for (bucket, parts) in &self.object_files {
+            for (part, file_metadata) in parts {
+                let ref_file = self
+                    .ref_files
+                    .get(bucket)
+                    .and_then(|parts| parts.get(part))
+                    .context(format!(
+                        "No ref files found for bucket: {bucket}, part: {part}"
+                    ))?;
+
+                let mut ref_hasher = Sha3_256::default();
+                let mut ref_digests = vec![];
+                for object_ref in ObjectRefIter::new(
+                    ref_file,
+                    self.local_staging_dir_root.clone(),
+                    epoch_dir.clone(),
+                )? {
+                    ref_hasher.update(object_ref.2.inner());
+                    ref_digests.push(object_ref.2);
+                }
+
+                let bytes = fs::read(
+                    file_metadata.local_file_path(&self.local_staging_dir_root, &epoch_dir)?,
+                )?;
+                let objects =
+                    LiveObjectIter::new(file_metadata, Bytes::from(bytes))?.collect::<Vec<_>>();
+                let mut obj_hasher = Sha3_256::default();
+                for object in &objects {
+                    obj_hasher.update(object.object_reference().2.inner());
+                }
+
+                if ref_hasher.finalize().digest != obj_hasher.finalize().digest {
+                    return Err(anyhow!(
+                        "Objects in bucket: {bucket}, part: {part} do not match their references"
+                    ));
+                }
+
+                root_state_hash.insert_all(ref_digests);
+                for object in objects {
+                    f(object)?;
+                }
+            }
+        }
+
+        Ok(root_state_hash)
+    }
+}
+
 pub async fn download_bytes(
     remote_object_store: Arc<dyn ObjectStoreGetExt>,
     file_metadata: &FileMetadata,
@@ -705,7 +1003,140 @@ pub async fn download_bytes(
     (bytes, sha3_digest)
 }
 
-/// An iterator over all object refs in a .ref file.
+/// Finds the directory of the snapshot of `epoch` in `store` and reads its MANIFEST. The full
+/// snapshot of the epoch, possibly archived, is preferred over its delta snapshot.
+pub(crate) async fn find_snapshot_dir<S: ObjectStoreGetExt>(
+    store: &S,
+    epoch: u64,
+) -> Result<(Path, Manifest)> {
+    for epoch_dir in [
+        Path::from(format!("epoch_{}", epoch)),
+        Path::from(format!("archive/epoch_{}", epoch)),
+        Path::from(format!("{}/epoch_{}", DELTA_DIR, epoch)),
+    ] {
+        let Ok(bytes) = store.get_bytes(&epoch_dir.child("MANIFEST")).await else {
+            continue;
+        };
+        let manifest = StateSnapshotReaderV1::parse_manifest(&bytes)?;
+        if manifest.epoch() != epoch {
+            return Err(anyhow!(
+                "Manifest in {} is not for epoch: {}",
+                epoch_dir,
+                epoch
+            ));
+        }
+        return Ok((epoch_dir, manifest));
+    }
+    Err(anyhow!("No state snapshot found for epoch: {}", epoch))
+}
+
+/// The reference and deletion files of the snapshot of an epoch, and of every snapshot it is a
+/// delta over, downloaded to a local directory. Object files are not downloaded.
+pub(crate) struct SnapshotChain {
+    local_root: PathBuf,
+    /// From the full snapshot at the root of the chain to the snapshot of the requested epoch.
+    snapshots: Vec<(Path, Manifest)>,
+}
+
+impl SnapshotChain {
+    pub(crate) async fn download<S: ObjectStoreGetExt>(
+        store: &S,
+        local_root: PathBuf,
+        epoch: u64,
+    ) -> Result<Self> {
+        let mut snapshots = vec![];
+        let mut next_epoch = Some(epoch);
+        while let Some(epoch) = next_epoch {
+            let (epoch_dir, manifest) = find_snapshot_dir(store, epoch).await?;
+            next_epoch = manifest.base_epoch();
+            if next_epoch.is_some_and(|base_epoch| base_epoch >= epoch) {
+                return Err(anyhow!(
+                    "Delta snapshot for epoch {epoch} is not over an earlier epoch"
+                ));
+            }
+            for file_metadata in manifest
+                .file_metadata()
+                .iter()
+                .filter(|file_metadata| file_metadata.file_type != FileType::Object)
+            {
+                let bytes = store
+                    .get_bytes(&file_metadata.file_path(&epoch_dir))
+                    .await?;
+                if compute_sha3_checksum_for_bytes(bytes.clone())? != file_metadata.sha3_digest {
+                    return Err(anyhow!(
+                        "Checksum mismatch for {}",
+                        file_metadata.file_path(&epoch_dir)
+                    ));
+                }
+                let local_path = file_metadata.local_file_path(&local_root, &epoch_dir)?;
+                if let Some(parent) = local_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(local_path, bytes)?;
+            }
+            snapshots.push((epoch_dir, manifest));
+        }
+        snapshots.reverse();
+        Ok(Self {
+            local_root,
+            snapshots,
+        })
+    }
+
+    /// The live object references as of the last snapshot of the chain, in ascending order of
+    /// object ID, streamed from the local files: the references of the full snapshot at the root
+    /// of the chain, minus the deletions of each delta, merged with the references of the delta.
+    pub(crate) fn live_object_refs(&self) -> Result<Box<dyn Iterator<Item = ObjectRef>>> {
+        let mut live_refs: Box<dyn Iterator<Item = ObjectRef>> = Box::new(std::iter::empty());
+        for (epoch_dir, manifest) in &self.snapshots {
+            let deleted_refs = self.sorted_refs(epoch_dir, manifest, FileType::Deletion)?;
+            let refs = self.sorted_refs(epoch_dir, manifest, FileType::Reference)?;
+            live_refs = Box::new(
+                live_refs
+                    .merge_join_by(deleted_refs, |live_ref, deleted_ref| {
+                        live_ref.cmp(deleted_ref)
+                    })
+                    .filter_map(|entry| entry.just_left())
+                    .merge_by(refs, |a, b| a.0 <= b.0),
+            );
+        }
+        Ok(live_refs)
+    }
+
+    /// The object references in the files of a snapshot of `file_type`. Files of the same bucket
+    /// are sorted one after the other, and buckets are merged with each other.
+    fn sorted_refs(
+        &self,
+        epoch_dir: &Path,
+        manifest: &Manifest,
+        file_type: FileType,
+    ) -> Result<impl Iterator<Item = ObjectRef> + use<>> {
+        let mut files: BTreeMap<u32, BTreeMap<u32, &FileMetadata>> = BTreeMap::new();
+        for file_metadata in manifest.file_metadata() {
+            if file_metadata.file_type == file_type {
+                files
+                    .entry(file_metadata.bucket_num)
+                    .or_default()
+                    .insert(file_metadata.part_num, file_metadata);
+            }
+        }
+        let mut buckets = vec![];
+        for parts in files.values() {
+            let mut bucket = vec![];
+            for file_metadata in parts.values() {
+                bucket.push(ObjectRefIter::new(
+                    file_metadata,
+                    self.local_root.clone(),
+                    epoch_dir.clone(),
+                )?);
+            }
+            buckets.push(bucket.into_iter().flatten());
+        }
+        Ok(buckets.into_iter().kmerge_by(|a, b| a.0 < b.0))
+    }
+}
+
+/// An iterator over all object refs in a .ref or .del file.
 pub struct ObjectRefIter {
     reader: Box<dyn Read>,
 }
@@ -713,11 +1144,25 @@ pub struct ObjectRefIter {
 impl ObjectRefIter {
     pub fn new(file_metadata: &FileMetadata, root_path: PathBuf, dir_path: Path) -> Result<Self> {
         let file_path = file_metadata.local_file_path(&root_path, &dir_path)?;
-        let mut reader = file_metadata.file_compression.decompress(&file_path)?;
+        let reader = file_metadata.file_compression.decompress(&file_path)?;
+        Self::with_reader(file_metadata, reader)
+    }
+
+    pub fn from_bytes(file_metadata: &FileMetadata, bytes: Bytes) -> Result<Self> {
+        let reader = file_metadata.file_compression.bytes_decompress(bytes)?;
+        Self::with_reader(file_metadata, reader)
+    }
+
+    fn with_reader(file_metadata: &FileMetadata, mut reader: Box<dyn Read>) -> Result<Self> {
+        let expected_magic = match file_metadata.file_type {
+            FileType::Deletion => DELETION_FILE_MAGIC,
+            _ => REFERENCE_FILE_MAGIC,
+        };
         let magic = reader.read_u32::<BigEndian>()?;
-        if magic != REFERENCE_FILE_MAGIC {
+        if magic != expected_magic {
             Err(anyhow!(
-                "Unexpected magic string in REFERENCE file: {:?}",
+                "Unexpected magic string in {:?} file: {:?}",
+                file_metadata.file_type,
                 magic
             ))
         } else {
@@ -1,10 +1,11 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::FileCompression;
-use crate::reader::StateSnapshotReaderV1;
+use crate::filter::ObjectFilter;
+use crate::reader::{LocalStateSnapshotReaderV1, ObjectRefIter, StateSnapshotReaderV1};
 use crate::uploader::StateSnapshotUploader;
-use crate::writer::StateSnapshotWriterV1;
+use crate::writer::{DeletionFileWriterV1, StateSnapshotWriterV1};
+use crate::{FileCompression, MAGIC_BYTES, OBJECT_REF_BYTES};
 use fastcrypto::hash::MultisetHash;
 use futures::StreamExt;
 use futures::future::AbortHandle;
@@ -14,15 +15,18 @@ use std::collections::HashSet;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 use sui_config::object_storage_config::{ObjectStoreConfig, ObjectStoreType};
-use sui_core::authority::authority_store_tables::AuthorityPerpetualTables;
+use sui_core::authority::AuthorityStore;
+use sui_core::authority::authority_store_tables::{AuthorityPerpetualTables, LiveObject};
 use sui_core::checkpoints::CheckpointStore;
 use sui_core::global_state_hasher::GlobalStateHasher;
 use sui_protocol_config::ProtocolConfig;
 use sui_storage::object_store::ObjectStoreListExt;
-use sui_types::base_types::ObjectID;
+use sui_types::base_types::{ObjectID, SequenceNumber, SuiAddress};
 use sui_types::global_state_hash::GlobalStateHash;
 use sui_types::messages_checkpoint::ECMHLiveObjectSetDigest;
-use sui_types::object::Object;
+use sui_types::object::{Object, Owner};
+use sui_types::parse_sui_struct_tag;
+use sui_types::storage::ObjectKey;
 use tempfile::tempdir;
 
 fn temp_dir() -> std::path::PathBuf {
@@ -214,6 +218,7 @@ async fn test_archive_epoch_if_needed() -> Result<(), anyhow::Error> {
         checkpoint_store,
         chain_identifier,
         30, // archive every 30 epochs
+        0,  // full snapshots only
     )?;
 
     let store = snapshot_store_config.make()?;
@@ -249,6 +254,64 @@ async fn test_archive_epoch_if_needed() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_delta_base_epoch() -> Result<(), anyhow::Error> {
+    let db_path = temp_dir();
+    let local = temp_dir().join("local_dir");
+    let remote = temp_dir().join("remote_dir");
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(local),
+        ..Default::default()
+    };
+    let remote_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(remote),
+        ..Default::default()
+    };
+
+    // Full snapshot at epoch 0
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&db_path, None, None));
+    insert_keys(&perpetual_db, 10)?;
+    let root_state_hash =
+        ECMHLiveObjectSetDigest::from(accumulate_live_object_set(&perpetual_db, true).digest());
+    StateSnapshotWriterV1::new(
+        &local_store_config,
+        &remote_store_config,
+        FileCompression::Zstd,
+        NonZeroUsize::new(1).unwrap(),
+    )
+    .await?
+    .write_internal(0, true, perpetual_db, root_state_hash)
+    .await?;
+
+    let uploader = |full_snapshot_interval_epochs| {
+        StateSnapshotUploader::new(
+            &temp_dir().join("db_checkpoints"),
+            &temp_dir().join("staging"),
+            remote_store_config.clone(),
+            60,
+            &Registry::new(),
+            CheckpointStore::new_for_tests(),
+            sui_types::digests::get_testnet_chain_identifier(),
+            0, // no archival
+            full_snapshot_interval_epochs,
+        )
+    };
+
+    // Full snapshots only
+    assert_eq!(uploader(0)?.delta_base_epoch(1).await, None);
+    assert_eq!(uploader(1)?.delta_base_epoch(1).await, None);
+
+    // A full snapshot every 3 epochs, and deltas over the previous epoch in between, if it has a
+    // snapshot.
+    let uploader = uploader(3)?;
+    assert_eq!(uploader.delta_base_epoch(1).await, Some(0));
+    assert_eq!(uploader.delta_base_epoch(2).await, None);
+    assert_eq!(uploader.delta_base_epoch(3).await, None);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_snapshot_restore_from_archive() -> Result<(), anyhow::Error> {
     let db_path = temp_dir();
@@ -311,3 +374,352 @@ async fn test_snapshot_restore_from_archive() -> Result<(), anyhow::Error> {
     compare_live_objects(&perpetual_db, &restored_perpetual_db, true)?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_snapshot_read_local() -> Result<(), anyhow::Error> {
+    let db_path = temp_dir();
+    let local = temp_dir().join("local_dir");
+    let remote = temp_dir().join("remote_dir");
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(local),
+        ..Default::default()
+    };
+    let remote_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(remote.clone()),
+        ..Default::default()
+    };
+
+    let snapshot_writer = StateSnapshotWriterV1::new(
+        &local_store_config,
+        &remote_store_config,
+        FileCompression::Zstd,
+        NonZeroUsize::new(1).unwrap(),
+    )
+    .await?;
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&db_path, None, None));
+    insert_keys(&perpetual_db, 1000)?;
+    let root_state_hash = accumulate_live_object_set(&perpetual_db, true);
+    let root_accumulator = ECMHLiveObjectSetDigest::from(root_state_hash.digest());
+    snapshot_writer
+        .write_internal(0, true, perpetual_db.clone(), root_accumulator)
+        .await?;
+
+    let snapshot_reader = LocalStateSnapshotReaderV1::new(0, remote)?;
+    let mut restored = HashSet::new();
+    let restored_state_hash = snapshot_reader.for_each_live_object(|live_object| {
+        restored.insert(live_object.object_reference());
+        Ok(())
+    })?;
+
+    let expected: HashSet<_> = perpetual_db
+        .iter_live_object_set(true)
+        .map(|live_object| live_object.object_reference())
+        .collect();
+    assert_eq!(expected, restored);
+    assert_eq!(root_state_hash.digest(), restored_state_hash.digest());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_delta() -> Result<(), anyhow::Error> {
+    let db_path = temp_dir();
+    let restored_db_path = temp_dir();
+    let local = temp_dir().join("local_dir");
+    let remote = temp_dir().join("remote_dir");
+    let restored_local = temp_dir().join("local_dir_restore");
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(local),
+        ..Default::default()
+    };
+    let remote_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(remote.clone()),
+        ..Default::default()
+    };
+    let local_store_restore_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(restored_local),
+        ..Default::default()
+    };
+    let new_writer = || {
+        StateSnapshotWriterV1::new(
+            &local_store_config,
+            &remote_store_config,
+            FileCompression::Zstd,
+            NonZeroUsize::new(1).unwrap(),
+        )
+    };
+    let root_state_hash = |db: &AuthorityPerpetualTables| {
+        ECMHLiveObjectSetDigest::from(accumulate_live_object_set(db, true).digest())
+    };
+
+    // Full snapshot at epoch 0
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&db_path, None, None));
+    insert_keys(&perpetual_db, 1000)?;
+    new_writer()
+        .await?
+        .write_internal(
+            0,
+            true,
+            perpetual_db.clone(),
+            root_state_hash(&perpetual_db),
+        )
+        .await?;
+
+    // Delta at epoch 1: delete, mutate and create objects
+    let ids = ObjectID::in_range(ObjectID::ZERO, 1200)?;
+    let deleted = ids[..100]
+        .iter()
+        .map(|id| Object::immutable_with_id_for_testing(*id).compute_object_reference());
+    AuthorityStore::bulk_remove_live_objects(&perpetual_db, deleted)?;
+    for id in &ids[100..200] {
+        perpetual_db.insert_object_test_only(Object::with_id_owner_version_for_testing(
+            *id,
+            SequenceNumber::from_u64(2),
+            Owner::Immutable,
+        ))?;
+        AuthorityStore::bulk_remove_live_objects(
+            &perpetual_db,
+            std::iter::once(Object::immutable_with_id_for_testing(*id).compute_object_reference()),
+        )?;
+    }
+    for id in &ids[1000..1100] {
+        perpetual_db.insert_object_test_only(Object::immutable_with_id_for_testing(*id))?;
+    }
+    new_writer()
+        .await?
+        .write_delta_internal(
+            1,
+            0,
+            true,
+            perpetual_db.clone(),
+            root_state_hash(&perpetual_db),
+        )
+        .await?;
+
+    // Delta at epoch 2, on top of epoch 1
+    let deleted = ids[1000..1050]
+        .iter()
+        .map(|id| Object::immutable_with_id_for_testing(*id).compute_object_reference());
+    AuthorityStore::bulk_remove_live_objects(&perpetual_db, deleted)?;
+    for id in &ids[1100..1200] {
+        perpetual_db.insert_object_test_only(Object::immutable_with_id_for_testing(*id))?;
+    }
+    let root = root_state_hash(&perpetual_db);
+    new_writer()
+        .await?
+        .write_delta_internal(2, 1, true, perpetual_db.clone(), root)
+        .await?;
+
+    // Deltas are kept apart from full snapshots, so they cannot be read as one
+    assert!(remote.join("delta/epoch_2/MANIFEST").exists());
+    assert!(remote.join("delta/epoch_2/1_1.del").exists());
+    assert!(!remote.join("epoch_2").exists());
+    assert!(
+        StateSnapshotReaderV1::new(
+            2,
+            &remote_store_config,
+            &local_store_restore_config,
+            NonZeroUsize::new(1).unwrap(),
+            MultiProgress::new(),
+            false, // skip_reset_local_store
+            0,     // max_retries
+        )
+        .await
+        .is_err()
+    );
+    let restored_perpetual_db = AuthorityPerpetualTables::open(&restored_db_path, None, None);
+
+    // A wrong root state hash is rejected before anything is restored
+    let wrong_root = root_state_hash(&restored_perpetual_db);
+    assert!(
+        StateSnapshotReaderV1::restore_with_deltas(
+            2,
+            &remote_store_config,
+            &local_store_restore_config,
+            NonZeroUsize::new(1).unwrap(),
+            MultiProgress::new(),
+            3,
+            &restored_perpetual_db,
+            Some(wrong_root),
+        )
+        .await
+        .is_err()
+    );
+
+    let (restored_hash, num_live_objects) = StateSnapshotReaderV1::restore_with_deltas(
+        2,
+        &remote_store_config,
+        &local_store_restore_config,
+        NonZeroUsize::new(1).unwrap(),
+        MultiProgress::new(),
+        3,
+        &restored_perpetual_db,
+        Some(root),
+    )
+    .await?;
+    assert_eq!(ECMHLiveObjectSetDigest::from(restored_hash.digest()), root);
+    assert_eq!(num_live_objects, 1050);
+    compare_live_objects(&perpetual_db, &restored_perpetual_db, true)?;
+    Ok(())
+}
+
+#[test]
+fn test_deletion_files_are_split() -> Result<(), anyhow::Error> {
+    let root = temp_dir();
+    std::fs::create_dir_all(root.join("deletions"))?;
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(10);
+    // Room for 4 object references per file
+    let mut writer = DeletionFileWriterV1::new(
+        root.join("deletions"),
+        MAGIC_BYTES + 4 * OBJECT_REF_BYTES,
+        FileCompression::Zstd,
+        sender,
+    )?;
+    let refs: Vec<_> = ObjectID::in_range(ObjectID::ZERO, 10)?
+        .into_iter()
+        .map(|id| Object::immutable_with_id_for_testing(id).compute_object_reference())
+        .collect();
+    for object_ref in &refs {
+        writer.write(object_ref)?;
+    }
+    let files = writer.done()?;
+    assert_eq!(
+        files.iter().map(|file| file.part_num).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+
+    let mut read_refs = vec![];
+    for file in &files {
+        assert_eq!(&receiver.try_recv()?, file);
+        read_refs.extend(ObjectRefIter::new(
+            file,
+            root.clone(),
+            object_store::path::Path::from("deletions"),
+        )?);
+    }
+    assert_eq!(read_refs, refs);
+    Ok(())
+}
+
+#[test]
+fn test_object_filter() {
+    let owner = SuiAddress::random_for_testing_only();
+    let coin = Object::with_owner_for_testing(owner);
+    let other_coin = Object::with_owner_for_testing(SuiAddress::random_for_testing_only());
+    let immutable = Object::immutable_with_id_for_testing(ObjectID::random());
+
+    assert!(!ObjectFilter::new().matches(&coin));
+
+    let by_owner = ObjectFilter::new().with_owner(owner);
+    assert!(by_owner.matches(&coin));
+    assert!(!by_owner.matches(&other_coin));
+    assert!(!by_owner.matches(&immutable));
+
+    // A type without type parameters matches all of its instantiations
+    let by_type = ObjectFilter::new().with_type(parse_sui_struct_tag("0x2::coin::Coin").unwrap());
+    assert!(by_type.matches(&coin));
+    assert!(by_type.matches(&immutable));
+    let by_instantiation = ObjectFilter::new()
+        .with_type(parse_sui_struct_tag("0x2::coin::Coin<0x2::sui::SUI>").unwrap());
+    assert!(by_instantiation.matches(&coin));
+    let by_other_instantiation = ObjectFilter::new()
+        .with_type(parse_sui_struct_tag("0x2::coin::Coin<0x3::foo::FOO>").unwrap());
+    assert!(!by_other_instantiation.matches(&coin));
+
+    let by_package = ObjectFilter::new().with_package(ObjectID::from_single_byte(2));
+    assert!(by_package.matches(&coin));
+    let by_other_package = ObjectFilter::new().with_package(ObjectID::from_single_byte(3));
+    assert!(!by_other_package.matches(&coin));
+
+    // Wrapped object tombstones are never restored by a filter
+    let wrapped = LiveObject::Wrapped(ObjectKey(ObjectID::random(), SequenceNumber::from_u64(1)));
+    assert!(!by_package.matches_live_object(&wrapped));
+}
+
+#[tokio::test]
+async fn test_snapshot_filtered_restore() -> Result<(), anyhow::Error> {
+    let db_path = temp_dir();
+    let restored_db_path = temp_dir();
+    let local = temp_dir().join("local_dir");
+    let remote = temp_dir().join("remote_dir");
+    let restored_local = temp_dir().join("local_dir_restore");
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(local),
+        ..Default::default()
+    };
+    let remote_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(remote),
+        ..Default::default()
+    };
+
+    let snapshot_writer = StateSnapshotWriterV1::new(
+        &local_store_config,
+        &remote_store_config,
+        FileCompression::Zstd,
+        NonZeroUsize::new(1).unwrap(),
+    )
+    .await?;
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&db_path, None, None));
+    insert_keys(&perpetual_db, 1000)?;
+    let owner = SuiAddress::random_for_testing_only();
+    let owned: HashSet<_> = (0..100)
+        .map(|_| {
+            let object = Object::with_owner_for_testing(owner);
+            perpetual_db.insert_object_test_only(object.clone())?;
+            Ok(object.compute_object_reference())
+        })
+        .collect::<Result<_, anyhow::Error>>()?;
+    let root_accumulator =
+        ECMHLiveObjectSetDigest::from(accumulate_live_object_set(&perpetual_db, true).digest());
+    snapshot_writer
+        .write_internal(0, true, perpetual_db.clone(), root_accumulator)
+        .await?;
+
+    let local_store_restore_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(restored_local),
+        ..Default::default()
+    };
+    let mut snapshot_reader = StateSnapshotReaderV1::new(
+        0,
+        &remote_store_config,
+        &local_store_restore_config,
+        NonZeroUsize::new(1).unwrap(),
+        MultiProgress::new(),
+        false, // skip_reset_local_store
+        3,     // max_retries
+    )
+    .await?
+    .with_filter(ObjectFilter::new().with_owner(owner));
+    let restored_perpetual_db = AuthorityPerpetualTables::open(&restored_db_path, None, None);
+    let (_abort_handle, abort_registration) = AbortHandle::new_pair();
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+    let accumulate = tokio::spawn(async move {
+        let mut root_state_hash = GlobalStateHash::default();
+        while let Some((partial_hash, _)) = receiver.recv().await {
+            root_state_hash.union(&partial_hash);
+        }
+        root_state_hash
+    });
+    snapshot_reader
+        .read(&restored_perpetual_db, abort_registration, Some(sender))
+        .await?;
+
+    // The whole snapshot is accumulated, but only the filtered objects are restored
+    assert_eq!(
+        ECMHLiveObjectSetDigest::from(accumulate.await?.digest()),
+        root_accumulator
+    );
+    let restored: HashSet<_> = restored_perpetual_db
+        .iter_live_object_set(true)
+        .map(|live_object| live_object.object_reference())
+        .collect();
+    assert_eq!(restored, owned);
+    Ok(())
+}
@@ -1,6 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::reader::find_snapshot_dir;
 use crate::writer::StateSnapshotWriterV1;
 use anyhow::Result;
 use bytes::Bytes;
@@ -74,6 +75,9 @@ pub struct StateSnapshotUploader {
     chain_identifier: ChainIdentifier,
     /// Archive snapshots every N epochs (0 = disabled)
     archive_interval_epochs: u64,
+    /// Write full snapshots every N epochs, and delta snapshots over the previous epoch in
+    /// between (0 or 1 = full snapshots only)
+    full_snapshot_interval_epochs: u64,
 }
 
 impl StateSnapshotUploader {
@@ -86,6 +90,7 @@ impl StateSnapshotUploader {
         checkpoint_store: Arc<CheckpointStore>,
         chain_identifier: ChainIdentifier,
         archive_interval_epochs: u64,
+        full_snapshot_interval_epochs: u64,
     ) -> Result<Arc<Self>> {
         let db_checkpoint_store_config = ObjectStoreConfig {
             object_store: Some(ObjectStoreType::File),
@@ -108,6 +113,7 @@ impl StateSnapshotUploader {
             metrics: StateSnapshotUploaderMetrics::new(registry),
             chain_identifier,
             archive_interval_epochs,
+            full_snapshot_interval_epochs,
         }))
     }
 
@@ -156,10 +162,26 @@ impl StateSnapshotUploader {
                     ECMHLiveObjectSetDigest(digest) => digest,
                     _ => return Err(anyhow::anyhow!("Expected ECMHLiveObjectSetDigest")),
                 };
-                state_snapshot_writer
-                    .write(*epoch, db, state_hash_commitment, self.chain_identifier)
-                    .await?;
-                info!("State snapshot creation successful for epoch: {}", *epoch);
+                let base_epoch = self.delta_base_epoch(*epoch).await;
+                if let Some(base_epoch) = base_epoch {
+                    state_snapshot_writer
+                        .write_delta(
+                            *epoch,
+                            base_epoch,
+                            db,
+                            state_hash_commitment,
+                            self.chain_identifier,
+                        )
+                        .await?;
+                } else {
+                    state_snapshot_writer
+                        .write(*epoch, db, state_hash_commitment, self.chain_identifier)
+                        .await?;
+                }
+                info!(
+                    "State snapshot creation successful for epoch: {} (base epoch: {:?})",
+                    *epoch, base_epoch
+                );
                 // Drop marker in the output directory that upload completed successfully
                 let bytes = Bytes::from_static(b"success");
                 let success_marker = db_path.child(SUCCESS_MARKER);
@@ -175,8 +197,11 @@ impl StateSnapshotUploader {
                 .await?;
                 info!("State snapshot completed for epoch: {epoch}");
 
-                // Archive snapshot if epoch meets archival criteria
-                if let Err(e) = self.archive_epoch_if_needed(*epoch).await {
+                // Archive snapshot if epoch meets archival criteria. Only full snapshots are
+                // archived, as a delta can't be restored once its base is gone.
+                if base_epoch.is_some() {
+                    debug!("Epoch {} has a delta snapshot, skipping archival", epoch);
+                } else if let Err(e) = self.archive_epoch_if_needed(*epoch).await {
                     error!(
                         "Failed to archive epoch {} (non-fatal, continuing): {:?}",
                         epoch, e
@@ -230,6 +255,29 @@ impl StateSnapshotUploader {
         Ok(())
     }
 
+    /// The epoch that the snapshot of `epoch` is written as a delta over, if any. Epochs between
+    /// two full snapshots get a delta over the previous epoch, as long as the previous epoch has a
+    /// snapshot to build on, and a full snapshot otherwise.
+    pub(crate) async fn delta_base_epoch(&self, epoch: u64) -> Option<u64> {
+        if self.full_snapshot_interval_epochs <= 1
+            || epoch.is_multiple_of(self.full_snapshot_interval_epochs)
+        {
+            return None;
+        }
+
+        let base_epoch = epoch - 1;
+        match find_snapshot_dir(&self.snapshot_store, base_epoch).await {
+            Ok(_) => Some(base_epoch),
+            Err(e) => {
+                info!(
+                    "No state snapshot for epoch {} to write a delta over, writing a full snapshot for epoch {}: {:?}",
+                    base_epoch, epoch, e
+                );
+                None
+            }
+        }
+    }
+
     async fn get_missing_epochs(&self) -> Result<Vec<u64>> {
         let missing_epochs = find_missing_epochs_dirs(&self.snapshot_store, SUCCESS_MARKER).await?;
         Ok(missing_epochs.to_vec())
@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 #![allow(dead_code)]
 
+use crate::reader::SnapshotChain;
 use crate::{
-    FILE_MAX_BYTES, FileCompression, FileMetadata, FileType, MAGIC_BYTES, MANIFEST_FILE_MAGIC,
-    Manifest, ManifestV1, OBJECT_FILE_MAGIC, OBJECT_REF_BYTES, REFERENCE_FILE_MAGIC,
-    SEQUENCE_NUM_BYTES, compute_sha3_checksum, create_file_metadata,
+    DELETION_FILE_MAGIC, DELTA_DIR, DeltaManifestV1, FILE_MAX_BYTES, FileCompression, FileMetadata,
+    FileType, MAGIC_BYTES, MANIFEST_FILE_MAGIC, Manifest, ManifestV1, OBJECT_FILE_MAGIC,
+    OBJECT_REF_BYTES, REFERENCE_FILE_MAGIC, SEQUENCE_NUM_BYTES, compute_sha3_checksum,
+    create_file_metadata,
 };
 use anyhow::{Context, Result};
 use byteorder::{BigEndian, ByteOrder};
@@ -193,15 +195,101 @@ impl LiveObjectSetWriterV1 {
         Ok(())
     }
     fn write_object_ref(&mut self, object_ref: &ObjectRef) -> Result<()> {
-        let mut buf = [0u8; OBJECT_REF_BYTES];
-        buf[0..ObjectID::LENGTH].copy_from_slice(object_ref.0.as_ref());
-        BigEndian::write_u64(
-            &mut buf[ObjectID::LENGTH..OBJECT_REF_BYTES],
-            object_ref.1.value(),
-        );
-        buf[ObjectID::LENGTH + SEQUENCE_NUM_BYTES..OBJECT_REF_BYTES]
-            .copy_from_slice(object_ref.2.as_ref());
-        self.ref_wbuf.write_all(&buf)?;
+        self.ref_wbuf.write_all(&encode_object_ref(object_ref))?;
+        Ok(())
+    }
+}
+
+fn encode_object_ref(object_ref: &ObjectRef) -> [u8; OBJECT_REF_BYTES] {
+    let mut buf = [0u8; OBJECT_REF_BYTES];
+    buf[0..ObjectID::LENGTH].copy_from_slice(object_ref.0.as_ref());
+    BigEndian::write_u64(
+        &mut buf[ObjectID::LENGTH..OBJECT_REF_BYTES],
+        object_ref.1.value(),
+    );
+    buf[ObjectID::LENGTH + SEQUENCE_NUM_BYTES..OBJECT_REF_BYTES]
+        .copy_from_slice(object_ref.2.as_ref());
+    buf
+}
+
+/// DeletionFileWriterV1 writes the *.del files of a delta snapshot, i.e. the object references of
+/// the base which are no longer live. They all go in the same bucket, and a new part file is cut
+/// once the current one reaches `max_bytes`.
+pub(crate) struct DeletionFileWriterV1 {
+    dir_path: PathBuf,
+    current_part_num: u32,
+    wbuf: BufWriter<File>,
+    n: usize,
+    max_bytes: usize,
+    files: Vec<FileMetadata>,
+    sender: Sender<FileMetadata>,
+    file_compression: FileCompression,
+}
+
+impl DeletionFileWriterV1 {
+    const BUCKET_NUM: u32 = 1;
+
+    pub(crate) fn new(
+        dir_path: PathBuf,
+        max_bytes: usize,
+        file_compression: FileCompression,
+        sender: Sender<FileMetadata>,
+    ) -> Result<Self> {
+        let part_num = 1;
+        let (n, wbuf) = Self::deletion_file(&dir_path, part_num)?;
+        Ok(DeletionFileWriterV1 {
+            dir_path,
+            current_part_num: part_num,
+            wbuf,
+            n,
+            max_bytes,
+            files: vec![],
+            sender,
+            file_compression,
+        })
+    }
+    pub(crate) fn write(&mut self, object_ref: &ObjectRef) -> Result<()> {
+        if self.n + OBJECT_REF_BYTES > self.max_bytes {
+            self.finalize()?;
+            self.current_part_num += 1;
+            (self.n, self.wbuf) = Self::deletion_file(&self.dir_path, self.current_part_num)?;
+        }
+        self.wbuf.write_all(&encode_object_ref(object_ref))?;
+        self.n += OBJECT_REF_BYTES;
+        Ok(())
+    }
+    pub(crate) fn done(mut self) -> Result<Vec<FileMetadata>> {
+        self.finalize()?;
+        Ok(self.files)
+    }
+    fn deletion_file(
+        dir_path: &std::path::Path,
+        part_num: u32,
+    ) -> Result<(usize, BufWriter<File>)> {
+        let file_path = dir_path.join(format!("{}_{part_num}.del", Self::BUCKET_NUM));
+        let mut wbuf = BufWriter::new(File::create(file_path)?);
+        let mut metab = [0u8; MAGIC_BYTES];
+        BigEndian::write_u32(&mut metab, DELETION_FILE_MAGIC);
+        wbuf.write_all(&metab)?;
+        Ok((MAGIC_BYTES, wbuf))
+    }
+    fn finalize(&mut self) -> Result<()> {
+        self.wbuf.flush()?;
+        self.wbuf.get_ref().sync_data()?;
+        let file_path = self.dir_path.join(format!(
+            "{}_{}.del",
+            Self::BUCKET_NUM,
+            self.current_part_num
+        ));
+        let file_metadata = create_file_metadata(
+            &file_path,
+            self.file_compression,
+            FileType::Deletion,
+            Self::BUCKET_NUM,
+            self.current_part_num,
+        )?;
+        self.files.push(file_metadata.clone());
+        self.sender.blocking_send(file_metadata)?;
         Ok(())
     }
 }
@@ -279,25 +367,114 @@ impl StateSnapshotWriterV1 {
         .await
     }
 
+    /// Writes a delta snapshot under `delta/epoch_<epoch>`, which only holds the objects that
+    /// changed since the snapshot of `base_epoch` in the remote store. The base may itself be a
+    /// delta snapshot.
+    ///
+    /// The reference and deletion files of the base are downloaded to the local staging dir, and
+    /// streamed alongside the live object set while the delta is computed.
+    pub async fn write_delta(
+        self,
+        epoch: u64,
+        base_epoch: u64,
+        perpetual_db: Arc<AuthorityPerpetualTables>,
+        root_state_hash: ECMHLiveObjectSetDigest,
+        chain_identifier: ChainIdentifier,
+    ) -> Result<()> {
+        let system_state_object = get_sui_system_state(&perpetual_db)?;
+
+        let protocol_version = system_state_object.protocol_version();
+        let protocol_config = ProtocolConfig::get_for_version(
+            ProtocolVersion::new(protocol_version),
+            chain_identifier.chain(),
+        );
+        let include_wrapped_tombstone = !protocol_config.simplified_unwrap_then_delete();
+        self.write_delta_internal(
+            epoch,
+            base_epoch,
+            include_wrapped_tombstone,
+            perpetual_db,
+            root_state_hash,
+        )
+        .await
+    }
+
     pub(crate) async fn write_internal(
+        self,
+        epoch: u64,
+        include_wrapped_tombstone: bool,
+        perpetual_db: Arc<AuthorityPerpetualTables>,
+        root_state_hash: ECMHLiveObjectSetDigest,
+    ) -> Result<()> {
+        self.write_snapshot(
+            Self::epoch_dir(epoch),
+            epoch,
+            None,
+            include_wrapped_tombstone,
+            perpetual_db,
+            root_state_hash,
+        )
+        .await
+    }
+
+    pub(crate) async fn write_delta_internal(
+        self,
+        epoch: u64,
+        base_epoch: u64,
+        include_wrapped_tombstone: bool,
+        perpetual_db: Arc<AuthorityPerpetualTables>,
+        root_state_hash: ECMHLiveObjectSetDigest,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            base_epoch < epoch,
+            "Base epoch {base_epoch} of a delta snapshot must be before its epoch {epoch}"
+        );
+        let base_dir = self.local_staging_dir.join("base");
+        if base_dir.exists() {
+            fs::remove_dir_all(&base_dir)?;
+        }
+        let base = SnapshotChain::download(&self.remote_object_store, base_dir.clone(), base_epoch)
+            .await
+            .context(format!(
+                "Failed to read base state snapshot for epoch: {}",
+                &base_epoch
+            ))?;
+        self.write_snapshot(
+            Self::delta_epoch_dir(epoch),
+            epoch,
+            Some((base_epoch, base)),
+            include_wrapped_tombstone,
+            perpetual_db,
+            root_state_hash,
+        )
+        .await?;
+        fs::remove_dir_all(&base_dir)?;
+        Ok(())
+    }
+
+    async fn write_snapshot(
         mut self,
+        epoch_dir: Path,
         epoch: u64,
+        base: Option<(u64, SnapshotChain)>,
         include_wrapped_tombstone: bool,
         perpetual_db: Arc<AuthorityPerpetualTables>,
         root_state_hash: ECMHLiveObjectSetDigest,
     ) -> Result<()> {
-        self.setup_epoch_dir(epoch).await?;
+        self.setup_epoch_dir(&epoch_dir).await?;
 
-        let manifest_file_path = self.epoch_dir(epoch).child("MANIFEST");
+        let manifest_file_path = epoch_dir.child("MANIFEST");
         let local_staging_dir = self.local_staging_dir.clone();
         let local_object_store = self.local_staging_store.clone();
         let remote_object_store = self.remote_object_store.clone();
 
         let (sender, receiver) = mpsc::channel::<FileMetadata>(1000);
-        let upload_handle = self.start_upload(epoch, receiver)?;
+        let upload_handle = self.start_upload(epoch_dir.clone(), receiver)?;
         let write_handler = tokio::task::spawn_blocking(move || {
             self.write_live_object_set(
+                epoch_dir,
                 epoch,
+                base,
                 perpetual_db,
                 sender,
                 Self::bucket_func,
@@ -327,13 +504,12 @@ impl StateSnapshotWriterV1 {
 
     fn start_upload(
         &self,
-        epoch: u64,
+        epoch_dir: Path,
         receiver: Receiver<FileMetadata>,
     ) -> Result<JoinHandle<Result<Vec<()>, anyhow::Error>>> {
         let remote_object_store = self.remote_object_store.clone();
         let local_staging_store = self.local_staging_store.clone();
         let local_dir_path = self.local_staging_dir.clone();
-        let epoch_dir = self.epoch_dir(epoch);
         let upload_concurrency = self.concurrency;
         let join_handle = tokio::spawn(async move {
             let results: Vec<Result<(), anyhow::Error>> = ReceiverStream::new(receiver)
@@ -364,9 +540,18 @@ impl StateSnapshotWriterV1 {
         Ok(join_handle)
     }
 
+    /// Writes the live object set, or only the objects that changed since `base` for a delta
+    /// snapshot. The whole live object set is accumulated either way, to check it against the
+    /// root state hash.
+    ///
+    /// For a delta snapshot, the live object references of the base are merged with the live
+    /// object set, both being in ascending order of object ID: references of the base that are not
+    /// in the live object set anymore are written to the deletion files.
     fn write_live_object_set<F>(
         &mut self,
+        epoch_dir: Path,
         epoch: u64,
+        base: Option<(u64, SnapshotChain)>,
         perpetual_db: Arc<AuthorityPerpetualTables>,
         sender: Sender<FileMetadata>,
         bucket_func: F,
@@ -378,10 +563,45 @@ impl StateSnapshotWriterV1 {
     {
         let mut object_writers: HashMap<u32, LiveObjectSetWriterV1> = HashMap::new();
         let local_staging_dir_path =
-            path_to_filesystem(self.local_staging_dir.clone(), &self.epoch_dir(epoch))?;
+            path_to_filesystem(self.local_staging_dir.clone(), &epoch_dir)?;
+        let mut delta = match &base {
+            Some((_, base_snapshots)) => Some((
+                base_snapshots.live_object_refs()?.peekable(),
+                DeletionFileWriterV1::new(
+                    local_staging_dir_path.clone(),
+                    FILE_MAX_BYTES,
+                    self.file_compression,
+                    sender.clone(),
+                )?,
+            )),
+            None => None,
+        };
+        let mut last_base_id = None;
         let mut acc = GlobalStateHash::default();
         for object in perpetual_db.iter_live_object_set(include_wrapped_tombstone) {
             GlobalStateHasher::accumulate_live_object(&mut acc, &object);
+            if let Some((base_refs, deletions)) = &mut delta {
+                // References of the base up to this object are no longer live, unless one of them
+                // is the reference of this very object, which is then unchanged since the base
+                let object_ref = object.object_reference();
+                let mut unchanged = false;
+                while let Some(base_ref) = base_refs.next_if(|base_ref| base_ref.0 <= object_ref.0)
+                {
+                    anyhow::ensure!(
+                        last_base_id < Some(base_ref.0),
+                        "Object references of the base snapshot are not sorted"
+                    );
+                    last_base_id = Some(base_ref.0);
+                    if base_ref == object_ref {
+                        unchanged = true;
+                    } else {
+                        deletions.write(&base_ref)?;
+                    }
+                }
+                if unchanged {
+                    continue;
+                }
+            }
             let bucket_num = bucket_func(&object);
             if let Vacant(entry) = object_writers.entry(bucket_num) {
                 entry.insert(LiveObjectSetWriterV1::new(
@@ -405,19 +625,47 @@ impl StateSnapshotWriterV1 {
         for (_, writer) in object_writers.into_iter() {
             files.extend(writer.done()?);
         }
-        self.write_manifest(epoch, files)?;
+        if let Some((base_refs, mut deletions)) = delta {
+            // Whatever is left of the base was deleted since
+            for base_ref in base_refs {
+                anyhow::ensure!(
+                    last_base_id < Some(base_ref.0),
+                    "Object references of the base snapshot are not sorted"
+                );
+                last_base_id = Some(base_ref.0);
+                deletions.write(&base_ref)?;
+            }
+            files.extend(deletions.done()?);
+        }
+        let base_epoch = base.map(|(base_epoch, _)| base_epoch);
+        self.write_manifest(&epoch_dir, epoch, base_epoch, files)?;
         Ok(())
     }
 
-    fn write_manifest(&mut self, epoch: u64, file_metadata: Vec<FileMetadata>) -> Result<()> {
-        let (f, manifest_file_path) = self.manifest_file(epoch)?;
+    fn write_manifest(
+        &mut self,
+        epoch_dir: &Path,
+        epoch: u64,
+        base_epoch: Option<u64>,
+        file_metadata: Vec<FileMetadata>,
+    ) -> Result<()> {
+        let (f, manifest_file_path) = self.manifest_file(epoch_dir)?;
         let mut wbuf = BufWriter::new(f);
-        let manifest: Manifest = Manifest::V1(ManifestV1 {
-            snapshot_version: 1,
-            address_length: ObjectID::LENGTH as u64,
-            file_metadata,
-            epoch,
-        });
+        let manifest: Manifest = match base_epoch {
+            None => Manifest::V1(ManifestV1 {
+                snapshot_version: 1,
+                address_length: ObjectID::LENGTH as u64,
+                file_metadata,
+                epoch,
+            }),
+            Some(base_epoch) => Manifest::DeltaV1(DeltaManifestV1 {
+                snapshot_version: 1,
+                address_length: ObjectID::LENGTH as u64,
+                file_metadata,
+                epoch,
+                base_epoch,
+            }),
+        };
         let serialized_manifest = bcs::to_bytes(&manifest)?;
         wbuf.write_all(&serialized_manifest)?;
         wbuf.flush()?;
@@ -431,14 +679,12 @@ impl StateSnapshotWriterV1 {
         Ok(())
     }
 
-    fn manifest_file(&mut self, epoch: u64) -> Result<(File, PathBuf)> {
-        let manifest_file_path = path_to_filesystem(
-            self.local_staging_dir.clone(),
-            &self.epoch_dir(epoch).child("MANIFEST"),
-        )?;
+    fn manifest_file(&mut self, epoch_dir: &Path) -> Result<(File, PathBuf)> {
+        let manifest_file_path =
+            path_to_filesystem(self.local_staging_dir.clone(), &epoch_dir.child("MANIFEST"))?;
         let manifest_file_tmp_path = path_to_filesystem(
             self.local_staging_dir.clone(),
-            &self.epoch_dir(epoch).child("MANIFEST.tmp"),
+            &epoch_dir.child("MANIFEST.tmp"),
         )?;
         let mut f = File::create(manifest_file_tmp_path.clone())?;
         let mut metab = vec![0u8; MAGIC_BYTES];
@@ -459,21 +705,24 @@ impl StateSnapshotWriterV1 {
         1u32
     }
 
-    fn epoch_dir(&self, epoch: u64) -> Path {
+    fn epoch_dir(epoch: u64) -> Path {
         Path::from(format!("epoch_{}", epoch))
     }
 
-    async fn setup_epoch_dir(&self, epoch: u64) -> Result<()> {
-        let epoch_dir = self.epoch_dir(epoch);
+    fn delta_epoch_dir(epoch: u64) -> Path {
+        Path::from(format!("{}/epoch_{}", DELTA_DIR, epoch))
+    }
+
+    async fn setup_epoch_dir(&self, epoch_dir: &Path) -> Result<()> {
         // Delete remote epoch dir if it exists
         delete_recursively(
-            &epoch_dir,
+            epoch_dir,
             &self.remote_object_store,
             NonZeroUsize::new(self.concurrency).unwrap(),
         )
         .await?;
         // Delete local staging epoch dir if it exists
-        let local_epoch_dir_path = self.local_staging_dir.join(format!("epoch_{}", epoch));
+        let local_epoch_dir_path = path_to_filesystem(self.local_staging_dir.clone(), epoch_dir)?;
         if local_epoch_dir_path.exists() {
             fs::remove_dir_all(&local_epoch_dir_path)?;
         }
@@ -1,22 +1,42 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, ensure};
 use byteorder::ReadBytesExt;
 use integer_encoding::{VarInt, VarIntReader};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serde::Serialize;
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
 use std::marker::PhantomData;
 
 pub const MAX_VARINT_LENGTH: usize = 10;
 pub const BLOB_ENCODING_BYTES: usize = 1;
+pub const ZSTD_COMPRESSION_LEVEL: i32 = 3;
+/// Upper bound on the decompressed size of a blob. The largest blobs hold a checkpoint's data, and
+/// the limit stops a small, highly compressed blob from exhausting memory when it is decoded.
+pub const MAX_CHECKPOINT_BYTES: u64 = 1 << 30;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, TryFromPrimitive, IntoPrimitive)]
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    TryFromPrimitive,
+    IntoPrimitive,
+)]
 #[repr(u8)]
+#[serde(rename_all = "lowercase")]
 pub enum BlobEncoding {
+    #[default]
     Bcs = 1,
+    /// BCS, compressed with zstd. Decoding it needs no other input, so any reader of blobs
+    /// understands it.
+    Zstd = 2,
 }
 
 pub struct Blob {
@@ -29,12 +49,17 @@ impl Blob {
         let value_buf = bcs::to_bytes(value)?;
         let (data, encoding) = match encoding {
             BlobEncoding::Bcs => (value_buf, encoding),
+            BlobEncoding::Zstd => (
+                zstd::encode_all(value_buf.as_slice(), ZSTD_COMPRESSION_LEVEL)?,
+                encoding,
+            ),
         };
         Ok(Blob { data, encoding })
     }
     pub fn decode<T: DeserializeOwned>(self) -> Result<T> {
         let data = match &self.encoding {
             BlobEncoding::Bcs => self.data,
+            BlobEncoding::Zstd => zstd_decode(&self.data, MAX_CHECKPOINT_BYTES)?,
         };
         let res = bcs::from_bytes(&data)?;
         Ok(res)
@@ -85,6 +110,19 @@ impl Blob {
     }
 }
 
+/// Decompresses `data`, failing if its decompressed size exceeds `limit` bytes.
+fn zstd_decode(data: &[u8], limit: u64) -> Result<Vec<u8>> {
+    let mut decoded = vec![];
+    zstd::Decoder::new(data)?
+        .take(limit + 1)
+        .read_to_end(&mut decoded)?;
+    ensure!(
+        decoded.len() as u64 <= limit,
+        "Decompressed blob is larger than {limit} bytes"
+    );
+    Ok(decoded)
+}
+
 /// An iterator over blobs in a blob file.
 pub struct BlobIter<T> {
     reader: Box<dyn Read>,
@@ -110,3 +148,48 @@ impl<T: DeserializeOwned> Iterator for BlobIter<T> {
         self.next_blob().ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_encodings_round_trip() {
+        let value: Vec<u64> = (0..1000).map(|i| i % 10).collect();
+        for encoding in [BlobEncoding::Bcs, BlobEncoding::Zstd] {
+            let blob = Blob::encode(&value, encoding).unwrap();
+            assert_eq!(blob.encoding, encoding);
+            assert_eq!(
+                Blob::from_bytes::<Vec<u64>>(&blob.to_bytes()).unwrap(),
+                value
+            );
+
+            let mut buf = vec![];
+            let written = blob.write(&mut buf).unwrap();
+            assert_eq!(written, blob.size());
+            let read = Blob::read(&mut buf.as_slice()).unwrap();
+            assert_eq!(read.decode::<Vec<u64>>().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_zstd_blob_is_smaller() {
+        let value: Vec<u64> = (0..1000).map(|i| i % 10).collect();
+        let bcs = Blob::encode(&value, BlobEncoding::Bcs).unwrap();
+        let zstd = Blob::encode(&value, BlobEncoding::Zstd).unwrap();
+        assert!(zstd.size() < bcs.size());
+    }
+
+    #[test]
+    fn test_zstd_decode_limit() {
+        let data = zstd::encode_all([0u8; 1024].as_slice(), ZSTD_COMPRESSION_LEVEL).unwrap();
+        assert_eq!(zstd_decode(&data, 1024).unwrap(), vec![0u8; 1024]);
+        let err = zstd_decode(&data, 1023).unwrap_err();
+        assert!(err.to_string().contains("larger than 1023 bytes"), "{err}");
+    }
+
+    #[test]
+    fn test_unknown_blob_encoding() {
+        assert!(Blob::from_bytes::<Vec<u64>>(&[0xff, 0]).is_err());
+    }
+}
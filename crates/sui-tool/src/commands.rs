@@ -10,17 +10,32 @@ use crate::{
     restore_from_db_checkpoint,
 };
 use anyhow::Result;
+#[cfg(not(tidehunter))]
+use consensus_core::commit_replayer::CommitReplayer;
+#[cfg(not(tidehunter))]
+use consensus_core::dag_inspector::InspectedDag;
 use consensus_core::storage::{Store, rocksdb_store::RocksDBStore};
 use consensus_core::{BlockAPI, CommitAPI, CommitRange};
 use futures::{StreamExt, future::join_all};
+use move_core_types::language_storage::StructTag;
 use std::path::PathBuf;
 use std::{collections::BTreeMap, env, sync::Arc};
 use sui_config::genesis::Genesis;
+#[cfg(not(tidehunter))]
+use sui_core::authority::authority_store_tables::AuthorityPerpetualTables;
+#[cfg(not(tidehunter))]
+use sui_core::authority::epoch_start_configuration::EpochStartConfigTrait;
 use sui_core::authority_client::AuthorityAPI;
 use sui_protocol_config::Chain;
+#[cfg(not(tidehunter))]
+use sui_protocol_config::ProtocolConfig;
 use sui_replay::{ReplayToolCommand, execute_replay_command};
 use sui_sdk::{SuiClient, SuiClientBuilder, rpc_types::SuiTransactionBlockResponseOptions};
+use sui_snapshot::filter::ObjectFilter;
 use sui_types::messages_consensus::ConsensusTransaction;
+use sui_types::parse_sui_struct_tag;
+#[cfg(not(tidehunter))]
+use sui_types::sui_system_state::epoch_start_sui_system_state::EpochStartSystemStateTrait;
 use telemetry_subscribers::TracingHandle;
 
 use sui_types::{
@@ -44,6 +59,13 @@ pub enum Verbosity {
     Verbose,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DagFormat {
+    Json,
+    Dot,
+    DagText,
+}
+
 #[derive(Parser)]
 pub enum ToolCommand {
     #[command(name = "scan-consensus-commits")]
@@ -56,6 +78,43 @@ pub enum ToolCommand {
         end_commit: Option<u32>,
     },
 
+    /// Export the consensus DAG between two rounds from a validator's consensus db, annotated with
+    /// commit leaders, committed blocks, missing ancestors and equivocations. The db is opened
+    /// read-only, so this can run against the db of a live validator.
+    #[cfg(not(tidehunter))]
+    #[command(name = "inspect-consensus-dag")]
+    InspectConsensusDag {
+        #[arg(long = "db-path")]
+        db_path: String,
+        #[arg(long = "start-round")]
+        start_round: u32,
+        #[arg(long = "end-round")]
+        end_round: u32,
+        /// Output format: JSON, Graphviz DOT, or the text syntax of the consensus DAG parser
+        /// used in tests.
+        #[arg(long = "format", value_enum, default_value = "json")]
+        format: DagFormat,
+    },
+
+    /// Replay the commit sequence of an epoch from the blocks in a validator's consensus db, and
+    /// report where it diverges from the commits persisted in the db. The committee and protocol
+    /// config of the epoch are read from the validator's authority db, which must have been
+    /// captured in the same epoch as the consensus db. Both dbs are only read from.
+    #[cfg(not(tidehunter))]
+    #[command(name = "replay-consensus-commits")]
+    ReplayConsensusCommits {
+        #[arg(long = "db-path")]
+        db_path: String,
+        /// Path to the authority db of the validator, i.e. the `store` directory of its db path.
+        #[arg(long = "authority-db-path")]
+        authority_db_path: PathBuf,
+        /// Index of the validator in the consensus committee.
+        #[arg(long = "own-index", default_value_t = 0)]
+        own_index: usize,
+        #[arg(long = "network", default_value = "mainnet")]
+        network: Chain,
+    },
+
     /// Inspect if a specific object is or all gas objects owned by an address are locked by validators
     #[command(name = "locked-object")]
     LockedObject {
@@ -342,6 +401,17 @@ pub enum ToolCommand {
         /// Defaults to 3 retries. Set to 0 to disable retries.
         #[clap(long = "max-retries", default_value = "3")]
         max_retries: usize,
+
+        /// Only restore Move objects of these types. A type without type parameters matches all
+        /// of its instantiations. The snapshot is still verified in full.
+        #[clap(long = "filter-type", value_parser = parse_sui_struct_tag, num_args(1..))]
+        filter_types: Vec<StructTag>,
+        /// Only restore objects owned by these addresses, or by these objects.
+        #[clap(long = "filter-owner", num_args(1..))]
+        filter_owners: Vec<SuiAddress>,
+        /// Only restore these packages, and the Move objects of the types they define.
+        #[clap(long = "filter-package", num_args(1..))]
+        filter_packages: Vec<ObjectID>,
     },
 
     #[clap(name = "replay")]
@@ -486,6 +556,78 @@ impl ToolCommand {
                     }
                 }
             }
+            #[cfg(not(tidehunter))]
+            ToolCommand::InspectConsensusDag {
+                db_path,
+                start_round,
+                end_round,
+                format,
+            } => {
+                anyhow::ensure!(
+                    start_round <= end_round,
+                    "start-round must not be greater than end-round"
+                );
+
+                let rocks_db_store = RocksDBStore::open_read_only(&db_path);
+                let dag = InspectedDag::from_store(&rocks_db_store, start_round, end_round)?;
+                match format {
+                    DagFormat::Json => println!("{}", serde_json::to_string_pretty(&dag)?),
+                    DagFormat::Dot => print!("{}", dag.to_dot()),
+                    DagFormat::DagText => print!("{}", dag.to_dag_text()?),
+                }
+            }
+            #[cfg(not(tidehunter))]
+            ToolCommand::ReplayConsensusCommits {
+                db_path,
+                authority_db_path,
+                own_index,
+                network,
+            } => {
+                let perpetual_tables = AuthorityPerpetualTables::open_readonly(&authority_db_path);
+                let epoch_start_configuration = perpetual_tables
+                    .epoch_start_configuration
+                    .get(&())?
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "No epoch start configuration in {}",
+                            authority_db_path.display()
+                        )
+                    })?;
+                let epoch_start_state = epoch_start_configuration.epoch_start_state();
+                let committee = epoch_start_state.get_consensus_committee();
+                let own_index = committee.to_authority_index(own_index).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "own-index must be less than the committee size {}",
+                        committee.size()
+                    )
+                })?;
+                let protocol_config =
+                    ProtocolConfig::get_for_version(epoch_start_state.protocol_version(), network);
+                println!(
+                    "Replaying epoch {} with protocol version {}",
+                    epoch_start_state.epoch(),
+                    epoch_start_state.protocol_version().as_u64()
+                );
+
+                let rocks_db_store = RocksDBStore::open_read_only(&db_path);
+                let report = CommitReplayer::new(
+                    epoch_start_state.epoch_start_timestamp_ms(),
+                    own_index,
+                    committee,
+                    protocol_config,
+                )
+                .replay(&rocks_db_store)
+                .await?;
+
+                println!(
+                    "Read {} blocks, replayed {} commits, {} commits persisted",
+                    report.blocks, report.replayed_commits, report.persisted_commits
+                );
+                for divergence in &report.divergences {
+                    println!("{divergence}");
+                }
+                println!("Found {} divergences", report.divergences.len());
+            }
             ToolCommand::LockedObject {
                 id,
                 fullnode_rpc_url,
@@ -683,6 +825,9 @@ impl ToolCommand {
                 latest,
                 verbose,
                 max_retries,
+                filter_types,
+                filter_owners,
+                filter_packages,
             } => {
                 if !verbose {
                     tracing_handle
@@ -801,6 +946,15 @@ impl ToolCommand {
                 }
 
                 let verify = verify.unwrap_or_default();
+                let filter = filter_types
+                    .into_iter()
+                    .fold(ObjectFilter::new(), ObjectFilter::with_type);
+                let filter = filter_owners
+                    .into_iter()
+                    .fold(filter, ObjectFilter::with_owner);
+                let filter = filter_packages
+                    .into_iter()
+                    .fold(filter, ObjectFilter::with_package);
                 download_formal_snapshot(
                     &path,
                     epoch_to_download,
@@ -811,6 +965,7 @@ impl ToolCommand {
                     network,
                     verify,
                     max_retries,
+                    (!filter.is_empty()).then_some(filter),
                 )
                 .await?;
             }
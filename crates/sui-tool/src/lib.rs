@@ -51,6 +51,7 @@ use sui_core::authority::authority_store_tables::AuthorityPerpetualTables;
 use sui_core::checkpoints::CheckpointStore;
 use sui_core::epoch::committee_store::CommitteeStore;
 use sui_core::storage::RocksDbStore;
+use sui_snapshot::filter::ObjectFilter;
 use sui_snapshot::reader::StateSnapshotReaderV1;
 use sui_snapshot::setup_db_state;
 use sui_storage::object_store::ObjectStoreGetExt;
@@ -786,7 +787,15 @@ pub async fn download_formal_snapshot(
     network: Chain,
     verify: SnapshotVerifyMode,
     max_retries: usize,
+    filter: Option<ObjectFilter>,
 ) -> Result<(), anyhow::Error> {
+    // Strict verification re-accumulates the restored store, which only holds part of the live
+    // object set when filtering.
+    if filter.is_some() && verify == SnapshotVerifyMode::Strict {
+        return Err(anyhow!(
+            "Strict verification is not supported when restoring a filtered snapshot"
+        ));
+    }
     let m = MultiProgress::new();
     m.println(format!(
         "Beginning formal snapshot restore to end of epoch {}, network: {:?}, verification mode: {:?}",
@@ -861,6 +870,7 @@ pub async fn download_formal_snapshot(
 
     let (_abort_handle, abort_registration) = AbortHandle::new_pair();
     let perpetual_db_clone = perpetual_db.clone();
+    let filtered = filter.is_some();
     let snapshot_dir = path.parent().unwrap().join("snapshot");
     if snapshot_dir.exists() {
         fs::remove_dir_all(snapshot_dir.clone())?;
@@ -883,12 +893,42 @@ pub async fn download_formal_snapshot(
             &snapshot_store_config,
             &local_store_config,
             NonZeroUsize::new(num_parallel_downloads).unwrap(),
-            m_clone,
+            m_clone.clone(),
             false, // skip_reset_local_store
             max_retries,
         )
         .await
         .unwrap_or_else(|err| panic!("Failed to create reader: {}", err));
+        // Epochs without a full snapshot may still have a delta snapshot, which is restored along
+        // with the chain of snapshots it builds on.
+        if let Some(base_epoch) = reader.base_epoch() {
+            assert!(
+                filter.is_none(),
+                "Snapshot for epoch {epoch} is a delta over epoch {base_epoch}, which does not \
+                support filtered restores",
+            );
+            drop(reader);
+            let (root_state_hash, num_live_objects) = StateSnapshotReaderV1::restore_with_deltas(
+                epoch,
+                &snapshot_store_config,
+                &local_store_config,
+                NonZeroUsize::new(num_parallel_downloads).unwrap(),
+                m_clone,
+                max_retries,
+                &perpetual_db_clone,
+                None, // verified against the end of epoch commitment below
+            )
+            .await
+            .unwrap_or_else(|err| panic!("Failed during read: {}", err));
+            sender
+                .send((root_state_hash, num_live_objects))
+                .await
+                .expect("Failed to send root state hash");
+            return Ok::<(), anyhow::Error>(());
+        }
+        if let Some(filter) = filter {
+            reader = reader.with_filter(filter);
+        }
         reader
             .read(&perpetual_db_clone, abort_registration, Some(sender))
             .await
@@ -971,18 +1011,22 @@ pub async fn download_formal_snapshot(
     // in checkpoint store, but not in the corresponding functions in ObjectStore trait
     checkpoint_store.insert_epoch_last_checkpoint(epoch, &last_checkpoint)?;
 
-    setup_db_state(
-        epoch,
-        root_global_state_hash.clone(),
-        perpetual_db.clone(),
-        checkpoint_store.clone(),
-        committee_store,
-        network,
-        verify == SnapshotVerifyMode::Strict,
-        num_live_objects,
-        m.clone(),
-    )
-    .await?;
+    // A filtered store only holds part of the live object set, and cannot be used to start a
+    // node from, so it is not set up as one.
+    if !filtered {
+        setup_db_state(
+            epoch,
+            root_global_state_hash.clone(),
+            perpetual_db.clone(),
+            checkpoint_store.clone(),
+            committee_store,
+            network,
+            verify == SnapshotVerifyMode::Strict,
+            num_live_objects,
+            m.clone(),
+        )
+        .await?;
+    }
 
     // Wait for backfill to complete
     backfill_handle.await.expect("Task join failed")?;
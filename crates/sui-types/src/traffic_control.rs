@@ -3,6 +3,7 @@
 
 use serde::{Deserialize, Serialize, de::Deserializer};
 use serde_with::serde_as;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 // These values set to loosely attempt to limit
@@ -218,6 +219,112 @@ fn default_sketch_tolerance() -> f64 {
     DEFAULT_SKETCH_TOLERANCE
 }
 
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TokenBucketConfig {
+    /// Number of requests a client can burst before being blocked.
+    #[serde(default = "default_bucket_capacity")]
+    pub client_capacity: u64,
+    /// Rate (requests per second) at which a client's burst allowance
+    /// is replenished, i.e. its sustained request rate.
+    #[serde(default = "default_bucket_refill_rate")]
+    pub client_refill_rate: f64,
+    #[serde(default = "default_proxied_bucket_capacity")]
+    pub proxied_client_capacity: u64,
+    #[serde(default = "default_proxied_bucket_refill_rate")]
+    pub proxied_client_refill_rate: f64,
+    /// Upper bound on the number of clients tracked at once. Clients whose
+    /// buckets have refilled carry no state, and are evicted first.
+    #[serde(default = "default_max_tracked_clients")]
+    pub max_tracked_clients: usize,
+}
+
+impl Default for TokenBucketConfig {
+    fn default() -> Self {
+        Self {
+            client_capacity: default_bucket_capacity(),
+            client_refill_rate: default_bucket_refill_rate(),
+            proxied_client_capacity: default_proxied_bucket_capacity(),
+            proxied_client_refill_rate: default_proxied_bucket_refill_rate(),
+            max_tracked_clients: default_max_tracked_clients(),
+        }
+    }
+}
+
+fn default_bucket_capacity() -> u64 {
+    // As with the frequency threshold, a direct client may be a fullnode
+    // proxying traffic for many clients, so only block extreme bursts
+    // by default.
+    1_000_000
+}
+
+fn default_bucket_refill_rate() -> f64 {
+    100_000.0
+}
+
+fn default_proxied_bucket_capacity() -> u64 {
+    100
+}
+
+fn default_proxied_bucket_refill_rate() -> f64 {
+    10.0
+}
+
+fn default_max_tracked_clients() -> usize {
+    100_000
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ErrorWeightedConfig {
+    /// Weight that each error type counts towards a client's score, keyed by
+    /// error type (e.g. "InvalidSignature"). Error types that are not listed
+    /// count with `default-weight`.
+    #[serde(default)]
+    pub error_weights: BTreeMap<String, f64>,
+    #[serde(default = "default_error_weight")]
+    pub default_weight: f64,
+    /// Score at which a client is blocked.
+    #[serde(default = "default_error_score_threshold")]
+    pub client_threshold: f64,
+    #[serde(default = "default_error_score_threshold")]
+    pub proxied_client_threshold: f64,
+    /// Time it takes for a client's score to decay by half.
+    #[serde(default = "default_half_life_secs")]
+    pub half_life_secs: u64,
+    /// Upper bound on the number of clients tracked at once. Clients with
+    /// the lowest scores are evicted first.
+    #[serde(default = "default_max_tracked_clients")]
+    pub max_tracked_clients: usize,
+}
+
+impl Default for ErrorWeightedConfig {
+    fn default() -> Self {
+        Self {
+            error_weights: BTreeMap::new(),
+            default_weight: default_error_weight(),
+            client_threshold: default_error_score_threshold(),
+            proxied_client_threshold: default_error_score_threshold(),
+            half_life_secs: default_half_life_secs(),
+            max_tracked_clients: default_max_tracked_clients(),
+        }
+    }
+}
+
+fn default_error_weight() -> f64 {
+    1.0
+}
+
+fn default_error_score_threshold() -> f64 {
+    100.0
+}
+
+fn default_half_life_secs() -> u64 {
+    30
+}
+
 // Serializable representation of policy types, used in config
 // in order to easily change in tests or to killswitch
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
@@ -232,6 +339,28 @@ pub enum PolicyType {
     #[serde(rename = "freq-threshold", alias = "FreqThreshold")]
     FreqThreshold(FreqThresholdConfig),
 
+    /// Blocks a client once it has exhausted a bucket of `capacity` tokens, one
+    /// token per tally, which refills at `refill-rate` tokens per second. Unlike
+    /// `freq-threshold`, this allows short bursts while bounding the sustained rate.
+    #[serde(rename = "token-bucket", alias = "TokenBucket")]
+    TokenBucket(TokenBucketConfig),
+
+    /// Blocks a client once its score reaches a threshold, where each tally adds
+    /// the weight of its error type (e.g. invalid signatures can count for more
+    /// than other errors), and scores decay exponentially over time.
+    #[serde(rename = "error-weighted", alias = "ErrorWeighted")]
+    ErrorWeighted(ErrorWeightedConfig),
+
+    /// Passes each tally to all of the given policies, and blocks a client if
+    /// any of them would.
+    #[serde(rename = "any-of", alias = "AnyOf")]
+    AnyOf(Vec<PolicyType>),
+
+    /// Passes each tally to all of the given policies, and blocks a client only
+    /// if all of them would.
+    #[serde(rename = "all-of", alias = "AllOf")]
+    AllOf(Vec<PolicyType>),
+
     /* Below this point are test policies, and thus should not be used in production */
     ///
     /// Simple policy that adds connection_ip to blocklist when the same connection_ip
@@ -276,6 +405,11 @@ pub struct PolicyConfig {
     /// and any blocklist related configuration will be ignored.
     #[serde(default)]
     pub allow_list: Option<Vec<String>>,
+    /// Directory in which to persist blocklist state across restarts, along with
+    /// an append-only audit log of every block and unblock. If unset, blocklists
+    /// are kept in memory only.
+    #[serde(default)]
+    pub blocklist_persistence_path: Option<PathBuf>,
 }
 
 impl Default for PolicyConfig {
@@ -290,6 +424,7 @@ impl Default for PolicyConfig {
             spam_sample_rate: default_spam_sample_rate(),
             dry_run: default_dry_run(),
             allow_list: None,
+            blocklist_persistence_path: None,
         }
     }
 }
@@ -52,6 +52,7 @@ use sui_json_rpc_types::{
 };
 use sui_keys::key_identity::KeyIdentity;
 use sui_keys::keystore::AccountKeystore;
+use sui_keys::signing_policy::{PolicyDecision, ResolvedInputs};
 use sui_move_build::{
     BuildConfig, CompiledPackage, build_from_resolution_graph, check_conflicting_addresses,
     check_invalid_dependencies, check_unpublished_dependencies, gather_published_ids,
@@ -3328,6 +3329,7 @@ pub(crate) async fn dry_run_or_execute_or_serialize(
     } else if tx_digest {
         Ok(SuiClientCommandResult::ComputeTransactionDigest(tx_data))
     } else {
+        check_signing_policy(&client, context, signer, &tx_data).await?;
         let mut signatures = vec![
             context
                 .config
@@ -3340,6 +3342,7 @@ pub(crate) async fn dry_run_or_execute_or_serialize(
         if let Some(gas_sponsor) = gas_sponsor
             && gas_sponsor != signer
         {
+            check_signing_policy(&client, context, gas_sponsor, &tx_data).await?;
             signatures.push(
                 context
                     .config
@@ -3412,6 +3415,76 @@ async fn execute_dev_inspect(
     Ok(SuiClientCommandResult::DevInspect(dev_inspect_result))
 }
 
+/// Evaluate the keystore's signing policy (if any) for `signer` signing `tx_data`, with the object
+/// inputs of the transaction read from the network, and ask for confirmation if the policy requires
+/// it. The keystore then signs the transaction without evaluating the policy again.
+async fn check_signing_policy(
+    client: &SuiClient,
+    context: &WalletContext,
+    signer: SuiAddress,
+    tx_data: &TransactionData,
+) -> Result<(), anyhow::Error> {
+    let Some(policy) = context.config.keystore.signing_policy() else {
+        return Ok(());
+    };
+
+    let inputs = resolve_policy_inputs(client.read_api(), tx_data).await?;
+    policy.authorize(signer, tx_data, &inputs, |decision| {
+        confirm_signing(signer, decision)
+    })
+}
+
+/// Read what signing policies need to know about the object inputs of `tx_data` from the network.
+/// Objects that can't be read stay unresolved, and are treated as coins of unbounded value.
+pub(crate) async fn resolve_policy_inputs(
+    read_api: &ReadApi,
+    tx_data: &TransactionData,
+) -> Result<ResolvedInputs, anyhow::Error> {
+    let object_ids = tx_data
+        .input_objects()?
+        .iter()
+        .filter(|input| !matches!(input, InputObjectKind::MovePackage(_)))
+        .map(|input| input.object_id())
+        .collect();
+    let objects = read_api
+        .multi_get_object_with_options(
+            object_ids,
+            SuiObjectDataOptions::new().with_type().with_bcs(),
+        )
+        .await?;
+
+    let mut inputs = ResolvedInputs::default();
+    for object in objects.into_iter().filter_map(|o| o.data) {
+        if !object.type_.as_ref().is_some_and(ObjectType::is_gas_coin) {
+            inputs.insert_other(object.object_id);
+        } else if let Some(SuiRawData::MoveObject(raw)) = &object.bcs
+            && let Ok(coin) = bcs::from_bytes::<GasCoin>(&raw.bcs_bytes)
+        {
+            inputs.insert_sui_coin(object.object_id, coin.value());
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// Ask on the terminal whether `signer` should sign a transaction despite the signing policy's
+/// `decision`.
+pub(crate) fn confirm_signing(
+    signer: SuiAddress,
+    decision: &PolicyDecision,
+) -> Result<bool, anyhow::Error> {
+    ensure!(
+        std::io::IsTerminal::is_terminal(&std::io::stdin()),
+        "Signing for {signer} {decision}, but there is no terminal to confirm on"
+    );
+
+    eprint!("Signing for {signer} {decision}. Sign anyway? [y/N] ");
+    std::io::Write::flush(&mut std::io::stderr())?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
 pub(crate) async fn prerender_clever_errors(
     effects: &mut SuiTransactionBlockEffects,
     read_api: &ReadApi,
@@ -43,6 +43,9 @@ pub const DEV_INSPECT: &str = "dev-inspect";
 pub const SERIALIZE_UNSIGNED: &str = "serialize-unsigned-transaction";
 pub const SERIALIZE_SIGNED: &str = "serialize-signed-transaction";
 pub const SENDER: &str = "sender";
+pub const REPEAT: &str = "repeat";
+pub const FOR: &str = "for";
+pub const END: &str = "end";
 
 // Types
 pub const U8: &str = "u8";
@@ -59,6 +62,7 @@ pub const VECTOR: &str = "vector";
 pub const SOME: &str = "some";
 pub const NONE: &str = "none";
 pub const GAS: &str = "gas";
+pub const IN: &str = "in";
 
 pub const KEYWORDS: &[&str] = &[
     ADDRESS, BOOL, VECTOR, SOME, NONE, GAS, U8, U16, U32, U64, U128, U256,
@@ -85,6 +89,9 @@ pub const COMMANDS: &[&str] = &[
     SERIALIZE_UNSIGNED,
     SERIALIZE_SIGNED,
     SENDER,
+    REPEAT,
+    FOR,
+    END,
 ];
 
 pub fn is_keyword(s: &str) -> bool {
@@ -154,6 +161,15 @@ pub enum ParsedPTBCommand {
     Upgrade(Spanned<String>, Spanned<Argument>),
     WarnShadows,
     Preview,
+    /// `--repeat <count> <command> ... --end`: The body is unrolled `count` times.
+    Repeat(Spanned<u64>, Vec<Spanned<ParsedPTBCommand>>),
+    /// `--for <variable> in [<value>, ...] <command> ... --end`: The body is unrolled once per
+    /// value, with `variable` bound to that value.
+    For(
+        Spanned<String>,
+        Spanned<Vec<Spanned<Argument>>>,
+        Vec<Spanned<ParsedPTBCommand>>,
+    ),
 }
 
 /// An enum representing the parsed arguments of a PTB command.
@@ -423,6 +439,18 @@ impl fmt::Display for ParsedPTBCommand {
             ParsedPTBCommand::Upgrade(s, a) => write!(f, "{UPGRADE} {} {}", s.value, a.value),
             ParsedPTBCommand::WarnShadows => write!(f, "{WARN_SHADOWS}"),
             ParsedPTBCommand::Preview => write!(f, "{PREVIEW}"),
+            ParsedPTBCommand::Repeat(count, body) => {
+                write!(f, "{REPEAT} {} {{ ", count.value)?;
+                delimited_list(f, "; ", body.iter().map(|x| &x.value))?;
+                write!(f, " }}")
+            }
+            ParsedPTBCommand::For(var, values, body) => {
+                write!(f, "{FOR} {} {IN} [", var.value)?;
+                delimited_list(f, ", ", values.value.iter().map(|x| &x.value))?;
+                write!(f, "] {{ ")?;
+                delimited_list(f, "; ", body.iter().map(|x| &x.value))?;
+                write!(f, " }}")
+            }
             ParsedPTBCommand::MakeMoveVec(ty, args) => {
                 write!(f, "{MAKE_MOVE_VEC} <",)?;
                 write!(f, "{}", TyDisplay(&ty.value))?;
@@ -28,10 +28,16 @@ use move_core_types::{
     },
 };
 use move_package::BuildConfig as MoveBuildConfig;
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
 use sui_json::{is_receiving_argument, primitive_type};
-use sui_json_rpc_types::{SuiObjectData, SuiObjectDataOptions, SuiRawData};
+use sui_json_rpc_types::{
+    ProtocolConfigResponse, SuiObjectData, SuiObjectDataOptions, SuiProtocolConfigValue, SuiRawData,
+};
 use sui_move::manage_package::resolve_lock_file_path;
+use sui_protocol_config::ProtocolConfig;
 use sui_sdk::apis::ReadApi;
 use sui_types::{
     Identifier, SUI_FRAMEWORK_PACKAGE_ID, TypeTag,
@@ -242,6 +248,10 @@ pub struct PTBBuilder<'a> {
     /// The list of errors that we have built up while processing commands. We do not report errors
     /// eagerly but instead wait until we have processed all commands to report any errors.
     errors: Vec<PTBError>,
+    /// The spans and messages of `errors`. Commands unrolled from a loop keep the spans of the
+    /// source they were copied from, so the same error is only reported once, rather than once per
+    /// iteration.
+    reported_errors: HashSet<(Span, String)>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -296,9 +306,16 @@ impl<'a> PTBBuilder<'a> {
             reader,
             last_command: None,
             errors: Vec::new(),
+            reported_errors: HashSet::new(),
         }
     }
 
+    /// The maximum number of commands a PTB can contain on the connected network. See
+    /// [max_commands].
+    async fn max_commands(&self) -> u64 {
+        max_commands(self.reader.get_protocol_config(None).await.ok().as_ref())
+    }
+
     /// Finalize a PTB. If there were errors during the construction of the PTB these are returned
     /// now. Otherwise, the PTB is finalized and returned.
     /// If the warn_on_shadowing flag was set, then we will print warnings for any shadowed
@@ -356,16 +373,26 @@ impl<'a> PTBBuilder<'a> {
         Result<Tx::ProgrammableTransaction, Vec<PTBError>>,
         Vec<PTBError>,
     ) {
-        for command in program.commands.into_iter() {
-            self.handle_command(command).await;
+        let max_commands = self.max_commands().await;
+        match unroll_loops(program.commands, max_commands) {
+            Ok(commands) => {
+                for command in commands {
+                    self.handle_command(command).await;
+                }
+            }
+            Err(e) => self.errors.push(e),
         }
         self.finish(program.warn_shadows_set)
     }
 
     /// Add a single PTB command to the PTB that we are building up.
-    /// Errors are added to the `errors` field of the PTBBuilder.
+    /// Errors are added to the `errors` field of the PTBBuilder. Commands that come from unrolling
+    /// a loop can fail in the same way on every iteration, so each distinct error is only reported
+    /// once.
     async fn handle_command(&mut self, sp!(span, command): Spanned<ParsedPTBCommand>) {
-        if let Err(e) = self.handle_command_(span, command).await {
+        if let Err(e) = self.handle_command_(span, command).await
+            && self.reported_errors.insert((e.span, e.message.clone()))
+        {
             self.errors.push(e);
         }
     }
@@ -1086,11 +1113,239 @@ impl<'a> PTBBuilder<'a> {
             }
             ParsedPTBCommand::WarnShadows => {}
             ParsedPTBCommand::Preview => {}
+            ParsedPTBCommand::Repeat(..) | ParsedPTBCommand::For(..) => {
+                error!(cmd_span, "Loops must be unrolled before the PTB is built")
+            }
         }
         Ok(())
     }
 }
 
+// ===========================================================================
+// Loop unrolling
+// ===========================================================================
+// `--repeat` and `--for` loops are a compile-time construct: before the PTB is built, each loop is
+// replaced by copies of its body, with the loop variable (for `--for` loops) replaced by each of
+// the values being looped over. Commands in an unrolled body keep the spans of the commands they
+// were copied from, and loop values keep the spans of the values in the loop header, so errors
+// point back at the source of the loop.
+
+/// The maximum number of commands a PTB can contain, according to the network's protocol
+/// `config`. If the config could not be fetched, or does not set a limit, this falls back to the
+/// limit in the latest protocol version known locally, so that loops are never unrolled without a
+/// bound.
+pub(crate) fn max_commands(config: Option<&ProtocolConfigResponse>) -> u64 {
+    let limit =
+        config.and_then(
+            |config| match config.attributes.get("max_programmable_tx_commands")? {
+                Some(SuiProtocolConfigValue::U32(max)) => Some(*max as u64),
+                _ => None,
+            },
+        );
+
+    limit.unwrap_or_else(|| {
+        ProtocolConfig::get_for_max_version_UNSAFE().max_programmable_tx_commands() as u64
+    })
+}
+
+/// Unroll all the loops in `commands`, erroring if the resulting PTB would contain more than
+/// `max_commands` transaction commands. The limit is checked before each command is unrolled.
+pub(crate) fn unroll_loops(
+    commands: Vec<Spanned<ParsedPTBCommand>>,
+    max_commands: u64,
+) -> PTBResult<Vec<Spanned<ParsedPTBCommand>>> {
+    let mut total: u64 = 0;
+    let mut unrolled = vec![];
+
+    for sp!(sp, command) in commands {
+        let len = unrolled_len(&command);
+        total = total.saturating_add(len);
+
+        if total > max_commands {
+            let help = format!(
+                "PTBs can contain at most {max_commands} commands, consider splitting this \
+                 transaction into multiple PTBs."
+            );
+            if matches!(
+                command,
+                ParsedPTBCommand::Repeat(..) | ParsedPTBCommand::For(..)
+            ) {
+                error!(
+                    sp => help: { "{help}" },
+                    "This loop unrolls to {len} commands, taking the PTB over the limit of \
+                     {max_commands} commands",
+                );
+            } else {
+                error!(
+                    sp => help: { "{help}" },
+                    "This command takes the PTB over the limit of {max_commands} commands",
+                );
+            }
+        }
+
+        unroll(sp.wrap(command), &mut unrolled)?;
+    }
+
+    Ok(unrolled)
+}
+
+/// The number of transaction commands that `command` adds to the PTB, once unrolled.
+fn unrolled_len(command: &ParsedPTBCommand) -> u64 {
+    use ParsedPTBCommand as C;
+    match command {
+        C::TransferObjects(..)
+        | C::SplitCoins(..)
+        | C::MergeCoins(..)
+        | C::MakeMoveVec(..)
+        | C::MoveCall(..)
+        | C::Publish(..) => 1,
+        // Authorizing, performing, and committing the upgrade.
+        C::Upgrade(..) => 3,
+        C::Assign(..) | C::WarnShadows | C::Preview => 0,
+        C::Repeat(sp!(_, count), body) => count.saturating_mul(body_len(body)),
+        C::For(_, sp!(_, values), body) => (values.len() as u64).saturating_mul(body_len(body)),
+    }
+}
+
+fn body_len(body: &[Spanned<ParsedPTBCommand>]) -> u64 {
+    body.iter()
+        .map(|sp!(_, command)| unrolled_len(command))
+        .fold(0, u64::saturating_add)
+}
+
+/// Unroll `command` (if it is a loop) and add the resulting commands to `out`.
+fn unroll(
+    sp!(sp, command): Spanned<ParsedPTBCommand>,
+    out: &mut Vec<Spanned<ParsedPTBCommand>>,
+) -> PTBResult<()> {
+    match command {
+        ParsedPTBCommand::Repeat(sp!(_, count), body) => {
+            // A body that adds no commands to the PTB (e.g. only assignments) has the same effect
+            // however many times it is repeated, and could otherwise be repeated an unbounded
+            // number of times.
+            let count = if body_len(&body) == 0 {
+                count.min(1)
+            } else {
+                count
+            };
+
+            for _ in 0..count {
+                for command in &body {
+                    unroll(command.clone(), out)?;
+                }
+            }
+        }
+
+        ParsedPTBCommand::For(sp!(_, var), sp!(_, values), body) => {
+            for value in &values {
+                for command in &body {
+                    unroll(substitute_command(command, &var, value)?, out)?;
+                }
+            }
+        }
+
+        command => out.push(sp.wrap(command)),
+    }
+
+    Ok(())
+}
+
+/// Replace uses of the loop variable `var` in `command` with `value`.
+fn substitute_command(
+    sp!(sp, command): &Spanned<ParsedPTBCommand>,
+    var: &str,
+    value: &Spanned<PTBArg>,
+) -> PTBResult<Spanned<ParsedPTBCommand>> {
+    use ParsedPTBCommand as C;
+
+    let arg = |arg: &Spanned<PTBArg>| substitute_arg(arg, var, value);
+    let args = |sp!(sp, args): &Spanned<Vec<Spanned<PTBArg>>>| -> PTBResult<_> {
+        Ok(sp.wrap(args.iter().map(arg).collect::<PTBResult<Vec<_>>>()?))
+    };
+    let body = |body: &Vec<Spanned<ParsedPTBCommand>>| {
+        body.iter()
+            .map(|command| substitute_command(command, var, value))
+            .collect::<PTBResult<Vec<_>>>()
+    };
+
+    Ok(sp.wrap(match command {
+        C::TransferObjects(objs, to) => C::TransferObjects(args(objs)?, arg(to)?),
+        C::SplitCoins(coin, amounts) => C::SplitCoins(arg(coin)?, args(amounts)?),
+        C::MergeCoins(coin, coins) => C::MergeCoins(arg(coin)?, args(coins)?),
+        C::MakeMoveVec(ty, elems) => C::MakeMoveVec(ty.clone(), args(elems)?),
+        C::MoveCall(function, ty_args, call_args) => C::MoveCall(
+            function.clone(),
+            ty_args.clone(),
+            call_args.iter().map(arg).collect::<PTBResult<_>>()?,
+        ),
+
+        C::Assign(sp!(ident_loc, ident), _) if ident == var => {
+            error!(*ident_loc => help: {
+                "Assign the value to a different variable name."
+            },
+            "Cannot assign to loop variable '{var}'")
+        }
+
+        C::Assign(ident, assigned) => {
+            C::Assign(ident.clone(), assigned.as_ref().map(arg).transpose()?)
+        }
+        C::Upgrade(path, cap) => C::Upgrade(path.clone(), arg(cap)?),
+        C::Repeat(count, inner) => C::Repeat(*count, body(inner)?),
+
+        // An inner loop over a variable with the same name shadows this loop's variable.
+        C::For(inner_var, values, inner) if inner_var.value == var => {
+            C::For(inner_var.clone(), args(values)?, inner.clone())
+        }
+
+        C::For(inner_var, values, inner) => C::For(inner_var.clone(), args(values)?, body(inner)?),
+
+        command @ (C::Publish(_) | C::WarnShadows | C::Preview) => command.clone(),
+    }))
+}
+
+/// Replace uses of the loop variable `var` in `arg` with `value`.
+fn substitute_arg(
+    arg: &Spanned<PTBArg>,
+    var: &str,
+    value: &Spanned<PTBArg>,
+) -> PTBResult<Spanned<PTBArg>> {
+    let sp!(sp, arg) = arg;
+    Ok(match arg {
+        PTBArg::Identifier(ident) if ident == var => value.clone(),
+
+        PTBArg::VariableAccess(sp!(_, head), fields) if head == var => match &value.value {
+            PTBArg::Identifier(ident) => sp.wrap(PTBArg::VariableAccess(
+                value.span.wrap(ident.clone()),
+                fields.clone(),
+            )),
+
+            PTBArg::VariableAccess(head, prefix) => sp.wrap(PTBArg::VariableAccess(
+                head.clone(),
+                prefix.iter().chain(fields).cloned().collect(),
+            )),
+
+            other => error!(*sp => help: {
+                "Only values bound to variables have fields that can be accessed."
+            },
+            "Cannot access a field of loop variable '{var}', bound to '{other}'"),
+        },
+
+        PTBArg::Vector(elems) => sp.wrap(PTBArg::Vector(
+            elems
+                .iter()
+                .map(|elem| substitute_arg(elem, var, value))
+                .collect::<PTBResult<_>>()?,
+        )),
+
+        PTBArg::Option(sp!(opt_sp, Some(inner))) => {
+            let sp!(_, inner) = substitute_arg(&opt_sp.wrap((**inner).clone()), var, value)?;
+            sp.wrap(PTBArg::Option(opt_sp.wrap(Some(Box::new(inner)))))
+        }
+
+        arg => sp.wrap(arg.clone()),
+    })
+}
+
 // ===========================================================================
 // Helper methods
 // ===========================================================================
@@ -8,7 +8,7 @@ use thiserror::Error;
 pub type PTBResult<T> = Result<T, PTBError>;
 
 /// Represents the location of a range of text in the PTB source.
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
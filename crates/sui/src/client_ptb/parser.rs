@@ -33,6 +33,9 @@ pub struct ProgramParser<'a, I: Iterator<Item = &'a str>> {
 
 struct ProgramParsingState {
     parsed: Vec<Spanned<ParsedPTBCommand>>,
+    /// Loops whose `--end` has not been reached yet, innermost last. Commands are added to the
+    /// body of the innermost open loop, if there is one.
+    open_loops: Vec<OpenLoop>,
     errors: Vec<PTBError>,
     mvr_names_with_span: BTreeMap<String, Span>,
     preview_set: bool,
@@ -51,6 +54,17 @@ struct ProgramParsingState {
     sender: Option<Spanned<NumericalAddress>>,
 }
 
+/// The header of a `--repeat` or `--for` loop, and the commands in its body parsed so far.
+struct OpenLoop {
+    header: Spanned<LoopHeader>,
+    body: Vec<Spanned<ParsedPTBCommand>>,
+}
+
+enum LoopHeader {
+    Repeat(Spanned<u64>),
+    For(Spanned<String>, Spanned<Vec<Spanned<Argument>>>),
+}
+
 macro_rules! mvr_ident {
     () => {
         Token::Ident | Token::Number | Token::HexNumber
@@ -67,6 +81,7 @@ impl<'a, I: Iterator<Item = &'a str>> ProgramParser<'a, I> {
             tokens: tokens.peekable(),
             state: ProgramParsingState {
                 parsed: Vec::new(),
+                open_loops: Vec::new(),
                 errors: Vec::new(),
                 mvr_names_with_span: BTreeMap::new(),
                 preview_set: false,
@@ -112,7 +127,7 @@ impl<'a, I: Iterator<Item = &'a str>> ProgramParser<'a, I> {
                 ($args:expr) => {{
                     let sp!(sp_args, value) = try_!($args);
                     let cmd = sp.widen(sp_args).wrap(value);
-                    self.state.parsed.push(cmd);
+                    self.state.push_command(cmd);
                 }};
             }
 
@@ -187,6 +202,44 @@ impl<'a, I: Iterator<Item = &'a str>> ProgramParser<'a, I> {
                 L(T::Command, A::MAKE_MOVE_VEC) => command!(self.parse_make_move_vec()),
                 L(T::Command, A::MOVE_CALL) => command!(self.parse_move_call()),
 
+                L(T::Command, A::REPEAT) => {
+                    let count = try_!(self.parse_gas_denomination());
+                    let header = sp.widen(count.span).wrap(LoopHeader::Repeat(count));
+                    self.state.open_loops.push(OpenLoop {
+                        header,
+                        body: vec![],
+                    });
+                }
+
+                L(T::Command, A::FOR) => {
+                    let (var, values) = try_!(self.parse_for_header());
+                    let header = sp.widen(values.span).wrap(LoopHeader::For(var, values));
+                    self.state.open_loops.push(OpenLoop {
+                        header,
+                        body: vec![],
+                    });
+                }
+
+                L(T::Command, A::END) => {
+                    let Some(OpenLoop { header, body }) = self.state.open_loops.pop() else {
+                        self.state.errors.push(err!(
+                            sp => help: { "Loops are started with --{} or --{}", A::REPEAT, A::FOR },
+                            "Found --{} outside of a loop",
+                            A::END,
+                        ));
+                        self.fast_forward_to_next_command();
+                        continue;
+                    };
+
+                    let sp!(header_sp, header) = header;
+                    let cmd = match header {
+                        LoopHeader::Repeat(count) => ParsedPTBCommand::Repeat(count, body),
+                        LoopHeader::For(var, values) => ParsedPTBCommand::For(var, values, body),
+                    };
+
+                    self.state.push_command(header_sp.widen(sp).wrap(cmd));
+                }
+
                 L(T::Publish, src) => command!({
                     let src = sp.wrap(src.to_owned());
                     Ok(sp.wrap(ParsedPTBCommand::Publish(src)))
@@ -233,6 +286,14 @@ impl<'a, I: Iterator<Item = &'a str>> ProgramParser<'a, I> {
             }
         }
 
+        for OpenLoop { header, .. } in std::mem::take(&mut self.state.open_loops) {
+            self.state.errors.push(err!(
+                header.span => help: { "Close the loop with --{}", A::END },
+                "Loop is missing its --{}",
+                A::END,
+            ));
+        }
+
         let sp!(sp, tok) = self.peek();
 
         if !tok.is_terminal() {
@@ -270,6 +331,16 @@ impl<'a, I: Iterator<Item = &'a str>> ProgramParser<'a, I> {
     }
 }
 
+impl ProgramParsingState {
+    /// Add a parsed command to the innermost open loop, or to the program if there is none.
+    fn push_command(&mut self, cmd: Spanned<ParsedPTBCommand>) {
+        match self.open_loops.last_mut() {
+            Some(open) => open.body.push(cmd),
+            None => self.parsed.push(cmd),
+        }
+    }
+}
+
 /// Iterator convenience methods over tokens
 impl<'a, I: Iterator<Item = &'a str>> ProgramParser<'a, I> {
     /// Advance the iterator and return the next lexeme. If the next lexeme's token is not the
@@ -409,6 +480,39 @@ impl<'a, I: Iterator<Item = &'a str>> ProgramParser<'a, I> {
         Ok(sp.wrap(ParsedPTBCommand::MoveCall(function, ty_args, args)))
     }
 
+    /// Parse the header of a for loop.
+    /// The expected format is: `--for <variable> in [<value>, ...]`
+    #[allow(clippy::type_complexity)]
+    fn parse_for_header(
+        &mut self,
+    ) -> PTBResult<(Spanned<String>, Spanned<Vec<Spanned<Argument>>>)> {
+        use Lexeme as L;
+        use Token as T;
+
+        let sp!(sp, L(_, contents)) = self.expect(T::Ident)?;
+        if is_keyword(contents) {
+            error!(sp => help: {
+                "Variable names cannot be {}.",
+                all_keywords()
+            },
+            "Expected a variable name but found reserved word '{contents}'.");
+        }
+
+        let var = sp.wrap(contents.to_owned());
+
+        match self.peek() {
+            sp!(_, L(T::Ident, A::IN)) => self.bump(),
+            sp!(sp, lexeme) => error!(
+                sp => help: { "The expected format is: --{} <variable> in [<value>, ...]", A::FOR },
+                "Expected '{}' but found {lexeme}",
+                A::IN,
+            ),
+        }
+
+        let values = self.parse_array()?;
+        Ok((var, values))
+    }
+
     /// Parse a quantity of gas, as a numeric literal that is or can be inferred to be a u64.
     fn parse_gas_denomination(&mut self) -> PTBResult<Spanned<u64>> {
         Ok(match self.parse_argument()? {
@@ -908,6 +1012,98 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_loops() {
+        use crate::client_ptb::builder::unroll_loops;
+
+        let input = "--for r in [@1, @2] \
+                        --repeat 2 --split-coins gas [1] --assign c --transfer-objects [c] r --end \
+                     --end \
+                     --gas-budget 1";
+        let x = shlex::split(input).unwrap();
+        let parser = ProgramParser::new(x.iter().map(|x| x.as_str())).unwrap();
+        let (program, _) = parser.parse().unwrap();
+
+        assert_eq!(program.commands.len(), 1);
+        assert_eq!(
+            program.commands[0].value.to_string(),
+            "for r in [@1, @2] { repeat 2 { split-coins gas [1]; assign c; \
+             transfer-objects [c] r } }",
+        );
+
+        let unrolled: Vec<_> = unroll_loops(program.commands.clone(), 1024)
+            .unwrap()
+            .into_iter()
+            .map(|sp!(_, cmd)| cmd.to_string())
+            .collect();
+
+        assert_eq!(
+            unrolled,
+            vec![
+                "split-coins gas [1]",
+                "assign c",
+                "transfer-objects [c] @1",
+                "split-coins gas [1]",
+                "assign c",
+                "transfer-objects [c] @1",
+                "split-coins gas [1]",
+                "assign c",
+                "transfer-objects [c] @2",
+                "split-coins gas [1]",
+                "assign c",
+                "transfer-objects [c] @2",
+            ],
+        );
+
+        let loop_span = program.commands[0].span;
+        let err = unroll_loops(program.commands, 7).unwrap_err();
+        assert_eq!(err.span, loop_span);
+        assert!(err.message.contains("unrolls to 8 commands"));
+    }
+
+    #[test]
+    fn test_loop_limit_without_protocol_config() {
+        use crate::client_ptb::builder::{max_commands, unroll_loops};
+        use sui_protocol_config::ProtocolConfig;
+
+        // If the protocol config cannot be fetched, the limit from the latest known protocol
+        // version is used instead, and loops are still checked against it before being unrolled.
+        let local = ProtocolConfig::get_for_max_version_UNSAFE();
+        let limit = local.max_programmable_tx_commands() as u64;
+        assert_eq!(max_commands(None), limit);
+        assert_eq!(max_commands(Some(&local.into())), limit);
+
+        let input = "--repeat 18446744073709551615 --split-coins gas [1] --end --gas-budget 1";
+        let x = shlex::split(input).unwrap();
+        let parser = ProgramParser::new(x.iter().map(|x| x.as_str())).unwrap();
+        let (program, _) = parser.parse().unwrap();
+
+        let err = unroll_loops(program.commands, max_commands(None)).unwrap_err();
+        assert!(err.message.contains(&format!("limit of {limit} commands")));
+    }
+
+    #[test]
+    fn test_parse_loops_invalid() {
+        let inputs = vec![
+            // Unclosed loop
+            "--repeat 2 --split-coins gas [1]",
+            // End without a loop
+            "--split-coins gas [1] --end",
+            // Missing 'in'
+            "--for r [@0x1] --transfer-objects [gas] r --end",
+            // Reserved loop variable
+            "--for gas in [@0x1] --end",
+            // Count is not a number
+            "--repeat many --split-coins gas [1] --end",
+        ];
+
+        for input in inputs {
+            let x = shlex::split(input).unwrap();
+            let parser = ProgramParser::new(x.iter().map(|x| x.as_str())).unwrap();
+            assert!(parser.parse().is_err(), "{input}");
+        }
+    }
+
     #[test]
     fn test_parse_unexpected_top_level() {
         let input = "\"0x\" ";
@@ -9,6 +9,7 @@ use crate::{
         ast::{ParsedProgram, Program},
         builder::{PTBBuilder, resolve_package},
         error::{PTBError, Span, build_error_reports},
+        script::expand_args,
         token::{Lexeme, Token},
     },
     displays::Pretty,
@@ -81,10 +82,13 @@ impl PTB {
             ptb_description().print_help().unwrap();
             return Ok(());
         }
-        let source_string = to_source_string(self.args.clone());
+
+        // Splice in the contents of any PTB scripts
+        let args = expand_args(self.args)?;
+        let source_string = to_source_string(args.clone());
 
         // Tokenize once to detect help flags
-        let tokens = self.args.iter().map(|s| s.as_str());
+        let tokens = args.iter().map(|s| s.as_str());
         for sp!(_, lexeme) in Lexer::new(tokens.clone()).into_iter().flatten() {
             match lexeme {
                 Lexeme(Token::Command, "help") => return Ok(ptb_description().print_long_help()?),
@@ -361,6 +365,52 @@ pub fn ptb_description() -> clap::Command {
             --"dry-run"
             "Perform a dry run of the PTB instead of executing it."
         ))
+        .arg(arg!(
+            --"file" <PTB_SCRIPT_PATH>
+            "Run the PTB commands in a script file, in place of this argument."
+        )
+        .long_help(
+            "Run the PTB commands in a script file, in place of this argument. \
+            \n Scripts can split commands across multiple lines, and anything following a '#' \
+            is a comment. A script can include another with '--include <PATH>', where relative \
+            paths are resolved against the including script's directory. Scripts can also refer \
+            to parameters as ${NAME}, which are bound with --arg.\
+            \n\nExamples:\
+            \n --file release.ptb --arg recipient=0x42 --gas-budget 50000000"
+        )
+        .value_hint(ValueHint::FilePath))
+        .arg(arg!(
+            --"arg" <NAME_VALUE>
+            "Bind a parameter used by the PTB scripts passed with --file, as NAME=VALUE."
+        ))
+        .arg(arg!(
+            --"repeat" <COUNT>
+            "Repeat the commands up to the matching --end COUNT times."
+        )
+        .long_help(
+            "Repeat the commands up to the matching --end COUNT times. The loop is unrolled \
+            into ordinary commands before the PTB is built, so the unrolled PTB must fit within \
+            the protocol's limit on the number of commands in a PTB.\
+            \n\nExamples:\
+            \n --repeat 3 --split-coins gas [1000] --assign coin --transfer-objects [coin] @0x1 --end"
+        ))
+        .arg(arg!(
+            --"for" <VARIABLE_IN_VALUES>
+            "Repeat the commands up to the matching --end for every value in a list."
+        )
+        .long_help(
+            "Repeat the commands up to the matching --end for every value in a list, with the \
+            variable bound to that value. Like --repeat, the loop is unrolled into ordinary \
+            commands before the PTB is built. \
+            \n\nExamples:\
+            \n --split-coins gas [1000] --assign coin \
+            \n --for addr in [@0x1, @0x2] --split-coins coin [10] --assign c --transfer-objects [c] addr --end"
+        )
+        .value_names(["VARIABLE", "in", "[VALUES]"]))
+        .arg(arg!(
+            --"end"
+            "Close the innermost --repeat or --for loop."
+        ))
         .arg(arg!(
             --"dev-inspect"
             "Perform a dev-inspect of the PTB instead of executing it."
@@ -0,0 +1,320 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for PTB scripts: `.ptb` files holding PTB commands, which are expanded into the shell
+//! tokens the lexer consumes before the PTB is parsed.
+//!
+//! A script is split into tokens the same way a POSIX shell would split it, so commands can span
+//! multiple lines and anything following a `#` is a comment. On top of that, scripts support:
+//!
+//! - Parameters: `${name}` is replaced by the value passed for `name` with `--arg name=value` on
+//!   the command line, anywhere it appears in a token.
+//! - Includes: `--include path/to/other.ptb` is replaced by the (expanded) contents of that
+//!   script. Relative paths are resolved against the directory of the including script.
+//!
+//! Scripts are run with `sui client ptb --file path/to/script.ptb`, which can be combined with
+//! other PTB commands on the command line, e.g. to set the gas budget.
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Command line flag to run a script.
+const FILE_FLAG: &str = "--file";
+/// Command line flag to bind a parameter used by scripts.
+const ARG_FLAG: &str = "--arg";
+/// Script directive to splice the contents of another script in its place.
+const INCLUDE_DIRECTIVE: &str = "--include";
+
+/// Expands the shell tokens passed to `sui client ptb`, replacing every `--file <path>` with the
+/// commands in that script, and binding the script parameters passed with `--arg name=value`.
+///
+/// Shell tokens that do not relate to scripts are passed through as is.
+pub fn expand_args(args: Vec<String>) -> Result<Vec<String>> {
+    if !args.iter().any(|arg| arg == FILE_FLAG || arg == ARG_FLAG) {
+        return Ok(args);
+    }
+
+    // First pass: bind parameters, as they may be passed before or after the scripts using them.
+    let mut params = BTreeMap::new();
+    let mut rest = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg != ARG_FLAG {
+            rest.push(arg);
+            continue;
+        }
+
+        let param = args
+            .next()
+            .ok_or_else(|| anyhow!("Expected 'name=value' after {ARG_FLAG}"))?;
+        let (name, value) = param
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected 'name=value' after {ARG_FLAG}, got {param:?}"))?;
+        ensure!(
+            is_param_name(name),
+            "Invalid script parameter name {name:?}, names must be identifiers"
+        );
+        ensure!(
+            params.insert(name.to_string(), value.to_string()).is_none(),
+            "Script parameter {name:?} is bound more than once"
+        );
+    }
+
+    // Second pass: expand scripts in place.
+    let mut expanded = vec![];
+    let mut has_script = false;
+    let mut rest = rest.into_iter();
+    while let Some(arg) = rest.next() {
+        if arg != FILE_FLAG {
+            expanded.push(arg);
+            continue;
+        }
+
+        let path = rest
+            .next()
+            .ok_or_else(|| anyhow!("Expected a path to a PTB script after {FILE_FLAG}"))?;
+        expand_file(Path::new(&path), &params, &mut vec![], &mut expanded)?;
+        has_script = true;
+    }
+
+    ensure!(
+        has_script || params.is_empty(),
+        "{ARG_FLAG} can only be used to pass parameters to a PTB script run with {FILE_FLAG}"
+    );
+
+    Ok(expanded)
+}
+
+/// Expand the script at `path` into shell tokens, appending them to `out`. `stack` holds the
+/// canonical paths of the scripts currently being expanded, to detect include cycles.
+fn expand_file(
+    path: &Path,
+    params: &BTreeMap<String, String>,
+    stack: &mut Vec<PathBuf>,
+    out: &mut Vec<String>,
+) -> Result<()> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("Cannot find PTB script {}", path.display()))?;
+
+    if stack.contains(&canonical) {
+        bail!(
+            "PTB script {} includes itself: {}",
+            path.display(),
+            stack
+                .iter()
+                .chain([&canonical])
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        );
+    }
+
+    let contents = std::fs::read_to_string(&canonical)
+        .with_context(|| format!("Cannot read PTB script {}", path.display()))?;
+    let tokens = shlex::split(&contents).ok_or_else(|| {
+        anyhow!(
+            "Unterminated quote or escape in PTB script {}",
+            path.display()
+        )
+    })?;
+
+    let dir = canonical.parent().unwrap_or(Path::new(".")).to_path_buf();
+    stack.push(canonical);
+
+    let mut tokens = tokens.into_iter().filter(|t| !t.trim().is_empty());
+    while let Some(token) = tokens.next() {
+        if token == INCLUDE_DIRECTIVE {
+            let include = tokens.next().ok_or_else(|| {
+                anyhow!(
+                    "Expected a path after {INCLUDE_DIRECTIVE} in PTB script {}",
+                    path.display()
+                )
+            })?;
+            let include = substitute(&include, params)
+                .with_context(|| format!("In PTB script {}", path.display()))?;
+            expand_file(&dir.join(include), params, stack, out)?;
+        } else {
+            out.push(
+                substitute(&token, params)
+                    .with_context(|| format!("In PTB script {}", path.display()))?,
+            );
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+/// Replace every `${name}` in `token` with the value bound to `name` in `params`.
+fn substitute(token: &str, params: &BTreeMap<String, String>) -> Result<String> {
+    let mut result = String::with_capacity(token.len());
+    let mut rest = token;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow!("Unterminated parameter reference in {token:?}"))?;
+
+        let name = &after[..end];
+        ensure!(
+            is_param_name(name),
+            "Invalid parameter reference '${{{name}}}' in {token:?}"
+        );
+
+        let value = params.get(name).ok_or_else(|| {
+            anyhow!("Script parameter {name:?} is not bound, pass it with '{ARG_FLAG} {name}=...'")
+        })?;
+
+        result.push_str(value);
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn is_param_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn write(dir: &Path, name: &str, contents: &str) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn no_script() {
+        let input = args(&["--split-coins", "gas", "[1000]", "--assign", "coins"]);
+        assert_eq!(expand_args(input.clone()).unwrap(), input);
+
+        let err =
+            expand_args(args(&["--split-coins", "gas", "[1000]", "--arg", "x=1"])).unwrap_err();
+        assert!(format!("{err:#}").contains("can only be used"));
+    }
+
+    #[test]
+    fn comments_and_multiline_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = write(
+            dir.path(),
+            "split.ptb",
+            "# Split a coin\n\
+             --split-coins gas\n    [1000, 2000] # two amounts\n\
+             --assign coins\n",
+        );
+
+        assert_eq!(
+            expand_args(args(&["--file", &script, "--gas-budget", "100"])).unwrap(),
+            args(&[
+                "--split-coins",
+                "gas",
+                "[1000,",
+                "2000]",
+                "--assign",
+                "coins",
+                "--gas-budget",
+                "100",
+            ]),
+        );
+    }
+
+    #[test]
+    fn parameters() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = write(
+            dir.path(),
+            "transfer.ptb",
+            "--split-coins gas [${amount}]\n\
+             --assign coin\n\
+             --transfer-objects [coin] @${recipient}\n",
+        );
+
+        assert_eq!(
+            expand_args(args(&[
+                "--arg",
+                "recipient=0x42",
+                "--file",
+                &script,
+                "--arg",
+                "amount=1000",
+            ]))
+            .unwrap(),
+            args(&[
+                "--split-coins",
+                "gas",
+                "[1000]",
+                "--assign",
+                "coin",
+                "--transfer-objects",
+                "[coin]",
+                "@0x42",
+            ]),
+        );
+
+        let err = expand_args(args(&["--file", &script, "--arg", "amount=1000"])).unwrap_err();
+        assert!(format!("{err:#}").contains("\"recipient\" is not bound"));
+
+        let err = expand_args(args(&["--file", &script, "--arg", "amount"])).unwrap_err();
+        assert!(format!("{err:#}").contains("Expected 'name=value'"));
+    }
+
+    #[test]
+    fn includes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("common")).unwrap();
+        write(
+            dir.path(),
+            "common/split.ptb",
+            "--split-coins gas [${amount}]\n--assign coin\n",
+        );
+        let script = write(
+            dir.path(),
+            "main.ptb",
+            "--include common/split.ptb\n--transfer-objects [coin] @0x42\n",
+        );
+
+        assert_eq!(
+            expand_args(args(&["--file", &script, "--arg", "amount=5"])).unwrap(),
+            args(&[
+                "--split-coins",
+                "gas",
+                "[5]",
+                "--assign",
+                "coin",
+                "--transfer-objects",
+                "[coin]",
+                "@0x42",
+            ]),
+        );
+    }
+
+    #[test]
+    fn include_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.ptb", "--include b.ptb\n");
+        write(dir.path(), "b.ptb", "--include a.ptb\n");
+
+        let script = dir.path().join("a.ptb").to_string_lossy().to_string();
+        let err = expand_args(args(&["--file", &script])).unwrap_err();
+        assert!(format!("{err:#}").contains("includes itself"));
+    }
+}
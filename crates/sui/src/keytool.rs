@@ -1,5 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
+use crate::client_commands::{confirm_signing, resolve_policy_inputs};
+use crate::multisig_session::{MultiSigSessionCommand, MultiSigSessionOutput};
 use crate::zklogin_commands_util::{perform_zk_login_test_tx, read_cli_line};
 use anyhow::anyhow;
 use aws_sdk_kms::{
@@ -35,13 +37,21 @@ use std::fmt::{Debug, Display, Formatter};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use sui_config::SUI_CLIENT_CONFIG;
+use sui_keys::encrypted::{
+    NEW_PASSPHRASE_ENV_VAR, PASSPHRASE_ENV_VAR, read_new_passphrase, read_passphrase,
+};
 use sui_keys::key_derive::generate_new_key;
 use sui_keys::key_identity::KeyIdentity;
 use sui_keys::keypair_file::{
     read_authority_keypair_from_file, read_keypair_from_file, write_authority_keypair_to_file,
     write_keypair_to_file,
 };
-use sui_keys::keystore::{AccountKeystore, Keystore};
+use sui_keys::keystore::{AccountKeystore, EncryptedFileKeystore, Keystore};
+use sui_keys::shamir::{self, Secret, Share};
+use sui_keys::signing_policy::ResolvedInputs;
+use sui_sdk::SuiClientBuilder;
 use sui_types::base_types::SuiAddress;
 use sui_types::committee::EpochId;
 use sui_types::crypto::{DefaultHash, PublicKey};
@@ -76,6 +86,10 @@ pub enum KeyToolCommand {
         /// The alias must start with a letter and can contain only letters, digits, dots, hyphens (-), or underscores (_).
         new_alias: Option<String>,
     },
+    /// Change the passphrase of an encrypted keystore. The new passphrase is read from
+    /// SUI_KEYSTORE_NEW_PASSPHRASE if it is set, and prompted for otherwise. Ends any session
+    /// started by `sui keytool unlock`.
+    ChangePassphrase,
     /// Convert private key in Hex or Base64 to new format (Bech32
     /// encoded 33 byte flag || private key starting with "suiprivkey").
     /// Hex private key format import and export are both deprecated in
@@ -102,6 +116,11 @@ pub enum KeyToolCommand {
         #[clap(long, default_value = "0")]
         cur_epoch: u64,
     },
+    /// Encrypt the keys in Sui CLI Keystore with a passphrase, replacing the keystore file. The
+    /// passphrase is read from SUI_KEYSTORE_PASSPHRASE if it is set, and prompted for otherwise.
+    /// If a client.yaml next to the keystore refers to it, it is updated to use the encrypted
+    /// keystore.
+    EncryptKeystore,
     /// Generate a new keypair with key scheme flag {ed25519 | secp256k1 | secp256r1}
     /// with optional derivation path, default to m/44'/784'/0'/0'/0' for ed25519 or
     /// m/54'/784'/0'/0/0 for secp256k1 or m/74'/784'/0'/0/0 for secp256r1. Word
@@ -150,6 +169,8 @@ pub enum KeyToolCommand {
     /// (Base64 encoded `privkey`). This prints out the account keypair as Base64 encoded `flag || privkey`,
     /// the network keypair, worker keypair, protocol keypair as Base64 encoded `privkey`.
     LoadKeypair { file: PathBuf },
+    /// Lock an encrypted keystore, ending the session started by `sui keytool unlock`.
+    Lock,
     /// To MultiSig Sui Address. Pass in a list of all public keys `flag || pk` in Base64.
     /// See `keytool list` for example public keys.
     MultiSigAddress {
@@ -189,15 +210,61 @@ pub enum KeyToolCommand {
         #[clap(long)]
         threshold: ThresholdUnit,
     },
+    /// Collect the partial signatures of a MultiSig transaction in a session file that is passed
+    /// between signers, combining them once the threshold is met.
+    #[clap(name = "multisig-session")]
+    MultiSigSession {
+        #[clap(subcommand)]
+        cmd: MultiSigSessionCommand,
+    },
+    /// Recover a key from the backup shares created by `sui keytool split-backup`, and add it to
+    /// Sui CLI Keystore. Pass at least as many shares as the threshold of the backup, each as a
+    /// quoted list of words. If the backup is of a mnemonic phrase, the key is derived from it
+    /// with the key scheme flag {ed25519 | secp256k1 | secp256r1} (default ed25519) and optional
+    /// derivation path, as in `sui keytool import`.
+    RecoverBackup {
+        #[clap(long, num_args(1..), required = true)]
+        shares: Vec<String>,
+        /// Sets an alias for the recovered key. The alias must start with a letter and can contain only letters, digits, hyphens (-), or underscores (_).
+        #[clap(long)]
+        alias: Option<String>,
+        #[clap(long, default_value = "ed25519")]
+        key_scheme: SignatureScheme,
+        #[clap(long)]
+        derivation_path: Option<DerivationPath>,
+    },
 
     /// Read the content at the provided file path. The accepted format can be
     /// [enum SuiKeyPair] (Base64 encoded of 33-byte `flag || privkey`) or `type AuthorityKeyPair`
     /// (Base64 encoded `privkey`). It prints its Base64 encoded public key and the key scheme flag.
     Show { file: PathBuf },
+    /// Split the private key for the given address (or its alias) in Sui CLI Keystore, or a
+    /// mnemonic phrase, into backup shares, any `threshold` of which can recover it with
+    /// `sui keytool recover-backup`. Each share is output as a list of words ending in a
+    /// checksum, to be written down and stored separately from the other shares.
+    SplitBackup {
+        #[clap(
+            long,
+            conflicts_with = "mnemonic",
+            required_unless_present = "mnemonic"
+        )]
+        key_identity: Option<KeyIdentity>,
+        /// Back up this mnemonic phrase, instead of a key in Sui CLI Keystore.
+        #[clap(long)]
+        mnemonic: Option<String>,
+        /// The number of shares needed to recover the backup.
+        #[clap(long)]
+        threshold: u8,
+        /// The number of shares to split the backup into, at most 255.
+        #[clap(long)]
+        shares: u8,
+    },
     /// Create signature using the private key for the given address (or its alias) in sui keystore.
     /// Any signature commits to a [struct IntentMessage] consisting of the Base64 encoded
     /// of the BCS serialized transaction bytes itself and its intent. If intent is absent,
     /// default will be used.
+    ///
+    /// If the keystore has a signing policy, transactions are checked against it before signing.
     Sign {
         #[clap(long)]
         address: KeyIdentity,
@@ -205,6 +272,14 @@ pub enum KeyToolCommand {
         data: String,
         #[clap(long)]
         intent: Option<Intent>,
+        /// Full node RPC URL to read the transaction's object inputs from, so that the signing
+        /// policy can tell which of them are coins, and their balances. Without it, the policy
+        /// assumes that every owned object input is a coin of unbounded value.
+        #[clap(long)]
+        rpc_url: Option<String>,
+        /// Sign transactions that the signing policy requires confirmation for without asking.
+        #[clap(long)]
+        confirm: bool,
     },
     /// Creates a signature by leveraging AWS KMS. Pass in a key-id to leverage Amazon
     /// KMS to sign a message and the base64 pubkey.
@@ -226,6 +301,15 @@ pub enum KeyToolCommand {
     /// outputs the keypair into a file at the current directory where the address is the filename,
     /// and prints out its Sui address, Base64 encoded public key, the key scheme, and the key scheme flag.
     Unpack { keypair: String },
+    /// Unlock an encrypted keystore for a while, so that other commands can sign without asking
+    /// for its passphrase. The passphrase is read from SUI_KEYSTORE_PASSPHRASE if it is set, and
+    /// prompted for otherwise. Other commands join the session by setting SUI_KEYSTORE_SESSION to
+    /// the session token that is printed.
+    Unlock {
+        /// How long the keystore stays unlocked for, in minutes.
+        #[clap(long, default_value = "15")]
+        minutes: u64,
+    },
 
     /// Given the max_epoch, generate an OAuth url, ask user to paste the redirect with id_token, call salt server, then call the prover server,
     /// create a test transaction, use the ephemeral key to sign and execute it by assembling to a serialized zkLogin signature.
@@ -349,6 +433,21 @@ pub struct ExportedKey {
     key: Key,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeystoreStatus {
+    keystore_path: PathBuf,
+    locked: bool,
+    /// When the session started by `sui keytool unlock` expires, in milliseconds since the epoch.
+    unlocked_until_ms: Option<u64>,
+    /// Token of the session that was just started, to set SUI_KEYSTORE_SESSION to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_token: Option<String>,
+    /// The client config that was updated to refer to the encrypted keystore, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_config: Option<PathBuf>,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct KeypairData {
@@ -404,6 +503,21 @@ pub struct PrivateKeyBase64 {
     base64: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupShare {
+    index: u8,
+    words: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitBackupOutput {
+    sui_address: Option<SuiAddress>,
+    threshold: u8,
+    shares: Vec<BackupShare>,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SerializedSig {
@@ -465,15 +579,19 @@ pub enum CommandOutput {
     Generate(Key),
     Import(Key),
     Export(ExportedKey),
+    KeystoreStatus(KeystoreStatus),
     List(Vec<Key>),
     LoadKeypair(KeypairData),
     MultiSigAddress(MultiSigAddress),
     MultiSigCombinePartialSig(MultiSigCombinePartialSig),
     MultiSigCombinePartialSigLegacy(MultiSigCombinePartialSigLegacyOutput),
+    MultiSigSession(MultiSigSessionOutput),
+    RecoverBackup(Key),
     PrivateKeyBase64(PrivateKeyBase64),
     Show(Key),
     Sign(SignData),
     SignKMS(SerializedSig),
+    SplitBackup(SplitBackupOutput),
     ZkLoginSignAndExecuteTx(ZkLoginSignAndExecuteTx),
     ZkLoginInsecureSignPersonalMessage(ZkLoginInsecureSignPersonalMessage),
     ZkLoginSigVerify(ZkLoginSigVerifyResponse),
@@ -494,6 +612,17 @@ impl KeyToolCommand {
                     new_alias,
                 })
             }
+            KeyToolCommand::ChangePassphrase => {
+                let encrypted = encrypted_keystore(keystore)?;
+                let old_passphrase = read_passphrase(&format!(
+                    "Enter current passphrase for keystore {}: ",
+                    encrypted.path().display()
+                ))?;
+                let new_passphrase = read_new_passphrase(NEW_PASSPHRASE_ENV_VAR)?;
+                encrypted.change_passphrase(&old_passphrase, &new_passphrase)?;
+                CommandOutput::KeystoreStatus(KeystoreStatus::new(encrypted, None))
+            }
+
             KeyToolCommand::Convert { value } => {
                 let result = convert_private_key_to_bech32(value)?;
                 CommandOutput::Convert(result)
@@ -700,6 +829,39 @@ impl KeyToolCommand {
                 CommandOutput::List(keys)
             }
 
+            KeyToolCommand::EncryptKeystore => {
+                let Keystore::File(file) = keystore else {
+                    return Err(anyhow!(
+                        "Only a plain file-based keystore can be encrypted, found: {keystore}"
+                    ));
+                };
+                let passphrase = read_new_passphrase(PASSPHRASE_ENV_VAR)?;
+                let encrypted = EncryptedFileKeystore::migrate(file, &passphrase)?;
+                let client_config = use_encrypted_keystore_in_client_config(encrypted.path())?;
+                let status = KeystoreStatus::new(&encrypted, client_config);
+                *keystore = Keystore::Encrypted(encrypted);
+                CommandOutput::KeystoreStatus(status)
+            }
+
+            KeyToolCommand::Lock => {
+                let encrypted = encrypted_keystore(keystore)?;
+                encrypted.lock()?;
+                CommandOutput::KeystoreStatus(KeystoreStatus::new(encrypted, None))
+            }
+
+            KeyToolCommand::Unlock { minutes } => {
+                let encrypted = encrypted_keystore(keystore)?;
+                let passphrase = read_passphrase(&format!(
+                    "Enter passphrase for keystore {}: ",
+                    encrypted.path().display()
+                ))?;
+                let session =
+                    encrypted.unlock(&passphrase, Some(Duration::from_secs(minutes * 60)))?;
+                let mut status = KeystoreStatus::new(encrypted, None);
+                status.session_token = session.map(|session| session.token.to_string());
+                CommandOutput::KeystoreStatus(status)
+            }
+
             KeyToolCommand::LoadKeypair { file } => {
                 let output = match read_keypair_from_file(&file) {
                     Ok(keypair) => {
@@ -801,6 +963,42 @@ impl KeyToolCommand {
                 )
             }
 
+            KeyToolCommand::MultiSigSession { cmd } => {
+                CommandOutput::MultiSigSession(cmd.execute(keystore).await?)
+            }
+
+            KeyToolCommand::RecoverBackup {
+                shares,
+                alias,
+                key_scheme,
+                derivation_path,
+            } => {
+                let shares = shares
+                    .iter()
+                    .enumerate()
+                    .map(|(i, words)| {
+                        Share::from_words(words).map_err(|e| anyhow!("Share {}: {e}", i + 1))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let sui_address = match shamir::combine(&shares)? {
+                    Secret::KeyPair(skp) => {
+                        let sui_address = SuiAddress::from(&skp.public());
+                        keystore.import(alias, skp).await?;
+                        sui_address
+                    }
+                    Secret::Mnemonic(phrase) => {
+                        keystore
+                            .import_from_mnemonic(&phrase, key_scheme, derivation_path, alias)
+                            .await?
+                    }
+                };
+
+                let mut key = Key::from(keystore.export(&sui_address)?);
+                key.alias = keystore.get_alias(&sui_address).ok();
+                CommandOutput::RecoverBackup(key)
+            }
+
             KeyToolCommand::Show { file } => {
                 let res = read_keypair_from_file(&file);
                 match res {
@@ -833,6 +1031,8 @@ impl KeyToolCommand {
                 address,
                 data,
                 intent,
+                rpc_url,
+                confirm,
             } => {
                 let address = keystore.get_by_identity(&address)?;
                 let intent = intent.unwrap_or_else(Intent::sui_transaction);
@@ -846,6 +1046,27 @@ impl KeyToolCommand {
                 let mut hasher = DefaultHash::default();
                 hasher.update(bcs::to_bytes(&intent_msg)?);
                 let digest = hasher.finalize().digest;
+
+                if intent_msg.intent.scope == IntentScope::TransactionData
+                    && let Some(policy) = keystore.signing_policy()
+                {
+                    let inputs = match rpc_url {
+                        Some(url) => {
+                            let client = SuiClientBuilder::default().build(url).await?;
+                            resolve_policy_inputs(client.read_api(), &intent_msg.value).await?
+                        }
+                        None => ResolvedInputs::default(),
+                    };
+
+                    policy.authorize(address, &intent_msg.value, &inputs, |decision| {
+                        if confirm {
+                            Ok(true)
+                        } else {
+                            confirm_signing(address, decision)
+                        }
+                    })?;
+                }
+
                 let sui_signature = keystore
                     .sign_secure(&address, &intent_msg.value, intent_msg.intent)
                     .await?;
@@ -918,6 +1139,39 @@ impl KeyToolCommand {
                 })
             }
 
+            KeyToolCommand::SplitBackup {
+                key_identity,
+                mnemonic,
+                threshold,
+                shares,
+            } => {
+                let (sui_address, secret) = match (key_identity, mnemonic) {
+                    (Some(key_identity), _) => {
+                        let address = keystore.get_by_identity(&key_identity)?;
+                        let skp = keystore.export(&address)?.copy();
+                        (Some(address), Secret::KeyPair(skp))
+                    }
+                    (None, Some(mnemonic)) => (None, Secret::Mnemonic(mnemonic.into())),
+                    (None, None) => {
+                        return Err(anyhow!("Either a key identity or a mnemonic is required"));
+                    }
+                };
+
+                let shares = shamir::split(&secret, threshold, shares)?
+                    .into_iter()
+                    .map(|share| BackupShare {
+                        index: share.index(),
+                        words: share.to_words(),
+                    })
+                    .collect();
+
+                CommandOutput::SplitBackup(SplitBackupOutput {
+                    sui_address,
+                    threshold,
+                    shares,
+                })
+            }
+
             KeyToolCommand::Unpack { keypair } => {
                 let keypair = SuiKeyPair::decode_base64(&keypair)
                     .map_err(|_| anyhow!("Invalid Base64 encode keypair"))?;
@@ -1334,6 +1588,23 @@ impl KeyToolCommand {
     }
 }
 
+impl KeystoreStatus {
+    fn new(keystore: &EncryptedFileKeystore, client_config: Option<PathBuf>) -> Self {
+        let unlocked_until_ms = keystore.session_expiry().and_then(|expiry| {
+            let since_epoch = expiry.duration_since(UNIX_EPOCH).ok()?;
+            Some(since_epoch.as_millis() as u64)
+        });
+
+        Self {
+            keystore_path: keystore.path().to_path_buf(),
+            locked: unlocked_until_ms.is_none(),
+            unlocked_until_ms,
+            session_token: None,
+            client_config,
+        }
+    }
+}
+
 impl From<&SuiKeyPair> for Key {
     fn from(skp: &SuiKeyPair) -> Self {
         Key::from(skp.public())
@@ -1434,6 +1705,55 @@ impl Debug for CommandOutput {
     }
 }
 
+fn encrypted_keystore(
+    keystore: &mut Keystore,
+) -> Result<&mut EncryptedFileKeystore, anyhow::Error> {
+    match keystore {
+        Keystore::Encrypted(encrypted) => Ok(encrypted),
+        _ => Err(anyhow!(
+            "The keystore is not encrypted, use `sui keytool encrypt-keystore` to encrypt it"
+        )),
+    }
+}
+
+/// Point the client config next to the keystore at `keystore_path` to the encrypted keystore, if
+/// it currently refers to that keystore as a plain file-based keystore. The config is edited as
+/// YAML rather than loaded, because loading it would load the (now encrypted) keystore as a plain
+/// one. Returns the path of the config, if it was updated.
+fn use_encrypted_keystore_in_client_config(
+    keystore_path: &Path,
+) -> Result<Option<PathBuf>, anyhow::Error> {
+    let Some(config_path) = keystore_path
+        .parent()
+        .map(|dir| dir.join(SUI_CLIENT_CONFIG))
+    else {
+        return Ok(None);
+    };
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let mut config: serde_yaml::Value = serde_yaml::from_str(&fs::read_to_string(&config_path)?)?;
+    let Some(keystore) = config.get_mut("keystore") else {
+        return Ok(None);
+    };
+
+    let file_path = keystore.get("File").and_then(|path| path.as_str());
+    if file_path.map(Path::new) != Some(keystore_path) {
+        return Ok(None);
+    }
+
+    let mut encrypted = serde_yaml::Mapping::new();
+    encrypted.insert(
+        "Encrypted".into(),
+        keystore_path.to_string_lossy().into_owned().into(),
+    );
+    *keystore = serde_yaml::Value::Mapping(encrypted);
+
+    fs::write(&config_path, serde_yaml::to_string(&config)?)?;
+    Ok(Some(config_path))
+}
+
 /// Converts legacy formatted private key to 33 bytes bech32 encoded private key or vice versa.
 /// It can handle:
 /// 1) Hex encoded 32 byte private key (assumes scheme is Ed25519), this is the legacy wallet format
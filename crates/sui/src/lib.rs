@@ -11,6 +11,7 @@ pub mod fire_drill;
 pub mod genesis_ceremony;
 pub mod genesis_inspector;
 pub mod keytool;
+pub mod multisig_session;
 pub mod mvr_resolver;
 pub mod sui_commands;
 pub mod trace_analysis_commands;
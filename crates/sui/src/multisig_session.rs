@@ -0,0 +1,344 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline multisig signing sessions.
+//!
+//! A session is a JSON file holding a transaction, the multisig public key that its sender is
+//! derived from, and the partial signatures collected for it so far. The file is passed between
+//! signers (e.g. on removable media to air-gapped machines), each of whom adds their signature
+//! with `sui keytool multisig-session sign`. Once the collected signatures meet the threshold,
+//! they are combined into the multisig, which is also stored in the file.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, ensure};
+use clap::*;
+use fastcrypto::encoding::{Base64, Encoding};
+use serde::{Deserialize, Serialize};
+use shared_crypto::intent::{Intent, IntentMessage};
+use sui_keys::key_identity::KeyIdentity;
+use sui_keys::keystore::{AccountKeystore, Keystore};
+use sui_types::base_types::SuiAddress;
+use sui_types::crypto::{EncodeDecodeBase64, PublicKey, SuiSignature};
+use sui_types::digests::TransactionDigest;
+use sui_types::multisig::{MultiSig, MultiSigPublicKey, ThresholdUnit, WeightUnit};
+use sui_types::signature::{GenericSignature, VerifyParams};
+use sui_types::signature_verification::VerifiedDigestCache;
+use sui_types::transaction::{TransactionData, TransactionDataAPI};
+
+const SESSION_VERSION: u8 = 1;
+
+#[derive(Subcommand)]
+#[clap(rename_all = "kebab-case")]
+pub enum MultiSigSessionCommand {
+    /// Start a signing session for a transaction whose sender is a MultiSig address, and write
+    /// it to a file. The public keys, weights and threshold are those of the MultiSig address,
+    /// as passed to `sui keytool multi-sig-address`.
+    Create {
+        /// The Base64 encoded BCS bytes of the transaction to sign.
+        #[clap(long)]
+        tx_bytes: String,
+        #[clap(long, num_args(1..))]
+        pks: Vec<PublicKey>,
+        #[clap(long, num_args(1..))]
+        weights: Vec<WeightUnit>,
+        #[clap(long)]
+        threshold: ThresholdUnit,
+        /// The file to write the session to.
+        #[clap(long)]
+        output: PathBuf,
+    },
+    /// Sign the transaction in a session file with every key in Sui CLI Keystore that is part of
+    /// its MultiSig (or only the given key), and save the signatures to the file. Once enough
+    /// signatures have been collected to meet the threshold, they are combined into the
+    /// MultiSig signature.
+    Sign {
+        file: PathBuf,
+        /// Only sign with the key for this address (or alias).
+        #[clap(long)]
+        key_identity: Option<KeyIdentity>,
+    },
+    /// Show the transaction in a session file, who has signed it so far, and the combined
+    /// MultiSig signature once the threshold has been met.
+    Status { file: PathBuf },
+}
+
+/// The contents of a session file.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    version: u8,
+    /// Base64 encoded BCS bytes of the transaction.
+    tx_bytes: String,
+    signers: Vec<SessionSigner>,
+    threshold: ThresholdUnit,
+    /// Base64 encoded combined MultiSig signature, once the threshold has been met.
+    multisig: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionSigner {
+    /// Base64 encoded `flag || pk`.
+    public_key: String,
+    weight: WeightUnit,
+    /// Base64 encoded `flag || signature || pk`, once this signer has signed.
+    signature: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiSigSessionOutput {
+    session: PathBuf,
+    multisig_address: SuiAddress,
+    tx_digest: TransactionDigest,
+    signers: Vec<MultiSigSessionSigner>,
+    signed_weight: ThresholdUnit,
+    threshold: ThresholdUnit,
+    /// The combined MultiSig signature, to pass to `sui client execute-signed-tx`.
+    multisig_serialized: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiSigSessionSigner {
+    address: SuiAddress,
+    public_base64_key: String,
+    weight: WeightUnit,
+    signed: bool,
+}
+
+impl MultiSigSessionCommand {
+    pub async fn execute(
+        self,
+        keystore: &Keystore,
+    ) -> Result<MultiSigSessionOutput, anyhow::Error> {
+        match self {
+            MultiSigSessionCommand::Create {
+                tx_bytes,
+                pks,
+                weights,
+                threshold,
+                output,
+            } => {
+                ensure!(
+                    pks.len() == weights.len(),
+                    "Got {} public keys but {} weights",
+                    pks.len(),
+                    weights.len()
+                );
+                ensure!(
+                    !output.exists(),
+                    "Session file {} already exists",
+                    output.display()
+                );
+
+                let session = Session {
+                    version: SESSION_VERSION,
+                    tx_bytes,
+                    signers: pks
+                        .iter()
+                        .zip(&weights)
+                        .map(|(pk, weight)| SessionSigner {
+                            public_key: pk.encode_base64(),
+                            weight: *weight,
+                            signature: None,
+                        })
+                        .collect(),
+                    threshold,
+                    multisig: None,
+                };
+
+                // Check the transaction and MultiSig before writing anything out.
+                session.tx_data()?;
+                session.multisig_pk()?;
+
+                session.save(&output)?;
+                session.output(&output)
+            }
+
+            MultiSigSessionCommand::Sign { file, key_identity } => {
+                let mut session = Session::load(&file)?;
+                let tx_data = session.tx_data()?;
+                let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
+
+                let only = key_identity
+                    .map(|identity| keystore.get_by_identity(&identity))
+                    .transpose()?;
+
+                let mut signed = 0;
+                for i in 0..session.signers.len() {
+                    let address = SuiAddress::from(&session.signers[i].public_key()?);
+                    if session.signers[i].signature.is_some()
+                        || only.is_some_and(|only| only != address)
+                        || !keystore.addresses().contains(&address)
+                    {
+                        continue;
+                    }
+
+                    let signature = keystore
+                        .sign_secure(&address, &tx_data, Intent::sui_transaction())
+                        .await?;
+                    signature
+                        .verify_secure(&intent_msg, address, signature.scheme())
+                        .map_err(|e| anyhow!("Signature by {address} does not verify: {e}"))?;
+                    session.signers[i].signature = Some(signature.encode_base64());
+                    signed += 1;
+                }
+
+                if signed == 0 {
+                    match only {
+                        Some(address) => {
+                            bail!("{address} is not part of this MultiSig, or has already signed")
+                        }
+                        None => {
+                            bail!("No unsigned keys of this MultiSig were found in the keystore")
+                        }
+                    }
+                }
+
+                if session.multisig.is_none() && session.signed_weight() >= session.threshold {
+                    session.multisig = Some(session.combine()?.encode_base64());
+                }
+
+                session.save(&file)?;
+                session.output(&file)
+            }
+
+            MultiSigSessionCommand::Status { file } => Session::load(&file)?.output(&file),
+        }
+    }
+}
+
+impl Session {
+    fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read session file {}: {e}", path.display()))?;
+        let session: Session = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid session file {}: {e}", path.display()))?;
+        ensure!(
+            session.version == SESSION_VERSION,
+            "Unsupported session file version {}",
+            session.version
+        );
+        Ok(session)
+    }
+
+    /// Write the session to a temporary file next to `path` and rename it into place, so that an
+    /// interrupted write never leaves a truncated session behind.
+    fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let contents = serde_json::to_string_pretty(self)?;
+
+        let file = tempfile::NamedTempFile::new_in(dir)
+            .and_then(|mut file| {
+                file.write_all(contents.as_bytes())?;
+                file.as_file().sync_all()?;
+                Ok(file)
+            })
+            .map_err(|e| anyhow!("Cannot write session file {}: {e}", path.display()))?;
+        file.persist(path)
+            .map_err(|e| anyhow!("Cannot write session file {}: {e}", path.display()))?;
+        Ok(())
+    }
+
+    fn tx_data(&self) -> Result<TransactionData, anyhow::Error> {
+        let tx_bytes =
+            Base64::decode(&self.tx_bytes).map_err(|e| anyhow!("Invalid tx bytes: {:?}", e))?;
+        let tx_data: TransactionData = bcs::from_bytes(&tx_bytes)?;
+
+        let multisig_address = SuiAddress::from(&self.multisig_pk()?);
+        ensure!(
+            tx_data.sender() == multisig_address,
+            "The transaction is sent by {}, not by the MultiSig address {multisig_address}",
+            tx_data.sender(),
+        );
+
+        Ok(tx_data)
+    }
+
+    fn multisig_pk(&self) -> Result<MultiSigPublicKey, anyhow::Error> {
+        let pks = self
+            .signers
+            .iter()
+            .map(SessionSigner::public_key)
+            .collect::<Result<_, _>>()?;
+        let weights = self.signers.iter().map(|s| s.weight).collect();
+        Ok(MultiSigPublicKey::new(pks, weights, self.threshold)?)
+    }
+
+    fn signed_weight(&self) -> ThresholdUnit {
+        self.signers
+            .iter()
+            .filter(|s| s.signature.is_some())
+            .map(|s| s.weight as ThresholdUnit)
+            .sum()
+    }
+
+    /// Combine the partial signatures, in the order of their public keys, and check that the
+    /// result verifies against the transaction.
+    fn combine(&self) -> Result<GenericSignature, anyhow::Error> {
+        let sigs = self
+            .signers
+            .iter()
+            .filter_map(|s| s.signature.as_ref())
+            .map(|sig| {
+                GenericSignature::decode_base64(sig)
+                    .map_err(|e| anyhow!("Invalid partial signature in session: {e}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let multisig: GenericSignature = MultiSig::combine(sigs, self.multisig_pk()?)?.into();
+        let tx_data = self.tx_data()?;
+        multisig
+            .verify_authenticator(
+                &IntentMessage::new(Intent::sui_transaction(), tx_data.clone()),
+                tx_data.sender(),
+                0,
+                &VerifyParams::default(),
+                Arc::new(VerifiedDigestCache::new_empty()),
+            )
+            .map_err(|e| anyhow!("Combined MultiSig does not verify: {e}"))?;
+
+        Ok(multisig)
+    }
+
+    fn output(&self, path: &Path) -> Result<MultiSigSessionOutput, anyhow::Error> {
+        let tx_data = self.tx_data()?;
+        let signers = self
+            .signers
+            .iter()
+            .map(|s| {
+                Ok(MultiSigSessionSigner {
+                    address: SuiAddress::from(&s.public_key()?),
+                    public_base64_key: s.public_key.clone(),
+                    weight: s.weight,
+                    signed: s.signature.is_some(),
+                })
+            })
+            .collect::<Result<_, anyhow::Error>>()?;
+
+        Ok(MultiSigSessionOutput {
+            session: path.to_path_buf(),
+            multisig_address: tx_data.sender(),
+            tx_digest: tx_data.digest(),
+            signers,
+            signed_weight: self.signed_weight(),
+            threshold: self.threshold,
+            multisig_serialized: self.multisig.clone(),
+        })
+    }
+}
+
+impl SessionSigner {
+    fn public_key(&self) -> Result<PublicKey, anyhow::Error> {
+        PublicKey::decode_base64(&self.public_key)
+            .map_err(|e| anyhow!("Invalid public key in session: {e}"))
+    }
+}
@@ -489,8 +489,7 @@ impl SuiCommand {
             } => {
                 let keystore_path =
                     keystore_path.unwrap_or(sui_config_dir()?.join(SUI_KEYSTORE_FILENAME));
-                let mut keystore =
-                    Keystore::from(FileBasedKeystore::load_or_create(&keystore_path)?);
+                let mut keystore = Keystore::load_or_create_from_path(&keystore_path)?;
                 cmd.execute(&mut keystore).await?.print(!json);
                 Ok(())
             }
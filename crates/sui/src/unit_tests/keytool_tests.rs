@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::str::FromStr;
+use std::sync::Arc;
 
 use crate::keytool::CommandOutput;
 use crate::keytool::read_authority_keypair_from_file;
 use crate::keytool::read_keypair_from_file;
+use crate::multisig_session::MultiSigSessionCommand;
 
 use super::KeyToolCommand;
 use super::write_keypair_to_file;
@@ -18,6 +20,7 @@ use fastcrypto::traits::ToFromBytes;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
 use shared_crypto::intent::Intent;
+use shared_crypto::intent::IntentMessage;
 use shared_crypto::intent::IntentScope;
 use sui_keys::key_identity::KeyIdentity;
 use sui_keys::keystore::{AccountKeystore, FileBasedKeystore, InMemKeystore, Keystore};
@@ -36,6 +39,9 @@ use sui_types::crypto::SuiKeyPair;
 use sui_types::crypto::SuiSignatureInner;
 use sui_types::crypto::get_key_pair;
 use sui_types::crypto::get_key_pair_from_rng;
+use sui_types::multisig::MultiSigPublicKey;
+use sui_types::signature::{GenericSignature, VerifyParams};
+use sui_types::signature_verification::VerifiedDigestCache;
 use sui_types::transaction::TEST_ONLY_GAS_UNIT_FOR_TRANSFER;
 use sui_types::transaction::TransactionData;
 use tempfile::TempDir;
@@ -597,6 +603,8 @@ async fn test_sign_command() -> Result<(), anyhow::Error> {
         address: KeyIdentity::Address(*sender),
         data: Base64::encode(bcs::to_bytes(&tx_data)?),
         intent: Some(Intent::sui_app(IntentScope::PersonalMessage)),
+        rpc_url: None,
+        confirm: false,
     }
     .execute(&mut keystore)
     .await?;
@@ -606,6 +614,8 @@ async fn test_sign_command() -> Result<(), anyhow::Error> {
         address: KeyIdentity::Address(*sender),
         data: Base64::encode(bcs::to_bytes(&tx_data)?),
         intent: None,
+        rpc_url: None,
+        confirm: false,
     }
     .execute(&mut keystore)
     .await?;
@@ -616,8 +626,164 @@ async fn test_sign_command() -> Result<(), anyhow::Error> {
         address: KeyIdentity::Alias(alias),
         data: Base64::encode(bcs::to_bytes(&tx_data)?),
         intent: None,
+        rpc_url: None,
+        confirm: false,
     }
     .execute(&mut keystore)
     .await?;
     Ok(())
 }
+
+#[test]
+async fn test_sign_command_with_signing_policy() -> Result<(), anyhow::Error> {
+    let mut keystore = Keystore::from(InMemKeystore::new_insecure_for_tests(1));
+    let sender = keystore.addresses()[0];
+
+    let gas = (
+        ObjectID::random(),
+        SequenceNumber::new(),
+        ObjectDigest::random(),
+    );
+    let tx_data = TransactionData::new_pay_sui(
+        sender,
+        vec![gas],
+        vec![SuiAddress::random_for_testing_only()],
+        vec![10000],
+        gas,
+        TEST_ONLY_GAS_UNIT_FOR_TRANSFER,
+        1,
+    )?;
+    let sign = |confirm| KeyToolCommand::Sign {
+        address: KeyIdentity::Address(sender),
+        data: Base64::encode(bcs::to_bytes(&tx_data).unwrap()),
+        intent: None,
+        rpc_url: None,
+        confirm,
+    };
+
+    // Transactions that the policy requires confirmation for are signed once confirmed.
+    keystore.set_signing_policy(Some(serde_yaml::from_str(
+        "rules: [{ name: everything, action: confirm }]",
+    )?));
+    sign(true).execute(&mut keystore).await?;
+
+    // Transactions that the policy denies are not signed, even if they are confirmed.
+    keystore.set_signing_policy(Some(serde_yaml::from_str(
+        "rules: [{ name: everything, action: deny }]",
+    )?));
+    let err = sign(true).execute(&mut keystore).await.err().unwrap();
+    assert!(err.to_string().contains("everything"), "{err}");
+    Ok(())
+}
+
+#[test]
+async fn test_multisig_session() -> Result<(), anyhow::Error> {
+    let mut keystore = Keystore::from(InMemKeystore::new_insecure_for_tests(3));
+    let pks = keystore.entries();
+    let multisig_pk = MultiSigPublicKey::new(pks.clone(), vec![1, 1, 1], 2)?;
+    let sender = SuiAddress::from(&multisig_pk);
+
+    let gas = (
+        ObjectID::random(),
+        SequenceNumber::new(),
+        ObjectDigest::random(),
+    );
+    let gas_price = 1;
+    let tx_data = TransactionData::new_pay_sui(
+        sender,
+        vec![gas],
+        vec![SuiAddress::random_for_testing_only()],
+        vec![10000],
+        gas,
+        gas_price * TEST_ONLY_GAS_UNIT_FOR_TRANSFER,
+        gas_price,
+    )
+    .unwrap();
+
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("session.json");
+
+    // Every public key needs a weight.
+    assert!(
+        KeyToolCommand::MultiSigSession {
+            cmd: MultiSigSessionCommand::Create {
+                tx_bytes: Base64::encode(bcs::to_bytes(&tx_data)?),
+                pks: pks.clone(),
+                weights: vec![1, 1],
+                threshold: 2,
+                output: file.clone(),
+            },
+        }
+        .execute(&mut keystore)
+        .await
+        .is_err()
+    );
+    assert!(!file.exists());
+
+    KeyToolCommand::MultiSigSession {
+        cmd: MultiSigSessionCommand::Create {
+            tx_bytes: Base64::encode(bcs::to_bytes(&tx_data)?),
+            pks: pks.clone(),
+            weights: vec![1, 1, 1],
+            threshold: 2,
+            output: file.clone(),
+        },
+    }
+    .execute(&mut keystore)
+    .await?;
+
+    // One signature is not enough to meet the threshold.
+    let output = KeyToolCommand::MultiSigSession {
+        cmd: MultiSigSessionCommand::Sign {
+            file: file.clone(),
+            key_identity: Some(KeyIdentity::Address(SuiAddress::from(&pks[1]))),
+        },
+    }
+    .execute(&mut keystore)
+    .await?;
+    let output = serde_json::to_value(&output)?;
+    assert_eq!(output["signedWeight"], 1);
+    assert!(output["multisigSerialized"].is_null());
+
+    // Signing again with the same key is an error.
+    assert!(
+        KeyToolCommand::MultiSigSession {
+            cmd: MultiSigSessionCommand::Sign {
+                file: file.clone(),
+                key_identity: Some(KeyIdentity::Address(SuiAddress::from(&pks[1]))),
+            },
+        }
+        .execute(&mut keystore)
+        .await
+        .is_err()
+    );
+
+    // The remaining keys meet the threshold, and the signatures are combined.
+    KeyToolCommand::MultiSigSession {
+        cmd: MultiSigSessionCommand::Sign {
+            file: file.clone(),
+            key_identity: None,
+        },
+    }
+    .execute(&mut keystore)
+    .await?;
+
+    let output = KeyToolCommand::MultiSigSession {
+        cmd: MultiSigSessionCommand::Status { file },
+    }
+    .execute(&mut keystore)
+    .await?;
+    let output = serde_json::to_value(&output)?;
+    assert_eq!(output["signedWeight"], 3);
+
+    let multisig = GenericSignature::from_str(output["multisigSerialized"].as_str().unwrap())
+        .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+    multisig.verify_authenticator(
+        &IntentMessage::new(Intent::sui_transaction(), tx_data.clone()),
+        sender,
+        0,
+        &VerifyParams::default(),
+        Arc::new(VerifiedDigestCache::new_empty()),
+    )?;
+    Ok(())
+}